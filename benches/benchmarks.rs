@@ -4,18 +4,24 @@ extern crate rand;
 extern crate stream_vbyte;
 extern crate test;
 
-#[cfg(feature = "x86_ssse3")]
-extern crate stdsimd;
-
 use self::test::Bencher;
 
-use self::rand::Rng;
-use self::rand::distributions::{IndependentSample, Range};
-
 use std::iter;
 
+#[cfg(all(feature = "x86_ssse3", target_arch = "x86"))]
+use std::arch::x86::__m128i;
+#[cfg(all(feature = "x86_ssse3", target_arch = "x86_64"))]
+use std::arch::x86_64::__m128i;
+
+use rand::Rng;
+
 use stream_vbyte::*;
 
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::*;
+
 #[bench]
 fn encode_scalar_rand_1k(b: &mut Bencher) {
     do_encode_bench(
@@ -54,6 +60,44 @@ fn encode_sse41_rand_1m(b: &mut Bencher) {
     );
 }
 
+// tiny inputs like short id lists in RPC messages, which take the small input fast path
+
+#[bench]
+fn encode_scalar_rand_3(b: &mut Bencher) {
+    do_encode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(3),
+        Scalar,
+    );
+}
+
+#[bench]
+fn encode_scalar_rand_10(b: &mut Bencher) {
+    do_encode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(10),
+        Scalar,
+    );
+}
+
+#[bench]
+fn decode_scalar_rand_3(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(3),
+        Scalar,
+    );
+}
+
+#[bench]
+fn decode_scalar_rand_10(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(10),
+        Scalar,
+    );
+}
+
 #[bench]
 fn encode_scalar_zeros_1k(b: &mut Bencher) {
     do_encode_bench(b, iter::repeat(0).take(1000), Scalar);
@@ -114,6 +158,49 @@ fn decode_ssse3_rand_1m(b: &mut Bencher) {
     );
 }
 
+// 16 million numbers take about 40MiB encoded, much larger than L2 (or even L3), so these show
+// whether software prefetching helps when decoding is memory-bound
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn decode_ssse3_rand_16m(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(16_000_000),
+        x86::Ssse3,
+    );
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn decode_ssse3_prefetch_256_rand_16m(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(16_000_000),
+        x86::Ssse3Prefetch::<x86::Prefetch256>::new(),
+    );
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn decode_ssse3_prefetch_512_rand_16m(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(16_000_000),
+        x86::Ssse3Prefetch::<x86::Prefetch512>::new(),
+    );
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn decode_ssse3_prefetch_1024_rand_16m(b: &mut Bencher) {
+    do_decode_bench(
+        b,
+        RandomVarintEncodedLengthIter::new(rand::weak_rng()).take(16_000_000),
+        x86::Ssse3Prefetch::<x86::Prefetch1024>::new(),
+    );
+}
+
 #[bench]
 fn decode_cursor_slice_scalar_rand_1k(b: &mut Bencher) {
     do_decode_cursor_slice_bench(
@@ -193,6 +280,103 @@ fn decode_ssse3_zeros_1m(b: &mut Bencher) {
     do_decode_bench(b, iter::repeat(0).take(1_000_000), x86::Ssse3);
 }
 
+#[bench]
+fn encode_scalar_sorted_gaps_1m(b: &mut Bencher) {
+    do_encode_bench(b, sorted_gaps().take(1_000_000), Scalar);
+}
+
+#[cfg(feature = "x86_sse41")]
+#[bench]
+fn encode_sse41_sorted_gaps_1m(b: &mut Bencher) {
+    do_encode_bench(b, sorted_gaps().take(1_000_000), x86::Sse41);
+}
+
+#[bench]
+fn decode_scalar_sorted_gaps_1m(b: &mut Bencher) {
+    do_decode_bench(b, sorted_gaps().take(1_000_000), Scalar);
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn decode_ssse3_sorted_gaps_1m(b: &mut Bencher) {
+    do_decode_bench(b, sorted_gaps().take(1_000_000), x86::Ssse3);
+}
+
+#[bench]
+fn decode_four_rand_100(b: &mut Bencher) {
+    let (encoded, counts) = four_short_streams(100);
+    let mut decoded = vec![vec![0; 100]; 4];
+
+    b.iter(|| {
+        let mut d = decoded.iter_mut();
+        decode_four(
+            [&encoded[0], &encoded[1], &encoded[2], &encoded[3]],
+            counts,
+            [
+                d.next().unwrap(),
+                d.next().unwrap(),
+                d.next().unwrap(),
+                d.next().unwrap(),
+            ],
+        )
+    });
+}
+
+#[bench]
+fn decode_scalar_four_separately_rand_100(b: &mut Bencher) {
+    let (encoded, counts) = four_short_streams(100);
+    let mut decoded = vec![vec![0; 100]; 4];
+
+    b.iter(|| {
+        for i in 0..4 {
+            stream_vbyte::decode::<Scalar>(&encoded[i], counts[i], &mut decoded[i]);
+        }
+    });
+}
+
+#[bench]
+fn decode_context_scalar_many_small(b: &mut Bencher) {
+    let (encoded, counts) = many_short_streams();
+    let mut context = DecodeContext::new();
+
+    b.iter(|| {
+        let mut sum = 0;
+        for (e, &count) in encoded.iter().zip(counts.iter()) {
+            sum += context.decode::<Scalar>(e, count).len();
+        }
+        sum
+    });
+}
+
+#[bench]
+fn decode_cursor_reset_scalar_many_small(b: &mut Bencher) {
+    let (encoded, counts) = many_short_streams();
+    let mut decoded = vec![0; 64];
+
+    b.iter(|| {
+        let mut cursor = DecodeCursor::new(&[], 0);
+        let mut sum = 0;
+        for (e, &count) in encoded.iter().zip(counts.iter()) {
+            cursor.reset(e, count);
+            sum += cursor.decode_slice::<Scalar>(&mut decoded);
+        }
+        sum
+    });
+}
+
+#[bench]
+fn decode_to_new_vec_scalar_many_small(b: &mut Bencher) {
+    let (encoded, counts) = many_short_streams();
+
+    b.iter(|| {
+        let mut sum = 0;
+        for (e, &count) in encoded.iter().zip(counts.iter()) {
+            sum += stream_vbyte::decode_to_new_vec::<Scalar>(e, count).len();
+        }
+        sum
+    });
+}
+
 #[bench]
 fn skip_all_1m(b: &mut Bencher) {
     let mut nums: Vec<u32> = Vec::new();
@@ -213,6 +397,192 @@ fn skip_all_1m(b: &mut Bencher) {
     });
 }
 
+// 250k control bytes, as for 1 million numbers; compare with and without the x86 features for
+// the SSSE3 and portable versions
+#[bench]
+fn length_histogram_rand_1m(b: &mut Bencher) {
+    let count = 1_000_000;
+    let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+        .take(count)
+        .collect();
+
+    let mut encoded = Vec::new();
+    encoded.resize(nums.len() * 5, 0);
+    stream_vbyte::encode::<Scalar>(&nums, &mut encoded);
+
+    b.iter(|| length_histogram(&encoded[0..(count / 4)]));
+}
+
+#[bench]
+fn iter_skip_then_next_1m(b: &mut Bencher) {
+    let count = 1_000_000;
+    let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+        .take(count)
+        .collect();
+
+    let mut encoded = Vec::new();
+    encoded.resize(nums.len() * 5, 0);
+    let bytes_written = stream_vbyte::encode::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded[0..bytes_written], count);
+
+    b.iter(|| {
+        assert_eq!(
+            Some(nums[count - 1]),
+            slice.iter::<Scalar>().skip(count - 1).next()
+        );
+    });
+}
+
+#[bench]
+fn intersect_encoded_scalar_dense_100k(b: &mut Bencher) {
+    do_intersect_encoded_bench(b, posting_list(2, 100_000), posting_list(3, 100_000));
+}
+
+#[bench]
+fn intersect_merge_scalar_dense_100k(b: &mut Bencher) {
+    do_intersect_merge_bench(b, posting_list(2, 100_000), posting_list(3, 100_000));
+}
+
+#[bench]
+fn intersect_encoded_scalar_skewed_1k_100k(b: &mut Bencher) {
+    do_intersect_encoded_bench(b, posting_list(200, 1000), posting_list(2, 100_000));
+}
+
+#[bench]
+fn intersect_merge_scalar_skewed_1k_100k(b: &mut Bencher) {
+    do_intersect_merge_bench(b, posting_list(200, 1000), posting_list(2, 100_000));
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[bench]
+fn intersect_encoded_ssse3_dense_100k(b: &mut Bencher) {
+    do_intersect_encoded_bench_with::<x86::Ssse3>(
+        b,
+        posting_list(2, 100_000),
+        posting_list(3, 100_000),
+    );
+}
+
+fn do_intersect_encoded_bench(b: &mut Bencher, list_a: Vec<u32>, list_b: Vec<u32>) {
+    do_intersect_encoded_bench_with::<Scalar>(b, list_a, list_b)
+}
+
+fn do_intersect_encoded_bench_with<D: Decoder>(
+    b: &mut Bencher,
+    list_a: Vec<u32>,
+    list_b: Vec<u32>,
+) {
+    let mut encoded_a = Vec::new();
+    stream_vbyte::encode_append::<Scalar>(&list_a, &mut encoded_a);
+    let mut encoded_b = Vec::new();
+    stream_vbyte::encode_append::<Scalar>(&list_b, &mut encoded_b);
+    let mut output = Vec::new();
+
+    b.iter(|| {
+        output.clear();
+        intersect_encoded::<D>(
+            &encoded_a,
+            list_a.len(),
+            &encoded_b,
+            list_b.len(),
+            &mut output,
+        )
+    });
+}
+
+/// Decode both lists and merge them a number at a time, as a baseline for `intersect_encoded()`.
+fn do_intersect_merge_bench(b: &mut Bencher, list_a: Vec<u32>, list_b: Vec<u32>) {
+    let mut encoded_a = Vec::new();
+    stream_vbyte::encode_append::<Scalar>(&list_a, &mut encoded_a);
+    let mut encoded_b = Vec::new();
+    stream_vbyte::encode_append::<Scalar>(&list_b, &mut encoded_b);
+    let mut decoded_a = vec![0; list_a.len()];
+    let mut decoded_b = vec![0; list_b.len()];
+    let mut output = Vec::new();
+
+    b.iter(|| {
+        stream_vbyte::decode::<Scalar>(&encoded_a, list_a.len(), &mut decoded_a);
+        stream_vbyte::decode::<Scalar>(&encoded_b, list_b.len(), &mut decoded_b);
+
+        output.clear();
+        let (mut i, mut j) = (0, 0);
+        while i < decoded_a.len() && j < decoded_b.len() {
+            if decoded_a[i] < decoded_b[j] {
+                i += 1;
+            } else if decoded_b[j] < decoded_a[i] {
+                j += 1;
+            } else {
+                output.push(decoded_a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+        output.len()
+    });
+}
+
+/// `len` ascending document ids, with gaps between consecutive ids averaging `mean_gap`, like the
+/// posting list of a term in one in `mean_gap` documents.
+fn posting_list(mean_gap: u32, len: usize) -> Vec<u32> {
+    let mut rng = rand::weak_rng();
+    let mut id = 0_u32;
+
+    (0..len)
+        .map(|_| {
+            id += rng.gen_range(1, 2 * mean_gap);
+            id
+        })
+        .collect()
+}
+
+/// Gaps between consecutive numbers of a dense sorted list, as delta encoding produces: mostly 1,
+/// with an occasional larger jump.
+fn sorted_gaps() -> impl Iterator<Item = u32> {
+    let mut rng = rand::weak_rng();
+    iter::repeat(()).map(move |_| {
+        if rng.gen_range(0, 100) == 0 {
+            rng.gen_range(2, 1000)
+        } else {
+            1
+        }
+    })
+}
+
+fn four_short_streams(count: usize) -> (Vec<Vec<u8>>, [usize; 4]) {
+    let encoded = (0..4)
+        .map(|_| {
+            let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+                .take(count)
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let len = stream_vbyte::encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(len);
+            encoded
+        })
+        .collect();
+
+    (encoded, [count; 4])
+}
+
+/// A thousand streams of 1 to 64 numbers, like the posting lists for the terms of a query.
+fn many_short_streams() -> (Vec<Vec<u8>>, Vec<usize>) {
+    let mut rng = rand::weak_rng();
+    let counts: Vec<usize> = (0..1000).map(|_| rng.gen_range(1, 65)).collect();
+    let mut nums = RandomVarintEncodedLengthIter::new(rand::weak_rng());
+    let encoded = counts
+        .iter()
+        .map(|&count| {
+            let stream: Vec<u32> = nums.by_ref().take(count).collect();
+            let mut encoded = vec![0; count * 5];
+            let len = stream_vbyte::encode::<Scalar>(&stream, &mut encoded);
+            encoded.truncate(len);
+            encoded
+        })
+        .collect();
+
+    (encoded, counts)
+}
+
 fn do_encode_bench<I: Iterator<Item = u32>, E: Encoder>(b: &mut Bencher, iter: I, _encoder: E) {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
@@ -230,10 +600,7 @@ fn do_encode_bench<I: Iterator<Item = u32>, E: Encoder>(b: &mut Bencher, iter: I
 
 // take a decoder param to save us some typing -- type inference won't work if you only specify some
 // of the generic types
-fn do_decode_bench<I: Iterator<Item = u32>, D: Decoder>(b: &mut Bencher, iter: I, _decoder: D)
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_decode_bench<I: Iterator<Item = u32>, D: Decoder>(b: &mut Bencher, iter: I, _decoder: D) {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -255,9 +622,7 @@ fn do_decode_cursor_slice_bench<I: Iterator<Item = u32>, D: Decoder>(
     b: &mut Bencher,
     iter: I,
     _decoder: D,
-) where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+) {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -300,39 +665,6 @@ fn do_decode_cursor_sink_no_op_bench<I: Iterator<Item = u32>, D: Decoder>(
     })
 }
 
-// copied from tests because it's handy here too
-struct RandomVarintEncodedLengthIter<R: Rng> {
-    ranges: [Range<u32>; 4],
-    range_for_picking_range: Range<usize>,
-    rng: R,
-}
-
-impl<R: Rng> RandomVarintEncodedLengthIter<R> {
-    fn new(rng: R) -> RandomVarintEncodedLengthIter<R> {
-        RandomVarintEncodedLengthIter {
-            ranges: [
-                Range::new(0, 1 << 8),
-                Range::new(1 << 8, 1 << 16),
-                Range::new(1 << 16, 1 << 24),
-                Range::new(1 << 24, u32::max_value()), // this won't ever emit the max value, sadly
-            ],
-            range_for_picking_range: Range::new(0, 4),
-            rng,
-        }
-    }
-}
-
-impl<R: Rng> Iterator for RandomVarintEncodedLengthIter<R> {
-    type Item = u32;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // pick the range we'll use
-        let value_range = self.ranges[self.range_for_picking_range.ind_sample(&mut self.rng)];
-
-        Some(value_range.ind_sample(&mut self.rng))
-    }
-}
-
 struct NoOpSink;
 
 impl DecodeSingleSink for NoOpSink {
@@ -344,6 +676,6 @@ impl DecodeQuadSink<()> for NoOpSink {
 }
 
 #[cfg(feature = "x86_ssse3")]
-impl DecodeQuadSink<stdsimd::simd::u8x16> for NoOpSink {
-    fn on_quad(&mut self, _quad: stdsimd::simd::u8x16, _nums_decoded: usize) {}
+impl DecodeQuadSink<__m128i> for NoOpSink {
+    fn on_quad(&mut self, _quad: __m128i, _nums_decoded: usize) {}
 }
@@ -1,50 +1,216 @@
+extern crate byteorder;
 extern crate clap;
+extern crate rand;
 extern crate stream_vbyte;
 
-use std::io::{BufRead, Read, Write};
+use std::fs::{self, File};
+use std::path::Path;
+use std::io::{BufWriter, Read, Write};
+use std::time::{Duration, Instant};
+use byteorder::{ByteOrder, LittleEndian};
 use clap::{App, Arg, SubCommand};
+use rand::Rng;
+use stream_vbyte::{DecodeCursor, Decoder, Encoder};
+
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::RandomVarintEncodedLengthIter;
+
+const FORMATS: &[&str] = &["text", "csv", "u32le"];
+const DISTRIBUTIONS: &[&str] = &["uniform", "varint-length", "sorted-delta", "zipf"];
+const ENCODERS: &[&str] = &[
+    "scalar",
+    #[cfg(feature = "x86_sse41")]
+    "sse41",
+];
+const DECODERS: &[&str] = &[
+    "scalar",
+    #[cfg(feature = "x86_ssse3")]
+    "ssse3",
+];
 
 fn main() {
     let matches = App::new("stream-vbyte cli")
-        .subcommand(SubCommand::with_name("enc").about("Encode numbers"))
         .subcommand(
-            SubCommand::with_name("dec").about("Decode numbers").arg(
-                Arg::with_name("count")
-                    .help("count of numbers in encoded input")
-                    .short("c")
-                    .long("count")
-                    .takes_value(true)
-                    .required(true),
-            ),
+            SubCommand::with_name("enc")
+                .about("Encode numbers")
+                .arg(
+                    Arg::with_name("input")
+                        .help("file to read numbers from instead of stdin")
+                        .short("i")
+                        .long("input")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("input-format")
+                        .help("format of input numbers")
+                        .long("input-format")
+                        .takes_value(true)
+                        .possible_values(FORMATS)
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dec")
+                .about("Decode numbers")
+                .arg(
+                    Arg::with_name("count")
+                        .help("count of numbers in encoded input")
+                        .short("c")
+                        .long("count")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("file to write numbers to instead of stdout")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("output-format")
+                        .help("format of output numbers")
+                        .long("output-format")
+                        .takes_value(true)
+                        .possible_values(FORMATS)
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Check that all enabled encoders and decoders agree on the input numbers")
+                .arg(
+                    Arg::with_name("input")
+                        .help("file to read numbers from instead of stdin")
+                        .short("i")
+                        .long("input")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("input-format")
+                        .help("format of input numbers")
+                        .long("input-format")
+                        .takes_value(true)
+                        .possible_values(FORMATS)
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measure encode, decode, and skip speed on generated numbers")
+                .arg(
+                    Arg::with_name("distribution")
+                        .help("how to generate numbers")
+                        .short("d")
+                        .long("distribution")
+                        .takes_value(true)
+                        .possible_values(DISTRIBUTIONS)
+                        .default_value("varint-length"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .help("how many numbers to generate")
+                        .short("c")
+                        .long("count")
+                        .takes_value(true)
+                        .default_value("1000000"),
+                )
+                .arg(
+                    Arg::with_name("iterations")
+                        .help("how many times to repeat each operation")
+                        .short("n")
+                        .long("iterations")
+                        .takes_value(true)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("encoder")
+                        .long("encoder")
+                        .takes_value(true)
+                        .possible_values(ENCODERS)
+                        .default_value("scalar"),
+                )
+                .arg(
+                    Arg::with_name("decoder")
+                        .long("decoder")
+                        .takes_value(true)
+                        .possible_values(DECODERS)
+                        .default_value("scalar"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("golden")
+                .about("Write golden encodings and a manifest for testing other implementations")
+                .arg(
+                    Arg::with_name("output")
+                        .help("directory to write to")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .required(true),
+                ),
         )
         .get_matches();
 
-    match matches.subcommand_name() {
-        Some("enc") => encode(),
-        Some("dec") => {
-            let count: usize = matches
-                .subcommand_matches("dec")
+    match matches.subcommand() {
+        ("enc", Some(enc_matches)) => encode(
+            enc_matches.value_of("input"),
+            enc_matches.value_of("input-format").unwrap(),
+        ),
+        ("dec", Some(dec_matches)) => {
+            let count: usize = dec_matches
+                .value_of("count")
                 .unwrap()
+                .parse()
+                .expect("count must be an int");
+
+            decode(
+                count,
+                dec_matches.value_of("output"),
+                dec_matches.value_of("output-format").unwrap(),
+            );
+        }
+        ("verify", Some(verify_matches)) => verify(
+            verify_matches.value_of("input"),
+            verify_matches.value_of("input-format").unwrap(),
+        ),
+        ("bench", Some(bench_matches)) => {
+            let count: usize = bench_matches
                 .value_of("count")
                 .unwrap()
                 .parse()
                 .expect("count must be an int");
+            let iterations: usize = bench_matches
+                .value_of("iterations")
+                .unwrap()
+                .parse()
+                .expect("iterations must be an int");
+            let nums = generate(bench_matches.value_of("distribution").unwrap(), count);
+
+            let encoded = match bench_matches.value_of("encoder").unwrap() {
+                #[cfg(feature = "x86_sse41")]
+                "sse41" => bench_encode::<stream_vbyte::x86::Sse41>(&nums, iterations),
+                _ => bench_encode::<stream_vbyte::Scalar>(&nums, iterations),
+            };
 
-            decode(count);
+            match bench_matches.value_of("decoder").unwrap() {
+                #[cfg(feature = "x86_ssse3")]
+                "ssse3" => bench_decode::<stream_vbyte::x86::Ssse3>(&encoded, count, iterations),
+                _ => bench_decode::<stream_vbyte::Scalar>(&encoded, count, iterations),
+            };
+
+            bench_skip(&encoded, count, iterations);
         }
+        ("golden", Some(golden_matches)) => golden(golden_matches.value_of("output").unwrap()),
         _ => println!("Invalid subcommand"),
     }
 }
 
-fn encode() {
-    let stdin = std::io::stdin();
-    let stdin_handle = stdin.lock();
-
-    let nums: Vec<u32> = stdin_handle
-        .lines()
-        .map(|l| l.expect("Should be able to read stdin"))
-        .map(|s| s.parse().expect("Each line must be a u32"))
-        .collect();
+fn encode(input_path: Option<&str>, input_format: &str) {
+    let input = read_input(input_path);
+    let nums = parse_nums(&input, input_format);
 
     let mut encoded = Vec::new();
     encoded.resize(nums.len() * 5, 0);
@@ -59,22 +225,271 @@ fn encode() {
     eprintln!("Encoded {} numbers", nums.len());
 }
 
-fn decode(count: usize) {
-    let stdin = std::io::stdin();
-    let mut stdin_handle = stdin.lock();
-
-    let mut encoded = Vec::new();
-    stdin_handle
-        .read_to_end(&mut encoded)
-        .expect("Should be able to read stdin");
+fn decode(count: usize, output_path: Option<&str>, output_format: &str) {
+    let encoded = read_input(None);
 
     let mut decoded = Vec::new();
     decoded.resize(count, 0);
     stream_vbyte::decode::<stream_vbyte::Scalar>(&encoded, count, &mut decoded);
 
-    for d in &decoded {
-        println!("{}", d);
+    match output_path {
+        Some(path) => {
+            let file = File::create(path).expect("Should be able to create output file");
+            write_nums(&decoded, output_format, &mut BufWriter::new(file));
+        }
+        None => {
+            let stdout = std::io::stdout();
+            let mut stdout_handle = stdout.lock();
+            write_nums(&decoded, output_format, &mut stdout_handle);
+        }
     }
 
     eprintln!("Decoded {} numbers", decoded.len());
 }
+
+fn verify(input_path: Option<&str>, input_format: &str) {
+    let input = read_input(input_path);
+    let nums = parse_nums(&input, input_format);
+
+    // Scalar is the reference for both encoding and decoding
+    let (reference, elapsed) = encode_timed::<stream_vbyte::Scalar>(&nums);
+    let mut ok = report("encode", "Scalar", true, nums.len(), elapsed);
+
+    #[cfg(feature = "x86_sse41")]
+    {
+        let (encoded, elapsed) = encode_timed::<stream_vbyte::x86::Sse41>(&nums);
+        ok &= report("encode", "x86::Sse41", encoded == reference, nums.len(), elapsed);
+    }
+
+    let (decoded, elapsed) = decode_timed::<stream_vbyte::Scalar>(&reference, nums.len());
+    ok &= report("decode", "Scalar", decoded == nums, nums.len(), elapsed);
+
+    #[cfg(feature = "x86_ssse3")]
+    {
+        let (decoded, elapsed) = decode_timed::<stream_vbyte::x86::Ssse3>(&reference, nums.len());
+        ok &= report("decode", "x86::Ssse3", decoded == nums, nums.len(), elapsed);
+    }
+
+    if !ok {
+        eprintln!("Verification failed");
+        std::process::exit(1);
+    }
+
+    eprintln!("Verified {} numbers", nums.len());
+}
+
+fn golden(output_dir: &str) {
+    let dir = Path::new(output_dir);
+    fs::create_dir_all(dir).expect("Should be able to create output directory");
+
+    let cases = stream_vbyte::golden::golden_cases();
+    for case in &cases {
+        let mut nums = vec![0; case.nums.len() * 4];
+        LittleEndian::write_u32_into(&case.nums, &mut nums);
+        fs::write(dir.join(format!("{}.u32le", case.name)), &nums)
+            .expect("Should be able to write numbers");
+        fs::write(dir.join(format!("{}.bin", case.name)), &case.encoded)
+            .expect("Should be able to write encoded numbers");
+    }
+
+    let mut manifest = String::new();
+    stream_vbyte::golden::write_manifest(&cases, &mut manifest).unwrap();
+    fs::write(dir.join("manifest.txt"), manifest).expect("Should be able to write manifest");
+
+    eprintln!("Wrote {} cases to {}", cases.len(), output_dir);
+}
+
+fn encode_timed<E: Encoder>(nums: &[u32]) -> (Vec<u8>, Duration) {
+    let mut encoded = vec![0; nums.len() * 5];
+
+    let start = Instant::now();
+    let encoded_len = stream_vbyte::encode::<E>(nums, &mut encoded);
+    let elapsed = start.elapsed();
+    encoded.truncate(encoded_len);
+
+    (encoded, elapsed)
+}
+
+fn decode_timed<D: Decoder>(encoded: &[u8], count: usize) -> (Vec<u32>, Duration) {
+    let mut decoded = vec![0; count];
+
+    let start = Instant::now();
+    stream_vbyte::decode::<D>(encoded, count, &mut decoded);
+    let elapsed = start.elapsed();
+
+    (decoded, elapsed)
+}
+
+/// Print one line of `verify` output. Returns `matches`.
+fn report(operation: &str, name: &str, matches: bool, count: usize, elapsed: Duration) -> bool {
+    println!(
+        "{} {:12} {:>8} {:>14.0} numbers/s",
+        operation,
+        name,
+        if matches { "ok" } else { "MISMATCH" },
+        per_second(count, elapsed)
+    );
+
+    matches
+}
+
+/// Generate `count` numbers with the named distribution.
+fn generate(distribution: &str, count: usize) -> Vec<u32> {
+    let mut rng = rand::weak_rng();
+
+    match distribution {
+        "uniform" => (0..count).map(|_| rng.gen()).collect(),
+        "varint-length" => RandomVarintEncodedLengthIter::new(rng).take(count).collect(),
+        "sorted-delta" => {
+            // the gaps between sorted numbers, as if delta encoding a sorted column
+            let mut sorted: Vec<u32> = (0..count).map(|_| rng.gen()).collect();
+            sorted.sort();
+            let mut prev = 0;
+            sorted
+                .into_iter()
+                .map(|n| {
+                    let delta = n - prev;
+                    prev = n;
+                    delta
+                })
+                .collect()
+        }
+        "zipf" => {
+            // ranks 1 to 1 million with exponent 1, sampled via the cumulative distribution
+            let mut cumulative = Vec::with_capacity(1_000_000);
+            let mut total = 0.0;
+            for rank in 1..(1_000_000 + 1) {
+                total += 1.0 / rank as f64;
+                cumulative.push(total);
+            }
+
+            (0..count)
+                .map(|_| {
+                    let target = rng.gen::<f64>() * total;
+                    let index =
+                        match cumulative.binary_search_by(|c| c.partial_cmp(&target).unwrap()) {
+                            Ok(i) | Err(i) => i,
+                        };
+                    index as u32 + 1
+                })
+                .collect()
+        }
+        _ => panic!("Unknown distribution {}", distribution),
+    }
+}
+
+fn bench_encode<E: Encoder>(nums: &[u32], iterations: usize) -> Vec<u8> {
+    let mut total = Duration::new(0, 0);
+    let mut encoded = Vec::new();
+
+    for _ in 0..iterations {
+        let (e, elapsed) = encode_timed::<E>(nums);
+        total += elapsed;
+        encoded = e;
+    }
+
+    println!(
+        "encode {:>14.0} numbers/s ({} bytes, {:.2} bytes/number)",
+        per_second(nums.len() * iterations, total),
+        encoded.len(),
+        encoded.len() as f64 / nums.len() as f64
+    );
+
+    encoded
+}
+
+fn bench_decode<D: Decoder>(encoded: &[u8], count: usize, iterations: usize) {
+    let mut total = Duration::new(0, 0);
+
+    for _ in 0..iterations {
+        let (_, elapsed) = decode_timed::<D>(encoded, count);
+        total += elapsed;
+    }
+
+    println!("decode {:>14.0} numbers/s", per_second(count * iterations, total));
+}
+
+fn bench_skip(encoded: &[u8], count: usize, iterations: usize) {
+    let to_skip = count / 4 * 4;
+    let mut total = Duration::new(0, 0);
+
+    for _ in 0..iterations {
+        let mut cursor = DecodeCursor::new(encoded, count);
+        let start = Instant::now();
+        cursor.skip(to_skip);
+        total += start.elapsed();
+    }
+
+    println!("skip   {:>14.0} numbers/s", per_second(to_skip * iterations, total));
+}
+
+fn per_second(count: usize, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+
+    count as f64 / seconds
+}
+
+/// Read all of the file at `path`, or stdin if there is no path.
+fn read_input(path: Option<&str>) -> Vec<u8> {
+    let mut input = Vec::new();
+
+    match path {
+        Some(p) => {
+            File::open(p)
+                .expect("Should be able to open input file")
+                .read_to_end(&mut input)
+                .expect("Should be able to read input file");
+        }
+        None => {
+            let stdin = std::io::stdin();
+            let mut stdin_handle = stdin.lock();
+            stdin_handle
+                .read_to_end(&mut input)
+                .expect("Should be able to read stdin");
+        }
+    }
+
+    input
+}
+
+fn parse_nums(input: &[u8], format: &str) -> Vec<u32> {
+    match format {
+        "u32le" => {
+            assert_eq!(0, input.len() % 4, "u32le input length must be a multiple of 4");
+            input.chunks(4).map(LittleEndian::read_u32).collect()
+        }
+        "text" | "csv" => {
+            let separator: &[char] = if format == "csv" { &[','] } else { &[] };
+            std::str::from_utf8(input)
+                .expect("Input must be UTF-8")
+                .split(|c: char| c.is_whitespace() || separator.contains(&c))
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse().expect("Each value must be a u32"))
+                .collect()
+        }
+        _ => panic!("Unknown format {}", format),
+    }
+}
+
+fn write_nums<W: Write>(nums: &[u32], format: &str, output: &mut W) {
+    let result = match format {
+        "u32le" => {
+            let mut bytes = vec![0; nums.len() * 4];
+            LittleEndian::write_u32_into(nums, &mut bytes);
+            output.write_all(&bytes)
+        }
+        "text" => nums.iter().try_for_each(|n| writeln!(output, "{}", n)),
+        "csv" => {
+            let line = nums.iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+            writeln!(output, "{}", line)
+        }
+        _ => panic!("Unknown format {}", format),
+    };
+
+    result
+        .and_then(|_| output.flush())
+        .expect("Should be able to write output");
+}
@@ -3,6 +3,10 @@ fn main() {
 
     // map control bytes to encoded num lengths
     println!("#[cfg_attr(rustfmt, rustfmt_skip)]");
+    println!(concat!(
+        "#[cfg(all(any(feature = \"encode\", feature = \"decode\"), ",
+        "not(feature = \"compact-tables\")))]"
+    ));
     println!("pub const DECODE_LENGTH_PER_NUM_TABLE: &'static [(u8, u8, u8, u8); 256] = &[");
 
     // work around lack of closed ranges until that hits stable rust
@@ -33,6 +37,7 @@ fn main() {
     // SSSE3 tables
 
     println!("#[cfg_attr(rustfmt, rustfmt_skip)]");
+    println!("#[cfg(not(feature = \"compact-tables\"))]");
     println!("pub const DECODE_LENGTH_PER_QUAD_TABLE: &'static [u8; 256] = &[");
 
     for b in 0..256 {
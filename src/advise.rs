@@ -0,0 +1,219 @@
+use {encoded_num_len, encoded_shape};
+use frame::FrameFormat;
+
+/// How many evenly spaced runs of numbers `advise()` samples from long input.
+const SAMPLE_RUNS: usize = 16;
+/// How many consecutive numbers are in each sampled run, so that transforms that depend on the
+/// previous number see realistic neighbors.
+const SAMPLE_RUN_LEN: usize = 256;
+
+/// A way of transforming numbers before encoding them that `advise()` tries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// The numbers as they are.
+    Plain,
+    /// Each number's difference from the previous one, as with `FrameFormat.delta`.
+    Delta,
+    /// Differences zigzag encoded, as with `FrameFormat.delta` and `FrameFormat.zigzag`, for
+    /// numbers that are close to their neighbors but not sorted.
+    DeltaZigzag,
+    /// Each number's difference from the smallest number, as with `Affine::new(min, 1)`.
+    FrameOfReference,
+}
+
+/// The result of `advise()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodecAdvice {
+    /// How many numbers were sampled to make the estimates.
+    pub sampled: usize,
+    /// Every transform tried, with the estimated length of the whole input encoded after that
+    /// transform, smallest first.
+    pub estimates: Vec<(Transform, usize)>,
+}
+
+impl CodecAdvice {
+    /// The transform with the smallest estimated encoded length.
+    pub fn best(&self) -> Transform {
+        self.estimates[0].0
+    }
+}
+
+/// Estimate how long `nums` would be encoded after each `Transform`, to help choose how to store
+/// a column without running the experiment by hand.
+///
+/// Long input is sampled: runs of consecutive numbers are taken from evenly spaced places, and
+/// their encoded lengths are scaled up to the length of the whole input. Short input is measured
+/// exactly. Lengths are computed from each number's encoded length, without encoding anything.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{advise, Transform};
+///
+/// let timestamps: Vec<u32> = (0..100_000).map(|i| 1_500_000_000 + i * 30).collect();
+/// let advice = advise(&timestamps);
+///
+/// assert_eq!(Transform::Delta, advice.best());
+/// ```
+pub fn advise(nums: &[u32]) -> CodecAdvice {
+    let min = nums.iter().cloned().min().unwrap_or(0);
+
+    let runs: Vec<(usize, usize)> = if nums.len() <= SAMPLE_RUNS * SAMPLE_RUN_LEN {
+        vec![(0, nums.len())]
+    } else {
+        (0..SAMPLE_RUNS)
+            .map(|i| {
+                let start = i * (nums.len() - SAMPLE_RUN_LEN) / (SAMPLE_RUNS - 1);
+                (start, start + SAMPLE_RUN_LEN)
+            })
+            .collect()
+    };
+    let sampled = runs.iter().map(|&(start, end)| end - start).sum();
+
+    let transforms = [
+        Transform::Plain,
+        Transform::Delta,
+        Transform::DeltaZigzag,
+        Transform::FrameOfReference,
+    ];
+    let mut estimates: Vec<(Transform, usize)> = transforms
+        .iter()
+        .map(|&transform| {
+            let sample_len: usize = runs.iter()
+                .map(|&(start, end)| transformed_len(nums, start, end, transform, min))
+                .sum();
+            (transform, estimate(sample_len, sampled, nums.len()))
+        })
+        .collect();
+    // stable, so ties keep the simpler transform first
+    estimates.sort_by_key(|&(_, len)| len);
+
+    CodecAdvice { sampled, estimates }
+}
+
+/// The total encoded length of the numbers in `nums[start..end]` after `transform`, not counting
+/// control bytes.
+fn transformed_len(
+    nums: &[u32],
+    start: usize,
+    end: usize,
+    transform: Transform,
+    min: u32,
+) -> usize {
+    let format = match transform {
+        Transform::Plain => {
+            return nums[start..end].iter().map(|&n| encoded_num_len(n)).sum();
+        }
+        Transform::FrameOfReference => {
+            return nums[start..end]
+                .iter()
+                .map(|&n| encoded_num_len(n - min))
+                .sum();
+        }
+        Transform::Delta => FrameFormat {
+            delta: true,
+            ..FrameFormat::default()
+        },
+        Transform::DeltaZigzag => FrameFormat {
+            delta: true,
+            zigzag: true,
+            ..FrameFormat::default()
+        },
+    };
+
+    // include the number before the run, if any, so the first difference is the real one
+    let context_start = if start > 0 { start - 1 } else { start };
+    let mut transformed = nums[context_start..end].to_vec();
+    format.transform(&mut transformed);
+
+    transformed[(start - context_start)..]
+        .iter()
+        .map(|&n| encoded_num_len(n))
+        .sum()
+}
+
+/// Scale the encoded length of a sample of `sampled` numbers up to `count` numbers, and add their
+/// control bytes.
+fn estimate(sample_len: usize, sampled: usize, count: usize) -> usize {
+    let data_len = if sampled == 0 {
+        0
+    } else {
+        (sample_len as f64 * count as f64 / sampled as f64).round() as usize
+    };
+
+    encoded_shape(count).control_bytes_len + data_len
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn short_input_is_measured_exactly() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 1000))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let advice = advise(&nums);
+            assert_eq!(nums.len(), advice.sampled);
+
+            let plain = advice
+                .estimates
+                .iter()
+                .find(|&&(t, _)| t == Transform::Plain)
+                .unwrap()
+                .1;
+            let mut encoded = vec![0; nums.len() * 5];
+            assert_eq!(encode::<Scalar>(&nums, &mut encoded), plain);
+        }
+    }
+
+    #[test]
+    fn picks_the_transform_that_suits_the_data() {
+        let mut rng = rand::weak_rng();
+
+        let sorted: Vec<u32> = (0..50_000).map(|i| i * 1000).collect();
+        assert_eq!(Transform::Delta, advise(&sorted).best());
+
+        let jittery: Vec<u32> = (0..50_000)
+            .map(|i| 1_000_000 + i * 10 - rng.gen_range(0, 30))
+            .collect();
+        assert_eq!(Transform::DeltaZigzag, advise(&jittery).best());
+
+        let offset: Vec<u32> = (0..50_000)
+            .map(|_| 3_000_000_000 + rng.gen_range(0, 200))
+            .collect();
+        assert_eq!(Transform::FrameOfReference, advise(&offset).best());
+
+        let small: Vec<u32> = (0..50_000).map(|_| rng.gen_range(0, 200)).collect();
+        assert_eq!(Transform::Plain, advise(&small).best());
+    }
+
+    #[test]
+    fn long_input_estimate_is_close() {
+        let mut rng = rand::weak_rng();
+        let nums: Vec<u32> = (0..100_000)
+            .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+            .collect();
+
+        let advice = advise(&nums);
+        assert_eq!(SAMPLE_RUNS * SAMPLE_RUN_LEN, advice.sampled);
+
+        let plain = advice
+            .estimates
+            .iter()
+            .find(|&&(t, _)| t == Transform::Plain)
+            .unwrap()
+            .1;
+        let mut encoded = vec![0; nums.len() * 5];
+        let actual = encode::<Scalar>(&nums, &mut encoded);
+        assert!((plain as f64 - actual as f64).abs() < actual as f64 * 0.05);
+    }
+}
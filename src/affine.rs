@@ -0,0 +1,180 @@
+use std::cmp;
+
+use {DecodeCursor, Decoder, Encoder};
+use encode::chunked::ChunkedEncoder;
+
+/// How many numbers to transform at a time, in a buffer on the stack.
+const BATCH_LEN: usize = 64;
+
+/// Maps numbers to smaller ones with `(x - offset) / scale` while encoding, and back with
+/// `x * scale + offset` while decoding, so that e.g. millisecond timestamps that are all whole
+/// seconds after some epoch take fewer bytes, without a separate pass over the numbers to convert
+/// them.
+///
+/// The transform is only lossless for numbers that are at least `offset` and are `offset` plus a
+/// multiple of `scale`, so encoding anything else fails rather than rounding.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{Affine, Scalar};
+///
+/// // milliseconds since some epoch, but only ever whole seconds after 1_500_000_000 ms
+/// let timestamps: Vec<u32> = (0..100).map(|i| 1_500_000_000 + i * 7_000).collect();
+/// let seconds = Affine::new(1_500_000_000, 1_000);
+///
+/// let mut encoded = vec![0; timestamps.len() * 5];
+/// let encoded_len = seconds.encode::<Scalar>(&timestamps, &mut encoded).unwrap();
+/// // each number fits in 2 bytes rather than 4
+/// assert!(encoded_len < 25 + 2 * 100);
+///
+/// let mut decoded = vec![0; timestamps.len()];
+/// assert_eq!(
+///     Some(encoded_len),
+///     seconds.decode::<Scalar>(&encoded, timestamps.len(), &mut decoded)
+/// );
+/// assert_eq!(timestamps, decoded);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Affine {
+    offset: u32,
+    scale: u32,
+}
+
+impl Affine {
+    /// Subtract `offset`, then divide by `scale`, which must be non-zero.
+    pub fn new(offset: u32, scale: u32) -> Affine {
+        assert!(scale > 0, "Scale must be non-zero");
+
+        Affine { offset, scale }
+    }
+
+    /// `(num - offset) / scale`, or `None` if `num` is less than `offset` or isn't `offset` plus a
+    /// multiple of `scale`.
+    pub fn forward(&self, num: u32) -> Option<u32> {
+        let shifted = num.checked_sub(self.offset)?;
+        if shifted % self.scale != 0 {
+            return None;
+        }
+
+        Some(shifted / self.scale)
+    }
+
+    /// `num * scale + offset`, or `None` if that doesn't fit in a `u32`.
+    pub fn inverse(&self, num: u32) -> Option<u32> {
+        num.checked_mul(self.scale)?.checked_add(self.offset)
+    }
+
+    /// Transform `input` with `forward()` and encode it with `E` into `output`, which should be
+    /// sized as for `encode()`.
+    ///
+    /// Returns the number of bytes written, or `None` if any number can't be transformed, in
+    /// which case `output` may have been partly written.
+    pub fn encode<E: Encoder>(&self, input: &[u32], output: &mut [u8]) -> Option<usize> {
+        let mut encoder = ChunkedEncoder::new(output, input.len());
+        let mut batch = [0_u32; BATCH_LEN];
+
+        for chunk in input.chunks(BATCH_LEN) {
+            for (transformed, &num) in batch.iter_mut().zip(chunk.iter()) {
+                *transformed = self.forward(num)?;
+            }
+            encoder.push::<E>(&batch[0..chunk.len()]);
+        }
+
+        Some(encoder.finish())
+    }
+
+    /// Decode `count` numbers from `input` with `D` into `output`, then transform them with
+    /// `inverse()`.
+    ///
+    /// Returns the number of bytes read, or `None` if any decoded number can't be transformed
+    /// back, which can only happen if `input` wasn't encoded with the same `Affine`. In that case,
+    /// `output` holds some mix of transformed and untransformed numbers.
+    pub fn decode<D: Decoder>(
+        &self,
+        input: &[u8],
+        count: usize,
+        output: &mut [u32],
+    ) -> Option<usize> {
+        let mut cursor = DecodeCursor::new(input, count);
+        let mut nums_decoded = 0;
+
+        // decode in batches so the numbers are still in cache when they're transformed
+        while cursor.has_more() {
+            let batch_end = cmp::min(nums_decoded + BATCH_LEN, count);
+            let batch = &mut output[nums_decoded..batch_end];
+            let batch_decoded = cursor.decode_slice::<D>(batch);
+
+            for num in batch[0..batch_decoded].iter_mut() {
+                *num = self.inverse(*num)?;
+            }
+            nums_decoded += batch_decoded;
+        }
+
+        Some(cursor.input_consumed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {decode, encode, Scalar};
+
+    #[test]
+    fn random_roundtrip() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let offset = rng.gen::<u32>() >> rng.gen_range(0, 32);
+            let scale = (rng.gen::<u32>() >> rng.gen_range(0, 32)) | 1;
+            let affine = Affine::new(offset, scale);
+            let max_transformed = (u32::max_value() - offset) / scale;
+            let transformed: Vec<u32> = (0..rng.gen_range(0, 300))
+                .map(|_| rng.gen_range(0, max_transformed as u64 + 1) as u32)
+                .collect();
+            let nums: Vec<u32> = transformed.iter().map(|&n| n * scale + offset).collect();
+
+            let mut encoded = vec![0; nums.len() * 5];
+            let encoded_len = affine.encode::<Scalar>(&nums, &mut encoded).unwrap();
+
+            // the encoded numbers are the transformed ones
+            let mut expected = vec![0; nums.len() * 5];
+            assert_eq!(encoded_len, encode::<Scalar>(&transformed, &mut expected));
+            assert_eq!(expected, encoded);
+
+            let mut decoded = vec![0; nums.len()];
+            assert_eq!(
+                Some(encoded_len),
+                affine.decode::<Scalar>(&encoded, nums.len(), &mut decoded)
+            );
+            assert_eq!(nums, decoded);
+        }
+    }
+
+    #[test]
+    fn untransformable_numbers_are_rejected() {
+        let affine = Affine::new(100, 10);
+        let mut encoded = [0; 20];
+
+        assert_eq!(None, affine.encode::<Scalar>(&[110, 90], &mut encoded));
+        assert_eq!(None, affine.encode::<Scalar>(&[110, 115], &mut encoded));
+        assert_eq!(Some(4), affine.encode::<Scalar>(&[110, 100, 200], &mut encoded));
+
+        // too big to transform back
+        let encoded_len = encode::<Scalar>(&[u32::max_value() / 10], &mut encoded);
+        let mut decoded = [0; 1];
+        assert_eq!(None, affine.decode::<Scalar>(&encoded[0..encoded_len], 1, &mut decoded));
+        decode::<Scalar>(&encoded[0..encoded_len], 1, &mut decoded);
+        assert_eq!(u32::max_value() / 10, decoded[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Scale must be non-zero")]
+    fn zero_scale_panics() {
+        Affine::new(0, 0);
+    }
+}
@@ -0,0 +1,345 @@
+use std::cmp;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {encode, DecodeCursor, Encoder};
+
+/// How many quads to encode into scratch space at a time before copying them into the segment.
+const CHUNK_QUADS: usize = 16;
+
+/// A fixed-capacity buffer of encoded numbers that one writer appends to in place while any number
+/// of `AppendSegmentReader`s, on any thread, decode what has been published so far, without locks
+/// or copying: the core of a low-latency telemetry buffer.
+///
+/// The buffer is allocated up front with room for the control bytes and the longest possible
+/// encoding of `capacity` numbers, so appending never moves the numbers already written.
+///
+/// Only complete quads are published. A control byte describes four numbers, so publishing a
+/// partial quad would mean rewriting its control byte while readers might be reading it. Up to 3
+/// appended numbers are therefore held back until the rest of their quad is appended.
+///
+/// # Memory ordering
+///
+/// The writer encodes each append's quads into bytes past the published end, which no reader
+/// looks at, and then publishes the new end with release stores. Readers load the end with
+/// acquire loads when they create a cursor, and only ever look at bytes before it. So:
+///
+/// - everything the writer wrote before publishing happens-before a reader's cursor decodes it,
+/// - published bytes are never written again, so a cursor never sees them change,
+/// - a cursor is bounded by the count published when it was created, and readers see the
+///   published count only grow.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use stream_vbyte::{AppendSegment, Scalar};
+///
+/// let mut segment = AppendSegment::with_capacity(1000);
+/// let reader = segment.reader();
+///
+/// let tailer = thread::spawn(move || {
+///     let mut decoded = vec![0; 1000];
+///     while reader.len() < 1000 {
+///         thread::yield_now();
+///     }
+///     reader.cursor().decode_slice::<Scalar>(&mut decoded);
+///     decoded
+/// });
+///
+/// for batch in 0..10 {
+///     let nums: Vec<u32> = (batch * 100..(batch + 1) * 100).collect();
+///     assert_eq!(100, segment.append::<Scalar>(&nums));
+/// }
+///
+/// assert_eq!((0..1000).collect::<Vec<u32>>(), tailer.join().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct AppendSegment {
+    buffer: Arc<Buffer>,
+    /// Quads written, all of which have been published.
+    quads: usize,
+    /// Length of the encoded numbers written, not counting control bytes.
+    nums_len: usize,
+    pending: [u32; 4],
+    pending_len: usize,
+}
+
+impl AppendSegment {
+    /// Create an empty segment with room for at least `capacity` numbers.
+    pub fn with_capacity(capacity: usize) -> AppendSegment {
+        let capacity_quads = (capacity + 3) / 4;
+        let storage = vec![0_u8; capacity_quads * 17].into_boxed_slice();
+        let storage_len = storage.len();
+
+        AppendSegment {
+            buffer: Arc::new(Buffer {
+                storage: Box::into_raw(storage) as *mut u8,
+                storage_len,
+                capacity_quads,
+                published_count: AtomicUsize::new(0),
+                published_nums_len: AtomicUsize::new(0),
+            }),
+            quads: 0,
+            nums_len: 0,
+            pending: [0; 4],
+            pending_len: 0,
+        }
+    }
+
+    /// Append as many of `nums` as there's room for, encoding them with `E`, and publish every
+    /// quad they complete.
+    ///
+    /// Returns the number of numbers appended, which is less than `nums.len()` only if the
+    /// segment is full.
+    pub fn append<E: Encoder>(&mut self, nums: &[u32]) -> usize {
+        let appended = cmp::min(nums.len(), self.capacity() - self.len());
+        let mut rest = &nums[0..appended];
+
+        if self.pending_len > 0 {
+            let fill = cmp::min(4 - self.pending_len, rest.len());
+            self.pending[self.pending_len..(self.pending_len + fill)]
+                .copy_from_slice(&rest[0..fill]);
+            self.pending_len += fill;
+            rest = &rest[fill..];
+
+            if self.pending_len == 4 {
+                let quad = self.pending;
+                self.write_quads::<E>(&quad);
+                self.pending_len = 0;
+            }
+        }
+
+        let complete_nums = rest.len() / 4 * 4;
+        for chunk in rest[0..complete_nums].chunks(CHUNK_QUADS * 4) {
+            self.write_quads::<E>(chunk);
+        }
+
+        // if the pending quad wasn't completed, there's nothing left here
+        let leftover = &rest[complete_nums..];
+        self.pending[self.pending_len..(self.pending_len + leftover.len())]
+            .copy_from_slice(leftover);
+        self.pending_len += leftover.len();
+
+        self.publish();
+
+        appended
+    }
+
+    /// The number of numbers appended, including any not yet published.
+    pub fn len(&self) -> usize {
+        self.quads * 4 + self.pending_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of numbers the segment has room for.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity_quads * 4
+    }
+
+    /// Returns a reader of the numbers published so far and from now on.
+    pub fn reader(&self) -> AppendSegmentReader {
+        AppendSegmentReader {
+            buffer: self.buffer.clone(),
+        }
+    }
+
+    /// Encode `nums`, a whole number of quads and at most `CHUNK_QUADS` of them, into the
+    /// unpublished part of the buffer.
+    fn write_quads<E: Encoder>(&mut self, nums: &[u32]) {
+        debug_assert_eq!(0, nums.len() % 4);
+
+        let mut scratch = [0_u8; CHUNK_QUADS * 17];
+        let encoded_len = encode::<E>(nums, &mut scratch);
+        let quads = nums.len() / 4;
+        debug_assert!(self.quads + quads <= self.buffer.capacity_quads);
+
+        // Safe because these bytes are past the published end, so no reader has a slice over
+        // them, and the capacity check in `append()` keeps them inside the buffer.
+        unsafe {
+            let control_bytes = self.buffer.storage.add(self.quads);
+            control_bytes.copy_from_nonoverlapping(scratch.as_ptr(), quads);
+
+            let encoded_nums = self.buffer
+                .storage
+                .add(self.buffer.capacity_quads + self.nums_len);
+            encoded_nums.copy_from_nonoverlapping(scratch[quads..].as_ptr(), encoded_len - quads);
+        }
+
+        self.quads += quads;
+        self.nums_len += encoded_len - quads;
+    }
+
+    fn publish(&self) {
+        // the length first, so that any reader that sees the new count also sees a length at
+        // least long enough for it
+        self.buffer
+            .published_nums_len
+            .store(self.nums_len, Ordering::Release);
+        self.buffer
+            .published_count
+            .store(self.quads * 4, Ordering::Release);
+    }
+}
+
+/// A handle for decoding the numbers published by an `AppendSegment`, which can be cloned and
+/// sent to other threads.
+///
+/// See `AppendSegment` for what readers are guaranteed to see.
+#[derive(Debug, Clone)]
+pub struct AppendSegmentReader {
+    buffer: Arc<Buffer>,
+}
+
+impl AppendSegmentReader {
+    /// The number of numbers published so far, always a multiple of 4.
+    pub fn len(&self) -> usize {
+        self.buffer.published_count.load(Ordering::Acquire)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a cursor over the numbers published so far.
+    ///
+    /// Numbers published after the cursor is created aren't decoded by it; create another cursor
+    /// and `skip()` to where this one left off to continue.
+    pub fn cursor<'a>(&'a self) -> DecodeCursor<'a> {
+        let count = self.buffer.published_count.load(Ordering::Acquire);
+        // at least as long as needed for `count`, since it was published first
+        let nums_len = self.buffer.published_nums_len.load(Ordering::Acquire);
+
+        // Safe because the writer finished with these bytes before publishing the loaded values,
+        // and never writes to them again.
+        let (control_bytes, encoded_nums) = unsafe {
+            (
+                slice::from_raw_parts(self.buffer.storage, count / 4),
+                slice::from_raw_parts(
+                    self.buffer.storage.add(self.buffer.capacity_quads),
+                    nums_len,
+                ),
+            )
+        };
+
+        DecodeCursor::from_parts(control_bytes, encoded_nums, count)
+    }
+}
+
+/// The storage shared by an `AppendSegment` and its readers: room for a control byte and 16
+/// bytes of encoded numbers for each quad, with all the control bytes first.
+#[derive(Debug)]
+struct Buffer {
+    storage: *mut u8,
+    storage_len: usize,
+    capacity_quads: usize,
+    published_count: AtomicUsize,
+    published_nums_len: AtomicUsize,
+}
+
+// Safe because the only writer writes to bytes that no reader is reading, as described on
+// `AppendSegment`.
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        // Safe because the pointer and length came from the boxed slice in `with_capacity()`.
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                self.storage,
+                self.storage_len,
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::thread;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn publishes_complete_quads() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let capacity = rng.gen_range(0, 500);
+            let mut segment = AppendSegment::with_capacity(capacity);
+            let reader = segment.reader();
+            let mut appended = Vec::new();
+
+            for _ in 0..rng.gen_range(0, 30) {
+                let nums: Vec<u32> = (0..rng.gen_range(0, 40))
+                    .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                    .collect();
+                let len = segment.append::<Scalar>(&nums);
+                assert_eq!(cmp::min(nums.len(), segment.capacity() - appended.len()), len);
+                appended.extend_from_slice(&nums[0..len]);
+                assert_eq!(appended.len(), segment.len());
+
+                let published = appended.len() / 4 * 4;
+                assert_eq!(published, reader.len());
+                let mut decoded = vec![0; published];
+                assert_eq!(
+                    published,
+                    reader.cursor().decode_slice::<Scalar>(&mut decoded)
+                );
+                assert_eq!(&appended[0..published], &decoded[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn readers_see_published_prefix_while_appending() {
+        const TOTAL: usize = 200_000;
+
+        let mut segment = AppendSegment::with_capacity(TOTAL);
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = segment.reader();
+                thread::spawn(move || {
+                    let mut decoded = vec![0; TOTAL];
+                    let mut last_len = 0;
+
+                    while last_len < TOTAL {
+                        let mut cursor = reader.cursor();
+                        let len = cursor.decode_slice::<Scalar>(&mut decoded);
+                        assert!(len >= last_len);
+                        assert_eq!(0, len % 4);
+                        for i in last_len..len {
+                            assert_eq!(i as u32 * 7919, decoded[i]);
+                        }
+                        last_len = len;
+                    }
+                })
+            })
+            .collect();
+
+        let mut rng = rand::weak_rng();
+        let mut next = 0;
+        while next < TOTAL {
+            let nums: Vec<u32> = (next..cmp::min(next + rng.gen_range(1, 1000), TOTAL))
+                .map(|i| i as u32 * 7919)
+                .collect();
+            assert_eq!(nums.len(), segment.append::<Scalar>(&nums));
+            next += nums.len();
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Decoding with an implementation chosen at runtime, for applications that ship one binary to
+//! machines with different CPUs.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {available_codecs, decode, CodecInfo, CodecKind, Scalar};
+
+/// The decoder `decode_auto()` uses, as an index into `DECODERS` plus one, or zero if none has
+/// been chosen yet.
+static GLOBAL_DECODER: AtomicUsize = AtomicUsize::new(0);
+
+type DecodeFn = fn(&[u8], usize, &mut [u32]) -> usize;
+
+/// Every decoder compiled into this build, fastest first, so the first one the CPU supports is
+/// the default.
+const DECODERS: &[(&str, DecodeFn)] = &[
+    #[cfg(feature = "x86_ssse3")]
+    ("x86::Ssse3", decode::<::x86::Ssse3>),
+    ("Scalar", decode::<Scalar>),
+];
+
+/// Set the decoders `decode_auto()` may use, in order of preference, by their `CodecInfo` names,
+/// e.g. `&["x86::Avx2", "x86::Ssse3", "Scalar"]`.
+///
+/// The first decoder in `chain` that is compiled into this build and supported by the current CPU
+/// is used from then on. Names of decoders that aren't available are skipped, so the same chain
+/// can be used whatever `feature`s this crate was built with, and leaving a decoder out of the
+/// chain ensures it never runs.
+///
+/// This is meant to be called once at startup, before any decoding.
+///
+/// Returns the decoder that will be used, or `None` if no decoder in `chain` can be used, in which
+/// case the previous setting is kept.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{decode_auto, encode, set_global_decoder_chain, Scalar};
+///
+/// let chosen = set_global_decoder_chain(&["x86::Ssse3", "Scalar"]).unwrap();
+/// println!("Decoding with {}", chosen.name);
+///
+/// let nums: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+///
+/// let mut decoded = vec![0; nums.len()];
+/// assert_eq!(encoded_len, decode_auto(&encoded, nums.len(), &mut decoded));
+/// assert_eq!(nums, decoded);
+/// ```
+pub fn set_global_decoder_chain(chain: &[&str]) -> Option<&'static CodecInfo> {
+    let index = chain.iter().filter_map(|&name| usable_decoder(name)).next()?;
+    GLOBAL_DECODER.store(index + 1, Ordering::Relaxed);
+
+    Some(codec_info(DECODERS[index].0))
+}
+
+/// The decoder `decode_auto()` currently uses: the one chosen by `set_global_decoder_chain()`, or
+/// if that hasn't been called, the fastest decoder available and supported by the current CPU.
+pub fn global_decoder() -> &'static CodecInfo {
+    codec_info(DECODERS[global_decoder_index()].0)
+}
+
+/// Decode `count` numbers from `input` into `output` with `global_decoder()`.
+///
+/// Returns the number of bytes read, as with `decode()`.
+pub fn decode_auto(input: &[u8], count: usize, output: &mut [u32]) -> usize {
+    (DECODERS[global_decoder_index()].1)(input, count, output)
+}
+
+/// The index in `DECODERS` of the global decoder, choosing the default the first time it's needed
+/// if no chain has been set.
+#[inline]
+fn global_decoder_index() -> usize {
+    match GLOBAL_DECODER.load(Ordering::Relaxed) {
+        0 => {
+            let index = (0..DECODERS.len())
+                .find(|&i| codec_info(DECODERS[i].0).is_supported())
+                .expect("Scalar is always usable");
+            // a chain set in the meantime takes precedence
+            match GLOBAL_DECODER.compare_exchange(
+                0,
+                index + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => index,
+                Err(i) => i - 1,
+            }
+        }
+        i => i - 1,
+    }
+}
+
+/// The index in `DECODERS` of the decoder named `name`, if it's supported.
+fn usable_decoder(name: &str) -> Option<usize> {
+    DECODERS
+        .iter()
+        .position(|&(n, _)| n == name)
+        .filter(|_| codec_info(name).is_supported())
+}
+
+fn codec_info(name: &str) -> &'static CodecInfo {
+    available_codecs()
+        .iter()
+        .find(|c| c.name == name && c.kind == CodecKind::Decoder)
+        .expect("Every decoder is in available_codecs()")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {encode, Scalar};
+
+    // these all touch the global setting, so they're in one test to avoid racing each other
+    #[test]
+    fn chain_selects_first_usable_decoder() {
+        assert!(global_decoder().is_supported());
+
+        assert_eq!(None, set_global_decoder_chain(&[]));
+        assert_eq!(None, set_global_decoder_chain(&["x86::Avx512", "x86::Sse41"]));
+
+        let chosen = set_global_decoder_chain(&["x86::Avx512", "Scalar", "x86::Ssse3"]).unwrap();
+        assert_eq!("Scalar", chosen.name);
+        assert_eq!(chosen, global_decoder());
+
+        // an unusable chain leaves the previous choice in place
+        assert_eq!(None, set_global_decoder_chain(&["Nonexistent"]));
+        assert_eq!("Scalar", global_decoder().name);
+
+        let nums: Vec<u32> = (0..1001).map(|i| i * 7919).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let mut decoded = vec![0; nums.len()];
+        assert_eq!(encoded_len, decode_auto(&encoded, nums.len(), &mut decoded));
+        assert_eq!(nums, decoded);
+    }
+}
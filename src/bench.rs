@@ -0,0 +1,138 @@
+//! Non-generic entry points into each codec, so that benchmark harnesses that run on stable Rust
+//! (like criterion) can measure the same code paths as the nightly-only `cargo bench` suite.
+//!
+//! Codecs are selected at runtime with a `CodecInfo` from `available_codecs()`, so a benchmark can
+//! iterate over whatever this build supports.
+
+use {available_codecs, decode, encode, CodecInfo, CodecKind, DecodeCursor, Scalar};
+
+/// Encode `input` into `output` with the encoder described by `codec`.
+///
+/// Returns the number of bytes written, as with `encode()`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{available_codecs, bench_decode, bench_encode, CodecKind};
+///
+/// let nums: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let mut decoded = vec![0; nums.len()];
+///
+/// for codec in available_codecs().iter().filter(|c| c.is_supported()) {
+///     // in a real benchmark, these would be inside e.g. criterion's `Bencher::iter()`
+///     match codec.kind {
+///         CodecKind::Encoder => {
+///             bench_encode(codec, &nums, &mut encoded);
+///         }
+///         CodecKind::Decoder => {
+///             bench_decode(codec, &encoded, nums.len(), &mut decoded);
+///             assert_eq!(nums, decoded);
+///         }
+///     }
+/// }
+/// ```
+#[inline(never)]
+pub fn bench_encode(codec: &CodecInfo, input: &[u32], output: &mut [u8]) -> usize {
+    check_codec(codec, CodecKind::Encoder);
+
+    match codec.name {
+        #[cfg(feature = "x86_sse41")]
+        "x86::Sse41" => encode::<::x86::Sse41>(input, output),
+        "Scalar" => encode::<Scalar>(input, output),
+        _ => panic!("Unknown encoder {}", codec.name),
+    }
+}
+
+/// Decode `count` numbers from `input` into `output` with the decoder described by `codec`.
+///
+/// Returns the number of bytes read, as with `decode()`.
+#[inline(never)]
+pub fn bench_decode(codec: &CodecInfo, input: &[u8], count: usize, output: &mut [u32]) -> usize {
+    check_codec(codec, CodecKind::Decoder);
+
+    match codec.name {
+        #[cfg(feature = "x86_ssse3")]
+        "x86::Ssse3" => decode::<::x86::Ssse3>(input, count, output),
+        "Scalar" => decode::<Scalar>(input, count, output),
+        _ => panic!("Unknown decoder {}", codec.name),
+    }
+}
+
+/// Skip all complete quads of `count` numbers in `input`, which only reads control bytes and so
+/// doesn't depend on the decoder.
+///
+/// Returns the number of bytes skipped.
+#[inline(never)]
+pub fn bench_skip(input: &[u8], count: usize) -> usize {
+    let mut cursor = DecodeCursor::new(input, count);
+    cursor.skip(count / 4 * 4);
+
+    cursor.input_consumed()
+}
+
+fn check_codec(codec: &CodecInfo, kind: CodecKind) {
+    assert_eq!(kind, codec.kind, "Codec {} is not an {:?}", codec.name, kind);
+    assert!(
+        available_codecs().contains(codec),
+        "Codec {} is not available in this build",
+        codec.name
+    );
+    // running SIMD instructions the CPU doesn't have would crash rather than panic
+    assert!(
+        codec.is_supported(),
+        "Codec {} is not supported by this CPU",
+        codec.name
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_supported_codecs_roundtrip() {
+        let nums: Vec<u32> = (0..10_001).map(|i| i * 7919).collect();
+        let mut expected = vec![0; nums.len() * 5];
+        let expected_len = encode::<Scalar>(&nums, &mut expected);
+
+        for codec in available_codecs().iter().filter(|c| c.is_supported()) {
+            match codec.kind {
+                CodecKind::Encoder => {
+                    let mut encoded = vec![0; nums.len() * 5];
+                    assert_eq!(expected_len, bench_encode(codec, &nums, &mut encoded));
+                    assert_eq!(expected, encoded);
+                }
+                CodecKind::Decoder => {
+                    let mut decoded = vec![0; nums.len()];
+                    assert_eq!(
+                        expected_len,
+                        bench_decode(codec, &expected, nums.len(), &mut decoded)
+                    );
+                    assert_eq!(nums, decoded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skip_consumes_complete_quads() {
+        let nums: Vec<u32> = (0..10).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        // 3 control bytes, then 8 1-byte numbers in complete quads
+        assert_eq!(3 + 8, bench_skip(&encoded, nums.len()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Codec Scalar is not an Encoder")]
+    fn decoder_as_encoder_panics() {
+        let decoder = available_codecs()
+            .iter()
+            .find(|c| c.kind == CodecKind::Decoder)
+            .unwrap();
+
+        bench_encode(decoder, &[1, 2, 3], &mut [0; 15]);
+    }
+}
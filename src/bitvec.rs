@@ -0,0 +1,131 @@
+/// A fixed-length vector of bits, used to report which numbers matched a predicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    /// Create a bit vector of `len` bits, all unset.
+    pub fn new(len: usize) -> BitVec {
+        let words = vec![0; (len + 63) / 64];
+
+        BitVec { words, len }
+    }
+
+    /// The number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns whether the bit at `index` is set.
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "Bit index out of range");
+
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Set the bit at `index`.
+    pub fn set(&mut self, index: usize) {
+        assert!(index < self.len, "Bit index out of range");
+
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The underlying words. Bit `i` is bit `i % 64` of word `i / 64`, and any bits past `len()`
+    /// in the last word are unset.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Iterate over the indices of the set bits in increasing order.
+    pub fn ones<'a>(&'a self) -> Ones<'a> {
+        Ones {
+            words: &self.words,
+            word_index: 0,
+            current: self.words.first().cloned().unwrap_or(0),
+        }
+    }
+
+    /// OR `bits` into the vector, shifted so that bit 0 of `bits` lands at `index`. All set bits
+    /// must land in the same word.
+    #[cfg(feature = "decode")]
+    #[inline]
+    pub(crate) fn or_bits(&mut self, index: usize, bits: u64) {
+        self.words[index / 64] |= bits << (index % 64);
+    }
+}
+
+/// Iterator over the indices of set bits in a `BitVec`.
+pub struct Ones<'a> {
+    words: &'a [u64],
+    word_index: usize,
+    current: u64,
+}
+
+impl<'a> Iterator for Ones<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.word_index += 1;
+            if self.word_index >= self.words.len() {
+                return None;
+            }
+            self.current = self.words[self.word_index];
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        // clear lowest set bit
+        self.current &= self.current - 1;
+
+        Some(self.word_index * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_get_count() {
+        let mut bits = BitVec::new(130);
+        assert_eq!(3, bits.words().len());
+
+        for &i in &[0, 63, 64, 129] {
+            bits.set(i);
+        }
+
+        assert!(bits.get(0));
+        assert!(!bits.get(1));
+        assert!(bits.get(63));
+        assert!(bits.get(64));
+        assert!(bits.get(129));
+        assert_eq!(4, bits.count_ones());
+        assert_eq!(vec![0, 63, 64, 129], bits.ones().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn empty() {
+        let bits = BitVec::new(0);
+
+        assert!(bits.is_empty());
+        assert_eq!(0, bits.count_ones());
+        assert_eq!(None, bits.ones().next());
+    }
+
+    #[test]
+    #[should_panic(expected = "Bit index out of range")]
+    fn get_out_of_range_panics() {
+        BitVec::new(10).get(10);
+    }
+}
@@ -0,0 +1,154 @@
+use {DecodeCursor, Decoder};
+
+/// Per-block maximum values of an encoded stream, for skipping blocks that can't contain anything
+/// interesting.
+///
+/// This is the metadata needed for Block-Max WAND style query evaluation: build an index over a
+/// stream of payloads (e.g. scores or term frequencies), then use `next_block_exceeding()` to find
+/// the next block whose maximum payload beats the current threshold and skip straight to it.
+///
+/// Blocks are `block_len` numbers long (the final block may be shorter). `block_len` must be a
+/// multiple of 4 so that every block boundary can be reached with `DecodeCursor.skip()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMaxIndex {
+    block_len: usize,
+    maxes: Vec<u32>,
+}
+
+impl BlockMaxIndex {
+    /// Build an index by decoding `count` numbers from `input`.
+    pub fn build<D: Decoder>(input: &[u8], count: usize, block_len: usize) -> BlockMaxIndex {
+        assert_block_len(block_len);
+
+        let mut cursor = DecodeCursor::new(input, count);
+        let mut block = vec![0; block_len];
+        let mut maxes = Vec::with_capacity((count + block_len - 1) / block_len);
+
+        while cursor.has_more() {
+            let nums_decoded = cursor.decode_slice::<D>(&mut block);
+            maxes.push(block[0..nums_decoded].iter().cloned().max().unwrap_or(0));
+        }
+
+        BlockMaxIndex { block_len, maxes }
+    }
+
+    /// Create an index from previously calculated (perhaps persisted) per-block maxima.
+    pub fn from_maxes(block_len: usize, maxes: Vec<u32>) -> BlockMaxIndex {
+        assert_block_len(block_len);
+
+        BlockMaxIndex { block_len, maxes }
+    }
+
+    /// The number of numbers in each block.
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+
+    /// The maximum of each block.
+    pub fn maxes(&self) -> &[u32] {
+        &self.maxes
+    }
+
+    /// Returns the index of the first block at or after `from_block` whose maximum is greater than
+    /// `threshold`, or `None` if there is no such block.
+    pub fn next_block_exceeding(&self, from_block: usize, threshold: u32) -> Option<usize> {
+        self.maxes
+            .iter()
+            .skip(from_block)
+            .position(|&m| m > threshold)
+            .map(|offset| from_block + offset)
+    }
+
+    /// Skip `cursor`, which must be positioned at the start of `current_block`, to the start of the
+    /// next block (possibly `current_block` itself) whose maximum is greater than `threshold`.
+    ///
+    /// Returns the index of the block the cursor is now positioned at, or `None` if no remaining
+    /// block exceeds `threshold`, in which case the cursor is not moved.
+    pub fn skip_to_block_exceeding(
+        &self,
+        cursor: &mut DecodeCursor,
+        current_block: usize,
+        threshold: u32,
+    ) -> Option<usize> {
+        let block = self.next_block_exceeding(current_block, threshold)?;
+        cursor.skip((block - current_block) * self.block_len);
+
+        Some(block)
+    }
+}
+
+fn assert_block_len(block_len: usize) {
+    assert!(
+        block_len > 0 && block_len % 4 == 0,
+        "Block length must be a non-zero multiple of 4"
+    );
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn build_calculates_max_per_block_including_final_partial_block() {
+        let nums: Vec<u32> = (0..19).map(|i| (i * 7) % 11).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let index = BlockMaxIndex::build::<Scalar>(&encoded, nums.len(), 8);
+
+        assert_eq!(8, index.block_len());
+        let expected: Vec<u32> = nums.chunks(8).map(|c| *c.iter().max().unwrap()).collect();
+        assert_eq!(&expected[..], index.maxes());
+    }
+
+    #[test]
+    fn build_empty_input_has_no_blocks() {
+        let index = BlockMaxIndex::build::<Scalar>(&[], 0, 4);
+
+        assert_eq!(0, index.maxes().len());
+        assert_eq!(None, index.next_block_exceeding(0, 0));
+    }
+
+    #[test]
+    fn next_block_exceeding_finds_first_block_over_threshold() {
+        let index = BlockMaxIndex::from_maxes(4, vec![3, 10, 2, 7, 11]);
+
+        assert_eq!(Some(1), index.next_block_exceeding(0, 5));
+        assert_eq!(Some(1), index.next_block_exceeding(1, 5));
+        assert_eq!(Some(3), index.next_block_exceeding(2, 5));
+        assert_eq!(Some(4), index.next_block_exceeding(0, 10));
+        assert_eq!(None, index.next_block_exceeding(0, 11));
+        assert_eq!(None, index.next_block_exceeding(10, 0));
+    }
+
+    #[test]
+    fn skip_to_block_exceeding_positions_cursor_at_block_start() {
+        // block 2 (nums 8-11) holds the only large numbers
+        let nums: Vec<u32> = (0..14)
+            .map(|i| if (8..12).contains(&i) { 1000 + i } else { i })
+            .collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let index = BlockMaxIndex::build::<Scalar>(&encoded, nums.len(), 4);
+        let mut cursor = DecodeCursor::new(&encoded, nums.len());
+
+        assert_eq!(Some(2), index.skip_to_block_exceeding(&mut cursor, 0, 100));
+
+        let mut decoded = [0; 4];
+        assert_eq!(4, cursor.decode_slice::<Scalar>(&mut decoded));
+        assert_eq!(&nums[8..12], &decoded);
+
+        // nothing else exceeds the threshold, so the cursor stays put
+        assert_eq!(None, index.skip_to_block_exceeding(&mut cursor, 3, 100));
+        assert_eq!(2, cursor.decode_slice::<Scalar>(&mut decoded));
+        assert_eq!(&nums[12..14], &decoded[0..2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Block length must be a non-zero multiple of 4")]
+    fn from_maxes_rejects_unaligned_block_len() {
+        BlockMaxIndex::from_maxes(6, vec![]);
+    }
+}
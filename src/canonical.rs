@@ -0,0 +1,166 @@
+use {decode, encoded_shape, lengths, Decoder};
+
+/// Returns true iff `count` numbers in `input` are encoded canonically, as every `Encoder` in this
+/// crate encodes them.
+///
+/// A canonical encoding uses the fewest bytes possible for each number (so its most significant
+/// encoded byte is non-zero, except for zero itself, which takes one byte), and leaves the bits
+/// for missing numbers in a trailing partial quad's control byte as zero. Since there is exactly
+/// one canonical encoding of any input, equal inputs always produce identical bytes, which is
+/// useful for deduplicating or hashing encoded data.
+///
+/// Returns false if `input` is too short to hold the encoded numbers. Any bytes after the encoded
+/// numbers are ignored.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, is_canonical, Scalar};
+///
+/// let nums = [1, 1000, 1_000_000];
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+/// assert!(is_canonical(&encoded[0..encoded_len], nums.len()));
+///
+/// // 1 encoded in 2 bytes decodes correctly, but isn't canonical
+/// assert!(!is_canonical(&[0x01, 0x01, 0x00], 1));
+/// # }
+/// ```
+pub fn is_canonical(input: &[u8], count: usize) -> bool {
+    let shape = encoded_shape(count);
+    if input.len() < shape.control_bytes_len {
+        return false;
+    }
+
+    let (control_bytes, encoded_nums) = input.split_at(shape.control_bytes_len);
+    let mut offset = 0;
+
+    for (quad_index, &control_byte) in control_bytes.iter().enumerate() {
+        let nums_in_quad = if quad_index < shape.complete_control_bytes_len {
+            4
+        } else {
+            // unused bits must be zero
+            if control_byte >> (shape.leftover_numbers * 2) != 0 {
+                return false;
+            }
+            shape.leftover_numbers
+        };
+
+        let (len0, len1, len2, len3) = lengths::per_num(control_byte);
+        for &len in [len0, len1, len2, len3][0..nums_in_quad].iter() {
+            let len = len as usize;
+            if offset + len > encoded_nums.len() {
+                return false;
+            }
+            // a longer than necessary encoding has a zero most significant byte
+            if len > 1 && encoded_nums[offset + len - 1] == 0 {
+                return false;
+            }
+            offset += len;
+        }
+    }
+
+    true
+}
+
+/// Like `decode()`, but only decodes canonically encoded input (see `is_canonical()`), to reject
+/// the output of buggy encoders at system boundaries rather than accept numbers that would encode
+/// differently if written back out.
+///
+/// Returns the number of bytes read from `input`, or `None` if it isn't canonical or is too short
+/// to hold `count` encoded numbers, in which case nothing is written to `output`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{decode_strict, Scalar};
+///
+/// let mut output = [0; 1];
+/// assert_eq!(Some(2), decode_strict::<Scalar>(&[0x00, 0x07], 1, &mut output));
+/// assert_eq!(7, output[0]);
+///
+/// // 7 encoded in 4 bytes
+/// assert_eq!(None, decode_strict::<Scalar>(&[0x03, 0x07, 0x00, 0x00, 0x00], 1, &mut output));
+/// ```
+pub fn decode_strict<D: Decoder>(input: &[u8], count: usize, output: &mut [u32]) -> Option<usize> {
+    if !is_canonical(input, count) {
+        return None;
+    }
+
+    Some(decode::<D>(input, count, output))
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Encoder, Scalar};
+
+    #[test]
+    fn scalar_encodings_are_canonical() {
+        do_encodings_are_canonical::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_sse41")]
+    #[test]
+    fn sse41_encodings_are_canonical() {
+        do_encodings_are_canonical::<::x86::Sse41>();
+    }
+
+    #[test]
+    fn overlong_number_is_not_canonical() {
+        // 5 in 2 bytes in a complete quad
+        assert!(!is_canonical(&[0x01, 0x05, 0x00, 0x00, 0x00, 0x00], 4));
+        assert!(is_canonical(&[0x00, 0x05, 0x00, 0x00, 0x00], 4));
+    }
+
+    #[test]
+    fn unused_control_bits_are_not_canonical() {
+        assert!(is_canonical(&[0x00, 0x05], 1));
+        // lengths for the missing 2nd number are non-zero
+        assert!(!is_canonical(&[0x04, 0x05, 0x00], 1));
+    }
+
+    #[test]
+    fn truncated_input_is_not_canonical() {
+        assert!(!is_canonical(&[0x03, 0x01, 0x02, 0x03], 1));
+        assert!(!is_canonical(&[], 1));
+        assert!(is_canonical(&[], 0));
+    }
+
+    #[test]
+    fn decode_strict_rejects_overlong_and_truncated() {
+        let mut output = [0xFF; 4];
+        assert_eq!(
+            None,
+            decode_strict::<Scalar>(&[0x01, 0x05, 0x00, 0x00, 0x00, 0x00], 4, &mut output)
+        );
+        assert_eq!(None, decode_strict::<Scalar>(&[0x03, 0x01], 1, &mut output));
+        assert_eq!([0xFF; 4], output);
+
+        assert_eq!(
+            Some(5),
+            decode_strict::<Scalar>(&[0x00, 0x05, 0x00, 0x01, 0x02], 4, &mut output)
+        );
+        assert_eq!([5, 0, 1, 2], output);
+    }
+
+    fn do_encodings_are_canonical<E: Encoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 100);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> (rng.gen_range(0, 4) * 8))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let encoded_len = encode::<E>(&nums, &mut encoded);
+
+            assert!(is_canonical(&encoded[0..encoded_len], count));
+        }
+    }
+}
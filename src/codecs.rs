@@ -0,0 +1,108 @@
+/// Whether a codec is an `Encoder` or a `Decoder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecKind {
+    Encoder,
+    Decoder,
+}
+
+/// Describes an `Encoder` or `Decoder` implementation that was compiled into this build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecInfo {
+    /// The path of the implementing type relative to the crate root, e.g. `"x86::Ssse3"`.
+    pub name: &'static str,
+    pub kind: CodecKind,
+    /// The CPU features the implementation needs, named as for `target_feature`.
+    pub cpu_features: &'static [&'static str],
+}
+
+impl CodecInfo {
+    /// Returns true iff the CPU this is running on has all of the required `cpu_features`.
+    ///
+    /// CPU features are detected at runtime on x86 and x86_64. On other architectures, only
+    /// implementations without any required CPU features are considered supported.
+    pub fn is_supported(&self) -> bool {
+        self.cpu_features.iter().all(|f| cpu_has_feature(f))
+    }
+}
+
+static CODECS: &[CodecInfo] = &[
+    CodecInfo {
+        name: "Scalar",
+        kind: CodecKind::Encoder,
+        cpu_features: &[],
+    },
+    CodecInfo {
+        name: "Scalar",
+        kind: CodecKind::Decoder,
+        cpu_features: &[],
+    },
+    #[cfg(feature = "x86_sse41")]
+    CodecInfo {
+        name: "x86::Sse41",
+        kind: CodecKind::Encoder,
+        cpu_features: &["sse4.1"],
+    },
+    #[cfg(feature = "x86_ssse3")]
+    CodecInfo {
+        name: "x86::Ssse3",
+        kind: CodecKind::Decoder,
+        cpu_features: &["ssse3"],
+    },
+];
+
+/// Returns the codecs enabled by this crate's `feature`s, so applications can report which
+/// implementations are available and which ones the current CPU can run.
+pub fn available_codecs() -> &'static [CodecInfo] {
+    CODECS
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpu_has_feature(feature: &str) -> bool {
+    match feature {
+        "ssse3" => is_x86_feature_detected!("ssse3"),
+        "sse4.1" => is_x86_feature_detected!("sse4.1"),
+        _ => false,
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn cpu_has_feature(_feature: &str) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalar_always_available_and_supported() {
+        for &kind in &[CodecKind::Encoder, CodecKind::Decoder] {
+            let scalar = available_codecs()
+                .iter()
+                .find(|c| c.name == "Scalar" && c.kind == kind)
+                .unwrap();
+
+            assert!(scalar.cpu_features.is_empty());
+            assert!(scalar.is_supported());
+        }
+    }
+
+    #[test]
+    fn simd_codecs_listed_iff_feature_enabled() {
+        let names: Vec<&str> = available_codecs().iter().map(|c| c.name).collect();
+
+        assert_eq!(cfg!(feature = "x86_sse41"), names.contains(&"x86::Sse41"));
+        assert_eq!(cfg!(feature = "x86_ssse3"), names.contains(&"x86::Ssse3"));
+    }
+
+    #[test]
+    fn unknown_cpu_feature_is_unsupported() {
+        let info = CodecInfo {
+            name: "Imaginary",
+            kind: CodecKind::Decoder,
+            cpu_features: &["imaginary"],
+        };
+
+        assert!(!info.is_supported());
+    }
+}
@@ -0,0 +1,738 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::slice;
+use std::sync::Arc;
+#[cfg(not(all(test, loom)))]
+use std::sync::{RwLock, RwLockReadGuard};
+
+#[cfg(all(test, loom))]
+use loom::sync::{RwLock, RwLockReadGuard};
+
+use {encode, Decoder, EncodedSegment, Encoder, SegmentCursor};
+
+/// How many numbers `SnapshotIter` decodes at a time.
+const BATCH_LEN: usize = 64;
+
+/// The segments published and not yet evicted.
+#[derive(Debug, Default)]
+struct Published {
+    segments: VecDeque<EncodedSegment>,
+    /// The number of numbers in `segments`.
+    len: usize,
+    /// How many segments have been evicted, so that cursors can tell which segments are new to
+    /// them.
+    evicted_segments: usize,
+    /// The number of numbers in the evicted segments.
+    evicted_len: usize,
+}
+
+/// The only writer of a growing sequence of encoded numbers that any number of
+/// `ConcurrentSegmentReader`s, on any thread, can read while it grows.
+///
+/// Each `append()` encodes its numbers into a new immutable `EncodedSegment` and then publishes
+/// it. There's only ever one appender: it can't be cloned, and readers can't append.
+///
+/// # Memory ordering
+///
+/// A segment's bytes are all written before it's published, and are never written again. The
+/// list of published segments is behind a lock that `append()` takes to publish and readers take
+/// to look, so everything an `append()` did happens-before anything a reader does after seeing
+/// its segment. Readers therefore:
+///
+/// - see all of an `append()`'s numbers or none of them, never a partly written segment,
+/// - see appends in the order they were made, with no gaps,
+/// - see at least every append that returned before they last looked.
+///
+/// Evicting segments with `evict_front()` only removes them from the list, so cursors that have
+/// already picked them up can still decode them. A cursor that falls so far behind that segments
+/// are evicted before it picks them up skips them, and carries on from the oldest one left.
+///
+/// Readers only hold the lock long enough to clone the new segments' reference counts, and
+/// decode without it, so they don't hold up the appender or each other.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use stream_vbyte::{Scalar, SegmentAppender};
+///
+/// let mut appender = SegmentAppender::new();
+/// let reader = appender.reader();
+///
+/// let tailer = thread::spawn(move || {
+///     let mut cursor = reader.cursor();
+///     let mut buf = [0; 16];
+///     let mut sum = 0;
+///     let mut seen = 0;
+///     while seen < 1000 {
+///         cursor.refresh();
+///         while cursor.has_more() {
+///             let decoded = cursor.decode_slice::<Scalar>(&mut buf);
+///             sum += buf[0..decoded].iter().sum::<u32>();
+///             seen += decoded;
+///         }
+///     }
+///     sum
+/// });
+///
+/// for batch in 0..10 {
+///     let nums: Vec<u32> = (batch * 100..(batch + 1) * 100).collect();
+///     appender.append::<Scalar>(&nums);
+/// }
+///
+/// assert_eq!((0..1000).sum::<u32>(), tailer.join().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct SegmentAppender {
+    published: Arc<RwLock<Published>>,
+}
+
+impl SegmentAppender {
+    /// Create an appender with no numbers.
+    pub fn new() -> SegmentAppender {
+        SegmentAppender {
+            published: Arc::new(RwLock::new(Published::default())),
+        }
+    }
+
+    /// Encode `nums` with `E` and publish them as a new segment.
+    ///
+    /// Appending no numbers does nothing.
+    pub fn append<E: Encoder>(&mut self, nums: &[u32]) {
+        if nums.is_empty() {
+            return;
+        }
+
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<E>(nums, &mut encoded);
+        encoded.truncate(encoded_len);
+
+        self.append_segment(EncodedSegment::new(Arc::from(encoded), nums.len()));
+    }
+
+    /// Publish numbers that are already encoded.
+    ///
+    /// Appending an empty segment does nothing.
+    pub fn append_segment(&mut self, segment: EncodedSegment) {
+        if segment.is_empty() {
+            return;
+        }
+
+        let mut published = self.published
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        published.len += segment.len();
+        published.segments.push_back(segment);
+    }
+
+    /// Remove the oldest `n_segments` segments (or all of them, if there are fewer), for
+    /// retention that keeps only the most recent numbers.
+    ///
+    /// None of the remaining segments are touched, and the memory of the evicted ones is freed
+    /// once no cursor is using them.
+    ///
+    /// Returns the number of numbers evicted.
+    pub fn evict_front(&mut self, n_segments: usize) -> usize {
+        let mut published = self.published
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let n_segments = cmp::min(n_segments, published.segments.len());
+        let evicted_len: usize = published
+            .segments
+            .drain(0..n_segments)
+            .map(|s| s.len())
+            .sum();
+
+        published.len -= evicted_len;
+        published.evicted_segments += n_segments;
+        published.evicted_len += evicted_len;
+
+        evicted_len
+    }
+
+    /// The number of numbers appended and not evicted.
+    pub fn len(&self) -> usize {
+        read(&self.published).len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a reader of the numbers appended so far and from now on.
+    pub fn reader(&self) -> ConcurrentSegmentReader {
+        ConcurrentSegmentReader {
+            published: self.published.clone(),
+        }
+    }
+}
+
+impl Default for SegmentAppender {
+    fn default() -> SegmentAppender {
+        SegmentAppender::new()
+    }
+}
+
+/// A handle for reading the numbers published by a `SegmentAppender`, which can be cloned and
+/// sent to other threads.
+///
+/// See `SegmentAppender` for what readers are guaranteed to see.
+#[derive(Debug, Clone)]
+pub struct ConcurrentSegmentReader {
+    published: Arc<RwLock<Published>>,
+}
+
+impl ConcurrentSegmentReader {
+    /// The number of numbers published and not evicted.
+    pub fn len(&self) -> usize {
+        read(&self.published).len
+    }
+
+    /// The number of numbers evicted so far, which is also the index, counting every number ever
+    /// appended, of the oldest number left.
+    pub fn evicted_len(&self) -> usize {
+        read(&self.published).evicted_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The segments published and not evicted, in the order they were appended.
+    pub fn segments(&self) -> Vec<EncodedSegment> {
+        read(&self.published).segments.iter().cloned().collect()
+    }
+
+    /// Returns a snapshot of the numbers published and not evicted, which stays the same however
+    /// much is appended or evicted while it's in use.
+    pub fn snapshot(&self) -> SegmentSnapshot {
+        let published = read(&self.published);
+
+        SegmentSnapshot {
+            segments: published.segments.iter().cloned().collect(),
+            len: published.len,
+            first_index: published.evicted_len,
+        }
+    }
+
+    /// Returns a cursor at the oldest number not evicted, which can decode the numbers published
+    /// so far.
+    pub fn cursor(&self) -> ConcurrentCursor {
+        let mut cursor = ConcurrentCursor {
+            published: self.published.clone(),
+            queued: VecDeque::new(),
+            segments_seen: 0,
+            current: None,
+        };
+        cursor.refresh();
+
+        cursor
+    }
+}
+
+/// A cursor over the numbers published by a `SegmentAppender`.
+///
+/// A cursor only decodes the numbers published before it was created or last `refresh()`ed, so
+/// that `has_more()` returning false means the cursor has caught up, not that no more numbers
+/// will ever be appended.
+#[derive(Debug)]
+pub struct ConcurrentCursor {
+    published: Arc<RwLock<Published>>,
+    /// Segments picked up but not yet started.
+    queued: VecDeque<EncodedSegment>,
+    /// How many segments, counting evicted ones, have been picked up or skipped.
+    segments_seen: usize,
+    current: Option<SegmentCursor>,
+}
+
+impl ConcurrentCursor {
+    /// Make the segments published since the cursor last looked available to decode, without
+    /// moving the cursor.
+    pub fn refresh(&mut self) {
+        let published = read(&self.published);
+        // any segments evicted before the cursor saw them are skipped
+        let first_new = self.segments_seen.saturating_sub(published.evicted_segments);
+        self.queued
+            .extend(published.segments.iter().skip(first_new).cloned());
+        self.segments_seen = published.evicted_segments + published.segments.len();
+    }
+
+    /// Decode into `output` until it's full or the cursor has caught up, returning how many
+    /// numbers were decoded.
+    ///
+    /// As with `DecodeCursor.decode_slice()`, numbers are decoded a quad at a time except at the
+    /// end of each appended segment, so fewer numbers may be decoded than would fit if `output`
+    /// isn't a multiple of 4 long.
+    pub fn decode_slice<D: Decoder>(&mut self, output: &mut [u32]) -> usize {
+        let mut decoded = 0;
+
+        while decoded < output.len() {
+            if let Some(ref mut current) = self.current {
+                if current.has_more() {
+                    let nums = current.decode_slice::<D>(&mut output[decoded..]);
+                    if nums == 0 {
+                        // not enough room left for the next quad
+                        break;
+                    }
+                    decoded += nums;
+                    continue;
+                }
+            }
+
+            match self.queued.pop_front() {
+                Some(segment) => self.current = Some(segment.cursor()),
+                None => break,
+            }
+        }
+
+        decoded
+    }
+
+    /// Returns true if there are numbers published before the last refresh left to decode.
+    pub fn has_more(&self) -> bool {
+        self.current.as_ref().map(|c| c.has_more()).unwrap_or(false) || !self.queued.is_empty()
+    }
+}
+
+/// The segments of a `SegmentAppender` as they were at one moment, for long-running scans that
+/// need a consistent view while the appender carries on.
+///
+/// A snapshot holds a reference to each segment published when it was created, so it always has
+/// exactly those numbers: it doesn't see later appends, and evicting segments doesn't remove them
+/// from the snapshot, though it does mean their memory isn't freed until the snapshot is dropped.
+/// Creating a snapshot only holds the lock long enough to clone the segments' reference counts.
+///
+/// Use a `ConcurrentCursor` instead to follow the numbers as they're appended.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{Scalar, SegmentAppender};
+///
+/// let mut appender = SegmentAppender::new();
+/// appender.append::<Scalar>(&[1, 2, 3]);
+/// let reader = appender.reader();
+///
+/// let snapshot = reader.snapshot();
+/// appender.append::<Scalar>(&[4, 5]);
+/// appender.evict_front(1);
+///
+/// assert_eq!(vec![1, 2, 3], snapshot.iter::<Scalar>().collect::<Vec<u32>>());
+/// assert_eq!(vec![4, 5], reader.snapshot().iter::<Scalar>().collect::<Vec<u32>>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SegmentSnapshot {
+    segments: Vec<EncodedSegment>,
+    len: usize,
+    first_index: usize,
+}
+
+impl SegmentSnapshot {
+    /// The number of numbers in the snapshot.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The index of the snapshot's first number, counting every number ever appended: the number
+    /// of numbers that had been evicted when the snapshot was created.
+    pub fn first_index(&self) -> usize {
+        self.first_index
+    }
+
+    /// The segments in the snapshot, in the order they were appended.
+    pub fn segments(&self) -> &[EncodedSegment] {
+        &self.segments
+    }
+
+    /// Returns an iterator over the numbers in the snapshot, decoded with `D`.
+    pub fn iter<'a, D: Decoder>(&'a self) -> SnapshotIter<'a, D> {
+        SnapshotIter {
+            segments: self.segments.iter(),
+            current: None,
+            remaining: self.len,
+            buf: [0; BATCH_LEN],
+            buf_pos: 0,
+            buf_len: 0,
+            decoder: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the numbers in a `SegmentSnapshot`.
+pub struct SnapshotIter<'a, D: Decoder> {
+    segments: slice::Iter<'a, EncodedSegment>,
+    current: Option<SegmentCursor>,
+    remaining: usize,
+    buf: [u32; BATCH_LEN],
+    buf_pos: usize,
+    buf_len: usize,
+    decoder: PhantomData<D>,
+}
+
+impl<'a, D: Decoder> Iterator for SnapshotIter<'a, D> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        while self.buf_pos == self.buf_len {
+            if let Some(ref mut current) = self.current {
+                if current.has_more() {
+                    // never 0, since the buffer is a whole number of quads long
+                    self.buf_len = current.decode_slice::<D>(&mut self.buf);
+                    self.buf_pos = 0;
+                    continue;
+                }
+            }
+
+            match self.segments.next() {
+                Some(segment) => self.current = Some(segment.cursor()),
+                None => return None,
+            }
+        }
+
+        let num = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+        self.remaining -= 1;
+
+        Some(num)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Take the read lock.
+///
+/// The lock is only held while pushing to or cloning from the list of segments, which leaves the
+/// list intact even if it panics, so a poisoned lock is safe to use.
+fn read<'a>(published: &'a RwLock<Published>) -> RwLockReadGuard<'a, Published> {
+    published
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    extern crate rand;
+
+    use std::thread;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn cursor_sees_appends_after_refresh() {
+        let mut rng = rand::weak_rng();
+        let mut appender = SegmentAppender::new();
+        let reader = appender.reader();
+        let mut cursor = reader.cursor();
+        let mut expected = Vec::new();
+        let mut decoded = Vec::new();
+
+        for _ in 0..100 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 50))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            appender.append::<Scalar>(&nums);
+            expected.extend_from_slice(&nums);
+            assert_eq!(expected.len(), reader.len());
+
+            if rng.gen() {
+                cursor.refresh();
+            }
+            let mut buf = vec![0; rng.gen_range(1, 20)];
+            while cursor.has_more() {
+                let len = cursor.decode_slice::<Scalar>(&mut buf);
+                if len == 0 {
+                    // too short for a quad
+                    buf = vec![0; 4];
+                }
+                decoded.extend_from_slice(&buf[0..len]);
+            }
+            assert_eq!(&expected[0..decoded.len()], &decoded[..]);
+        }
+
+        cursor.refresh();
+        let mut buf = [0; 64];
+        while cursor.has_more() {
+            let len = cursor.decode_slice::<Scalar>(&mut buf);
+            decoded.extend_from_slice(&buf[0..len]);
+        }
+        assert_eq!(expected, decoded);
+        assert_eq!(
+            expected.len(),
+            reader.segments().iter().map(|s| s.len()).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn evicting_skips_only_segments_not_yet_picked_up() {
+        let mut appender = SegmentAppender::new();
+        let reader = appender.reader();
+        let mut lagging = reader.cursor();
+
+        for segment in 0..10 {
+            let nums: Vec<u32> = (segment * 10..(segment + 1) * 10).collect();
+            appender.append::<Scalar>(&nums);
+        }
+        let mut started = reader.cursor();
+        let mut buf = [0; 100];
+        assert_eq!(10, started.decode_slice::<Scalar>(&mut buf[0..10]));
+
+        assert_eq!(30, appender.evict_front(3));
+        assert_eq!(70, reader.len());
+        assert_eq!(30, reader.evicted_len());
+        assert_eq!(7, reader.segments().len());
+
+        // segments already picked up can still be decoded
+        assert_eq!(90, decode_all(&mut started, &mut buf));
+        assert_eq!((10..100).collect::<Vec<u32>>(), &buf[0..90]);
+
+        lagging.refresh();
+        assert_eq!(70, decode_all(&mut lagging, &mut buf));
+        assert_eq!((30..100).collect::<Vec<u32>>(), &buf[0..70]);
+
+        assert_eq!(70, decode_all(&mut reader.cursor(), &mut buf));
+        assert_eq!((30..100).collect::<Vec<u32>>(), &buf[0..70]);
+
+        assert_eq!(70, appender.evict_front(100));
+        assert!(reader.is_empty());
+        assert_eq!(100, reader.evicted_len());
+        assert!(!reader.cursor().has_more());
+    }
+
+    #[test]
+    fn snapshots_are_unaffected_by_appends_and_evictions() {
+        let mut rng = rand::weak_rng();
+        let mut appender = SegmentAppender::new();
+        let reader = appender.reader();
+        let mut next = 0;
+        let mut snapshots = Vec::new();
+
+        for _ in 0..200 {
+            if rng.gen_range(0, 4) == 0 {
+                appender.evict_front(rng.gen_range(0, 3));
+            } else {
+                let len = rng.gen_range(0, 20);
+                let nums: Vec<u32> = (next..(next + len)).collect();
+                appender.append::<Scalar>(&nums);
+                next += len;
+            }
+
+            let snapshot = reader.snapshot();
+            assert_eq!(next as usize, snapshot.first_index() + snapshot.len());
+            snapshots.push(snapshot);
+        }
+
+        for snapshot in snapshots {
+            let first = snapshot.first_index() as u32;
+            let iter = snapshot.iter::<Scalar>();
+            assert_eq!(snapshot.len(), iter.size_hint().0);
+            assert_eq!(
+                (first..(first + snapshot.len() as u32)).collect::<Vec<u32>>(),
+                iter.collect::<Vec<u32>>()
+            );
+        }
+    }
+
+    #[test]
+    fn snapshots_are_consistent_while_appending_and_evicting() {
+        const TOTAL: u32 = 200_000;
+
+        let mut appender = SegmentAppender::new();
+        let scanners: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = appender.reader();
+                thread::spawn(move || loop {
+                    let snapshot = reader.snapshot();
+                    let mut expected = snapshot.first_index() as u32;
+                    for num in snapshot.iter::<Scalar>() {
+                        assert_eq!(expected, num);
+                        expected += 1;
+                    }
+                    assert_eq!(snapshot.first_index() + snapshot.len(), expected as usize);
+
+                    if expected == TOTAL {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        let mut rng = rand::weak_rng();
+        let mut next = 0;
+        while next < TOTAL {
+            let nums: Vec<u32> = (next..(next + rng.gen_range(1, 1000)).min(TOTAL)).collect();
+            appender.append::<Scalar>(&nums);
+            next += nums.len() as u32;
+            // keep about the last 20 appends
+            if appender.reader().segments().len() > 20 {
+                appender.evict_front(1);
+            }
+        }
+
+        for scanner in scanners {
+            scanner.join().unwrap();
+        }
+    }
+
+    fn decode_all(cursor: &mut ConcurrentCursor, output: &mut [u32]) -> usize {
+        let mut decoded = 0;
+        while cursor.has_more() {
+            decoded += cursor.decode_slice::<Scalar>(&mut output[decoded..]);
+        }
+
+        decoded
+    }
+
+    #[test]
+    fn readers_see_whole_appends_in_order_while_appending() {
+        const TOTAL: u32 = 200_000;
+
+        let mut appender = SegmentAppender::new();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = appender.reader();
+                thread::spawn(move || {
+                    let mut rng = rand::weak_rng();
+                    let mut cursor = reader.cursor();
+                    let mut buf = [0; 128];
+                    let mut next = 0;
+                    let mut last_len = 0;
+
+                    while next < TOTAL {
+                        let len = reader.len();
+                        assert!(len >= last_len);
+                        last_len = len;
+
+                        cursor.refresh();
+                        while cursor.has_more() {
+                            let decoded = cursor
+                                .decode_slice::<Scalar>(&mut buf[0..rng.gen_range(4, 128)]);
+                            for &num in &buf[0..decoded] {
+                                assert_eq!(next, num);
+                                next += 1;
+                            }
+                        }
+                        // appends are whole, and every one of them ends on a multiple of 10
+                        assert_eq!(0, next % 10);
+                    }
+                })
+            })
+            .collect();
+
+        let mut rng = rand::weak_rng();
+        let mut next = 0;
+        while next < TOTAL {
+            let batch_len = rng.gen_range(1, 100) * 10;
+            let nums: Vec<u32> = (next..(next + batch_len).min(TOTAL)).collect();
+            appender.append::<Scalar>(&nums);
+            next += nums.len() as u32;
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
+
+/// Model checking of every interleaving of the appender and its readers, which needs
+/// `RUSTFLAGS="--cfg loom"`. Run just these, as the other tests don't use loom's threads.
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn cursor_sees_whole_appends_in_order() {
+        loom::model(|| {
+            let mut appender = SegmentAppender::new();
+            let reader = appender.reader();
+
+            let appending = thread::spawn(move || {
+                appender.append::<Scalar>(&[1, 2, 3]);
+                appender.append::<Scalar>(&[4, 5]);
+            });
+
+            let mut cursor = reader.cursor();
+            let mut decoded = [0; 8];
+            let decoded_len = cursor.decode_slice::<Scalar>(&mut decoded);
+            assert!([0, 3, 5].contains(&decoded_len));
+            assert_eq!(&[1, 2, 3, 4, 5][0..decoded_len], &decoded[0..decoded_len]);
+
+            appending.join().unwrap();
+
+            cursor.refresh();
+            let rest_len = cursor.decode_slice::<Scalar>(&mut decoded[decoded_len..]);
+            assert_eq!(5, decoded_len + rest_len);
+            assert_eq!(&[1, 2, 3, 4, 5], &decoded[0..5]);
+        });
+    }
+
+    #[test]
+    fn snapshot_is_before_or_after_eviction() {
+        loom::model(|| {
+            let mut appender = SegmentAppender::new();
+            appender.append::<Scalar>(&[1, 2, 3]);
+            appender.append::<Scalar>(&[4, 5]);
+            let reader = appender.reader();
+
+            let evicting = thread::spawn(move || {
+                assert_eq!(3, appender.evict_front(1));
+                appender.append::<Scalar>(&[6]);
+            });
+
+            let snapshot = reader.snapshot();
+            let nums: Vec<u32> = snapshot.iter::<Scalar>().collect();
+            match snapshot.first_index() {
+                0 => assert_eq!(vec![1, 2, 3, 4, 5], nums),
+                3 => assert!(nums == vec![4, 5] || nums == vec![4, 5, 6]),
+                first_index => panic!("Unexpected first index {}", first_index),
+            }
+            assert_eq!(nums.len(), snapshot.len());
+
+            evicting.join().unwrap();
+
+            assert_eq!(3, reader.evicted_len());
+            assert_eq!(vec![4, 5, 6], reader.snapshot().iter::<Scalar>().collect::<Vec<u32>>());
+        });
+    }
+
+    #[test]
+    fn cursor_skips_segments_evicted_before_it_saw_them() {
+        loom::model(|| {
+            let mut appender = SegmentAppender::new();
+            appender.append::<Scalar>(&[1, 2, 3]);
+            let reader = appender.reader();
+
+            let evicting = thread::spawn(move || {
+                appender.evict_front(1);
+                appender.append::<Scalar>(&[4, 5]);
+            });
+
+            let mut cursor = reader.cursor();
+            evicting.join().unwrap();
+            cursor.refresh();
+
+            let mut decoded = [0; 8];
+            let decoded_len = cursor.decode_slice::<Scalar>(&mut decoded);
+            // the first segment is either decoded whole or skipped whole
+            match decoded_len {
+                5 => assert_eq!(&[1, 2, 3, 4, 5], &decoded[0..5]),
+                2 => assert_eq!(&[4, 5], &decoded[0..2]),
+                _ => panic!("Unexpected number of numbers {}", decoded_len),
+            }
+            assert!(!cursor.has_more());
+        });
+    }
+}
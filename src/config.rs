@@ -0,0 +1,691 @@
+//! Encode and decode with optional transforms and framing chosen at runtime, rather than by picking
+//! among separate functions for each combination.
+//!
+//! An `EncoderConfig` writes a sequence of frames (see the `frame` module) of at most
+//! `block_size` numbers each. With `delta`, each number is stored as its difference from the
+//! previous one, which makes sorted numbers much smaller. With `auto_delta`, only the frames whose
+//! numbers are sorted are delta encoded, for input whose shape isn't known in advance. With
+//! `zigzag`, numbers (or differences) are treated as `i32`s and mapped so that small negative
+//! numbers are small too. With `checksum`, each frame is followed by a 4-byte checksum of its
+//! encoded numbers. With `adaptive_block_size`, the encoder tries several block sizes and keeps
+//! whichever encodes smallest. Frames whose numbers all fit in a byte store them a byte each,
+//! without control bytes, unless `one_byte` is turned off.
+//!
+//! Each frame is transformed independently, so frames can be decoded on their own. Frames have a
+//! versioned header that records these options (see the `frame` module), so any `DecoderConfig`,
+//! or `frame::decode_framed()`, can decode them. A `DecoderConfig`'s own options only apply to
+//! frames with the original header, which don't record how they were encoded.
+//!
+//! # Examples
+//!
+//! ```
+//! use stream_vbyte::Scalar;
+//! use stream_vbyte::config::EncoderConfig;
+//!
+//! let config = EncoderConfig::new()
+//!     .delta(true)
+//!     .zigzag(true)
+//!     .block_size(128)
+//!     .checksum(true);
+//!
+//! let timestamps: Vec<u32> = (0..1000).map(|i| 1_500_000_000 + i * 10).collect();
+//! let mut encoded = Vec::new();
+//! config.encode::<Scalar>(&timestamps, &mut encoded);
+//!
+//! let mut decoded = Vec::new();
+//! config
+//!     .decoder_config()
+//!     .decode::<Scalar>(&encoded, &mut decoded)
+//!     .unwrap();
+//! assert_eq!(timestamps, decoded);
+//! ```
+
+use std::error;
+use std::fmt;
+use std::mem;
+
+use {Decoder, EncodeStats, Encoder};
+use stats::{encoded_len_histogram, ratio};
+use frame::{decode_frame, encode_frame_with_format, FrameError, FrameFormat, FrameHeader,
+            HeaderError};
+
+/// The block sizes that `EncoderConfig.adaptive_block_size()` chooses among, smallest first.
+pub const ADAPTIVE_BLOCK_SIZES: [usize; 3] = [128, 256, 512];
+
+/// Options for encoding. See the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderConfig {
+    delta: bool,
+    auto_delta: bool,
+    zigzag: bool,
+    block_size: usize,
+    adaptive_block_size: bool,
+    checksum: bool,
+    one_byte: bool,
+}
+
+impl EncoderConfig {
+    /// No transforms, no checksums, and as few frames as possible, with one byte frames where the
+    /// numbers fit.
+    pub fn new() -> EncoderConfig {
+        EncoderConfig {
+            delta: false,
+            auto_delta: false,
+            zigzag: false,
+            block_size: u32::MAX as usize - 1,
+            adaptive_block_size: false,
+            checksum: false,
+            one_byte: true,
+        }
+    }
+
+    /// Store each number as the difference from the previous one in its frame.
+    pub fn delta(mut self, delta: bool) -> EncoderConfig {
+        self.delta = delta;
+        self
+    }
+
+    /// Store each number as the difference from the previous one in its frame, but only in frames
+    /// whose numbers are in non-decreasing order. Each frame's header records whether it was
+    /// delta encoded, so decoders needn't know which were.
+    ///
+    /// Has no effect if `delta` is enabled, since then every frame is delta encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stream_vbyte::Scalar;
+    /// use stream_vbyte::config::{DecoderConfig, EncoderConfig};
+    ///
+    /// // sorted IDs, then some unsorted scores
+    /// let mut nums: Vec<u32> = (0..100).map(|i| 1_000_000 + i * 3).collect();
+    /// nums.extend((0..100).map(|i| (i * 7919) % 1000));
+    ///
+    /// let mut plain = Vec::new();
+    /// EncoderConfig::new().block_size(100).encode::<Scalar>(&nums, &mut plain);
+    /// let mut auto = Vec::new();
+    /// let config = EncoderConfig::new().block_size(100).auto_delta(true);
+    /// config.encode::<Scalar>(&nums, &mut auto);
+    /// assert!(auto.len() < plain.len());
+    ///
+    /// let mut decoded = Vec::new();
+    /// DecoderConfig::new().decode::<Scalar>(&auto, &mut decoded).unwrap();
+    /// assert_eq!(nums, decoded);
+    /// ```
+    pub fn auto_delta(mut self, auto_delta: bool) -> EncoderConfig {
+        self.auto_delta = auto_delta;
+        self
+    }
+
+    /// Zigzag encode numbers (after `delta`, if enabled), so that small negative `i32`s or
+    /// differences take few bytes.
+    pub fn zigzag(mut self, zigzag: bool) -> EncoderConfig {
+        self.zigzag = zigzag;
+        self
+    }
+
+    /// Put at most `block_size` numbers in each frame. Must be non-zero.
+    pub fn block_size(mut self, block_size: usize) -> EncoderConfig {
+        assert!(block_size > 0, "Block size must be non-zero");
+        assert!(
+            block_size < u32::MAX as usize,
+            "Frames can hold fewer than u32::max_value() numbers"
+        );
+        self.block_size = block_size;
+        self
+    }
+
+    /// Instead of a fixed `block_size`, choose a block size for every
+    /// `ADAPTIVE_BLOCK_SIZES[2]` numbers: each of `ADAPTIVE_BLOCK_SIZES` is tried, and whichever
+    /// encodes those numbers in the fewest bytes is kept, preferring larger blocks in a tie.
+    /// Smaller blocks cost more in headers, but let `auto_delta` pick out shorter sorted runs, and
+    /// start each delta encoding afresh.
+    ///
+    /// Every frame's header records its count, so decoders needn't know what was chosen;
+    /// `encode_with_stats()` reports it. Encoding takes about three times as long as with a fixed
+    /// block size.
+    pub fn adaptive_block_size(mut self, adaptive_block_size: bool) -> EncoderConfig {
+        self.adaptive_block_size = adaptive_block_size;
+        self
+    }
+
+    /// Write a checksum of each frame's encoded numbers after the frame.
+    pub fn checksum(mut self, checksum: bool) -> EncoderConfig {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Store the numbers of each frame whose numbers (after `delta` and `zigzag`) are all less than
+    /// 256 as one byte each, without the quarter byte per number of control bytes that Stream VByte
+    /// needs. Frames with larger numbers are encoded as usual. Each frame's header records which
+    /// it is, so decoders needn't know.
+    ///
+    /// Enabled by default; turn it off to always write control bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stream_vbyte::Scalar;
+    /// use stream_vbyte::config::{DecoderConfig, EncoderConfig};
+    ///
+    /// // gaps between dense sorted IDs are tiny
+    /// let ids: Vec<u32> = (0..1000).map(|i| 100 + i * 5).collect();
+    ///
+    /// let mut one_byte = Vec::new();
+    /// let config = EncoderConfig::new().delta(true);
+    /// config.encode::<Scalar>(&ids, &mut one_byte);
+    /// let mut plain = Vec::new();
+    /// config.one_byte(false).encode::<Scalar>(&ids, &mut plain);
+    /// assert_eq!(plain.len() - 1000 / 4, one_byte.len());
+    ///
+    /// let mut decoded = Vec::new();
+    /// DecoderConfig::new().decode::<Scalar>(&one_byte, &mut decoded).unwrap();
+    /// assert_eq!(ids, decoded);
+    /// ```
+    pub fn one_byte(mut self, one_byte: bool) -> EncoderConfig {
+        self.one_byte = one_byte;
+        self
+    }
+
+    /// A `DecoderConfig` that decodes what this config encodes.
+    pub fn decoder_config(&self) -> DecoderConfig {
+        DecoderConfig {
+            delta: self.delta,
+            zigzag: self.zigzag,
+            checksum: self.checksum,
+        }
+    }
+
+    /// Encode `input` with `E`, appending the frames to `output`.
+    ///
+    /// Returns the number of bytes appended to `output`.
+    pub fn encode<E: Encoder>(&self, input: &[u32], output: &mut Vec<u8>) -> usize {
+        let start = output.len();
+        self.encode_frames::<E, _>(input, output, |_| {});
+
+        output.len() - start
+    }
+
+    /// Like `encode()`, but also report statistics about the encoded form, including the size of
+    /// each frame. The length histogram counts the numbers as they were encoded, i.e. after any
+    /// transforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stream_vbyte::Scalar;
+    /// use stream_vbyte::config::EncoderConfig;
+    ///
+    /// let nums: Vec<u32> = (0..1000).collect();
+    /// let mut encoded = Vec::new();
+    /// let stats = EncoderConfig::new()
+    ///     .adaptive_block_size(true)
+    ///     .encode_with_stats::<Scalar>(&nums, &mut encoded);
+    ///
+    /// assert_eq!(1000, stats.block_sizes.iter().sum::<usize>());
+    /// assert_eq!(encoded.len(), stats.output_bytes);
+    /// ```
+    pub fn encode_with_stats<E: Encoder>(
+        &self,
+        input: &[u32],
+        output: &mut Vec<u8>,
+    ) -> EncodeStats {
+        let start = output.len();
+        let mut block_sizes = Vec::new();
+        self.encode_frames::<E, _>(input, output, |len| block_sizes.push(len));
+
+        let mut len_histogram = [0; 4];
+        let mut offset = start;
+        for _ in &block_sizes {
+            let header = FrameHeader::parse(&output[offset..]).expect("Frame was just written");
+            let encoded_start = offset + header.header_len();
+            let frame_histogram = if header.format.one_byte {
+                [header.count, 0, 0, 0]
+            } else {
+                encoded_len_histogram(
+                    &output[encoded_start..(encoded_start + header.encoded_len)],
+                    header.count,
+                )
+            };
+            for (count, &frame_count) in len_histogram.iter_mut().zip(frame_histogram.iter()) {
+                *count += frame_count;
+            }
+            offset += header.frame_len();
+        }
+
+        let output_bytes = output.len() - start;
+        EncodeStats {
+            input_count: input.len(),
+            output_bytes,
+            len_histogram,
+            ratio: ratio(input.len(), output_bytes),
+            block_sizes,
+        }
+    }
+
+    /// Encode `input` as frames, calling `on_frame` with the count of each one.
+    fn encode_frames<E: Encoder, F: FnMut(usize)>(
+        &self,
+        input: &[u32],
+        output: &mut Vec<u8>,
+        mut on_frame: F,
+    ) {
+        if !self.adaptive_block_size {
+            for block in input.chunks(self.block_size) {
+                self.encode_frame::<E>(block, output);
+                on_frame(block.len());
+            }
+            return;
+        }
+
+        let segment_len = ADAPTIVE_BLOCK_SIZES[ADAPTIVE_BLOCK_SIZES.len() - 1];
+        let mut best = Vec::new();
+        let mut candidate = Vec::new();
+
+        for segment in input.chunks(segment_len) {
+            let mut best_block_size = 0;
+            // largest first, so that ties go to the fewest frames
+            for &block_size in ADAPTIVE_BLOCK_SIZES.iter().rev() {
+                candidate.clear();
+                for block in segment.chunks(block_size) {
+                    self.encode_frame::<E>(block, &mut candidate);
+                }
+                if best_block_size == 0 || candidate.len() < best.len() {
+                    mem::swap(&mut best, &mut candidate);
+                    best_block_size = block_size;
+                }
+            }
+
+            output.extend_from_slice(&best);
+            for block in segment.chunks(best_block_size) {
+                on_frame(block.len());
+            }
+        }
+    }
+
+    fn encode_frame<E: Encoder>(&self, block: &[u32], output: &mut Vec<u8>) {
+        let format = FrameFormat {
+            delta: self.delta || (self.auto_delta && is_sorted(block)),
+            zigzag: self.zigzag,
+            checksum: self.checksum,
+            one_byte: self.one_byte,
+        };
+        encode_frame_with_format::<E>(block, format, output);
+    }
+}
+
+impl Default for EncoderConfig {
+    fn default() -> EncoderConfig {
+        EncoderConfig::new()
+    }
+}
+
+fn is_sorted(nums: &[u32]) -> bool {
+    nums.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// Options for decoding frames with the original header, which must match how they were encoded.
+/// Frames with a versioned header are decoded as their header describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderConfig {
+    delta: bool,
+    zigzag: bool,
+    checksum: bool,
+}
+
+impl DecoderConfig {
+    /// No transforms and no checksums.
+    pub fn new() -> DecoderConfig {
+        DecoderConfig {
+            delta: false,
+            zigzag: false,
+            checksum: false,
+        }
+    }
+
+    /// Undo delta encoding.
+    pub fn delta(mut self, delta: bool) -> DecoderConfig {
+        self.delta = delta;
+        self
+    }
+
+    /// Undo zigzag encoding.
+    pub fn zigzag(mut self, zigzag: bool) -> DecoderConfig {
+        self.zigzag = zigzag;
+        self
+    }
+
+    /// Expect and verify a checksum after each frame.
+    pub fn checksum(mut self, checksum: bool) -> DecoderConfig {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Decode all the frames in `input` with `D`, appending the numbers to `output`.
+    ///
+    /// Returns the number of numbers appended to `output`. If there's an error, `output` may
+    /// have had some numbers appended.
+    pub fn decode<D: Decoder>(
+        &self,
+        input: &[u8],
+        output: &mut Vec<u32>,
+    ) -> Result<usize, DecodeError> {
+        let start = output.len();
+        let mut bytes_read = 0;
+        let mut frame_index = 0;
+
+        while bytes_read < input.len() {
+            let remaining = &input[bytes_read..];
+            let mut header = match FrameHeader::parse(remaining) {
+                Ok(h) => h,
+                Err(HeaderError::NeedsMoreInput(_)) => return Err(DecodeError::Truncated),
+                Err(HeaderError::UnsupportedFormat) => {
+                    return Err(DecodeError::UnsupportedFormat(frame_index))
+                }
+            };
+            if header.version == 0 {
+                header.format = FrameFormat {
+                    delta: self.delta,
+                    zigzag: self.zigzag,
+                    checksum: self.checksum,
+                    one_byte: false,
+                };
+            }
+            if remaining.len() < header.frame_len() {
+                return Err(DecodeError::Truncated);
+            }
+
+            match decode_frame::<D>(&header, &remaining[0..header.frame_len()], output) {
+                Ok(()) => {}
+                Err(FrameError::Malformed) => return Err(DecodeError::Truncated),
+                Err(FrameError::ChecksumMismatch) => {
+                    return Err(DecodeError::ChecksumMismatch(frame_index))
+                }
+            }
+
+            bytes_read += header.frame_len();
+            frame_index += 1;
+        }
+
+        Ok(output.len() - start)
+    }
+}
+
+impl Default for DecoderConfig {
+    fn default() -> DecoderConfig {
+        DecoderConfig::new()
+    }
+}
+
+/// Why `DecoderConfig.decode()` failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended partway through a frame, or a frame's header doesn't match its contents.
+    Truncated,
+    /// The checksum of the frame at this index (counting from 0) didn't match its contents.
+    ChecksumMismatch(usize),
+    /// The frame at this index has a versioned header in a format this version of the crate
+    /// doesn't support.
+    UnsupportedFormat(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated => write!(f, "Input is truncated or malformed"),
+            DecodeError::ChecksumMismatch(frame) => {
+                write!(f, "Checksum mismatch in frame {}", frame)
+            }
+            DecodeError::UnsupportedFormat(frame) => {
+                write!(f, "Unsupported format in frame {}", frame)
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn random_roundtrip_every_combination() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 500))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+
+            for &delta in &[false, true] {
+                for &zigzag in &[false, true] {
+                    for &checksum in &[false, true] {
+                        let config = EncoderConfig::new()
+                            .delta(delta)
+                            .zigzag(zigzag)
+                            .block_size(rng.gen_range(1, 200))
+                            .checksum(checksum);
+
+                        let mut encoded = vec![0xFF];
+                        let encoded_len = config.encode::<Scalar>(&nums, &mut encoded);
+                        assert_eq!(encoded.len() - 1, encoded_len);
+
+                        let mut decoded = Vec::new();
+                        assert_eq!(
+                            Ok(nums.len()),
+                            config
+                                .decoder_config()
+                                .decode::<Scalar>(&encoded[1..], &mut decoded)
+                        );
+                        assert_eq!(nums, decoded);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delta_zigzag_shrinks_nearly_sorted_input() {
+        let nums: Vec<u32> = (0..1000).map(|i| 1_000_000 + i * 10 - (i % 3) * 20).collect();
+
+        let mut plain = Vec::new();
+        EncoderConfig::new().encode::<Scalar>(&nums, &mut plain);
+        let mut transformed = Vec::new();
+        EncoderConfig::new()
+            .delta(true)
+            .zigzag(true)
+            .encode::<Scalar>(&nums, &mut transformed);
+
+        // 3 bytes per number vs 1
+        assert!(transformed.len() * 2 < plain.len());
+    }
+
+    #[test]
+    fn headers_record_options_for_any_decoder_config() {
+        let nums: Vec<u32> = (0..100).map(|i| 1000 - i * 3).collect();
+        let mut encoded = Vec::new();
+        EncoderConfig::new()
+            .delta(true)
+            .zigzag(true)
+            .block_size(30)
+            .checksum(true)
+            .encode::<Scalar>(&nums, &mut encoded);
+
+        let mut decoded = Vec::new();
+        assert_eq!(
+            Ok(100),
+            DecoderConfig::new().decode::<Scalar>(&encoded, &mut decoded)
+        );
+        assert_eq!(nums, decoded);
+    }
+
+    #[test]
+    fn auto_delta_only_transforms_sorted_frames() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let block_size = rng.gen_range(1, 50);
+            let mut nums = Vec::new();
+            let mut sorted_blocks = Vec::new();
+            for _ in 0..rng.gen_range(0, 10) {
+                let mut block: Vec<u32> = (0..block_size).map(|_| rng.gen()).collect();
+                let sorted = rng.gen();
+                if sorted {
+                    block.sort();
+                }
+                sorted_blocks.push(sorted || block.windows(2).all(|p| p[0] <= p[1]));
+                nums.extend(block);
+            }
+
+            let config = EncoderConfig::new()
+                .auto_delta(true)
+                .zigzag(rng.gen())
+                .block_size(block_size);
+            let mut encoded = Vec::new();
+            config.encode::<Scalar>(&nums, &mut encoded);
+
+            let mut offset = 0;
+            for &sorted in &sorted_blocks {
+                let header = FrameHeader::parse(&encoded[offset..]).unwrap();
+                assert_eq!(sorted, header.format.delta);
+                offset += header.frame_len();
+            }
+            assert_eq!(encoded.len(), offset);
+
+            let mut decoded = Vec::new();
+            assert_eq!(
+                Ok(nums.len()),
+                DecoderConfig::new().decode::<Scalar>(&encoded, &mut decoded)
+            );
+            assert_eq!(nums, decoded);
+        }
+    }
+
+    #[test]
+    fn adaptive_block_size_picks_smallest_encoding() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..50 {
+            // runs of sorted and unsorted numbers of random lengths
+            let mut nums = Vec::new();
+            for _ in 0..rng.gen_range(0, 10) {
+                let mut run: Vec<u32> = (0..rng.gen_range(1, 400))
+                    .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                    .collect();
+                if rng.gen() {
+                    run.sort();
+                }
+                nums.extend(run);
+            }
+
+            let config = EncoderConfig::new()
+                .auto_delta(true)
+                .checksum(rng.gen())
+                .adaptive_block_size(true);
+            let mut encoded = Vec::new();
+            let stats = config.encode_with_stats::<Scalar>(&nums, &mut encoded);
+            assert_eq!(encoded.len(), stats.output_bytes);
+            assert_eq!(nums.len(), stats.block_sizes.iter().sum::<usize>());
+            assert_eq!(nums.len(), stats.len_histogram.iter().sum::<usize>());
+
+            // no fixed candidate size does better, since each segment was as small as it could be
+            for &block_size in &ADAPTIVE_BLOCK_SIZES {
+                let mut fixed = Vec::new();
+                config
+                    .adaptive_block_size(false)
+                    .block_size(block_size)
+                    .encode::<Scalar>(&nums, &mut fixed);
+                assert!(encoded.len() <= fixed.len());
+            }
+
+            let mut decoded = Vec::new();
+            assert_eq!(
+                Ok(nums.len()),
+                DecoderConfig::new().decode::<Scalar>(&encoded, &mut decoded)
+            );
+            assert_eq!(nums, decoded);
+        }
+    }
+
+    #[test]
+    fn small_numbers_get_one_byte_frames_by_default() {
+        // a frame of numbers that fit in a byte, then one of numbers that don't
+        let nums: Vec<u32> = (0..100).map(|i| if i < 50 { i } else { i * 10 }).collect();
+        let mut encoded = Vec::new();
+        EncoderConfig::new().block_size(50).encode::<Scalar>(&nums, &mut encoded);
+
+        let first = FrameHeader::parse(&encoded).unwrap();
+        assert!(first.format.one_byte);
+        assert_eq!(0x20, encoded[5]);
+        let second = FrameHeader::parse(&encoded[first.frame_len()..]).unwrap();
+        assert!(!second.format.one_byte);
+
+        let mut plain = Vec::new();
+        EncoderConfig::new()
+            .block_size(50)
+            .one_byte(false)
+            .encode::<Scalar>(&nums, &mut plain);
+        assert_eq!(0x00, plain[5]);
+        // no control bytes for the first frame
+        assert_eq!(plain.len() - (50 + 3) / 4, encoded.len());
+
+        let mut decoded = Vec::new();
+        assert_eq!(
+            Ok(100),
+            DecoderConfig::new().decode::<Scalar>(&encoded, &mut decoded)
+        );
+        assert_eq!(nums, decoded);
+    }
+
+    #[test]
+    fn stats_report_fixed_block_sizes() {
+        let nums: Vec<u32> = (0..250).collect();
+        let mut encoded = Vec::new();
+        let stats = EncoderConfig::new()
+            .block_size(100)
+            .encode_with_stats::<Scalar>(&nums, &mut encoded);
+
+        assert_eq!(vec![100, 100, 50], stats.block_sizes);
+        assert_eq!([250, 0, 0, 0], stats.len_histogram);
+        assert_eq!(encoded.len() as f64 / 1000.0, stats.ratio);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_reported() {
+        let config = EncoderConfig::new().block_size(4).checksum(true);
+        let mut encoded = Vec::new();
+        config.encode::<Scalar>(&[1, 2, 3, 4, 5, 6, 7, 8], &mut encoded);
+
+        // second frame's first number
+        encoded[16 + 4 + 4 + 16] ^= 0x01;
+
+        assert_eq!(
+            Err(DecodeError::ChecksumMismatch(1)),
+            config
+                .decoder_config()
+                .decode::<Scalar>(&encoded, &mut Vec::new())
+        );
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let config = EncoderConfig::new().checksum(true);
+        let mut encoded = Vec::new();
+        config.encode::<Scalar>(&[1, 2, 3], &mut encoded);
+
+        for len in 1..encoded.len() {
+            assert_eq!(
+                Err(DecodeError::Truncated),
+                config
+                    .decoder_config()
+                    .decode::<Scalar>(&encoded[0..len], &mut Vec::new())
+            );
+        }
+    }
+}
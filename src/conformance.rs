@@ -0,0 +1,589 @@
+//! Test suites for `Encoder` and `Decoder` implementations, including those outside this crate,
+//! such as codecs for instruction sets this crate doesn't support. Enable the `conformance`
+//! feature to use them.
+//!
+//! Each check runs the same battery of tests the built-in codecs are held to, and panics with a
+//! description of the first problem it finds, so it can be called from a `#[test]` function.
+//!
+//! # Examples
+//!
+//! ```
+//! use stream_vbyte::{conformance, Scalar};
+//!
+//! conformance::check_encoder::<Scalar>();
+//! conformance::check_decoder::<Scalar>();
+//! ```
+
+extern crate rand;
+
+use std::cmp;
+
+use self::rand::Rng;
+
+use {cumulative_encoded_len, decode, encode, encoded_shape, is_canonical, lengths, DecodeCursor,
+     DecodeQuadSink, DecodeSingleSink, Decoder, Encoder, NoOverwrite, Scalar, WriteQuadToSlice};
+use random_varint::RandomVarintEncodedLengthIter;
+
+/// Written to output slots that shouldn't be touched, so stray writes can be detected.
+const SENTINEL: u32 = 0xDEAD_BEEF;
+
+/// Like `SENTINEL`, for encoded bytes.
+const SENTINEL_BYTE: u8 = 0xA5;
+
+/// The boundaries between encoded lengths, and their neighbors.
+const EDGE_NUMS: &[u32] = &[
+    0,
+    1,
+    (1 << 8) - 1,
+    1 << 8,
+    (1 << 16) - 1,
+    1 << 16,
+    (1 << 24) - 1,
+    1 << 24,
+    u32::MAX - 1,
+    u32::MAX,
+];
+
+/// Check that `E` encodes correctly and follows the contract of `Encoder.encode_quads()`.
+///
+/// This covers numbers of every encoded length, including the boundaries between lengths, in
+/// every position in a quad. `E`'s output must be identical to `Scalar`'s, decodable by `Scalar`,
+/// and canonical, and `E` must not write outside the area that the encoded quads occupy.
+///
+/// Panics if `E` misbehaves.
+pub fn check_encoder<E: Encoder>() {
+    check_encoder_every_control_byte::<E>();
+    check_encoder_edge_nums::<E>();
+    check_encoder_random::<E>();
+}
+
+/// Check that `E` meets the stronger contract promised by `NoOverwrite`, as well as everything
+/// `check_encoder()` checks.
+///
+/// Panics if `E` writes anything past the encoded numbers it reports writing.
+pub fn check_no_overwrite<E: NoOverwrite>() {
+    check_encoder::<E>();
+
+    let mut rng = rand::weak_rng();
+    let mut nums_iter = RandomVarintEncodedLengthIter::new(rand::weak_rng());
+
+    for _ in 0..1000 {
+        let quads = rng.gen_range(0, 75);
+        let nums: Vec<u32> = nums_iter.by_ref().take(quads * 4).collect();
+
+        let mut control_bytes = Vec::new();
+        control_bytes.resize(quads, SENTINEL_BYTE);
+        let mut output = Vec::new();
+        output.resize(nums.len() * 4 + 16, SENTINEL_BYTE);
+
+        let (_, bytes_written) = E::encode_quads(&nums, &mut control_bytes, &mut output);
+        for (i, &b) in output[bytes_written..].iter().enumerate() {
+            assert_eq!(
+                SENTINEL_BYTE, b,
+                "encode_quads() wrote past the encoded numbers it reported at {}",
+                bytes_written + i
+            );
+        }
+    }
+}
+
+/// Encode quads with every control byte, in order and shuffled.
+fn check_encoder_every_control_byte<E: Encoder>() {
+    let mut rng = rand::weak_rng();
+
+    let mut control_bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+    let in_order = nums_for_control_bytes(&control_bytes);
+    rng.shuffle(&mut control_bytes);
+    let shuffled = nums_for_control_bytes(&control_bytes);
+
+    for nums in &[in_order, shuffled] {
+        check_encode_quads::<E>(nums);
+        check_encode::<E>(nums);
+    }
+}
+
+/// Encode every pair of numbers at the boundaries of encoded lengths in every pair of positions.
+fn check_encoder_edge_nums<E: Encoder>() {
+    let mut nums = Vec::new();
+    for &a in EDGE_NUMS {
+        for &b in EDGE_NUMS {
+            for a_index in 0..4 {
+                for b_index in 0..4 {
+                    let mut quad = [0x0102; 4];
+                    quad[a_index] = a;
+                    quad[b_index] = b;
+                    nums.extend_from_slice(&quad);
+                }
+            }
+        }
+    }
+
+    check_encode_quads::<E>(&nums);
+    check_encode::<E>(&nums);
+}
+
+/// Encode random numbers of random lengths, including trailing partial quads.
+fn check_encoder_random<E: Encoder>() {
+    let mut rng = rand::weak_rng();
+    let mut nums_iter = RandomVarintEncodedLengthIter::new(rand::weak_rng());
+
+    for _ in 0..1000 {
+        let count = rng.gen_range(0, 300);
+        let nums: Vec<u32> = nums_iter.by_ref().take(count).collect();
+
+        check_encode_quads::<E>(&nums[0..(count / 4 * 4)]);
+        check_encode::<E>(&nums);
+    }
+}
+
+/// Call `E::encode_quads()` directly on `nums`, which must be complete quads, and check its result
+/// against the contract it documents.
+fn check_encode_quads<E: Encoder>(nums: &[u32]) {
+    debug_assert_eq!(0, nums.len() % 4);
+
+    let expected = encode_scalar(nums);
+    let shape = encoded_shape(nums.len());
+    let (expected_control_bytes, expected_nums) = expected.split_at(shape.control_bytes_len);
+
+    let mut control_bytes = Vec::new();
+    control_bytes.resize(shape.control_bytes_len, SENTINEL_BYTE);
+    // room for the worst case, which is more than the encoded quads need
+    let mut output = Vec::new();
+    output.resize(nums.len() * 4 + 16, SENTINEL_BYTE);
+
+    let (nums_encoded, bytes_written) = E::encode_quads(nums, &mut control_bytes, &mut output);
+
+    assert_eq!(
+        0,
+        nums_encoded % 4,
+        "encode_quads() must encode complete quads, but encoded {}",
+        nums_encoded
+    );
+    assert!(
+        nums_encoded <= nums.len(),
+        "encode_quads() encoded {} of {} numbers",
+        nums_encoded,
+        nums.len()
+    );
+
+    let quads_encoded = nums_encoded / 4;
+    assert_bytes_eq(
+        &expected_control_bytes[0..quads_encoded],
+        &control_bytes[0..quads_encoded],
+        "control byte",
+    );
+    assert_eq!(
+        cumulative_encoded_len(&expected_control_bytes[0..quads_encoded]),
+        bytes_written,
+        "encode_quads() reported the wrong number of bytes written for {} numbers",
+        nums_encoded
+    );
+    assert_bytes_eq(
+        &expected_nums[0..bytes_written],
+        &output[0..bytes_written],
+        "encoded byte",
+    );
+
+    // may write scratch data into the rest of the area the quads occupy, but not beyond
+    for (i, &b) in output[expected_nums.len()..].iter().enumerate() {
+        assert_eq!(
+            SENTINEL_BYTE, b,
+            "encode_quads() wrote past the end of the encoded quads at {}",
+            expected_nums.len() + i
+        );
+    }
+}
+
+/// Encode `nums` with `encode()` and check the result against `Scalar`.
+fn check_encode<E: Encoder>(nums: &[u32]) {
+    let expected = encode_scalar(nums);
+
+    let mut encoded = Vec::new();
+    encoded.resize(nums.len() * 5 + 16, SENTINEL_BYTE);
+    let encoded_len = encode::<E>(nums, &mut encoded);
+
+    assert_eq!(
+        expected.len(),
+        encoded_len,
+        "Wrong encoded length for {} numbers",
+        nums.len()
+    );
+    assert_bytes_eq(&expected, &encoded[0..encoded_len], "encoded byte");
+    assert!(
+        is_canonical(&encoded[0..encoded_len], nums.len()),
+        "Encoding of {} numbers isn't canonical",
+        nums.len()
+    );
+    for (i, &b) in encoded[encoded_len..].iter().enumerate() {
+        assert_eq!(
+            SENTINEL_BYTE, b,
+            "Wrote past the end of the encoded numbers at {}",
+            encoded_len + i
+        );
+    }
+
+    let mut decoded = vec![0; nums.len()];
+    assert_eq!(
+        encoded_len,
+        decode::<Scalar>(&encoded[0..encoded_len], nums.len(), &mut decoded),
+        "Scalar read the wrong number of bytes"
+    );
+    assert_decoded(nums, &decoded, 0);
+}
+
+/// Check that `D` decodes correctly and follows the contract of `Decoder.decode_quads()`.
+///
+/// This covers every control byte value, inputs that end in a partial quad, and decoding with
+/// `DecodeCursor.decode_sink()` in chunks of many sizes with skips in between.
+///
+/// Panics if `D` misbehaves.
+pub fn check_decoder<D: Decoder>() {
+    check_decoder_every_control_byte::<D>();
+    check_decoder_partial_quads::<D>();
+    check_decoder_chunked_sink::<D>();
+}
+
+/// Decode quads with every control byte, in order and shuffled, both through a cursor and by
+/// calling `D::decode_quads()` directly.
+fn check_decoder_every_control_byte<D: Decoder>() {
+    let mut rng = rand::weak_rng();
+
+    let mut control_bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+    let in_order = nums_for_control_bytes(&control_bytes);
+    rng.shuffle(&mut control_bytes);
+    let shuffled = nums_for_control_bytes(&control_bytes);
+
+    for nums in &[in_order, shuffled] {
+        let encoded = encode_scalar(nums);
+
+        for &max_control_bytes in &[0, 1, 2, 3, 4, 5, 16, 17, 255, 256, 1000] {
+            check_decode_quads::<D>(nums, &encoded, max_control_bytes);
+        }
+
+        let mut decoded = vec![0; nums.len()];
+        let mut cursor = DecodeCursor::new(&encoded, nums.len());
+        assert_eq!(
+            nums.len(),
+            cursor.decode_slice::<D>(&mut decoded),
+            "Didn't decode every number"
+        );
+        assert_eq!(encoded.len(), cursor.input_consumed(), "Wrong encoded length");
+        assert_decoded(nums, &decoded, 0);
+    }
+}
+
+/// Decode every count up to a few quads, so that the input ends in each size of partial quad.
+fn check_decoder_partial_quads<D: Decoder>() {
+    let mut nums_iter = RandomVarintEncodedLengthIter::new(rand::weak_rng());
+
+    for count in 0..100 {
+        for _ in 0..10 {
+            let nums: Vec<u32> = nums_iter.by_ref().take(count).collect();
+            let encoded = encode_scalar(&nums);
+
+            // exactly as long as the count, as `decode()` documents, plus sentinels after
+            let mut decoded = vec![SENTINEL; count + 4];
+            let mut cursor = DecodeCursor::new(&encoded, count);
+            assert_eq!(
+                count,
+                cursor.decode_slice::<D>(&mut decoded[0..cmp::max(count, 4)]),
+                "Didn't decode every number of {}",
+                count
+            );
+            assert_eq!(
+                encoded.len(),
+                cursor.input_consumed(),
+                "Wrong encoded length for {} numbers",
+                count
+            );
+            assert!(!cursor.has_more(), "Cursor not finished after {} numbers", count);
+            assert_decoded(&nums, &decoded[0..count], 0);
+            assert_untouched(&decoded[count..], count);
+        }
+    }
+}
+
+/// Decode with `DecodeCursor.decode_sink()` in randomly sized chunks, with random skips, checking
+/// that each invocation decodes what it should and numbers its output from 0.
+fn check_decoder_chunked_sink<D: Decoder>() {
+    let mut rng = rand::weak_rng();
+    let mut nums_iter = RandomVarintEncodedLengthIter::new(rand::weak_rng());
+
+    for _ in 0..500 {
+        let count = rng.gen_range(0, 300);
+        let nums: Vec<u32> = nums_iter.by_ref().take(count).collect();
+        let encoded = encode_scalar(&nums);
+        let shape = encoded_shape(count);
+
+        let mut cursor = DecodeCursor::new(&encoded, count);
+        let mut pos = 0;
+
+        while cursor.has_more() {
+            let complete_remaining = shape.complete_control_bytes_len * 4 - cmp::min(
+                pos,
+                shape.complete_control_bytes_len * 4,
+            );
+
+            if complete_remaining >= 4 && rng.gen_range(0, 4) == 0 {
+                let to_skip = rng.gen_range(0, complete_remaining / 4 + 1) * 4;
+                cursor.skip(to_skip);
+                pos += to_skip;
+                continue;
+            }
+
+            let max = rng.gen_range(0, 50);
+            let expected = if max / 4 * 4 < complete_remaining {
+                max / 4 * 4
+            } else if max - complete_remaining >= shape.leftover_numbers {
+                complete_remaining + shape.leftover_numbers
+            } else {
+                complete_remaining
+            };
+
+            let mut sink = CheckingSink::new(max);
+            assert_eq!(
+                expected,
+                cursor.decode_sink::<D, _>(&mut sink, max),
+                "Wrong number of numbers decoded at {} of {} with max {}",
+                pos,
+                count,
+                max
+            );
+            assert_decoded(&nums[pos..(pos + expected)], &sink.output[0..expected], pos);
+            assert_untouched(&sink.output[expected..], pos + expected);
+
+            pos += expected;
+        }
+
+        assert_eq!(count, pos);
+        assert_eq!(encoded.len(), cursor.input_consumed(), "Wrong encoded length");
+    }
+}
+
+/// Call `D::decode_quads()` directly and check its result against the contract it documents.
+fn check_decode_quads<D: Decoder>(nums: &[u32], encoded: &[u8], max_control_bytes: usize) {
+    let shape = encoded_shape(nums.len());
+    let (control_bytes, encoded_nums) = encoded.split_at(shape.control_bytes_len);
+    let control_bytes = &control_bytes[0..shape.complete_control_bytes_len];
+
+    let mut sink = CheckingSink::new(nums.len());
+    let (nums_decoded, bytes_read) =
+        D::decode_quads(control_bytes, encoded_nums, max_control_bytes, 0, &mut sink);
+
+    assert_eq!(
+        0,
+        nums_decoded % 4,
+        "decode_quads() must decode complete quads, but decoded {}",
+        nums_decoded
+    );
+    assert!(
+        nums_decoded <= cmp::min(max_control_bytes, control_bytes.len()) * 4,
+        "decode_quads() decoded {} numbers with max control bytes {}",
+        nums_decoded,
+        max_control_bytes
+    );
+    assert_eq!(
+        cumulative_encoded_len(&control_bytes[0..(nums_decoded / 4)]),
+        bytes_read,
+        "decode_quads() reported the wrong number of bytes read for {} numbers",
+        nums_decoded
+    );
+    assert_decoded(&nums[0..nums_decoded], &sink.output[0..nums_decoded], 0);
+    assert_untouched(&sink.output[nums_decoded..], nums_decoded);
+}
+
+/// Four numbers per control byte, each with its most significant byte non-zero so that dropped
+/// bytes are noticed.
+fn nums_for_control_bytes(control_bytes: &[u8]) -> Vec<u32> {
+    let mut nums = Vec::with_capacity(control_bytes.len() * 4);
+
+    for &control_byte in control_bytes {
+        for i in 0..4 {
+            let len = lengths::num_len(control_byte, i) as u32;
+            // distinct low bytes, so misplaced bytes are noticed too
+            let low_bytes = 0x0403_0201 & ((1_u64 << ((len - 1) * 8)) - 1) as u32;
+            nums.push((0xA0 + i as u32) << ((len - 1) * 8) | low_bytes);
+        }
+    }
+
+    nums
+}
+
+fn encode_scalar(nums: &[u32]) -> Vec<u8> {
+    let mut encoded = vec![0; nums.len() * 5];
+    let encoded_len = encode::<Scalar>(nums, &mut encoded);
+    encoded.truncate(encoded_len);
+
+    encoded
+}
+
+/// `offset` is the index of `expected[0]` in the whole input, for error messages.
+fn assert_decoded(expected: &[u32], decoded: &[u32], offset: usize) {
+    for (i, (&e, &d)) in expected.iter().zip(decoded.iter()).enumerate() {
+        assert_eq!(e, d, "Wrong number decoded at index {}", offset + i);
+    }
+}
+
+fn assert_bytes_eq(expected: &[u8], actual: &[u8], what: &str) {
+    for (i, (&e, &a)) in expected.iter().zip(actual.iter()).enumerate() {
+        assert_eq!(e, a, "Wrong {} at index {}", what, i);
+    }
+}
+
+fn assert_untouched(output: &[u32], offset: usize) {
+    for (i, &n) in output.iter().enumerate() {
+        assert_eq!(SENTINEL, n, "Wrote past the end of the decoded numbers at {}", offset + i);
+    }
+}
+
+/// Writes numbers to a buffer with room for `max` of them, plus sentinels beyond that to catch
+/// writes outside of what was asked for.
+struct CheckingSink {
+    output: Vec<u32>,
+}
+
+impl CheckingSink {
+    fn new(max: usize) -> CheckingSink {
+        let mut output = Vec::new();
+        output.resize(max + 4, SENTINEL);
+
+        CheckingSink { output }
+    }
+}
+
+impl DecodeSingleSink for CheckingSink {
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        assert_eq!(
+            SENTINEL, self.output[nums_decoded],
+            "Number {} was decoded twice",
+            nums_decoded
+        );
+        self.output[nums_decoded] = num;
+    }
+}
+
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for CheckingSink {
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        assert_eq!(0, nums_decoded % 4, "Quad at unaligned index {}", nums_decoded);
+        quad.write_to_slice(&mut self.output[nums_decoded..(nums_decoded + 4)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoded_num_len;
+
+    #[test]
+    fn scalar_encoder_conforms() {
+        check_encoder::<Scalar>();
+        check_no_overwrite::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_sse41")]
+    #[test]
+    fn sse41_encoder_conforms() {
+        check_encoder::<::x86::Sse41>();
+    }
+
+    #[test]
+    fn scalar_decoder_conforms() {
+        check_decoder::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn ssse3_decoder_conforms() {
+        check_decoder::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn control_byte_nums_have_expected_lengths() {
+        let nums = nums_for_control_bytes(&[0xE4]);
+        assert_eq!(vec![0xA0, 0xA101, 0xA2_0201, 0xA303_0201], nums);
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of bytes read")]
+    fn decoder_misreporting_bytes_read_fails() {
+        check_decoder::<OffByOne>();
+    }
+
+    #[test]
+    #[should_panic(expected = "wrote past the end of the encoded quads")]
+    fn encoder_writing_past_quads_fails() {
+        check_encoder::<Scribbler>();
+    }
+
+    #[test]
+    #[should_panic(expected = "wrote past the encoded numbers it reported")]
+    fn encoder_using_scratch_space_fails_no_overwrite() {
+        check_no_overwrite::<ZeroFiller>();
+    }
+
+    /// Like `Scalar`, but leaves the last quad unencoded and zeroes the area it would occupy, which
+    /// `Encoder` allows but `NoOverwrite` doesn't.
+    struct ZeroFiller;
+
+    impl Encoder for ZeroFiller {
+        fn encode_quads(
+            input: &[u32],
+            control_bytes: &mut [u8],
+            output: &mut [u8],
+        ) -> (usize, usize) {
+            let quads = control_bytes.len().saturating_sub(1);
+            let (nums_encoded, bytes_written) =
+                Scalar::encode_quads(&input[0..(quads * 4)], &mut control_bytes[0..quads], output);
+
+            let area_len = input.iter().map(|&n| encoded_num_len(n)).sum::<usize>();
+            for b in output[bytes_written..area_len].iter_mut() {
+                *b = 0;
+            }
+
+            (nums_encoded, bytes_written)
+        }
+    }
+
+    impl NoOverwrite for ZeroFiller {}
+
+    /// Like `Scalar`, but writes a byte after the encoded quads.
+    struct Scribbler;
+
+    impl Encoder for Scribbler {
+        fn encode_quads(
+            input: &[u32],
+            control_bytes: &mut [u8],
+            output: &mut [u8],
+        ) -> (usize, usize) {
+            let (nums_encoded, bytes_written) = Scalar::encode_quads(input, control_bytes, output);
+            output[cumulative_encoded_len(control_bytes)] = 0;
+
+            (nums_encoded, bytes_written)
+        }
+    }
+
+    /// Like `Scalar`, but claims to have read one more byte than it did.
+    struct OffByOne;
+
+    impl Decoder for OffByOne {
+        type DecodedQuad = ();
+
+        fn decode_quads<S: DecodeQuadSink<()>>(
+            control_bytes: &[u8],
+            encoded_nums: &[u8],
+            max_control_bytes_to_decode: usize,
+            nums_already_decoded: usize,
+            sink: &mut S,
+        ) -> (usize, usize) {
+            let (nums_decoded, bytes_read) = Scalar::decode_quads(
+                control_bytes,
+                encoded_nums,
+                max_control_bytes_to_decode,
+                nums_already_decoded,
+                sink,
+            );
+
+            (nums_decoded, bytes_read + 1)
+        }
+    }
+}
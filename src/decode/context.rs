@@ -0,0 +1,102 @@
+use super::{decode, Decoder};
+
+/// Scratch space for decoding many streams one after another, such as the short posting lists
+/// for each term of a query, without allocating for each one.
+///
+/// Decoding each stream with `decode_to_new_vec()` allocates (and zeroes) a new `Vec` every time,
+/// which for short streams can take longer than the decoding itself. A `DecodeContext` keeps one
+/// buffer, which only grows when a stream longer than any before it is decoded.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, DecodeContext, Scalar};
+///
+/// let lists: Vec<Vec<u32>> = (1..100).map(|k| (0..k).map(|i| i * k).collect()).collect();
+/// let encoded: Vec<Vec<u8>> = lists
+///     .iter()
+///     .map(|list| {
+///         let mut e = vec![0; list.len() * 5];
+///         let len = encode::<Scalar>(list, &mut e);
+///         e.truncate(len);
+///         e
+///     })
+///     .collect();
+///
+/// let mut context = DecodeContext::with_capacity(100);
+/// for (list, e) in lists.iter().zip(encoded.iter()) {
+///     assert_eq!(&list[..], context.decode::<Scalar>(e, list.len()));
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DecodeContext {
+    nums: Vec<u32>,
+    bytes_read: usize,
+}
+
+impl DecodeContext {
+    /// Create a context with no space allocated yet.
+    pub fn new() -> DecodeContext {
+        DecodeContext::default()
+    }
+
+    /// Create a context with room for streams of up to `count` numbers, so decoding them won't
+    /// allocate at all.
+    pub fn with_capacity(count: usize) -> DecodeContext {
+        let nums = vec![0; count];
+
+        DecodeContext {
+            nums,
+            bytes_read: 0,
+        }
+    }
+
+    /// Decode `count` numbers from `input` into the context's buffer, replacing whatever was
+    /// decoded before.
+    ///
+    /// Returns the decoded numbers. `bytes_read()` returns how many bytes of `input` they took.
+    pub fn decode<D: Decoder>(&mut self, input: &[u8], count: usize) -> &[u32] {
+        // never shrink, so that the buffer is zeroed at most once per length
+        if self.nums.len() < count {
+            self.nums.resize(count, 0);
+        }
+
+        self.bytes_read = decode::<D>(input, count, &mut self.nums[0..count]);
+
+        &self.nums[0..count]
+    }
+
+    /// The number of bytes of input read by the last `decode()`.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn decode_streams_of_varying_lengths() {
+        let mut rng = rand::weak_rng();
+        let mut context = DecodeContext::new();
+
+        for _ in 0..500 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 100))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; nums.len() * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+            assert_eq!(&nums[..], context.decode::<Scalar>(&encoded, nums.len()));
+            assert_eq!(encoded_len, context.bytes_read());
+        }
+    }
+}
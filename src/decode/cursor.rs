@@ -1,5 +1,7 @@
-use {cumulative_encoded_len, encoded_shape, EncodedShape, Scalar};
-use super::{decode_num_scalar, DecodeQuadSink, Decoder, SliceDecodeSink};
+use std::{cmp, error, fmt};
+
+use {cumulative_encoded_len, encoded_shape, lengths, EncodedShape, Scalar};
+use super::{decode_num_scalar, DecodeQuadSink, DecodeSingleSink, Decoder, SliceDecodeSink};
 
 /// Offers more flexible decoding than the top-level `decode()`.
 ///
@@ -20,8 +22,10 @@ use super::{decode_num_scalar, DecodeQuadSink, Decoder, SliceDecodeSink};
 /// There are two traits to represent a sink: one for complete quads
 /// (`DecodeQuadSink`), and one for any trailing leftover numbers that may not fill a quad
 /// (`DecodeSingleSink`). You will need to implement both with the appropriate
-/// `Decoder::DecodedQuad` type for the `Decoder` you are using. You can look at the implementations
-/// of `SliceDecodeSink` (used for `decode_slice()`) or `TupleSink` in the tests for examples.
+/// `Decoder::DecodedQuad` type for the `Decoder` you are using. Every `DecodedQuad` implements
+/// `WriteQuadToSlice`, so a sink that is happy to look at quads as 4 `u32`s can implement
+/// `DecodeQuadSink<Q>` for any `Q: WriteQuadToSlice` and work with every `Decoder`. You can look at
+/// `TupleSink` in the tests for an example.
 ///
 /// # Examples
 ///
@@ -63,6 +67,9 @@ use super::{decode_num_scalar, DecodeQuadSink, Decoder, SliceDecodeSink};
 ///     }
 /// }
 ///
+/// # #[cfg(not(feature = "encode"))]
+/// # fn main() {}
+/// # #[cfg(feature = "encode")]
 /// fn main() {
 ///     let mut nums = vec![1, 2, 3, 5, 8, 13, 21, 34];
 ///
@@ -109,20 +116,192 @@ impl<'a> DecodeCursor<'a> {
         }
     }
 
+    /// Start over on a new `input` of `count` numbers, as if this were a new cursor.
+    ///
+    /// A cursor only borrows its input, so creating one never allocates, and this is no faster
+    /// than `new()`. It's for code that keeps cursors around, e.g. in a pool shared by requests,
+    /// and would rather not replace them.
+    pub fn reset(&mut self, input: &'a [u8], count: usize) {
+        *self = DecodeCursor::new(input, count);
+    }
+
+    /// Create a new cursor over control bytes and encoded numbers that are stored separately,
+    /// e.g. a sub-range of a larger encoded input.
+    ///
+    /// `control_bytes` must have at least one control byte for every quad (complete or partial)
+    /// of the `count` numbers, and `encoded_nums` must start with the first number of the first
+    /// quad. Either may extend past what is needed for `count` numbers.
+    pub fn from_parts(
+        control_bytes: &'a [u8],
+        encoded_nums: &'a [u8],
+        count: usize,
+    ) -> DecodeCursor<'a> {
+        let shape = encoded_shape(count);
+
+        DecodeCursor {
+            control_bytes: &control_bytes[0..shape.control_bytes_len],
+            encoded_nums,
+            encoded_shape: shape,
+            total_nums: count,
+            nums_decoded: 0,
+            control_bytes_read: 0,
+            encoded_bytes_read: 0,
+        }
+    }
+
+    /// Returns the index of the control byte and the offset into the encoded numbers of number
+    /// `index`, counting from where the cursor started, without decoding anything.
+    ///
+    /// `index` must be a multiple of 4 and no more than the count of numbers. Passing the returned
+    /// control byte index and offset to `from_parts()` (as the start of the control bytes and
+    /// encoded numbers, respectively) makes a cursor starting at `index`, so storage layers can
+    /// record them to resume decoding later.
+    ///
+    /// This scans the control bytes before `index`. If you need many offsets into the same input,
+    /// `DecodePlan` computes them all at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    /// let (control_bytes, encoded_nums) = encoded.split_at(25);
+    ///
+    /// let cursor = DecodeCursor::new(&encoded, nums.len());
+    /// let (control_byte_index, encoded_nums_offset) = cursor.byte_offset_of(40);
+    ///
+    /// let mut resumed = DecodeCursor::from_parts(
+    ///     &control_bytes[control_byte_index..],
+    ///     &encoded_nums[encoded_nums_offset..],
+    ///     nums.len() - 40,
+    /// );
+    /// let mut decoded = vec![0; 60];
+    /// assert_eq!(60, resumed.decode_slice::<Scalar>(&mut decoded));
+    /// assert_eq!(&nums[40..], &decoded[..]);
+    /// # }
+    /// ```
+    pub fn byte_offset_of(&self, index: usize) -> (usize, usize) {
+        assert_eq!(index % 4, 0, "Must be a multiple of 4");
+        assert!(index <= self.total_nums, "Index out of range");
+
+        let control_byte_index = index / 4;
+
+        (
+            control_byte_index,
+            cumulative_encoded_len(&self.control_bytes[0..control_byte_index]),
+        )
+    }
+
+    /// Move the cursor to the control byte at `control_byte_index` and the encoded number at
+    /// `encoded_nums_offset`, as returned by `byte_offset_of()`, forwards or backwards, so that a
+    /// reader that stored a position can resume from it without skipping from the start.
+    ///
+    /// Only offsets that can't be right are detected: a control byte index past the complete
+    /// quads, or an offset that isn't within the input or couldn't be where that many quads end
+    /// (at least 4 and at most 16 bytes per quad). Offsets that are plausible but wrong make the
+    /// cursor decode the wrong numbers. The cursor doesn't move if there's an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, Scalar, SeekError};
+    ///
+    /// let nums: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    ///
+    /// let mut cursor = DecodeCursor::new(&encoded, nums.len());
+    /// let (control_byte_index, encoded_nums_offset) = cursor.byte_offset_of(60);
+    ///
+    /// // later, with the stored offsets
+    /// let mut resumed = DecodeCursor::new(&encoded, nums.len());
+    /// resumed.seek_to_byte_offset(control_byte_index, encoded_nums_offset).unwrap();
+    /// let mut decoded = vec![0; 40];
+    /// assert_eq!(40, resumed.decode_slice::<Scalar>(&mut decoded));
+    /// assert_eq!(&nums[60..], &decoded[..]);
+    ///
+    /// assert_eq!(
+    ///     Err(SeekError::ControlByteIndexOutOfRange),
+    ///     resumed.seek_to_byte_offset(26, encoded_nums_offset)
+    /// );
+    /// # }
+    /// ```
+    pub fn seek_to_byte_offset(
+        &mut self,
+        control_byte_index: usize,
+        encoded_nums_offset: usize,
+    ) -> Result<(), SeekError> {
+        if control_byte_index > self.encoded_shape.complete_control_bytes_len {
+            return Err(SeekError::ControlByteIndexOutOfRange);
+        }
+        if encoded_nums_offset > self.encoded_nums.len()
+            || encoded_nums_offset < control_byte_index * 4
+            || encoded_nums_offset > control_byte_index * 16
+        {
+            return Err(SeekError::EncodedNumsOffsetOutOfRange);
+        }
+
+        self.control_bytes_read = control_byte_index;
+        self.encoded_bytes_read = encoded_nums_offset;
+        self.nums_decoded = control_byte_index * 4;
+
+        Ok(())
+    }
+
     /// Skip `to_skip` numbers. `to_skip` must be a multiple of 4, and must not be greater than the
     /// count of remaining numbers that are in complete blocks of 4. In other words, if you have
     /// 7 numbers remaining (a block of 4 and a partial block of 3), the only count you can skip is
     /// 4.
     ///
     /// Skipping numbers is several times faster than decoding them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_skip` isn't a count that can be skipped. Use `try_skip()` to skip a count
+    /// that hasn't been checked, such as one that came from a user.
     pub fn skip(&mut self, to_skip: usize) {
-        assert_eq!(to_skip % 4, 0, "Must be a multiple of 4");
+        if let Err(e) = self.try_skip(to_skip) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Like `skip()`, but returns an error rather than panicking if `to_skip` isn't a multiple of
+    /// 4 or goes past the end of the complete quads. The cursor doesn't move if there's an error.
+    ///
+    /// This never panics, whatever `to_skip` is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, Scalar, SkipError};
+    ///
+    /// let nums: Vec<u32> = (0..10).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    /// let mut cursor = DecodeCursor::new(&encoded, nums.len());
+    ///
+    /// assert_eq!(Err(SkipError::NotMultipleOf4), cursor.try_skip(3));
+    /// // only 2 complete quads
+    /// assert_eq!(Err(SkipError::PastEnd), cursor.try_skip(12));
+    /// assert_eq!(Ok(()), cursor.try_skip(8));
+    /// # }
+    /// ```
+    pub fn try_skip(&mut self, to_skip: usize) -> Result<(), SkipError> {
+        if to_skip % 4 != 0 {
+            return Err(SkipError::NotMultipleOf4);
+        }
         let control_bytes_to_skip = to_skip / 4;
-        assert!(
-            self.control_bytes_read + control_bytes_to_skip
-                <= self.encoded_shape.complete_control_bytes_len,
-            "Can't skip past the end of complete control bytes"
-        );
+        if control_bytes_to_skip
+            > self.encoded_shape.complete_control_bytes_len - self.control_bytes_read
+        {
+            return Err(SkipError::PastEnd);
+        }
 
         let slice_to_skip = &self.control_bytes
             [self.control_bytes_read..(self.control_bytes_read + control_bytes_to_skip)];
@@ -131,6 +310,85 @@ impl<'a> DecodeCursor<'a> {
         self.control_bytes_read += control_bytes_to_skip;
         self.encoded_bytes_read += skipped_encoded_len;
         self.nums_decoded += to_skip;
+
+        Ok(())
+    }
+
+    /// Step back `to_skip_back` numbers, e.g. for a galloping search that skipped past what it
+    /// was looking for. The cursor must end up at the start of a quad, so `to_skip_back` must be a
+    /// multiple of 4, or, once the trailing partial quad has been decoded, the number of numbers
+    /// in it plus a multiple of 4.
+    ///
+    /// The control bytes of the quads stepped back over say how long they are, so this takes as
+    /// long as skipping forward over them would.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    /// let mut cursor = DecodeCursor::new(&encoded, nums.len());
+    ///
+    /// // look for 50_000, skipping ahead until past it
+    /// let mut quad = [0; 4];
+    /// cursor.skip(40);
+    /// cursor.decode_slice::<Scalar>(&mut quad);
+    /// assert_eq!(43_000, quad[3]);
+    /// cursor.skip(16);
+    /// cursor.decode_slice::<Scalar>(&mut quad);
+    /// assert_eq!(60_000, quad[0]);
+    ///
+    /// // overshot, so step back to the quad between
+    /// cursor.skip_back(16);
+    /// cursor.decode_slice::<Scalar>(&mut quad);
+    /// assert_eq!([48_000, 49_000, 50_000, 51_000], quad);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor wouldn't end up at the start of a quad, or if `to_skip_back` is more
+    /// than the numbers decoded or skipped so far. Use `try_skip_back()` to get an error instead.
+    pub fn skip_back(&mut self, to_skip_back: usize) {
+        if let Err(e) = self.try_skip_back(to_skip_back) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Like `skip_back()`, but returns an error rather than panicking. The cursor doesn't move if
+    /// there's an error.
+    pub fn try_skip_back(&mut self, to_skip_back: usize) -> Result<(), SkipError> {
+        if to_skip_back > self.nums_decoded {
+            return Err(SkipError::PastStart);
+        }
+        let target = self.nums_decoded - to_skip_back;
+        if target % 4 != 0 {
+            return Err(SkipError::NotMultipleOf4);
+        }
+        let target_control_byte = target / 4;
+
+        // the trailing partial quad's control byte isn't counted as read once it's decoded, and
+        // only some of the lengths in it are used
+        let partial_quad_nums = self.nums_decoded - self.control_bytes_read * 4;
+        let partial_quad_len: usize = if partial_quad_nums > 0 {
+            let control_byte = self.control_bytes[self.control_bytes_read];
+            lengths::partial_quad_len(control_byte, partial_quad_nums)
+        } else {
+            0
+        };
+        let skipped_back_len = cumulative_encoded_len(
+            &self.control_bytes[target_control_byte..self.control_bytes_read],
+        ) + partial_quad_len;
+
+        self.control_bytes_read = target_control_byte;
+        self.encoded_bytes_read -= skipped_back_len;
+        self.nums_decoded = target;
+
+        Ok(())
     }
 
     /// Decode into the `output` buffer.
@@ -143,10 +401,7 @@ impl<'a> DecodeCursor<'a> {
     ///
     /// Returns the number of numbers decoded by this invocation, which may be less than the size
     /// of the buffer.
-    pub fn decode_slice<D: Decoder>(&mut self, output: &mut [u32]) -> usize
-    where
-        for<'b> SliceDecodeSink<'b>: DecodeQuadSink<D::DecodedQuad>,
-    {
+    pub fn decode_slice<D: Decoder>(&mut self, output: &mut [u32]) -> usize {
         let output_len = output.len();
 
         let mut sink = SliceDecodeSink::new(output);
@@ -154,6 +409,47 @@ impl<'a> DecodeCursor<'a> {
         self.decode_sink::<D, SliceDecodeSink>(&mut sink, output_len)
     }
 
+    /// Like `decode_slice()`, but also reports why decoding stopped, so that callers decoding in
+    /// chunks can tell whether to call again with more room, a differently sized buffer, or not at
+    /// all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, DecodeStop, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..10).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    ///
+    /// let mut cursor = DecodeCursor::new(&encoded, nums.len());
+    /// let mut buf = [0; 6];
+    ///
+    /// let progress = cursor.decode_slice_progress::<Scalar>(&mut buf);
+    /// // only whole quads are decoded until the end of the input
+    /// assert_eq!(4, progress.nums_decoded);
+    /// assert_eq!(DecodeStop::OutputNotQuadAligned, progress.stop);
+    ///
+    /// let progress = cursor.decode_slice_progress::<Scalar>(&mut buf);
+    /// assert_eq!(6, progress.nums_decoded);
+    /// assert_eq!(DecodeStop::EndOfInput, progress.stop);
+    /// # }
+    /// ```
+    pub fn decode_slice_progress<D: Decoder>(&mut self, output: &mut [u32]) -> DecodeProgress {
+        let nums_decoded = self.decode_slice::<D>(output);
+
+        let stop = if !self.has_more() {
+            DecodeStop::EndOfInput
+        } else if nums_decoded == output.len() {
+            DecodeStop::OutputFull
+        } else {
+            DecodeStop::OutputNotQuadAligned
+        };
+
+        DecodeProgress { nums_decoded, stop }
+    }
+
     /// Decode at most `max_numbers_to_decode` numbers from the input and hand them to `sink`.
     ///
     /// Decoding is done one quad at a time, except for the last quad, which may have fewer than
@@ -165,6 +461,9 @@ impl<'a> DecodeCursor<'a> {
     /// With each invocation of `decode()`, the `nums_decoded` parameter used in
     /// `DecodeQuadSink.on_quad()` will start counting up from 0 again.
     ///
+    /// If the sink's `capacity_hint()` is smaller than `max_numbers_to_decode`, it is used
+    /// instead.
+    ///
     /// Returns the number of numbers decoded.
     pub fn decode_sink<D, S>(&mut self, sink: &mut S, max_numbers_to_decode: usize) -> usize
     where
@@ -172,26 +471,116 @@ impl<'a> DecodeCursor<'a> {
         S: DecodeQuadSink<D::DecodedQuad> + DecodeQuadSink<<Scalar as Decoder>::DecodedQuad>,
     {
         let start_nums_decoded = self.nums_decoded;
-        let mut complete_quad_nums_decoded_this_invocation;
 
+        let max_numbers_to_decode = match sink.capacity_hint() {
+            Some(capacity) => cmp::min(capacity, max_numbers_to_decode),
+            None => max_numbers_to_decode,
+        };
         let complete_control_bytes_to_decode = max_numbers_to_decode / 4;
 
-        {
-            // decode complete quads
-            let (primary_nums_decoded, primary_bytes_read) = D::decode_quads(
-                &self.control_bytes
-                    [self.control_bytes_read..self.encoded_shape.complete_control_bytes_len],
-                &self.encoded_nums[self.encoded_bytes_read..],
-                complete_control_bytes_to_decode,
-                0,
+        // decode complete quads
+        let primary_nums_decoded =
+            self.decode_complete_quads::<D, S>(complete_control_bytes_to_decode, 0, sink);
+
+        // The rest is done with `Scalar` through a trait object, so that it is compiled once
+        // rather than for every combination of `Decoder` and sink. It handles at most a few quads
+        // for SIMD decoders, so dynamic dispatch costs very little.
+        self.decode_tail_scalar(
+            complete_control_bytes_to_decode - primary_nums_decoded / 4,
+            max_numbers_to_decode,
+            primary_nums_decoded,
+            sink,
+        );
+
+        self.nums_decoded - start_nums_decoded
+    }
+
+    /// Like `decode_sink()`, but complete quads that `D` declines to decode are handed to `T`
+    /// rather than `Scalar`, e.g. to use a decoder for an older instruction set, or an
+    /// instrumented one, for the tail of the input.
+    ///
+    /// Any complete quads that `T` also declines, and the trailing partial quad, are still decoded
+    /// with `Scalar`.
+    ///
+    /// Returns the number of numbers decoded.
+    pub fn decode_sink_with_tail<D, T, S>(
+        &mut self,
+        sink: &mut S,
+        max_numbers_to_decode: usize,
+    ) -> usize
+    where
+        D: Decoder,
+        T: Decoder,
+        S: DecodeQuadSink<D::DecodedQuad>
+            + DecodeQuadSink<T::DecodedQuad>
+            + DecodeQuadSink<<Scalar as Decoder>::DecodedQuad>,
+    {
+        let start_nums_decoded = self.nums_decoded;
+
+        let max_numbers_to_decode = match sink.capacity_hint() {
+            Some(capacity) => cmp::min(capacity, max_numbers_to_decode),
+            None => max_numbers_to_decode,
+        };
+        let complete_control_bytes_to_decode = max_numbers_to_decode / 4;
+
+        let primary_nums_decoded =
+            self.decode_complete_quads::<D, S>(complete_control_bytes_to_decode, 0, sink);
+        let nums_decoded_this_invocation = primary_nums_decoded
+            + self.decode_complete_quads::<T, S>(
+                complete_control_bytes_to_decode - primary_nums_decoded / 4,
+                primary_nums_decoded,
                 sink,
             );
 
-            complete_quad_nums_decoded_this_invocation = primary_nums_decoded;
-            self.nums_decoded += primary_nums_decoded;
-            self.encoded_bytes_read += primary_bytes_read;
-            self.control_bytes_read += complete_quad_nums_decoded_this_invocation / 4;
-        }
+        self.decode_tail_scalar(
+            complete_control_bytes_to_decode - nums_decoded_this_invocation / 4,
+            max_numbers_to_decode,
+            nums_decoded_this_invocation,
+            sink,
+        );
+
+        self.nums_decoded - start_nums_decoded
+    }
+
+    /// Decode at most `max_control_bytes_to_decode` complete quads with `D`, which may decode
+    /// fewer. Returns the number of numbers decoded.
+    fn decode_complete_quads<D, S>(
+        &mut self,
+        max_control_bytes_to_decode: usize,
+        nums_decoded_this_invocation: usize,
+        sink: &mut S,
+    ) -> usize
+    where
+        D: Decoder,
+        S: DecodeQuadSink<D::DecodedQuad>,
+    {
+        let (nums_decoded, bytes_read) = D::decode_quads(
+            &self.control_bytes
+                [self.control_bytes_read..self.encoded_shape.complete_control_bytes_len],
+            &self.encoded_nums[self.encoded_bytes_read..],
+            max_control_bytes_to_decode,
+            nums_decoded_this_invocation,
+            sink,
+        );
+
+        self.nums_decoded += nums_decoded;
+        self.encoded_bytes_read += bytes_read;
+        self.control_bytes_read += nums_decoded / 4;
+
+        nums_decoded
+    }
+
+    /// Decode with `Scalar` any complete quads that the primary `Decoder` did not (at most
+    /// `max_control_bytes_to_decode` of them), then the trailing partial quad if it's reached and
+    /// `max_numbers_to_decode` (for the whole invocation of `decode_sink()`) allows.
+    fn decode_tail_scalar(
+        &mut self,
+        max_control_bytes_to_decode: usize,
+        max_numbers_to_decode: usize,
+        nums_decoded_this_invocation: usize,
+        sink: &mut dyn DecodeSingleSink,
+    ) {
+        let mut complete_quad_nums_decoded_this_invocation = nums_decoded_this_invocation;
 
         {
             // handle any remaining full quads if the provided Decoder did not consume all the
@@ -200,9 +589,9 @@ impl<'a> DecodeCursor<'a> {
                 &self.control_bytes
                     [self.control_bytes_read..self.encoded_shape.complete_control_bytes_len],
                 &self.encoded_nums[self.encoded_bytes_read..],
-                complete_control_bytes_to_decode - complete_quad_nums_decoded_this_invocation / 4,
+                max_control_bytes_to_decode,
                 complete_quad_nums_decoded_this_invocation,
-                sink,
+                &mut DynSingleSink { sink },
             );
 
             complete_quad_nums_decoded_this_invocation += more_nums_decoded;
@@ -221,9 +610,7 @@ impl<'a> DecodeCursor<'a> {
             let control_byte = self.control_bytes[self.encoded_shape.complete_control_bytes_len];
 
             for i in 0..self.encoded_shape.leftover_numbers {
-                // first num's length in low 2 bits, last in high 2 bits
-                let bitmask = 0x03 << (i * 2);
-                let len = ((control_byte & bitmask) >> (i * 2)) as usize + 1;
+                let len = lengths::num_len(control_byte, i);
                 sink.on_number(
                     decode_num_scalar(len, &self.encoded_nums[self.encoded_bytes_read..]),
                     complete_quad_nums_decoded_this_invocation + i,
@@ -232,8 +619,6 @@ impl<'a> DecodeCursor<'a> {
                 self.encoded_bytes_read += len;
             }
         }
-
-        self.nums_decoded - start_nums_decoded
     }
 
     /// Returns the total length of input scanned so far: the complete block of control bytes, plus
@@ -242,15 +627,185 @@ impl<'a> DecodeCursor<'a> {
         self.encoded_shape.control_bytes_len + self.encoded_bytes_read
     }
 
+    /// Returns the length of the input that hasn't been consumed yet: the rest of the encoded
+    /// numbers, and nothing past them. `input_consumed()` counts all the control bytes as consumed
+    /// from the start, so this is the same as `data_bytes_remaining()`.
+    ///
+    /// Together with `input_consumed()`, this says exactly which range of the input is left,
+    /// e.g. to hand it to another stage of processing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, DecodeCursor, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..10).map(|i| i * 1000).collect();
+    /// // encoded numbers followed by other data
+    /// let mut buf = vec![0xFF; nums.len() * 5 + 100];
+    /// let encoded_len = encode::<Scalar>(&nums, &mut buf);
+    ///
+    /// let mut cursor = DecodeCursor::new(&buf, nums.len());
+    /// cursor.skip(8);
+    /// let rest = cursor.input_consumed()..(cursor.input_consumed() + cursor.input_remaining());
+    /// assert_eq!(encoded_len, rest.end);
+    /// # }
+    /// ```
+    pub fn input_remaining(&self) -> usize {
+        self.data_bytes_remaining()
+    }
+
+    /// Returns the number of bytes of encoded numbers (not counting control bytes) that haven't
+    /// been decoded or skipped yet.
+    ///
+    /// This scans the remaining control bytes.
+    pub fn data_bytes_remaining(&self) -> usize {
+        let complete = cumulative_encoded_len(
+            &self.control_bytes
+                [self.control_bytes_read..self.encoded_shape.complete_control_bytes_len],
+        );
+
+        // the partial quad's control byte isn't counted in `control_bytes_read` once decoded
+        let partial = if self.has_more() && self.encoded_shape.leftover_numbers > 0 {
+            let control_byte = self.control_bytes[self.encoded_shape.complete_control_bytes_len];
+            lengths::partial_quad_len(control_byte, self.encoded_shape.leftover_numbers)
+        } else {
+            0
+        };
+
+        complete + partial
+    }
+
+    /// Returns the number of control bytes for quads (including a trailing partial quad) that
+    /// haven't been decoded or skipped yet.
+    pub fn control_bytes_remaining(&self) -> usize {
+        if self.has_more() {
+            self.control_bytes.len() - self.control_bytes_read
+        } else {
+            0
+        }
+    }
+
     /// Returns true iff there are more numbers to be decoded.
     pub fn has_more(&self) -> bool {
         self.nums_decoded < self.total_nums
     }
+
+    /// How far the cursor has gotten, to restore with `set_position()` on a new cursor over the
+    /// same input.
+    pub(crate) fn position(&self) -> CursorPosition {
+        CursorPosition {
+            nums_decoded: self.nums_decoded,
+            control_bytes_read: self.control_bytes_read,
+            encoded_bytes_read: self.encoded_bytes_read,
+        }
+    }
+
+    pub(crate) fn set_position(&mut self, position: CursorPosition) {
+        self.nums_decoded = position.nums_decoded;
+        self.control_bytes_read = position.control_bytes_read;
+        self.encoded_bytes_read = position.encoded_bytes_read;
+    }
+}
+
+/// The progress of a `DecodeCursor` through its input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CursorPosition {
+    pub(crate) nums_decoded: usize,
+    pub(crate) control_bytes_read: usize,
+    pub(crate) encoded_bytes_read: usize,
 }
 
-#[cfg(test)]
+/// The result of `DecodeCursor::decode_slice_progress()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeProgress {
+    /// The number of numbers decoded.
+    pub nums_decoded: usize,
+    /// Why no more numbers were decoded.
+    pub stop: DecodeStop,
+}
+
+/// Why `DecodeCursor::decode_slice_progress()` stopped decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeStop {
+    /// Every number in the input has been decoded.
+    EndOfInput,
+    /// The output is full, and there are more numbers to decode.
+    OutputFull,
+    /// The output has room left, but less than the next quad needs: 4 numbers for a complete quad,
+    /// or all of the trailing partial quad. Decoding more needs an output with room for at least
+    /// that many.
+    OutputNotQuadAligned,
+}
+
+/// Why `DecodeCursor::try_skip()` couldn't skip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipError {
+    /// Only whole quads can be skipped, so the count must be a multiple of 4.
+    NotMultipleOf4,
+    /// There aren't that many numbers left in complete quads.
+    PastEnd,
+    /// There aren't that many numbers before the cursor.
+    PastStart,
+}
+
+impl fmt::Display for SkipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SkipError::NotMultipleOf4 => write!(f, "Must be a multiple of 4"),
+            SkipError::PastEnd => write!(f, "Can't skip past the end of complete control bytes"),
+            SkipError::PastStart => write!(f, "Can't skip back past the start"),
+        }
+    }
+}
+
+impl error::Error for SkipError {}
+
+/// Why `DecodeCursor::seek_to_byte_offset()` couldn't seek.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekError {
+    /// The control byte index is past the last complete quad.
+    ControlByteIndexOutOfRange,
+    /// The offset is past the end of the encoded numbers, or couldn't be where the quads before
+    /// the control byte index end.
+    EncodedNumsOffsetOutOfRange,
+}
+
+impl fmt::Display for SeekError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SeekError::ControlByteIndexOutOfRange => write!(f, "Control byte index out of range"),
+            SeekError::EncodedNumsOffsetOutOfRange => {
+                write!(f, "Encoded numbers offset out of range")
+            }
+        }
+    }
+}
+
+impl error::Error for SeekError {}
+
+/// Lets `Scalar` hand numbers to a `DecodeSingleSink` trait object.
+struct DynSingleSink<'s> {
+    sink: &'s mut dyn DecodeSingleSink,
+}
+
+impl<'s> DecodeSingleSink for DynSingleSink<'s> {
+    #[inline]
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        self.sink.on_number(num, nums_decoded)
+    }
+}
+
+impl<'s> DecodeQuadSink<<Scalar as Decoder>::DecodedQuad> for DynSingleSink<'s> {
+    fn on_quad(&mut self, _quad: (), _nums_decoded: usize) {
+        unreachable!("Scalar only uses on_number()")
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
 mod tests {
     use super::*;
+    use decode::WriteQuadToSlice;
     use encode;
 
     #[test]
@@ -271,6 +826,151 @@ mod tests {
         DecodeCursor::new(&encoded[0..encoded_len], nums.len()).skip(104);
     }
 
+    #[test]
+    fn try_skip_never_panics_and_leaves_cursor_alone_on_error() {
+        let nums: Vec<u32> = (0..103).map(|i| i * 7919).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+        for start in (0..26).map(|q| q * 4) {
+            for to_skip in (0..110).chain(vec![usize::max_value() - 3, usize::max_value()]) {
+                let mut cursor = DecodeCursor::new(&encoded[0..encoded_len], nums.len());
+                cursor.skip(start);
+                let before = cursor.position();
+
+                let expected = if to_skip % 4 != 0 {
+                    Err(SkipError::NotMultipleOf4)
+                } else if to_skip > 100 - start {
+                    Err(SkipError::PastEnd)
+                } else {
+                    Ok(())
+                };
+                assert_eq!(expected, cursor.try_skip(to_skip));
+
+                if expected.is_ok() {
+                    let mut decoded = [0; 4];
+                    cursor.decode_slice::<Scalar>(&mut decoded);
+                    assert_eq!(nums[start + to_skip], decoded[0]);
+                } else {
+                    assert_eq!(before, cursor.position());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seek_to_every_byte_offset_in_both_directions() {
+        let nums: Vec<u32> = (0..103).map(|i| (i * 7919) >> (i % 32)).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let encoded = &encoded[0..encoded_len];
+        let offsets: Vec<(usize, usize)> = (0..26)
+            .map(|q| DecodeCursor::new(encoded, nums.len()).byte_offset_of(q * 4))
+            .collect();
+
+        let mut cursor = DecodeCursor::new(encoded, nums.len());
+        let mut decoded = [0; 4];
+        for &q in &[25, 0, 13, 12, 14, 1, 24] {
+            let (control_byte_index, encoded_nums_offset) = offsets[q];
+            assert_eq!(
+                Ok(()),
+                cursor.seek_to_byte_offset(control_byte_index, encoded_nums_offset)
+            );
+            let len = cursor.decode_slice::<Scalar>(&mut decoded);
+            assert_eq!(&nums[(q * 4)..(q * 4 + len)], &decoded[0..len]);
+        }
+        assert_eq!(Ok(()), cursor.seek_to_byte_offset(offsets[25].0, offsets[25].1));
+        assert_eq!(3, cursor.decode_slice::<Scalar>(&mut decoded));
+        assert_eq!(encoded_len, cursor.input_consumed());
+    }
+
+    #[test]
+    fn seek_to_impossible_byte_offset_is_an_error() {
+        let nums: Vec<u32> = (0..10).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let mut cursor = DecodeCursor::new(&encoded[0..encoded_len], nums.len());
+        cursor.skip(4);
+        let before = cursor.position();
+
+        for &(control_byte_index, encoded_nums_offset, error) in &[
+            (3, 12, SeekError::ControlByteIndexOutOfRange),
+            (1, 3, SeekError::EncodedNumsOffsetOutOfRange),
+            (1, 17, SeekError::EncodedNumsOffsetOutOfRange),
+            (2, encoded_len, SeekError::EncodedNumsOffsetOutOfRange),
+        ] {
+            assert_eq!(
+                Err(error),
+                cursor.seek_to_byte_offset(control_byte_index, encoded_nums_offset)
+            );
+            assert_eq!(before, cursor.position());
+        }
+    }
+
+    #[test]
+    fn skip_back_from_every_position() {
+        let nums: Vec<u32> = (0..103).map(|i| (i * 7919) >> (i % 32)).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let encoded = &encoded[0..encoded_len];
+
+        for start in (0..26).map(|q| q * 4) {
+            let mut cursor = DecodeCursor::new(encoded, nums.len());
+            cursor.skip(start);
+            // decode to the end, to cover stepping back over the trailing partial quad
+            let mut rest = vec![0; nums.len() - start];
+            cursor.decode_slice::<Scalar>(&mut rest);
+
+            for target in (0..(start / 4 + 1)).map(|q| q * 4) {
+                let mut c = DecodeCursor::new(encoded, nums.len());
+                c.skip(start);
+                c.skip_back(start - target);
+                let mut decoded = [0; 4];
+                let len = c.decode_slice::<Scalar>(&mut decoded);
+                assert_eq!(&nums[target..(target + len)], &decoded[0..len]);
+
+                let mut at_end = DecodeCursor::new(encoded, nums.len());
+                at_end.set_position(cursor.position());
+                at_end.skip_back(nums.len() - target);
+                let mut skipped_to = DecodeCursor::new(encoded, nums.len());
+                skipped_to.skip(target);
+                assert_eq!(skipped_to.position(), at_end.position());
+            }
+        }
+    }
+
+    #[test]
+    fn try_skip_back_reports_errors_and_leaves_cursor_alone() {
+        let nums: Vec<u32> = (0..10).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let mut cursor = DecodeCursor::new(&encoded[0..encoded_len], nums.len());
+        let mut decoded = [0; 10];
+        cursor.decode_slice::<Scalar>(&mut decoded);
+        let before = cursor.position();
+
+        assert_eq!(Err(SkipError::PastStart), cursor.try_skip_back(11));
+        assert_eq!(Err(SkipError::NotMultipleOf4), cursor.try_skip_back(4));
+        assert_eq!(before, cursor.position());
+
+        assert_eq!(Ok(()), cursor.try_skip_back(2));
+        assert_eq!(Err(SkipError::NotMultipleOf4), cursor.try_skip_back(2));
+        assert_eq!(Ok(()), cursor.try_skip_back(8));
+        assert_eq!(CursorPosition::default(), cursor.position());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't skip back past the start")]
+    fn skip_back_panics_past_start() {
+        let nums: Vec<u32> = (0..100).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let mut cursor = DecodeCursor::new(&encoded, nums.len());
+        cursor.skip(8);
+        cursor.skip_back(12);
+    }
+
     #[test]
     fn skip_entire_enput_is_done() {
         let nums: Vec<u32> = (0..100).collect();
@@ -292,4 +992,227 @@ mod tests {
         // but nothing gets decoded into it
         assert_eq!(0, cursor.decode_slice::<Scalar>(&mut decoded[..]))
     }
+
+    #[test]
+    fn remaining_bytes_track_decoding() {
+        let nums: Vec<u32> = (0..10).map(|i| 1 << (i * 3)).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let mut cursor = DecodeCursor::new(&encoded, nums.len());
+
+        assert_eq!(3, cursor.control_bytes_remaining());
+        assert_eq!(encoded_len - 3, cursor.data_bytes_remaining());
+
+        let mut decoded = [0; 10];
+        for &(to_decode, control_bytes) in &[(4, 2), (4, 1), (2, 0)] {
+            cursor.decode_slice::<Scalar>(&mut decoded[0..to_decode]);
+            assert_eq!(control_bytes, cursor.control_bytes_remaining());
+            assert_eq!(encoded_len, cursor.input_consumed() + cursor.input_remaining());
+        }
+        assert_eq!(0, cursor.data_bytes_remaining());
+    }
+
+    #[test]
+    fn reset_starts_over_on_new_input() {
+        let first: Vec<u32> = (0..10).collect();
+        let second: Vec<u32> = (0..7).map(|i| i * 1000).collect();
+        let mut first_encoded = vec![0; first.len() * 5];
+        encode::<Scalar>(&first, &mut first_encoded);
+        let mut second_encoded = vec![0; second.len() * 5];
+        let second_len = encode::<Scalar>(&second, &mut second_encoded);
+
+        let mut cursor = DecodeCursor::new(&first_encoded, first.len());
+        cursor.skip(4);
+        cursor.reset(&second_encoded, second.len());
+
+        let mut decoded = [0; 7];
+        assert_eq!(7, cursor.decode_slice::<Scalar>(&mut decoded));
+        assert_eq!(&second[..], &decoded[..]);
+        assert_eq!(second_len, cursor.input_consumed());
+        assert!(!cursor.has_more());
+    }
+
+    #[test]
+    fn decode_slice_progress_reports_each_stop() {
+        let nums: Vec<u32> = (0..10).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+        let mut cursor = DecodeCursor::new(&encoded, nums.len());
+
+        let mut buf = [0; 8];
+        assert_eq!(
+            DecodeProgress {
+                nums_decoded: 8,
+                stop: DecodeStop::OutputFull,
+            },
+            cursor.decode_slice_progress::<Scalar>(&mut buf)
+        );
+        // the partial quad needs room for both of its numbers
+        assert_eq!(
+            DecodeProgress {
+                nums_decoded: 0,
+                stop: DecodeStop::OutputNotQuadAligned,
+            },
+            cursor.decode_slice_progress::<Scalar>(&mut buf[0..1])
+        );
+        assert_eq!(
+            DecodeProgress {
+                nums_decoded: 2,
+                stop: DecodeStop::EndOfInput,
+            },
+            cursor.decode_slice_progress::<Scalar>(&mut buf)
+        );
+        assert_eq!(&nums[8..], &buf[0..2]);
+    }
+
+    #[test]
+    fn decode_sink_with_tail_uses_tail_decoder_then_scalar() {
+        let nums: Vec<u32> = (0..14).map(|i| i * 1000).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+        let mut cursor = DecodeCursor::new(&encoded[0..encoded_len], nums.len());
+        let mut sink = RecordingSink {
+            nums: Vec::new(),
+            quad_starts: Vec::new(),
+        };
+        assert_eq!(
+            14,
+            cursor.decode_sink_with_tail::<DeclineAll, TwoQuads, _>(&mut sink, 14)
+        );
+
+        // the tail decoder gets the first two quads, and Scalar the third and the partial quad
+        assert_eq!(vec![0, 4], sink.quad_starts);
+        assert_eq!(nums, sink.nums);
+        assert_eq!(encoded_len, cursor.input_consumed());
+    }
+
+    #[test]
+    fn decode_sink_stops_at_capacity_hint_and_resumes() {
+        let nums: Vec<u32> = (0..23).map(|i| i * 1000).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+        let mut cursor = DecodeCursor::new(&encoded[0..encoded_len], nums.len());
+        let mut sink = BoundedSink {
+            buf: Vec::new(),
+            capacity: 10,
+        };
+        let mut received = Vec::new();
+
+        while cursor.has_more() {
+            let decoded = cursor.decode_sink::<Scalar, _>(&mut sink, usize::max_value());
+            // stops at a quad boundary within the capacity, unless it reached the end
+            assert!(decoded <= 10);
+            assert!(decoded % 4 == 0 || !cursor.has_more());
+            received.append(&mut sink.buf);
+        }
+
+        assert_eq!(nums, received);
+        assert_eq!(encoded_len, cursor.input_consumed());
+    }
+
+    /// Buffers up to `capacity` numbers until they're drained.
+    struct BoundedSink {
+        buf: Vec<u32>,
+        capacity: usize,
+    }
+
+    impl DecodeSingleSink for BoundedSink {
+        fn on_number(&mut self, num: u32, _nums_decoded: usize) {
+            assert!(self.buf.len() < self.capacity, "Sink overflowed");
+            self.buf.push(num);
+        }
+
+        fn capacity_hint(&self) -> Option<usize> {
+            Some(self.capacity - self.buf.len())
+        }
+    }
+
+    impl DecodeQuadSink<()> for BoundedSink {
+        fn on_quad(&mut self, _quad: (), _nums_decoded: usize) {
+            unreachable!()
+        }
+    }
+
+    struct DeclineAll;
+
+    impl Decoder for DeclineAll {
+        type DecodedQuad = ();
+
+        fn decode_quads<S: DecodeQuadSink<()>>(
+            _control_bytes: &[u8],
+            _encoded_nums: &[u8],
+            _max_control_bytes_to_decode: usize,
+            _nums_already_decoded: usize,
+            _sink: &mut S,
+        ) -> (usize, usize) {
+            (0, 0)
+        }
+    }
+
+    /// Decodes at most two quads, handing them over as whole quads.
+    struct TwoQuads;
+
+    struct Quad([u32; 4]);
+
+    impl WriteQuadToSlice for Quad {
+        fn write_to_slice(self, output: &mut [u32]) {
+            output[0..4].copy_from_slice(&self.0);
+        }
+    }
+
+    impl Decoder for TwoQuads {
+        type DecodedQuad = Quad;
+
+        fn decode_quads<S: DecodeQuadSink<Quad>>(
+            control_bytes: &[u8],
+            encoded_nums: &[u8],
+            max_control_bytes_to_decode: usize,
+            nums_already_decoded: usize,
+            sink: &mut S,
+        ) -> (usize, usize) {
+            let quads = ::std::cmp::min(
+                2,
+                ::std::cmp::min(max_control_bytes_to_decode, control_bytes.len()),
+            );
+            let mut decoded = vec![0; quads * 4];
+            let mut cursor = DecodeCursor::from_parts(control_bytes, encoded_nums, quads * 4);
+            let nums_decoded = cursor.decode_slice::<Scalar>(&mut decoded);
+            let bytes_read = cursor.input_consumed() - quads;
+
+            for (i, quad) in decoded[0..nums_decoded].chunks(4).enumerate() {
+                sink.on_quad(
+                    Quad([quad[0], quad[1], quad[2], quad[3]]),
+                    nums_already_decoded + i * 4,
+                );
+            }
+
+            (nums_decoded, bytes_read)
+        }
+    }
+
+    struct RecordingSink {
+        nums: Vec<u32>,
+        quad_starts: Vec<usize>,
+    }
+
+    impl DecodeSingleSink for RecordingSink {
+        fn on_number(&mut self, num: u32, _nums_decoded: usize) {
+            self.nums.push(num);
+        }
+    }
+
+    impl DecodeQuadSink<()> for RecordingSink {
+        fn on_quad(&mut self, _quad: (), _nums_decoded: usize) {
+            unreachable!()
+        }
+    }
+
+    impl DecodeQuadSink<Quad> for RecordingSink {
+        fn on_quad(&mut self, quad: Quad, nums_decoded: usize) {
+            self.quad_starts.push(nums_decoded);
+            self.nums.extend_from_slice(&quad.0);
+        }
+    }
 }
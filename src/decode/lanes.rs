@@ -0,0 +1,172 @@
+use std::cmp;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {encoded_shape, lengths, DecodeCursor, Scalar};
+use super::decode_num_scalar;
+
+/// Masks for the low `len` bytes of a `u32`, indexed by `len`.
+const LEN_MASKS: [u32; 5] = [0, 0xFF, 0xFFFF, 0xFF_FFFF, 0xFFFF_FFFF];
+
+/// Decode four independent encoded streams at once, such as four short posting lists that are
+/// about to be merged.
+///
+/// `inputs[i]` holds `counts[i]` encoded numbers, which are decoded into `outputs[i]`. The streams
+/// may have different lengths.
+///
+/// Decoding a single short stream leaves the CPU mostly idle, since each quad's position depends
+/// on the previous quad's length. Here, one quad from each stream is decoded per step, so the four
+/// independent chains of work overlap. For long streams, decoding each with `decode()` and a SIMD
+/// `Decoder` is faster.
+///
+/// Returns the number of bytes read from each input.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{decode_four, encode, Scalar};
+///
+/// let lists: Vec<Vec<u32>> = (1..5).map(|k| (0..(k * 10)).map(|i| i * k).collect()).collect();
+/// let mut encoded = vec![vec![0; 250]; 4];
+/// let mut encoded_lens = [0; 4];
+/// for i in 0..4 {
+///     encoded_lens[i] = encode::<Scalar>(&lists[i], &mut encoded[i]);
+/// }
+///
+/// let mut decoded = vec![vec![0; 40]; 4];
+/// let bytes_read = {
+///     let mut d = decoded.iter_mut();
+///     decode_four(
+///         [&encoded[0], &encoded[1], &encoded[2], &encoded[3]],
+///         [10, 20, 30, 40],
+///         [d.next().unwrap(), d.next().unwrap(), d.next().unwrap(), d.next().unwrap()],
+///     )
+/// };
+/// assert_eq!(encoded_lens, bytes_read);
+///
+/// for i in 0..4 {
+///     assert_eq!(&lists[i][..], &decoded[i][0..lists[i].len()]);
+/// }
+/// # }
+/// ```
+pub fn decode_four(
+    inputs: [&[u8]; 4],
+    counts: [usize; 4],
+    outputs: [&mut [u32]; 4],
+) -> [usize; 4] {
+    let mut control_bytes: [&[u8]; 4] = [&[]; 4];
+    let mut encoded_nums: [&[u8]; 4] = [&[]; 4];
+    let mut bytes_read = [0; 4];
+    let mut lockstep_quads = usize::MAX;
+
+    for lane in 0..4 {
+        let shape = encoded_shape(counts[lane]);
+        assert!(
+            outputs[lane].len() >= counts[lane],
+            "Output must have room for every number"
+        );
+
+        let (c, n) = inputs[lane].split_at(shape.control_bytes_len);
+        control_bytes[lane] = c;
+        encoded_nums[lane] = n;
+        lockstep_quads = cmp::min(lockstep_quads, shape.complete_control_bytes_len);
+    }
+
+    for quad_index in 0..lockstep_quads {
+        // the compiler unrolls this, leaving four independent sequences of loads and stores
+        for lane in 0..4 {
+            let (len0, len1, len2, len3) = lengths::per_num(control_bytes[lane][quad_index]);
+            let offset = bytes_read[lane];
+            let nums = encoded_nums[lane];
+            let output = &mut outputs[lane][(quad_index * 4)..(quad_index * 4 + 4)];
+
+            output[0] = decode_num(nums, offset, len0 as usize);
+            output[1] = decode_num(nums, offset + len0 as usize, len1 as usize);
+            output[2] = decode_num(nums, offset + (len0 + len1) as usize, len2 as usize);
+            output[3] = decode_num(nums, offset + (len0 + len1 + len2) as usize, len3 as usize);
+
+            bytes_read[lane] += (len0 + len1 + len2 + len3) as usize;
+        }
+    }
+
+    // finish the longer streams one at a time
+    for lane in 0..4 {
+        let mut cursor = DecodeCursor::from_parts(
+            &control_bytes[lane][lockstep_quads..],
+            &encoded_nums[lane][bytes_read[lane]..],
+            counts[lane] - lockstep_quads * 4,
+        );
+        cursor.decode_slice::<Scalar>(&mut outputs[lane][(lockstep_quads * 4)..counts[lane]]);
+
+        // the cursor counts the control bytes it was given, but all of them have been read now
+        let tail_control_bytes_len = control_bytes[lane].len() - lockstep_quads;
+        bytes_read[lane] +=
+            control_bytes[lane].len() + cursor.input_consumed() - tail_control_bytes_len;
+    }
+
+    bytes_read
+}
+
+/// Decode the `len`-byte number at `offset`, reading a whole `u32` and masking off the excess
+/// when there's room to.
+#[inline]
+fn decode_num(nums: &[u8], offset: usize, len: usize) -> u32 {
+    if offset + 4 <= nums.len() {
+        LittleEndian::read_u32(&nums[offset..(offset + 4)]) & LEN_MASKS[len]
+    } else {
+        decode_num_scalar(len, &nums[offset..])
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use encode;
+
+    #[test]
+    fn decode_four_random_lengths() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let lists: Vec<Vec<u32>> = (0..4)
+                .map(|_| {
+                    (0..rng.gen_range(0, 100))
+                        .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                        .collect()
+                })
+                .collect();
+
+            let mut encoded: Vec<Vec<u8>> = Vec::new();
+            let mut encoded_lens = [0; 4];
+            for (i, list) in lists.iter().enumerate() {
+                let mut e = vec![0; list.len() * 5];
+                encoded_lens[i] = encode::<Scalar>(list, &mut e);
+                e.truncate(encoded_lens[i]);
+                encoded.push(e);
+            }
+
+            let mut decoded: Vec<Vec<u32>> = lists.iter().map(|l| vec![0; l.len()]).collect();
+            let bytes_read = {
+                let mut d = decoded.iter_mut();
+                decode_four(
+                    [&encoded[0], &encoded[1], &encoded[2], &encoded[3]],
+                    [lists[0].len(), lists[1].len(), lists[2].len(), lists[3].len()],
+                    [
+                        d.next().unwrap(),
+                        d.next().unwrap(),
+                        d.next().unwrap(),
+                        d.next().unwrap(),
+                    ],
+                )
+            };
+
+            assert_eq!(encoded_lens, bytes_read);
+            assert_eq!(lists, decoded);
+        }
+    }
+}
@@ -1,16 +1,36 @@
+use std::any;
+
 use byteorder::{ByteOrder, LittleEndian};
 
+use instrument::instrument;
+use lengths;
+
+pub mod context;
 pub mod cursor;
+pub mod lanes;
+pub mod owned;
+pub mod plan;
+pub mod sinks;
+pub mod unchecked;
+pub mod zip;
 
 #[cfg(feature = "x86_ssse3")]
 pub mod ssse3;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "encode"))]
 mod tests;
 
 /// Decode bytes to numbers.
+///
+/// `decode()` decodes fewer than 16 numbers one at a time itself, without calling the `Decoder`,
+/// since that's faster for so few. Use more numbers than that, or `DecodeCursor`, to exercise an
+/// implementation.
 pub trait Decoder {
-    type DecodedQuad;
+    /// The representation of a decoded quad handed to `DecodeQuadSink.on_quad()`.
+    ///
+    /// Use `()` if the implementation passes all numbers to `DecodeSingleSink.on_number()`
+    /// instead.
+    type DecodedQuad: WriteQuadToSlice;
 
     /// Decode encoded numbers in complete quads.
     ///
@@ -37,6 +57,35 @@ pub trait Decoder {
         nums_already_decoded: usize,
         sink: &mut S,
     ) -> (usize, usize);
+
+    /// Like `decode_quads()`, but implementations may skip bounds checks on `encoded_nums`. Used by
+    /// `decode_unchecked()`.
+    ///
+    /// The default implementation simply calls `decode_quads()`.
+    ///
+    /// `decode_unchecked()` writes the quads handed to the sink without bounds checks, so
+    /// implementations must hand it only the quads they decode, numbered from
+    /// `nums_already_decoded`.
+    ///
+    /// # Safety
+    ///
+    /// `encoded_nums` must hold all the numbers described by the first
+    /// `max_control_bytes_to_decode` control bytes.
+    unsafe fn decode_quads_unchecked<S: DecodeQuadSink<Self::DecodedQuad>>(
+        control_bytes: &[u8],
+        encoded_nums: &[u8],
+        max_control_bytes_to_decode: usize,
+        nums_already_decoded: usize,
+        sink: &mut S,
+    ) -> (usize, usize) {
+        Self::decode_quads(
+            control_bytes,
+            encoded_nums,
+            max_control_bytes_to_decode,
+            nums_already_decoded,
+            sink,
+        )
+    }
 }
 
 /// Receives numbers decoded via a Decoder in `DecodeCursor.decode_sink()`.
@@ -58,6 +107,27 @@ pub trait DecodeSingleSink {
     /// `nums_decoded` is the number of numbers that have already been decoded before this number
     /// in the current invocation of `DecodeCursor.decode_sink()`.
     fn on_number(&mut self, num: u32, nums_decoded: usize);
+
+    /// How many more numbers the sink can accept right now, or `None` (the default) if there's no
+    /// limit.
+    ///
+    /// This is checked once at the start of each `DecodeCursor.decode_sink()` invocation, which
+    /// then decodes no more numbers than this, stopping at a quad boundary as it does for its
+    /// `max_numbers_to_decode` parameter. A sink that buffers numbers for a bounded channel, say,
+    /// can return the free space in its buffer, and decoding resumes where it stopped with the next
+    /// call to `decode_sink()` once the buffer has drained.
+    fn capacity_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// A decoded quad that can be written into a slice of `u32`s.
+///
+/// Every `Decoder::DecodedQuad` type implements this, which is what lets `decode()` and
+/// `DecodeCursor.decode_slice()` work with any `Decoder` without extra trait bounds.
+pub trait WriteQuadToSlice {
+    /// Write the 4 numbers in the quad to the first 4 slots of `output`.
+    fn write_to_slice(self, output: &mut [u32]);
 }
 
 impl<'a> DecodeSingleSink for SliceDecodeSink<'a> {
@@ -67,18 +137,20 @@ impl<'a> DecodeSingleSink for SliceDecodeSink<'a> {
     }
 }
 
+impl<'a, Q: WriteQuadToSlice> DecodeQuadSink<Q> for SliceDecodeSink<'a> {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        quad.write_to_slice(&mut self.output[nums_decoded..(nums_decoded + 4)]);
+    }
+}
+
 /// A sink for writing to a slice.
-///
-/// Has to be public because it's in trait bounds on `decode()`.
-#[doc(hidden)]
 pub struct SliceDecodeSink<'a> {
     output: &'a mut [u32],
 }
 
 impl<'a> SliceDecodeSink<'a> {
     /// Create a new sink that wraps a slice.
-    ///
-    /// `output` must be at least as big as the
     fn new(output: &'a mut [u32]) -> SliceDecodeSink<'a> {
         SliceDecodeSink { output }
     }
@@ -91,10 +163,43 @@ impl<'a> SliceDecodeSink<'a> {
 /// `output` must be at least of size 4, and must be large enough for all `count` numbers.
 ///
 /// Returns the number of bytes read from `input`.
-pub fn decode<D: Decoder>(input: &[u8], count: usize, output: &mut [u32]) -> usize
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+pub fn decode<D: Decoder>(input: &[u8], count: usize, output: &mut [u32]) -> usize {
+    instrument("decode", any::type_name::<D>(), count, || {
+        decode_uninstrumented::<D>(input, count, output)
+    })
+}
+
+/// Decode `count` numbers from `input` into a new `Vec` of exactly `count` numbers.
+///
+/// The `count` must be the same as the number of items originally encoded.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{decode_to_new_vec, encode, Scalar};
+///
+/// let mut encoded = vec![0; 3 * 5];
+/// encode::<Scalar>(&[1, 1000, 1_000_000], &mut encoded);
+///
+/// assert_eq!(vec![1, 1000, 1_000_000], decode_to_new_vec::<Scalar>(&encoded, 3));
+/// # }
+/// ```
+pub fn decode_to_new_vec<D: Decoder>(input: &[u8], count: usize) -> Vec<u32> {
+    // A zeroed allocation costs no more than an uninitialized one for large vecs, since the
+    // allocator gets zeroed pages from the OS, and `Decoder`s never see uninitialized memory.
+    let mut output = vec![0; count];
+    decode::<D>(input, count, &mut output);
+
+    output
+}
+
+fn decode_uninstrumented<D: Decoder>(input: &[u8], count: usize, output: &mut [u32]) -> usize {
+    if count < SMALL_INPUT_LEN {
+        assert!(output.len() >= count, "output buffer was not large enough");
+        return decode_small(input, count, output);
+    }
+
     let mut cursor = cursor::DecodeCursor::new(&input, count);
 
     assert_eq!(
@@ -106,6 +211,25 @@ where
     cursor.input_consumed()
 }
 
+/// Counts smaller than this are decoded by `decode_small()`.
+const SMALL_INPUT_LEN: usize = 16;
+
+/// Decode a few numbers one at a time, which is faster for tiny inputs than setting up a cursor
+/// and sink.
+fn decode_small(input: &[u8], count: usize, output: &mut [u32]) -> usize {
+    let control_bytes_len = (count + 3) / 4;
+    let mut bytes_read = control_bytes_len;
+
+    for (i, num) in output[0..count].iter_mut().enumerate() {
+        let control_byte = input[i / 4];
+        let len = lengths::num_len(control_byte, i % 4);
+        *num = decode_num_scalar(len, &input[bytes_read..]);
+        bytes_read += len;
+    }
+
+    bytes_read
+}
+
 #[inline]
 pub fn decode_num_scalar(len: usize, input: &[u8]) -> u32 {
     let mut buf = [0_u8; 4];
@@ -0,0 +1,173 @@
+use super::{DecodeQuadSink, Decoder};
+use super::cursor::{CursorPosition, DecodeCursor, SeekError, SkipError};
+use Scalar;
+
+/// A `DecodeCursor` that owns its input (or holds any other kind of reference to it), for readers
+/// that keep their encoded data and their place in it in the same struct.
+///
+/// `B` can be anything that derefs to bytes with `AsRef<[u8]>`: a `Vec<u8>`, `Box<[u8]>`,
+/// `Arc<[u8]>`, `Cow<[u8]>`, or a plain `&[u8]`.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use std::sync::Arc;
+///
+/// use stream_vbyte::{encode, OwnedDecodeCursor, Scalar};
+///
+/// struct Reader {
+///     cursor: OwnedDecodeCursor<Arc<[u8]>>,
+/// }
+///
+/// let nums: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+/// encoded.truncate(encoded_len);
+///
+/// let mut reader = Reader {
+///     cursor: OwnedDecodeCursor::new(Arc::from(encoded), nums.len()),
+/// };
+/// reader.cursor.skip(40);
+///
+/// let mut decoded = vec![0; 60];
+/// assert_eq!(60, reader.cursor.decode_slice::<Scalar>(&mut decoded));
+/// assert_eq!(&nums[40..], &decoded[..]);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OwnedDecodeCursor<B: AsRef<[u8]>> {
+    input: B,
+    count: usize,
+    position: CursorPosition,
+}
+
+impl<B: AsRef<[u8]>> OwnedDecodeCursor<B> {
+    /// Create a cursor over the `count` numbers encoded in `input`.
+    pub fn new(input: B, count: usize) -> OwnedDecodeCursor<B> {
+        // check the input is long enough for the control bytes, as `DecodeCursor::new()` does
+        DecodeCursor::new(input.as_ref(), count);
+
+        OwnedDecodeCursor {
+            input,
+            count,
+            position: CursorPosition::default(),
+        }
+    }
+
+    /// See `DecodeCursor.skip()`.
+    pub fn skip(&mut self, to_skip: usize) {
+        self.with_cursor(|c| c.skip(to_skip))
+    }
+
+    /// See `DecodeCursor.skip_back()`.
+    pub fn skip_back(&mut self, to_skip_back: usize) {
+        self.with_cursor(|c| c.skip_back(to_skip_back))
+    }
+
+    /// See `DecodeCursor.try_skip_back()`.
+    pub fn try_skip_back(&mut self, to_skip_back: usize) -> Result<(), SkipError> {
+        self.with_cursor(|c| c.try_skip_back(to_skip_back))
+    }
+
+    /// See `DecodeCursor.seek_to_byte_offset()`.
+    pub fn seek_to_byte_offset(
+        &mut self,
+        control_byte_index: usize,
+        encoded_nums_offset: usize,
+    ) -> Result<(), SeekError> {
+        self.with_cursor(|c| c.seek_to_byte_offset(control_byte_index, encoded_nums_offset))
+    }
+
+    /// See `DecodeCursor.try_skip()`.
+    pub fn try_skip(&mut self, to_skip: usize) -> Result<(), SkipError> {
+        self.with_cursor(|c| c.try_skip(to_skip))
+    }
+
+    /// See `DecodeCursor.decode_slice()`.
+    pub fn decode_slice<D: Decoder>(&mut self, output: &mut [u32]) -> usize {
+        self.with_cursor(|c| c.decode_slice::<D>(output))
+    }
+
+    /// See `DecodeCursor.decode_sink()`.
+    pub fn decode_sink<D, S>(&mut self, sink: &mut S, max_numbers_to_decode: usize) -> usize
+    where
+        D: Decoder,
+        S: DecodeQuadSink<D::DecodedQuad> + DecodeQuadSink<<Scalar as Decoder>::DecodedQuad>,
+    {
+        self.with_cursor(|c| c.decode_sink::<D, S>(sink, max_numbers_to_decode))
+    }
+
+    /// See `DecodeCursor.input_consumed()`.
+    pub fn input_consumed(&self) -> usize {
+        self.cursor().input_consumed()
+    }
+
+    /// See `DecodeCursor.has_more()`.
+    pub fn has_more(&self) -> bool {
+        self.cursor().has_more()
+    }
+
+    /// The input the cursor was created with.
+    pub fn get_ref(&self) -> &B {
+        &self.input
+    }
+
+    /// Give up the cursor, returning its input.
+    pub fn into_inner(self) -> B {
+        self.input
+    }
+
+    /// A borrowing cursor at the same position.
+    pub fn cursor<'a>(&'a self) -> DecodeCursor<'a> {
+        let mut cursor = DecodeCursor::new(self.input.as_ref(), self.count);
+        cursor.set_position(self.position);
+
+        cursor
+    }
+
+    fn with_cursor<R, F: FnOnce(&mut DecodeCursor) -> R>(&mut self, f: F) -> R {
+        let mut cursor = self.cursor();
+        let result = f(&mut cursor);
+        self.position = cursor.position();
+
+        result
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use encode;
+
+    #[test]
+    fn owned_cursor_matches_borrowed_cursor() {
+        let nums: Vec<u32> = (0..103).map(|i| i * 7919).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        encoded.truncate(encoded_len);
+
+        let mut owned = OwnedDecodeCursor::new(Cow::from(encoded.clone()), nums.len());
+        let mut borrowed = DecodeCursor::new(&encoded, nums.len());
+
+        owned.skip(8);
+        borrowed.skip(8);
+
+        let mut owned_decoded = vec![0; 20];
+        let mut borrowed_decoded = vec![0; 20];
+        while borrowed.has_more() {
+            assert!(owned.has_more());
+            assert_eq!(
+                borrowed.decode_slice::<Scalar>(&mut borrowed_decoded),
+                owned.decode_slice::<Scalar>(&mut owned_decoded)
+            );
+            assert_eq!(borrowed_decoded, owned_decoded);
+            assert_eq!(borrowed.input_consumed(), owned.input_consumed());
+        }
+
+        assert!(!owned.has_more());
+        assert_eq!(encoded, owned.into_inner().into_owned());
+    }
+}
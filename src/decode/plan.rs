@@ -0,0 +1,194 @@
+use {encoded_shape, lengths, DecodeCursor, Decoder};
+use super::decode_num_scalar;
+
+/// The byte offset of every quad in some encoded numbers, computed once so that input that is
+/// decoded many times can be entered at any quad without scanning control bytes.
+///
+/// `DecodeCursor.skip()` and `EncodedSlice.get()` have to add up the lengths of every quad they
+/// pass over. A plan does that once, up front, at the cost of a `usize` per quad (twice the size
+/// of the encoded numbers in the worst case), so that positioning a cursor or reading one number
+/// takes constant time no matter how far into the input it is.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, DecodePlan, Scalar};
+///
+/// let nums: Vec<u32> = (0..10_000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// encode::<Scalar>(&nums, &mut encoded);
+///
+/// let plan = DecodePlan::new(&encoded, nums.len());
+/// assert_eq!(Some(9_999_000), plan.get(9_999));
+///
+/// let mut decoded = vec![0; 100];
+/// assert_eq!(100, plan.cursor_at(5000).decode_slice::<Scalar>(&mut decoded));
+/// assert_eq!(&nums[5000..5100], &decoded[..]);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecodePlan<'a> {
+    control_bytes: &'a [u8],
+    encoded_nums: &'a [u8],
+    count: usize,
+    /// Offset into `encoded_nums` of each quad, plus one more for the end of the last quad
+    quad_offsets: Vec<usize>,
+}
+
+impl<'a> DecodePlan<'a> {
+    /// Scan the control bytes of `count` numbers encoded in `input`.
+    pub fn new(input: &'a [u8], count: usize) -> DecodePlan<'a> {
+        let shape = encoded_shape(count);
+        let control_bytes = &input[0..shape.control_bytes_len];
+
+        let mut quad_offsets = Vec::with_capacity(shape.control_bytes_len + 1);
+        let mut offset = 0;
+        quad_offsets.push(offset);
+
+        for &control_byte in &control_bytes[0..shape.complete_control_bytes_len] {
+            offset += lengths::per_quad(control_byte) as usize;
+            quad_offsets.push(offset);
+        }
+
+        if shape.leftover_numbers > 0 {
+            let control_byte = control_bytes[shape.complete_control_bytes_len];
+            offset += lengths::partial_quad_len(control_byte, shape.leftover_numbers);
+            quad_offsets.push(offset);
+        }
+
+        DecodePlan {
+            control_bytes,
+            encoded_nums: &input[shape.control_bytes_len..],
+            count,
+            quad_offsets,
+        }
+    }
+
+    /// The number of numbers.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The total length of the control bytes and encoded numbers.
+    pub fn encoded_len(&self) -> usize {
+        self.control_bytes.len() + self.quad_offsets[self.quad_offsets.len() - 1]
+    }
+
+    /// The offset of quad `quad_index` from the start of the encoded numbers (i.e. after the
+    /// control bytes). `quad_index` may be one past the last quad, for the end of the input.
+    pub fn quad_offset(&self, quad_index: usize) -> usize {
+        self.quad_offsets[quad_index]
+    }
+
+    /// Returns a cursor positioned at number `index`, which must be a multiple of 4 and no more
+    /// than the count of numbers.
+    pub fn cursor_at(&self, index: usize) -> DecodeCursor<'a> {
+        assert_eq!(index % 4, 0, "Must be a multiple of 4");
+        assert!(index <= self.count, "Index out of range");
+
+        let quad_index = index / 4;
+
+        DecodeCursor::from_parts(
+            &self.control_bytes[quad_index..],
+            &self.encoded_nums[self.quad_offsets[quad_index]..],
+            self.count - index,
+        )
+    }
+
+    /// Decode numbers starting at `index`, which must be a multiple of 4, into `output` with `D`.
+    ///
+    /// As with `DecodeCursor.decode_slice()`, `output` must be at least 4 long if there is a
+    /// complete quad to decode.
+    ///
+    /// Returns the number of numbers decoded.
+    pub fn decode_from<D: Decoder>(&self, index: usize, output: &mut [u32]) -> usize {
+        self.cursor_at(index).decode_slice::<D>(output)
+    }
+
+    /// Returns the number at `index`, or `None` if `index` is out of range.
+    pub fn get(&self, index: usize) -> Option<u32> {
+        if index >= self.count {
+            return None;
+        }
+
+        let quad_index = index / 4;
+        let control_byte = self.control_bytes[quad_index];
+
+        let offset =
+            self.quad_offsets[quad_index] + lengths::partial_quad_len(control_byte, index % 4);
+
+        Some(decode_num_scalar(
+            lengths::num_len(control_byte, index % 4),
+            &self.encoded_nums[offset..],
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn random_get_and_decode_from() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let count = rng.gen_range(0, 300);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            let plan = DecodePlan::new(&encoded, count);
+            assert_eq!(encoded_len, plan.encoded_len());
+
+            for (i, &n) in nums.iter().enumerate() {
+                assert_eq!(Some(n), plan.get(i));
+            }
+            assert_eq!(None, plan.get(count));
+
+            for start in (0..(count + 1)).filter(|i| i % 4 == 0) {
+                let mut decoded = vec![0; count - start + 4];
+                assert_eq!(count - start, plan.decode_from::<Scalar>(start, &mut decoded));
+                assert_eq!(&nums[start..], &decoded[0..(count - start)]);
+            }
+        }
+    }
+
+    #[test]
+    fn quad_offsets_match_skip() {
+        let nums: Vec<u32> = (0..103).map(|i| i << (i % 32)).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let plan = DecodePlan::new(&encoded, nums.len());
+
+        for quad_index in 0..(nums.len() / 4) {
+            let mut cursor = DecodeCursor::new(&encoded, nums.len());
+            cursor.skip(quad_index * 4);
+            assert_eq!(
+                cursor.input_consumed(),
+                plan.control_bytes.len() + plan.quad_offset(quad_index)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be a multiple of 4")]
+    fn cursor_at_unaligned_panics() {
+        let encoded = [0; 10];
+        DecodePlan::new(&encoded, 8).cursor_at(2);
+    }
+}
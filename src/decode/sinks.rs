@@ -0,0 +1,379 @@
+//! Ready-made sinks for use with `DecodeCursor.decode_sink()`.
+
+use std::cmp;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::{DecodeQuadSink, DecodeSingleSink, WriteQuadToSlice};
+
+/// A sink that keeps the `k` largest numbers it sees along with their indices, without storing
+/// anything else.
+///
+/// Indices are `nums_decoded` as passed to the sink plus an offset (0 by default). If you decode
+/// your input with several calls to `decode_sink()`, use `set_index_offset()` before each call to
+/// keep indices relative to the start of the input.
+///
+/// When several numbers are tied for the `k`th largest, the ones that occur earliest are kept.
+#[derive(Debug, Clone)]
+pub struct TopKSink {
+    k: usize,
+    index_offset: usize,
+    // min-heap on value, with later indices considered smaller so they are evicted first
+    heap: BinaryHeap<Reverse<(u32, Reverse<usize>)>>,
+}
+
+impl TopKSink {
+    /// Create a sink that keeps the `k` largest numbers.
+    pub fn new(k: usize) -> TopKSink {
+        TopKSink {
+            k,
+            index_offset: 0,
+            heap: BinaryHeap::with_capacity(k),
+        }
+    }
+
+    /// Set the offset added to `nums_decoded` to get the index of each number.
+    pub fn set_index_offset(&mut self, index_offset: usize) {
+        self.index_offset = index_offset;
+    }
+
+    /// The smallest number currently kept, once `k` numbers have been seen. Anything less than or
+    /// equal to this will not be kept.
+    pub fn threshold(&self) -> Option<u32> {
+        if self.heap.len() < self.k {
+            None
+        } else {
+            self.heap.peek().map(|&Reverse((num, _))| num)
+        }
+    }
+
+    /// Returns `(number, index)` pairs, largest number first, with ties in index order.
+    pub fn into_sorted_vec(self) -> Vec<(u32, usize)> {
+        // ascending order of Reverse is descending order of the original
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((num, Reverse(index)))| (num, index))
+            .collect()
+    }
+
+    #[inline]
+    fn offer(&mut self, num: u32, index: usize) {
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse((num, Reverse(index))));
+        } else if let Some(mut smallest) = self.heap.peek_mut() {
+            if num > (smallest.0).0 {
+                *smallest = Reverse((num, Reverse(index)));
+            }
+        }
+    }
+}
+
+impl DecodeSingleSink for TopKSink {
+    #[inline]
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        let index = self.index_offset + nums_decoded;
+        self.offer(num, index);
+    }
+}
+
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for TopKSink {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        let mut nums = [0_u32; 4];
+        quad.write_to_slice(&mut nums);
+
+        let index = self.index_offset + nums_decoded;
+        for (i, &num) in nums.iter().enumerate() {
+            self.offer(num, index + i);
+        }
+    }
+}
+
+/// A sink that counts how many numbers fall into each of a set of buckets.
+///
+/// Buckets are defined by `n` ascending boundaries, which produce `n + 1` buckets: bucket 0 holds
+/// numbers less than the first boundary, bucket `i` holds numbers at least boundary `i - 1` and
+/// less than boundary `i`, and the last bucket holds numbers at least the last boundary.
+#[derive(Debug, Clone)]
+pub struct HistogramSink {
+    buckets: Buckets,
+    counts: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+enum Buckets {
+    Boundaries(Vec<u32>),
+    /// Boundaries at `first + i * width` for `i` in `0..num_boundaries`
+    Uniform {
+        first: u32,
+        width: u32,
+        num_boundaries: usize,
+    },
+}
+
+impl HistogramSink {
+    /// Create a sink with the provided boundaries, which must be strictly ascending.
+    ///
+    /// Each number is binned with a binary search over the boundaries.
+    pub fn new(boundaries: Vec<u32>) -> HistogramSink {
+        assert!(
+            boundaries.windows(2).all(|w| w[0] < w[1]),
+            "Boundaries must be strictly ascending"
+        );
+
+        let counts = vec![0; boundaries.len() + 1];
+
+        HistogramSink {
+            buckets: Buckets::Boundaries(boundaries),
+            counts,
+        }
+    }
+
+    /// Create a sink with `num_boundaries` evenly spaced boundaries, starting at `first` and
+    /// `width` apart.
+    ///
+    /// Each number is binned with arithmetic rather than a search, so all 4 numbers in a quad can
+    /// be binned at once.
+    pub fn uniform(first: u32, width: u32, num_boundaries: usize) -> HistogramSink {
+        assert!(width > 0, "Bucket width must be non-zero");
+        assert!(
+            (first as u64) + (width as u64) * (num_boundaries.saturating_sub(1) as u64)
+                <= u32::MAX as u64,
+            "Boundaries must fit in a u32"
+        );
+
+        let counts = vec![0; num_boundaries + 1];
+
+        HistogramSink {
+            buckets: Buckets::Uniform {
+                first,
+                width,
+                num_boundaries,
+            },
+            counts,
+        }
+    }
+
+    /// The count for each bucket.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn into_counts(self) -> Vec<u64> {
+        self.counts
+    }
+
+    #[inline]
+    fn bucket(&self, num: u32) -> usize {
+        match self.buckets {
+            // the number of boundaries <= num
+            Buckets::Boundaries(ref boundaries) => match boundaries.binary_search(&num) {
+                Ok(i) => i + 1,
+                Err(i) => i,
+            },
+            Buckets::Uniform {
+                first,
+                width,
+                num_boundaries,
+            } => uniform_bucket(num, first, width, num_boundaries),
+        }
+    }
+}
+
+#[inline]
+fn uniform_bucket(num: u32, first: u32, width: u32, num_boundaries: usize) -> usize {
+    if num < first {
+        0
+    } else {
+        cmp::min(((num - first) / width) as usize + 1, num_boundaries)
+    }
+}
+
+impl DecodeSingleSink for HistogramSink {
+    #[inline]
+    fn on_number(&mut self, num: u32, _nums_decoded: usize) {
+        let bucket = self.bucket(num);
+        self.counts[bucket] += 1;
+    }
+}
+
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for HistogramSink {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, _nums_decoded: usize) {
+        let mut nums = [0_u32; 4];
+        quad.write_to_slice(&mut nums);
+
+        let mut buckets = [0_usize; 4];
+        match self.buckets {
+            Buckets::Boundaries(_) => {
+                for (bucket, &num) in buckets.iter_mut().zip(nums.iter()) {
+                    *bucket = self.bucket(num);
+                }
+            }
+            Buckets::Uniform {
+                first,
+                width,
+                num_boundaries,
+            } => {
+                // only arithmetic and selects, so the 4 lanes can be computed together
+                for (bucket, &num) in buckets.iter_mut().zip(nums.iter()) {
+                    *bucket = uniform_bucket(num, first, width, num_boundaries);
+                }
+            }
+        }
+
+        for &bucket in buckets.iter() {
+            self.counts[bucket] += 1;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode_append, DecodeCursor, Decoder, Scalar};
+
+    #[test]
+    fn top_k_random_scalar() {
+        do_top_k_random::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn top_k_random_ssse3() {
+        do_top_k_random::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn top_k_ties_keep_earliest() {
+        let nums: Vec<u32> = vec![5, 9, 5, 9, 1, 5, 9];
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+
+        let mut sink = TopKSink::new(4);
+        DecodeCursor::new(&encoded, nums.len()).decode_sink::<Scalar, _>(&mut sink, nums.len());
+
+        assert_eq!(Some(5), sink.threshold());
+        assert_eq!(
+            vec![(9, 1), (9, 3), (9, 6), (5, 0)],
+            sink.into_sorted_vec()
+        );
+    }
+
+    #[test]
+    fn top_k_zero_keeps_nothing() {
+        let mut sink = TopKSink::new(0);
+        sink.on_number(10, 0);
+
+        assert_eq!(None, sink.threshold());
+        assert!(sink.into_sorted_vec().is_empty());
+    }
+
+    #[test]
+    fn histogram_boundaries() {
+        let nums: Vec<u32> = vec![0, 9, 10, 11, 99, 100, 1000, u32::MAX, 50];
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+
+        let mut sink = HistogramSink::new(vec![10, 100]);
+        DecodeCursor::new(&encoded, nums.len()).decode_sink::<Scalar, _>(&mut sink, nums.len());
+
+        assert_eq!(&[2, 4, 3], sink.counts());
+    }
+
+    #[test]
+    fn histogram_no_boundaries_is_one_bucket() {
+        let mut sink = HistogramSink::new(vec![]);
+        sink.on_number(7, 0);
+
+        assert_eq!(vec![1], sink.into_counts());
+    }
+
+    #[test]
+    #[should_panic(expected = "Boundaries must be strictly ascending")]
+    fn histogram_unsorted_boundaries_panics() {
+        HistogramSink::new(vec![10, 10]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Boundaries must fit in a u32")]
+    fn histogram_uniform_overflow_panics() {
+        HistogramSink::uniform(u32::MAX - 10, 5, 4);
+    }
+
+    #[test]
+    fn histogram_uniform_matches_boundaries_scalar() {
+        do_histogram_uniform_matches_boundaries::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn histogram_uniform_matches_boundaries_ssse3() {
+        do_histogram_uniform_matches_boundaries::<::x86::Ssse3>();
+    }
+
+    fn do_histogram_uniform_matches_boundaries<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let count = rng.gen_range(0, 1000);
+            let nums: Vec<u32> = (0..count).map(|_| rng.gen_range(0, 2000)).collect();
+            let mut encoded = Vec::new();
+            encode_append::<Scalar>(&nums, &mut encoded);
+
+            let first = rng.gen_range(0, 500);
+            let width = rng.gen_range(1, 200);
+            let num_boundaries = rng.gen_range(0, 20);
+            let boundaries: Vec<u32> = (0..num_boundaries)
+                .map(|i| first + i as u32 * width)
+                .collect();
+
+            let mut expected = vec![0; num_boundaries + 1];
+            for &n in &nums {
+                expected[boundaries.iter().filter(|&&b| b <= n).count()] += 1;
+            }
+
+            let mut uniform = HistogramSink::uniform(first, width, num_boundaries);
+            DecodeCursor::new(&encoded, count).decode_sink::<D, _>(&mut uniform, count);
+            let mut searched = HistogramSink::new(boundaries);
+            DecodeCursor::new(&encoded, count).decode_sink::<D, _>(&mut searched, count);
+
+            assert_eq!(expected, uniform.into_counts());
+            assert_eq!(expected, searched.into_counts());
+        }
+    }
+
+    fn do_top_k_random<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let count = rng.gen_range(0, 1000);
+            let k = rng.gen_range(0, 20);
+            let nums: Vec<u32> = (0..count).map(|_| rng.gen_range(0, 500)).collect();
+            let mut encoded = Vec::new();
+            encode_append::<Scalar>(&nums, &mut encoded);
+
+            // decode in a few chunks to exercise the index offset
+            let mut sink = TopKSink::new(k);
+            let mut cursor = DecodeCursor::new(&encoded, count);
+            let mut nums_decoded = 0;
+            while cursor.has_more() {
+                sink.set_index_offset(nums_decoded);
+                let chunk_len = rng.gen_range(1, 100) * 4;
+                nums_decoded += cursor.decode_sink::<D, _>(&mut sink, chunk_len);
+            }
+
+            let mut expected: Vec<(u32, usize)> =
+                nums.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+            expected.sort_by_key(|&(n, i)| (Reverse(n), i));
+            expected.truncate(k);
+
+            assert_eq!(expected, sink.into_sorted_vec());
+        }
+    }
+}
@@ -1,18 +1,25 @@
-extern crate stdsimd;
-
 use std::cmp;
+use std::marker::PhantomData;
 
-use self::stdsimd::simd;
-use self::stdsimd::vendor::{__m128i, _mm_loadu_si128, _mm_shuffle_epi8, _mm_storeu_si128};
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m128i, _mm_loadu_si128, _mm_prefetch, _mm_shuffle_epi8, _mm_storeu_si128,
+                     _MM_HINT_T0};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_prefetch, _mm_shuffle_epi8,
+                        _mm_storeu_si128, _MM_HINT_T0};
 
-use {tables, SliceDecodeSink};
-use super::{DecodeQuadSink, Decoder};
+use lengths;
+use tables;
+use super::{DecodeQuadSink, Decoder, WriteQuadToSlice};
 
 /// Decoder using SSSE3 instructions.
+///
+/// Decoding panics if the CPU doesn't support SSSE3. The check is done once per call to
+/// `decode_quads()`, and left out entirely when compiling with the `ssse3` target feature.
 pub struct Ssse3;
 
 impl Decoder for Ssse3 {
-    type DecodedQuad = simd::u8x16;
+    type DecodedQuad = __m128i;
 
     fn decode_quads<S: DecodeQuadSink<Self::DecodedQuad>>(
         control_bytes: &[u8],
@@ -21,66 +28,184 @@ impl Decoder for Ssse3 {
         nums_already_decoded: usize,
         sink: &mut S,
     ) -> (usize, usize) {
-        let mut bytes_read: usize = 0;
-        let mut nums_decoded: usize = nums_already_decoded;
-
-        // Decoding reads 16 bytes at a time from input, so we won't be able to read the last few
-        // control byte's worth because they may be encoded at 1 byte per number, so we need 3
-        // additional control bytes' worth of numbers to provide the extra 12 bytes.
-        // However, if control_bytes_to_decode is short enough, we can decode all the requested
-        // numbers because we'll have un-processed input to ensure we can read 16 bytes.
-        let control_byte_limit = cmp::min(
-            control_bytes_to_decode,
-            control_bytes.len().saturating_sub(3),
-        );
+        assert_ssse3_supported();
+
+        // Safe because the CPU supports SSSE3.
+        unsafe {
+            decode_quads_ssse3(
+                control_bytes,
+                encoded_nums,
+                control_bytes_to_decode,
+                nums_already_decoded,
+                sink,
+                0,
+            )
+        }
+    }
+}
+
+/// How far ahead `Ssse3Prefetch` prefetches encoded numbers.
+///
+/// Implement this on your own type to try other distances.
+pub trait PrefetchDistance {
+    /// The distance in bytes past the quad currently being decoded.
+    const BYTES: usize;
+}
+
+/// Prefetch 256 bytes ahead.
+pub struct Prefetch256;
+
+impl PrefetchDistance for Prefetch256 {
+    const BYTES: usize = 256;
+}
+
+/// Prefetch 512 bytes ahead.
+pub struct Prefetch512;
+
+impl PrefetchDistance for Prefetch512 {
+    const BYTES: usize = 512;
+}
+
+/// Prefetch 1024 bytes ahead.
+pub struct Prefetch1024;
 
-        // need to ensure that we can copy 16 encoded bytes, so last few quads will be handled
-        // by a slower loop
-        for &control_byte in control_bytes[0..control_byte_limit].iter() {
-            let length = tables::DECODE_LENGTH_PER_QUAD_TABLE[control_byte as usize];
-            let mask_bytes = tables::X86_SSSE3_DECODE_SHUFFLE_TABLE[control_byte as usize];
-            // we'll read 16 bytes from this always, so using explicit slice size to make sure it's
-            // ok to read unsafe
-            let next_4 = &encoded_nums[bytes_read..(bytes_read + 16)];
-
-            let mask;
-            let data;
-            unsafe {
-                // TODO load mask unaligned once https://github.com/rust-lang/rust/issues/33626
-                // hits stable
-                mask = simd::u8x16::from(_mm_loadu_si128(mask_bytes.as_ptr() as *const __m128i));
-                data = simd::u8x16::from(_mm_loadu_si128(next_4.as_ptr() as *const __m128i));
-            }
-
-            let decompressed = unsafe { _mm_shuffle_epi8(data, mask) };
-
-            sink.on_quad(decompressed, nums_decoded);
-
-            bytes_read += length as usize;
-            nums_decoded += 4;
+impl PrefetchDistance for Prefetch1024 {
+    const BYTES: usize = 1024;
+}
+
+/// Decoder using SSSE3 instructions that also issues software prefetches for encoded numbers
+/// `P::BYTES` ahead of the current position.
+///
+/// This only helps when decoding large inputs that aren't already in cache, where decoding is
+/// limited by memory bandwidth rather than computation. For inputs that fit in L2 cache, the extra
+/// instructions make this slightly slower than `Ssse3`. Run the `decode_ssse3*_16m` benchmarks to
+/// see which is better on your hardware.
+///
+/// Like `Ssse3`, decoding panics if the CPU doesn't support SSSE3.
+pub struct Ssse3Prefetch<P: PrefetchDistance = Prefetch512> {
+    distance: PhantomData<P>,
+}
+
+impl<P: PrefetchDistance> Ssse3Prefetch<P> {
+    pub fn new() -> Ssse3Prefetch<P> {
+        Ssse3Prefetch {
+            distance: PhantomData,
         }
+    }
+}
 
-        (nums_decoded - nums_already_decoded, bytes_read)
+impl<P: PrefetchDistance> Default for Ssse3Prefetch<P> {
+    fn default() -> Ssse3Prefetch<P> {
+        Ssse3Prefetch::new()
     }
 }
 
+impl<P: PrefetchDistance> Decoder for Ssse3Prefetch<P> {
+    type DecodedQuad = __m128i;
+
+    fn decode_quads<S: DecodeQuadSink<Self::DecodedQuad>>(
+        control_bytes: &[u8],
+        encoded_nums: &[u8],
+        control_bytes_to_decode: usize,
+        nums_already_decoded: usize,
+        sink: &mut S,
+    ) -> (usize, usize) {
+        assert_ssse3_supported();
+
+        // Safe because the CPU supports SSSE3.
+        unsafe {
+            decode_quads_ssse3(
+                control_bytes,
+                encoded_nums,
+                control_bytes_to_decode,
+                nums_already_decoded,
+                sink,
+                P::BYTES,
+            )
+        }
+    }
+}
+
+#[inline]
+fn assert_ssse3_supported() {
+    assert!(
+        cfg!(target_feature = "ssse3") || is_x86_feature_detected!("ssse3"),
+        "x86::Ssse3 needs a CPU with SSSE3"
+    );
+}
+
+/// The decode loop, compiled with SSSE3 enabled whatever the target features, prefetching
+/// `prefetch_distance` bytes ahead unless it's 0.
+///
+/// # Safety
+///
+/// The CPU must support SSSE3.
+#[target_feature(enable = "ssse3")]
+unsafe fn decode_quads_ssse3<S: DecodeQuadSink<__m128i>>(
+    control_bytes: &[u8],
+    encoded_nums: &[u8],
+    control_bytes_to_decode: usize,
+    nums_already_decoded: usize,
+    sink: &mut S,
+    prefetch_distance: usize,
+) -> (usize, usize) {
+    let mut bytes_read: usize = 0;
+    let mut nums_decoded: usize = nums_already_decoded;
+
+    // Decoding reads 16 bytes at a time from input, so we won't be able to read the last few
+    // control byte's worth because they may be encoded at 1 byte per number, so we need 3
+    // additional control bytes' worth of numbers to provide the extra 12 bytes.
+    // However, if control_bytes_to_decode is short enough, we can decode all the requested
+    // numbers because we'll have un-processed input to ensure we can read 16 bytes.
+    let control_byte_limit = cmp::min(
+        control_bytes_to_decode,
+        control_bytes.len().saturating_sub(3),
+    );
+
+    // need to ensure that we can copy 16 encoded bytes, so last few quads will be handled
+    // by a slower loop
+    for &control_byte in control_bytes[0..control_byte_limit].iter() {
+        let length = lengths::per_quad(control_byte);
+        let mask_bytes = tables::X86_SSSE3_DECODE_SHUFFLE_TABLE[control_byte as usize];
+        // we'll read 16 bytes from this always, so using explicit slice size to make sure it's
+        // ok to read unsafe
+        let next_4 = &encoded_nums[bytes_read..(bytes_read + 16)];
+
+        if prefetch_distance > 0 {
+            // prefetching is only a hint, so it's fine if this points past the end of the input
+            let ahead = next_4.as_ptr().wrapping_add(prefetch_distance);
+            _mm_prefetch(ahead as *const i8, _MM_HINT_T0);
+        }
+
+        let mask = _mm_loadu_si128(mask_bytes.as_ptr() as *const __m128i);
+        let data = _mm_loadu_si128(next_4.as_ptr() as *const __m128i);
+
+        let decompressed = _mm_shuffle_epi8(data, mask);
+
+        sink.on_quad(decompressed, nums_decoded);
+
+        bytes_read += length as usize;
+        nums_decoded += 4;
+    }
+
+    (nums_decoded - nums_already_decoded, bytes_read)
+}
+
 /// Used for SSSE3 decoding.
-impl<'a> DecodeQuadSink<simd::u8x16> for SliceDecodeSink<'a> {
+impl WriteQuadToSlice for __m128i {
     #[inline]
-    fn on_quad(&mut self, quad: simd::u8x16, nums_decoded: usize) {
+    fn write_to_slice(self, output: &mut [u32]) {
         unsafe {
             // using slice size to make sure it's ok to write 4 u32s
-            _mm_storeu_si128(
-                self.output[nums_decoded..(nums_decoded + 4)].as_ptr() as *mut __m128i,
-                simd::i8x16::from(quad),
-            )
+            _mm_storeu_si128(output[0..4].as_mut_ptr() as *mut __m128i, self)
         }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "encode"))]
 mod tests {
     use ::*;
+    use decode::SliceDecodeSink;
     use super::*;
 
     #[test]
@@ -139,4 +264,40 @@ mod tests {
             assert!(&decoded[nums_decoded..].iter().all(|&i| i == 54321_u32));
         }
     }
+
+    #[test]
+    fn prefetch_decodes_same_as_scalar() {
+        let nums: Vec<u32> = (0..100_003).map(|i| (i * 7919) >> (i % 32)).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        encoded.truncate(encoded_len);
+
+        let mut expected = vec![0; nums.len()];
+        assert_eq!(
+            encoded_len,
+            decode::<Scalar>(&encoded, nums.len(), &mut expected)
+        );
+        assert_eq!(nums, expected);
+
+        let mut decoded = vec![0; nums.len()];
+        assert_eq!(
+            encoded_len,
+            decode::<Ssse3Prefetch<Prefetch256>>(&encoded, nums.len(), &mut decoded)
+        );
+        assert_eq!(expected, decoded);
+
+        let mut decoded = vec![0; nums.len()];
+        assert_eq!(
+            encoded_len,
+            decode::<Ssse3Prefetch<Prefetch1024>>(&encoded, nums.len(), &mut decoded)
+        );
+        assert_eq!(expected, decoded);
+
+        let mut decoded = vec![0; nums.len()];
+        assert_eq!(
+            encoded_len,
+            decode::<Ssse3Prefetch>(&encoded, nums.len(), &mut decoded)
+        );
+        assert_eq!(expected, decoded);
+    }
 }
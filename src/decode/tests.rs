@@ -68,10 +68,7 @@ fn decoder_honors_nums_to_decode_ssse3() {
     decoder_honors_nums_to_decode::<::x86::Ssse3>(3);
 }
 
-fn decoder_honors_nums_to_decode<D: Decoder>(control_byte_limit_fudge_factor: usize)
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn decoder_honors_nums_to_decode<D: Decoder>(control_byte_limit_fudge_factor: usize) {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -122,3 +119,38 @@ where
         assert_eq!(&nums[0..nums_to_decode], &decoded[0..nums_to_decode]);
     }
 }
+
+#[test]
+fn decode_to_new_vec_random_scalar() {
+    decode_to_new_vec_random::<Scalar>();
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[test]
+fn decode_to_new_vec_random_ssse3() {
+    decode_to_new_vec_random::<::x86::Ssse3>();
+}
+
+fn decode_to_new_vec_random<D: Decoder>() {
+    for count in 0..200 {
+        let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+            .take(count)
+            .collect();
+        let mut encoded = Vec::new();
+        encoded.resize(count * 5, 0);
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let decoded = decode_to_new_vec::<D>(&encoded, count);
+        assert_eq!(nums, decoded);
+        assert_eq!(count, decoded.capacity());
+    }
+}
+
+#[test]
+#[should_panic(expected = "output buffer was not large enough")]
+fn decode_small_count_into_short_output_panics() {
+    let mut encoded = vec![0; 3 * 5];
+    encode::<Scalar>(&[1, 2, 3], &mut encoded);
+
+    decode::<Scalar>(&encoded, 3, &mut [0; 2]);
+}
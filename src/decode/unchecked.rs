@@ -0,0 +1,305 @@
+use std::ptr;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {cumulative_encoded_len, encoded_shape, lengths};
+use super::{DecodeQuadSink, DecodeSingleSink, Decoder, WriteQuadToSlice};
+
+/// Check that `input` is long enough to hold `count` encoded numbers, which is all that
+/// `decode_unchecked()` needs to be safe.
+///
+/// Returns the encoded length of the numbers, or `None` if `input` is too short.
+pub fn validate(input: &[u8], count: usize) -> Option<usize> {
+    let shape = encoded_shape(count);
+    if input.len() < shape.control_bytes_len {
+        return None;
+    }
+
+    let mut encoded_len = shape.control_bytes_len
+        + cumulative_encoded_len(&input[0..shape.complete_control_bytes_len]);
+
+    if shape.leftover_numbers > 0 {
+        let control_byte = input[shape.complete_control_bytes_len];
+        encoded_len += lengths::partial_quad_len(control_byte, shape.leftover_numbers);
+    }
+
+    if input.len() < encoded_len {
+        None
+    } else {
+        Some(encoded_len)
+    }
+}
+
+/// Like `decode()`, but without bounds checks on `input` or `output`.
+///
+/// This is for hot loops over input that has already been checked once, e.g. when it was loaded.
+/// The savings are a few percent at most, so prefer `decode()` unless you've measured a difference.
+///
+/// Returns the number of bytes read from `input`.
+///
+/// # Safety
+///
+/// `validate(input, count)` must return `Some`, and `output` must be at least `count` long.
+/// Otherwise, this will read and write out of bounds.
+///
+/// `D::decode_quads_unchecked()` must only hand the sink quads at the positions it decodes, since
+/// the sink writes them to `output` without bounds checks. This crate's decoders all do. The
+/// counts it returns are checked, though, so a `D` that miscounts panics rather than reading out of
+/// bounds.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{decode_unchecked, encode, validate, Scalar};
+///
+/// let nums: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+///
+/// assert_eq!(Some(encoded_len), validate(&encoded, nums.len()));
+///
+/// let mut decoded = vec![0; nums.len()];
+/// let bytes_read = unsafe { decode_unchecked::<Scalar>(&encoded, nums.len(), &mut decoded) };
+/// assert_eq!(encoded_len, bytes_read);
+/// assert_eq!(nums, decoded);
+/// # }
+/// ```
+pub unsafe fn decode_unchecked<D: Decoder>(
+    input: &[u8],
+    count: usize,
+    output: &mut [u32],
+) -> usize {
+    debug_assert!(validate(input, count).is_some());
+    debug_assert!(output.len() >= count);
+
+    let shape = encoded_shape(count);
+    let control_bytes = input.get_unchecked(0..shape.control_bytes_len);
+    let encoded_nums = input.get_unchecked(shape.control_bytes_len..);
+    let mut sink = UncheckedSliceSink { output };
+
+    let (nums_decoded, bytes_read) = D::decode_quads_unchecked(
+        control_bytes.get_unchecked(0..shape.complete_control_bytes_len),
+        encoded_nums,
+        shape.complete_control_bytes_len,
+        0,
+        &mut sink,
+    );
+
+    // `D` may be implemented outside this crate, so check that the rest of the numbers are in
+    // bounds after what it says it read. It's usually left little or nothing to go over.
+    assert!(
+        nums_decoded % 4 == 0 && nums_decoded <= shape.complete_control_bytes_len * 4,
+        "Decoder::decode_quads_unchecked() decoded numbers it wasn't given"
+    );
+    let rest_len = cumulative_encoded_len(
+        control_bytes.get_unchecked((nums_decoded / 4)..shape.complete_control_bytes_len),
+    ) + if shape.leftover_numbers > 0 {
+        let control_byte = *control_bytes.get_unchecked(shape.complete_control_bytes_len);
+        lengths::partial_quad_len(control_byte, shape.leftover_numbers)
+    } else {
+        0
+    };
+    assert!(
+        bytes_read <= encoded_nums.len() && rest_len <= encoded_nums.len() - bytes_read,
+        "Decoder::decode_quads_unchecked() read more bytes than the numbers it decoded"
+    );
+
+    // whatever complete quads `D` didn't decode, plus any trailing partial quad
+    let more_bytes_read = decode_nums_unchecked(
+        control_bytes.get_unchecked((nums_decoded / 4)..),
+        encoded_nums.get_unchecked(bytes_read..),
+        count - nums_decoded,
+        nums_decoded,
+        &mut sink,
+    );
+
+    shape.control_bytes_len + bytes_read + more_bytes_read
+}
+
+/// Decode `count` numbers one at a time, handing them to `sink`. `count` need not be a multiple
+/// of 4.
+///
+/// Returns the number of bytes read from `encoded_nums`.
+///
+/// # Safety
+///
+/// `control_bytes` must have a control byte for each quad (including a partial one), and
+/// `encoded_nums` must hold all the numbers they describe.
+#[inline]
+unsafe fn decode_nums_unchecked<S: DecodeSingleSink>(
+    control_bytes: &[u8],
+    encoded_nums: &[u8],
+    count: usize,
+    nums_already_decoded: usize,
+    sink: &mut S,
+) -> usize {
+    let mut bytes_read = 0;
+    let nums = encoded_nums.as_ptr();
+
+    for i in 0..count {
+        let control_byte = *control_bytes.get_unchecked(i / 4);
+        let len = lengths::num_len(control_byte, i % 4);
+
+        let mut buf = [0_u8; 4];
+        ptr::copy_nonoverlapping(nums.add(bytes_read), buf.as_mut_ptr(), len);
+        sink.on_number(LittleEndian::read_u32(&buf), nums_already_decoded + i);
+
+        bytes_read += len;
+    }
+
+    bytes_read
+}
+
+/// Like `decode_nums_unchecked()`, but a quad at a time, for complete quads only.
+///
+/// Returns the number of numbers decoded and the number of bytes read from `encoded_nums`.
+///
+/// # Safety
+///
+/// As with `decode_nums_unchecked()`.
+#[inline]
+pub(crate) unsafe fn decode_quads_unchecked_scalar<S: DecodeSingleSink>(
+    control_bytes: &[u8],
+    encoded_nums: &[u8],
+    nums_already_decoded: usize,
+    sink: &mut S,
+) -> (usize, usize) {
+    let mut bytes_read = 0;
+    let mut nums_decoded = nums_already_decoded;
+    let nums = encoded_nums.as_ptr();
+
+    for &control_byte in control_bytes {
+        let (len0, len1, len2, len3) = lengths::per_num(control_byte);
+
+        for &len in &[len0, len1, len2, len3] {
+            let len = len as usize;
+            let mut buf = [0_u8; 4];
+            ptr::copy_nonoverlapping(nums.add(bytes_read), buf.as_mut_ptr(), len);
+            sink.on_number(LittleEndian::read_u32(&buf), nums_decoded);
+
+            bytes_read += len;
+            nums_decoded += 1;
+        }
+    }
+
+    (nums_decoded - nums_already_decoded, bytes_read)
+}
+
+/// Writes to a slice without bounds checks. Only for use in `decode_unchecked()`, whose caller
+/// guarantees the slice is long enough.
+struct UncheckedSliceSink<'a> {
+    output: &'a mut [u32],
+}
+
+impl<'a> DecodeSingleSink for UncheckedSliceSink<'a> {
+    #[inline]
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        unsafe {
+            *self.output.get_unchecked_mut(nums_decoded) = num;
+        }
+    }
+}
+
+impl<'a, Q: WriteQuadToSlice> DecodeQuadSink<Q> for UncheckedSliceSink<'a> {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        quad.write_to_slice(unsafe {
+            self.output
+                .get_unchecked_mut(nums_decoded..(nums_decoded + 4))
+        });
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn random_roundtrip_scalar() {
+        do_random_roundtrip::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn random_roundtrip_ssse3() {
+        do_random_roundtrip::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn validate_rejects_short_input() {
+        let nums = [1, 1 << 8, 1 << 16, 1 << 24, 5];
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        assert_eq!(2 + 10 + 1, encoded_len);
+
+        assert_eq!(Some(encoded_len), validate(&encoded[0..encoded_len], nums.len()));
+        assert_eq!(None, validate(&encoded[0..(encoded_len - 1)], nums.len()));
+        assert_eq!(None, validate(&encoded[0..1], nums.len()));
+        assert_eq!(Some(0), validate(&[], 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "read more bytes than the numbers it decoded")]
+    fn decoder_that_overstates_bytes_read_panics() {
+        let nums: Vec<u32> = (0..20).map(|i| i << 20).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        encoded.truncate(encoded_len);
+
+        let mut decoded = vec![0; nums.len()];
+        unsafe { decode_unchecked::<Overreader>(&encoded, nums.len(), &mut decoded) };
+    }
+
+    /// Decodes like `Scalar`, but claims to have read one more byte than it did.
+    struct Overreader;
+
+    impl Decoder for Overreader {
+        type DecodedQuad = ();
+
+        fn decode_quads<S: DecodeQuadSink<()>>(
+            control_bytes: &[u8],
+            encoded_nums: &[u8],
+            max_control_bytes_to_decode: usize,
+            nums_already_decoded: usize,
+            sink: &mut S,
+        ) -> (usize, usize) {
+            let (nums_decoded, bytes_read) = Scalar::decode_quads(
+                control_bytes,
+                encoded_nums,
+                max_control_bytes_to_decode,
+                nums_already_decoded,
+                sink,
+            );
+
+            (nums_decoded, bytes_read + 1)
+        }
+    }
+
+    fn do_random_roundtrip<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 200);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            assert_eq!(Some(encoded_len), validate(&encoded, count));
+
+            let mut decoded = vec![0; count];
+            let bytes_read = unsafe { decode_unchecked::<D>(&encoded, count, &mut decoded) };
+
+            assert_eq!(encoded_len, bytes_read);
+            assert_eq!(nums, decoded);
+        }
+    }
+}
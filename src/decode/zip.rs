@@ -0,0 +1,116 @@
+use std::cmp;
+
+use super::Decoder;
+use super::cursor::DecodeCursor;
+
+/// How many numbers per stream to decode at a time before scattering them into rows.
+const CHUNK_LEN: usize = 256;
+
+/// Decodes `K` parallel streams in lockstep, producing one `[u32; K]` row per position.
+///
+/// This is handy when columnar data (e.g. `(doc_id, freq, position)` triples) is stored as
+/// separate encoded streams of the same length. Each stream is still decoded with the chosen
+/// `Decoder` a chunk at a time, so SIMD decoders remain effective; the decoded chunks are then
+/// scattered into rows.
+///
+/// All of the cursors must have the same number of numbers remaining.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::*;
+///
+/// let doc_ids: Vec<u32> = (0..100).map(|i| i * 3).collect();
+/// let freqs: Vec<u32> = (0..100).map(|i| i % 7).collect();
+///
+/// let mut encoded_doc_ids = vec![0; doc_ids.len() * 5];
+/// let mut encoded_freqs = vec![0; freqs.len() * 5];
+/// encode::<Scalar>(&doc_ids, &mut encoded_doc_ids);
+/// encode::<Scalar>(&freqs, &mut encoded_freqs);
+///
+/// let mut zip = ZipCursor::new([
+///     DecodeCursor::new(&encoded_doc_ids, doc_ids.len()),
+///     DecodeCursor::new(&encoded_freqs, freqs.len()),
+/// ]);
+///
+/// let mut rows = vec![[0; 2]; 100];
+/// assert_eq!(100, zip.decode_rows::<Scalar>(&mut rows));
+/// assert_eq!([30, 3], rows[10]);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ZipCursor<'a, const K: usize> {
+    cursors: [DecodeCursor<'a>; K],
+}
+
+impl<'a, const K: usize> ZipCursor<'a, K> {
+    /// Create a new cursor over the provided per-stream cursors.
+    pub fn new(cursors: [DecodeCursor<'a>; K]) -> ZipCursor<'a, K> {
+        ZipCursor { cursors }
+    }
+
+    /// Skip `to_skip` rows in every stream.
+    ///
+    /// The same restrictions apply as for `DecodeCursor.skip()`: `to_skip` must be a multiple of
+    /// 4 and must not extend into the trailing partial quad.
+    pub fn skip(&mut self, to_skip: usize) {
+        for cursor in self.cursors.iter_mut() {
+            cursor.skip(to_skip);
+        }
+    }
+
+    /// Decode rows into `output`, where the `k`th element of each row comes from the `k`th
+    /// stream.
+    ///
+    /// As with `DecodeCursor.decode_slice()`, the number of rows decoded will be a multiple of 4
+    /// unless the end of the input is reached, so `output` should be at least of size 4.
+    ///
+    /// Returns the number of rows decoded.
+    pub fn decode_rows<D: Decoder>(&mut self, output: &mut [[u32; K]]) -> usize {
+        let mut column = [0_u32; CHUNK_LEN];
+        let mut rows_decoded = 0;
+
+        while rows_decoded < output.len() {
+            let chunk_len = cmp::min(CHUNK_LEN, output.len() - rows_decoded);
+            let mut chunk_rows_decoded = None;
+
+            for (stream_index, cursor) in self.cursors.iter_mut().enumerate() {
+                let nums_decoded = cursor.decode_slice::<D>(&mut column[0..chunk_len]);
+
+                // streams of the same length at the same position always decode the same amount
+                if let Some(prev) = chunk_rows_decoded {
+                    assert_eq!(
+                        prev, nums_decoded,
+                        "Streams must have the same remaining length"
+                    );
+                }
+                chunk_rows_decoded = Some(nums_decoded);
+
+                for (row, &num) in output[rows_decoded..(rows_decoded + nums_decoded)]
+                    .iter_mut()
+                    .zip(column[0..nums_decoded].iter())
+                {
+                    row[stream_index] = num;
+                }
+            }
+
+            match chunk_rows_decoded {
+                Some(n) if n > 0 => rows_decoded += n,
+                _ => break,
+            }
+        }
+
+        rows_decoded
+    }
+
+    /// Returns true iff there are more rows to be decoded.
+    pub fn has_more(&self) -> bool {
+        self.cursors.iter().any(|c| c.has_more())
+    }
+
+    /// Returns the underlying per-stream cursors.
+    pub fn into_cursors(self) -> [DecodeCursor<'a>; K] {
+        self.cursors
+    }
+}
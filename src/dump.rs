@@ -0,0 +1,155 @@
+use std::fmt;
+
+use {encoded_shape, lengths};
+use decode::decode_num_scalar;
+
+/// Write a human-readable description of `count` numbers encoded in `input` to `out`, one line
+/// per quad, showing at most `max_quads` quads.
+///
+/// Each line shows the quad's control byte, the length of each number, the range of `input`
+/// holding the quad's encoded numbers, and the decoded numbers. This is meant for diagnosing
+/// mismatches with other implementations, so nothing about the input is assumed to be valid:
+/// if `input` ends early, the dump says so and stops.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{dump, encode, Scalar};
+///
+/// let nums = vec![1, 1 << 8, 1 << 16, 1 << 24, 5];
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+///
+/// let mut out = String::new();
+/// dump(&encoded[0..encoded_len], nums.len(), 10, &mut out).unwrap();
+///
+/// assert_eq!(
+///     "5 numbers in 2 quads (1 complete)\n\
+///      quad 0: control 0xe4 lengths [1, 2, 3, 4] bytes 2..12 nums [1, 256, 65536, 16777216]\n\
+///      quad 1: control 0x00 lengths [1] bytes 12..13 nums [5]\n",
+///     out);
+/// # }
+/// ```
+pub fn dump<W: fmt::Write>(
+    input: &[u8],
+    count: usize,
+    max_quads: usize,
+    out: &mut W,
+) -> fmt::Result {
+    let shape = encoded_shape(count);
+
+    writeln!(
+        out,
+        "{} numbers in {} quads ({} complete)",
+        count, shape.control_bytes_len, shape.complete_control_bytes_len
+    )?;
+
+    if input.len() < shape.control_bytes_len {
+        return writeln!(
+            out,
+            "input ends after {} of {} control bytes",
+            input.len(),
+            shape.control_bytes_len
+        );
+    }
+
+    let mut offset = shape.control_bytes_len;
+
+    for (quad_index, &control_byte) in input[0..shape.control_bytes_len].iter().enumerate() {
+        if quad_index == max_quads {
+            return writeln!(
+                out,
+                "... {} more quads",
+                shape.control_bytes_len - max_quads
+            );
+        }
+
+        let nums_in_quad = if quad_index < shape.complete_control_bytes_len {
+            4
+        } else {
+            shape.leftover_numbers
+        };
+
+        let lengths: Vec<usize> = (0..nums_in_quad)
+            .map(|i| lengths::num_len(control_byte, i))
+            .collect();
+        let quad_len: usize = lengths.iter().sum();
+
+        write!(
+            out,
+            "quad {}: control {:#04x} lengths {:?} bytes {}..{}",
+            quad_index,
+            control_byte,
+            lengths,
+            offset,
+            offset + quad_len
+        )?;
+
+        if input.len() < offset + quad_len {
+            return writeln!(out, " past end of input ({} bytes)", input.len());
+        }
+
+        let mut nums = Vec::with_capacity(lengths.len());
+        for &len in &lengths {
+            nums.push(decode_num_scalar(len, &input[offset..]));
+            offset += len;
+        }
+
+        writeln!(out, " nums {:?}", nums)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn dump_limits_quads() {
+        let nums: Vec<u32> = (0..12).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+        let mut out = String::new();
+        dump(&encoded[0..encoded_len], nums.len(), 1, &mut out).unwrap();
+
+        assert_eq!(
+            "12 numbers in 3 quads (3 complete)\n\
+             quad 0: control 0x00 lengths [1, 1, 1, 1] bytes 3..7 nums [0, 1, 2, 3]\n\
+             ... 2 more quads\n",
+            out
+        );
+    }
+
+    #[test]
+    fn dump_truncated_input() {
+        let nums: Vec<u32> = vec![1, 2, 3, 4, 1 << 30];
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+
+        let mut out = String::new();
+        dump(&encoded[0..(encoded_len - 1)], nums.len(), 10, &mut out).unwrap();
+
+        assert_eq!(
+            "5 numbers in 2 quads (1 complete)\n\
+             quad 0: control 0x00 lengths [1, 1, 1, 1] bytes 2..6 nums [1, 2, 3, 4]\n\
+             quad 1: control 0x03 lengths [4] bytes 6..10 past end of input (9 bytes)\n",
+            out
+        );
+    }
+
+    #[test]
+    fn dump_missing_control_bytes() {
+        let mut out = String::new();
+        dump(&[0], 8, 10, &mut out).unwrap();
+
+        assert_eq!(
+            "8 numbers in 2 quads (2 complete)\n\
+             input ends after 1 of 2 control bytes\n",
+            out
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::cmp;
+
+use {encoded_shape, EncodedShape, Encoder};
+use super::{encode_complete_quads, encode_partial_quad};
+
+/// Encode several slices as if they were one concatenated slice, without copying them into a
+/// contiguous buffer first.
+///
+/// `output` should be sized just as for `encode()` with the total length of all the chunks.
+///
+/// Returns the number of bytes written to `output`.
+pub fn encode_chunks<E: Encoder>(chunks: &[&[u32]], output: &mut [u8]) -> usize {
+    let count = chunks.iter().map(|c| c.len()).sum();
+
+    let mut encoder = ChunkedEncoder::new(output, count);
+    for chunk in chunks {
+        encoder.push::<E>(chunk);
+    }
+
+    encoder.finish()
+}
+
+/// Encodes numbers as they are provided, a chunk at a time.
+///
+/// Since control bytes are written before all encoded numbers, the total number of numbers must be
+/// known up front. Chunks may be any length; quads that straddle chunk boundaries are buffered
+/// until they are complete.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "decode")] {
+/// use stream_vbyte::{decode, ChunkedEncoder, Scalar};
+///
+/// let mut encoded = vec![0; 5 * 10];
+/// let encoded_len = {
+///     let mut encoder = ChunkedEncoder::new(&mut encoded, 10);
+///     encoder.push::<Scalar>(&[1, 2, 3]);
+///     encoder.push::<Scalar>(&[4, 5, 6, 7, 8, 9]);
+///     encoder.push::<Scalar>(&[10]);
+///     encoder.finish()
+/// };
+///
+/// let mut decoded = vec![0; 10];
+/// assert_eq!(encoded_len, decode::<Scalar>(&encoded[0..encoded_len], 10, &mut decoded));
+/// assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], decoded);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ChunkedEncoder<'a> {
+    output: &'a mut [u8],
+    count: usize,
+    shape: EncodedShape,
+    /// Numbers from a quad that hasn't been completed yet
+    pending: [u32; 4],
+    pending_len: usize,
+    nums_pushed: usize,
+    control_bytes_written: usize,
+    encoded_bytes_written: usize,
+}
+
+impl<'a> ChunkedEncoder<'a> {
+    /// Create an encoder that will write `count` numbers to `output`.
+    ///
+    /// `output` should be sized just as for `encode()`.
+    pub fn new(output: &'a mut [u8], count: usize) -> ChunkedEncoder<'a> {
+        ChunkedEncoder {
+            output,
+            count,
+            shape: encoded_shape(count),
+            pending: [0; 4],
+            pending_len: 0,
+            nums_pushed: 0,
+            control_bytes_written: 0,
+            encoded_bytes_written: 0,
+        }
+    }
+
+    /// Encode the numbers in `chunk`.
+    ///
+    /// Panics if this would push more than `count` numbers in total.
+    pub fn push<E: Encoder>(&mut self, chunk: &[u32]) {
+        assert!(
+            self.nums_pushed + chunk.len() <= self.count,
+            "Pushed more numbers than the declared count"
+        );
+        self.nums_pushed += chunk.len();
+
+        let mut chunk = chunk;
+
+        if self.pending_len > 0 {
+            let to_copy = cmp::min(4 - self.pending_len, chunk.len());
+            self.pending[self.pending_len..(self.pending_len + to_copy)]
+                .copy_from_slice(&chunk[0..to_copy]);
+            self.pending_len += to_copy;
+            chunk = &chunk[to_copy..];
+
+            if self.pending_len < 4 {
+                return;
+            }
+
+            let pending = self.pending;
+            self.encode_quads::<E>(&pending);
+            self.pending_len = 0;
+        }
+
+        let complete_len = chunk.len() / 4 * 4;
+        self.encode_quads::<E>(&chunk[0..complete_len]);
+
+        let leftover = &chunk[complete_len..];
+        self.pending[0..leftover.len()].copy_from_slice(leftover);
+        self.pending_len = leftover.len();
+    }
+
+    /// Write the trailing partial quad, if any.
+    ///
+    /// Panics if fewer than `count` numbers were pushed.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn finish(self) -> usize {
+        assert_eq!(
+            self.count, self.nums_pushed,
+            "Pushed fewer numbers than the declared count"
+        );
+
+        let mut encoded_bytes_written = self.encoded_bytes_written;
+
+        if self.pending_len > 0 {
+            let (control_bytes, encoded_bytes) =
+                self.output.split_at_mut(self.shape.control_bytes_len);
+            let (control_byte, len) = encode_partial_quad(
+                &self.pending[0..self.pending_len],
+                &mut encoded_bytes[encoded_bytes_written..],
+            );
+            control_bytes[self.control_bytes_written] = control_byte;
+            encoded_bytes_written += len;
+        }
+
+        self.shape.control_bytes_len + encoded_bytes_written
+    }
+
+    fn encode_quads<E: Encoder>(&mut self, input: &[u32]) {
+        let (control_bytes, encoded_bytes) = self.output.split_at_mut(self.shape.control_bytes_len);
+        let quads = input.len() / 4;
+
+        self.encoded_bytes_written += encode_complete_quads::<E>(
+            input,
+            &mut control_bytes[self.control_bytes_written..(self.control_bytes_written + quads)],
+            &mut encoded_bytes[self.encoded_bytes_written..],
+        );
+        self.control_bytes_written += quads;
+    }
+}
@@ -1,14 +1,24 @@
+use std::any;
 use std::cmp;
 
 use byteorder::{ByteOrder, LittleEndian};
 
 use encoded_shape;
+use instrument::instrument;
 use scalar::Scalar;
 
+pub mod chunked;
+pub mod source;
+pub mod split;
+
 #[cfg(feature = "x86_sse41")]
 pub mod sse41;
 
 /// Encode numbers to bytes.
+///
+/// `encode()` encodes fewer than 16 numbers one at a time itself, without calling the `Encoder`,
+/// since that's faster for so few. Use more numbers than that, or `encode_quads()` directly, to
+/// exercise an implementation.
 pub trait Encoder {
     /// Encode complete quads of input numbers.
     ///
@@ -22,66 +32,206 @@ pub trait Encoder {
     /// Implementations must not write to `output` outside of the area that will be populated by
     /// encoded numbers when all control bytes are processed..
     ///
+    /// Implementations must encode each number in as few bytes as possible, so that all encoders
+    /// produce identical output (see `is_canonical()`).
+    ///
     /// Returns the number of numbers encoded and the number of bytes written to `output`.
     fn encode_quads(input: &[u32], control_bytes: &mut [u8], output: &mut [u8]) -> (usize, usize);
 }
 
+/// An `Encoder` whose `encode_quads()` writes only the encoded numbers it reports writing, leaving
+/// the rest of `output` untouched, rather than possibly using the rest of the area the quads will
+/// occupy as scratch space (as `x86::Sse41` does).
+///
+/// This only matters when calling `encode_quads()` directly: `encode()` never writes past the
+/// length it returns, whatever the `Encoder`.
+pub trait NoOverwrite: Encoder {}
+
 /// Encode the `input` slice into the `output` slice.
 ///
 /// If you don't have specific knowledge of the input that would let you determine the encoded
 /// length ahead of time, make `output` 5x as long as `input`. The worst-case encoded length is 4
 /// bytes per `u32` plus another byte for every 4 `u32`s, including any trailing partial 4-some.
 ///
-/// Returns the number of bytes written to the `output` slice.
+/// Returns the number of bytes written to the `output` slice. Bytes of `output` after that are
+/// never written to, whichever `Encoder` is used, so `output` can be the unused tail of a buffer
+/// that already holds other data.
+///
+/// With the `debug-verify` feature, the output is decoded with `Scalar` before returning, and this
+/// panics if it doesn't match `input`.
 pub fn encode<E: Encoder>(input: &[u32], output: &mut [u8]) -> usize {
+    instrument("encode", any::type_name::<E>(), input.len(), || {
+        let encoded_len = encode_uninstrumented::<E>(input, output);
+        #[cfg(feature = "debug-verify")]
+        verify(input, &output[0..encoded_len]);
+
+        encoded_len
+    })
+}
+
+/// Panic if `encoded` doesn't decode to `input`.
+#[cfg(feature = "debug-verify")]
+pub(crate) fn verify(input: &[u32], encoded: &[u8]) {
+    use decode::cursor::DecodeCursor;
+
+    let mut cursor = DecodeCursor::new(encoded, input.len());
+    let mut decoded = [0; 64];
+    let mut index = 0;
+
+    while cursor.has_more() {
+        let len = cursor.decode_slice::<Scalar>(&mut decoded);
+        for (i, (&expected, &actual)) in input[index..].iter().zip(&decoded[0..len]).enumerate() {
+            assert!(
+                expected == actual,
+                "Encoded number {} decodes to {} instead of {}",
+                index + i,
+                actual,
+                expected
+            );
+        }
+        index += len;
+    }
+
+    assert!(
+        cursor.input_consumed() == encoded.len(),
+        "Encoded length doesn't match the encoded numbers"
+    );
+}
+
+/// Encode the `input` slice, appending the encoded bytes to `output`.
+///
+/// `output` is grown to fit the worst-case encoded length, then truncated to the actual length, so
+/// there's no need to size it first.
+///
+/// Returns the number of bytes appended to `output`.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "decode")] {
+/// use stream_vbyte::{decode_to_new_vec, encode_append, Scalar};
+///
+/// let mut encoded = Vec::new();
+/// let encoded_len = encode_append::<Scalar>(&[1, 1000, 1_000_000], &mut encoded);
+/// assert_eq!(encoded_len, encoded.len());
+///
+/// assert_eq!(vec![1, 1000, 1_000_000], decode_to_new_vec::<Scalar>(&encoded, 3));
+/// # }
+/// ```
+pub fn encode_append<E: Encoder>(input: &[u32], output: &mut Vec<u8>) -> usize {
+    let start = output.len();
+    let max_encoded_len = encoded_shape(input.len()).control_bytes_len + input.len() * 4;
+
+    output.resize(start + max_encoded_len, 0);
+    let encoded_len = encode::<E>(input, &mut output[start..]);
+    output.truncate(start + encoded_len);
+
+    encoded_len
+}
+
+fn encode_uninstrumented<E: Encoder>(input: &[u32], output: &mut [u8]) -> usize {
     if input.len() == 0 {
         return 0;
     }
 
+    if input.len() < SMALL_INPUT_LEN {
+        return encode_small(input, output);
+    }
+
     let shape = encoded_shape(input.len());
 
     let (control_bytes, encoded_bytes) = output.split_at_mut(shape.control_bytes_len);
 
-    let (nums_encoded, mut num_bytes_written) = E::encode_quads(
-        &input[..],
+    let mut num_bytes_written = encode_complete_quads::<E>(
+        &input[0..(shape.complete_control_bytes_len * 4)],
         &mut control_bytes[0..shape.complete_control_bytes_len],
-        &mut encoded_bytes[..],
+        encoded_bytes,
     );
 
-    // may be some input left, use Scalar to finish it
-    let control_bytes_written = nums_encoded / 4;
+    // last control byte, if there were leftovers
+    if shape.leftover_numbers > 0 {
+        let (control_byte, len) = encode_partial_quad(
+            &input[(shape.complete_control_bytes_len * 4)..],
+            &mut encoded_bytes[num_bytes_written..],
+        );
+        control_bytes[shape.complete_control_bytes_len] = control_byte;
+        num_bytes_written += len;
+    }
 
-    let (more_nums_encoded, more_bytes_written) = Scalar::encode_quads(
-        &input[nums_encoded..],
-        &mut control_bytes[control_bytes_written..shape.complete_control_bytes_len],
-        &mut encoded_bytes[num_bytes_written..],
-    );
+    control_bytes.len() + num_bytes_written
+}
 
-    num_bytes_written += more_bytes_written;
+/// Inputs shorter than this are encoded by `encode_small()`.
+const SMALL_INPUT_LEN: usize = 16;
 
-    debug_assert_eq!(
-        shape.complete_control_bytes_len * 4,
-        nums_encoded + more_nums_encoded
-    );
+/// Encode a few numbers one at a time, which is faster for tiny inputs than splitting the output
+/// and dispatching to an `Encoder`.
+fn encode_small(input: &[u32], output: &mut [u8]) -> usize {
+    let control_bytes_len = (input.len() + 3) / 4;
+    let mut num_bytes_written = control_bytes_len;
 
-    // last control byte, if there were leftovers
-    if shape.leftover_numbers > 0 {
+    for (quad_index, quad) in input.chunks(4).enumerate() {
         let mut control_byte = 0;
-        let mut nums_encoded = shape.complete_control_bytes_len * 4;
-
-        for i in 0..shape.leftover_numbers {
-            let num = input[nums_encoded];
-            let len = encode_num_scalar(num, &mut encoded_bytes[num_bytes_written..]);
 
+        for (i, &num) in quad.iter().enumerate() {
+            let len = encode_num_scalar(num, &mut output[num_bytes_written..]);
             control_byte |= ((len - 1) as u8) << (i * 2);
-
             num_bytes_written += len;
-            nums_encoded += 1;
         }
-        control_bytes[shape.complete_control_bytes_len] = control_byte;
+
+        output[quad_index] = control_byte;
     }
 
-    control_bytes.len() + num_bytes_written
+    num_bytes_written
+}
+
+/// Encode a trailing partial quad of 1 to 3 numbers.
+///
+/// Returns the control byte and the number of bytes written to `output`.
+pub fn encode_partial_quad(input: &[u32], output: &mut [u8]) -> (u8, usize) {
+    debug_assert!(input.len() < 4);
+
+    let mut control_byte = 0;
+    let mut num_bytes_written = 0;
+
+    for (i, &num) in input.iter().enumerate() {
+        let len = encode_num_scalar(num, &mut output[num_bytes_written..]);
+
+        control_byte |= ((len - 1) as u8) << (i * 2);
+
+        num_bytes_written += len;
+    }
+
+    (control_byte, num_bytes_written)
+}
+
+/// Encode all of `input`, which must consist of complete quads, writing one control byte per quad
+/// to `control_bytes` and encoded numbers to `output`.
+///
+/// Whatever quads `E` declines to encode are finished with `Scalar`.
+///
+/// Returns the number of bytes written to `output`.
+pub fn encode_complete_quads<E: Encoder>(
+    input: &[u32],
+    control_bytes: &mut [u8],
+    output: &mut [u8],
+) -> usize {
+    debug_assert_eq!(input.len(), control_bytes.len() * 4);
+
+    let (nums_encoded, num_bytes_written) = E::encode_quads(input, control_bytes, output);
+
+    // may be some input left, use Scalar to finish it
+    let control_bytes_written = nums_encoded / 4;
+
+    let (more_nums_encoded, more_bytes_written) = Scalar::encode_quads(
+        &input[nums_encoded..],
+        &mut control_bytes[control_bytes_written..],
+        &mut output[num_bytes_written..],
+    );
+
+    debug_assert_eq!(input.len(), nums_encoded + more_nums_encoded);
+
+    num_bytes_written + more_bytes_written
 }
 
 #[inline]
@@ -102,6 +252,43 @@ pub fn encode_num_scalar(num: u32, output: &mut [u8]) -> usize {
 mod tests {
     use super::*;
 
+    #[test]
+    fn encode_append_after_existing_bytes() {
+        for count in 0..100 {
+            let nums: Vec<u32> = (0..count).map(|i| 1 << (i % 32)).collect();
+            let mut expected = vec![0; count * 5];
+            let expected_len = encode::<Scalar>(&nums, &mut expected);
+
+            let mut output = vec![0xFF; 3];
+            assert_eq!(expected_len, encode_append::<Scalar>(&nums, &mut output));
+            assert_eq!(&[0xFF; 3], &output[0..3]);
+            assert_eq!(&expected[0..expected_len], &output[3..]);
+        }
+    }
+
+    #[cfg(feature = "debug-verify")]
+    #[test]
+    #[should_panic(expected = "Encoded number 2 decodes to 3 instead of 2")]
+    fn debug_verify_catches_bad_encoder() {
+        struct OffByOne;
+
+        impl Encoder for OffByOne {
+            fn encode_quads(
+                input: &[u32],
+                control_bytes: &mut [u8],
+                output: &mut [u8],
+            ) -> (usize, usize) {
+                let result = Scalar::encode_quads(input, control_bytes, output);
+                // the third number of the first quad, which is 1 byte long
+                output[2] += 1;
+                result
+            }
+        }
+
+        let nums: Vec<u32> = (0..20).collect();
+        encode::<OffByOne>(&nums, &mut vec![0; nums.len() * 5]);
+    }
+
     #[test]
     fn encode_num_zero() {
         let mut buf = [0; 4];
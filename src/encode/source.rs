@@ -0,0 +1,142 @@
+use std::cmp;
+
+use Encoder;
+use super::chunked::ChunkedEncoder;
+
+/// How many quads to pull from a source before handing them to the `Encoder`.
+const BATCH_QUADS: usize = 16;
+
+/// Provides numbers to `encode_source()` a quad at a time, so that numbers can be encoded straight
+/// from wherever they're produced (a generator, a ring buffer, a column of a larger page...)
+/// without first collecting them into a `&[u32]`.
+pub trait EncodeSource {
+    /// Returns the next 4 numbers, or `None` if there are no more.
+    ///
+    /// If the count being encoded isn't a multiple of 4, only the first `count % 4` numbers of the
+    /// last quad are used, so the rest can be anything.
+    fn next_quad(&mut self) -> Option<[u32; 4]>;
+}
+
+/// Encode `count` numbers pulled from `source` into `output`.
+///
+/// Quads are pulled in small batches into a buffer on the stack, so no allocation is needed.
+/// `output` should be sized just as for `encode()` with `count` numbers.
+///
+/// Returns the number of bytes written to `output`.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "decode")] {
+/// use stream_vbyte::{decode_to_new_vec, encode_source, EncodeSource, Scalar};
+///
+/// struct Squares {
+///     next: u32,
+/// }
+///
+/// impl EncodeSource for Squares {
+///     fn next_quad(&mut self) -> Option<[u32; 4]> {
+///         let n = self.next;
+///         self.next += 4;
+///         Some([n * n, (n + 1) * (n + 1), (n + 2) * (n + 2), (n + 3) * (n + 3)])
+///     }
+/// }
+///
+/// let mut encoded = vec![0; 5 * 10];
+/// let encoded_len = encode_source::<Scalar, _>(&mut Squares { next: 0 }, 10, &mut encoded);
+///
+/// let expected: Vec<u32> = (0..10).map(|n| n * n).collect();
+/// assert_eq!(expected, decode_to_new_vec::<Scalar>(&encoded[0..encoded_len], 10));
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `source` runs out before providing `count` numbers.
+pub fn encode_source<E: Encoder, S: EncodeSource>(
+    source: &mut S,
+    count: usize,
+    output: &mut [u8],
+) -> usize {
+    let mut encoder = ChunkedEncoder::new(output, count);
+    let mut batch = [0_u32; BATCH_QUADS * 4];
+    let mut nums_pulled = 0;
+
+    while nums_pulled < count {
+        let batch_len = cmp::min(batch.len(), count - nums_pulled);
+
+        for chunk in batch[0..batch_len].chunks_mut(4) {
+            let quad = source
+                .next_quad()
+                .expect("Source ran out before providing all numbers");
+            let len = chunk.len();
+            chunk.copy_from_slice(&quad[0..len]);
+        }
+
+        encoder.push::<E>(&batch[0..batch_len]);
+        nums_pulled += batch_len;
+    }
+
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    struct SliceSource<'a> {
+        nums: &'a [u32],
+        pos: usize,
+    }
+
+    impl<'a> EncodeSource for SliceSource<'a> {
+        fn next_quad(&mut self) -> Option<[u32; 4]> {
+            if self.pos >= self.nums.len() {
+                return None;
+            }
+
+            let mut quad = [0xDEAD_BEEF; 4];
+            let len = cmp::min(4, self.nums.len() - self.pos);
+            quad[0..len].copy_from_slice(&self.nums[self.pos..(self.pos + len)]);
+            self.pos += 4;
+
+            Some(quad)
+        }
+    }
+
+    #[test]
+    fn encode_source_matches_encode() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 300))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut expected = vec![0; nums.len() * 5];
+            let expected_len = encode::<Scalar>(&nums, &mut expected);
+
+            let mut encoded = vec![0; nums.len() * 5];
+            let mut source = SliceSource { nums: &nums, pos: 0 };
+            assert_eq!(
+                expected_len,
+                encode_source::<Scalar, _>(&mut source, nums.len(), &mut encoded)
+            );
+            assert_eq!(expected, encoded);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Source ran out before providing all numbers")]
+    fn encode_source_panics_when_source_runs_out() {
+        let mut source = SliceSource {
+            nums: &[1, 2, 3, 4, 5],
+            pos: 0,
+        };
+        encode_source::<Scalar, _>(&mut source, 9, &mut [0; 45]);
+    }
+}
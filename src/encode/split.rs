@@ -0,0 +1,167 @@
+use {encoded_num_len, encoded_shape, Encoder};
+use super::{encode, encode_complete_quads, encode_partial_quad};
+
+/// How many quads to encode into scratch space at a time before copying them to the output.
+const CHUNK_QUADS: usize = 16;
+
+/// Encode `input` into two discontiguous slices as if they were one, such as the free space in a
+/// ring buffer that wraps around: the encoded bytes start in `first` and continue into `second`.
+///
+/// If the encoded numbers fit in `first`, they are encoded directly into it. Otherwise, they are
+/// encoded a few quads at a time into a small buffer on the stack and copied into place, so no
+/// allocation is needed.
+///
+/// Returns the encoded length, or `None` (having written nothing) if the two slices together are
+/// too short.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "decode")] {
+/// use stream_vbyte::{decode, encode_split, Scalar};
+///
+/// let nums: Vec<u32> = (0..100).map(|i| i * 100).collect();
+/// let mut ring = vec![0; 300];
+/// // free space starts 50 bytes before the end and wraps around to the start
+/// let (head, tail) = ring.split_at_mut(250);
+/// let encoded_len = encode_split::<Scalar>(&nums, tail, &mut head[0..200]).unwrap();
+///
+/// let mut contiguous = ring[250..].to_vec();
+/// contiguous.extend_from_slice(&ring[0..(encoded_len - 50)]);
+/// let mut decoded = vec![0; nums.len()];
+/// decode::<Scalar>(&contiguous, nums.len(), &mut decoded);
+/// assert_eq!(nums, decoded);
+/// # }
+/// ```
+pub fn encode_split<E: Encoder>(
+    input: &[u32],
+    first: &mut [u8],
+    second: &mut [u8],
+) -> Option<usize> {
+    let shape = encoded_shape(input.len());
+    let encoded_len = shape.control_bytes_len
+        + input.iter().map(|&n| encoded_num_len(n)).sum::<usize>();
+
+    if encoded_len > first.len() + second.len() {
+        return None;
+    }
+
+    if encoded_len <= first.len() {
+        return Some(encode::<E>(input, first));
+    }
+
+    let mut output = SplitOutput { first, second };
+    let mut control_bytes = [0_u8; CHUNK_QUADS];
+    let mut encoded_nums = [0_u8; CHUNK_QUADS * 16];
+    let mut nums_offset = shape.control_bytes_len;
+
+    let complete_nums = shape.complete_control_bytes_len * 4;
+    for (chunk_index, chunk) in input[0..complete_nums].chunks(CHUNK_QUADS * 4).enumerate() {
+        let quads = chunk.len() / 4;
+        let len = encode_complete_quads::<E>(
+            chunk,
+            &mut control_bytes[0..quads],
+            &mut encoded_nums,
+        );
+
+        output.write(chunk_index * CHUNK_QUADS, &control_bytes[0..quads]);
+        output.write(nums_offset, &encoded_nums[0..len]);
+        nums_offset += len;
+    }
+
+    if shape.leftover_numbers > 0 {
+        let (control_byte, len) =
+            encode_partial_quad(&input[complete_nums..], &mut encoded_nums);
+        output.write(shape.complete_control_bytes_len, &[control_byte]);
+        output.write(nums_offset, &encoded_nums[0..len]);
+        nums_offset += len;
+    }
+
+    debug_assert_eq!(encoded_len, nums_offset);
+
+    Some(encoded_len)
+}
+
+/// Two slices addressed as if they were concatenated.
+struct SplitOutput<'a> {
+    first: &'a mut [u8],
+    second: &'a mut [u8],
+}
+
+impl<'a> SplitOutput<'a> {
+    fn write(&mut self, offset: usize, bytes: &[u8]) {
+        let first_len = self.first.len();
+
+        if offset >= first_len {
+            let start = offset - first_len;
+            self.second[start..(start + bytes.len())].copy_from_slice(bytes);
+        } else if offset + bytes.len() <= first_len {
+            self.first[offset..(offset + bytes.len())].copy_from_slice(bytes);
+        } else {
+            let (in_first, in_second) = bytes.split_at(first_len - offset);
+            self.first[offset..].copy_from_slice(in_first);
+            self.second[0..in_second.len()].copy_from_slice(in_second);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn encode_split_random_scalar() {
+        do_encode_split_random::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_sse41")]
+    #[test]
+    fn encode_split_random_sse41() {
+        do_encode_split_random::<::x86::Sse41>();
+    }
+
+    #[test]
+    fn too_short_writes_nothing() {
+        let mut first = [0xFF; 2];
+        let mut second = [0xFF; 2];
+        assert_eq!(
+            None,
+            encode_split::<Scalar>(&[1, 2, 3, 1 << 8], &mut first, &mut second)
+        );
+        assert_eq!([0xFF; 2], first);
+        assert_eq!([0xFF; 2], second);
+    }
+
+    fn do_encode_split_random<E: Encoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 300);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut expected = vec![0; count * 5];
+            let expected_len = encode::<Scalar>(&nums, &mut expected);
+            expected.truncate(expected_len);
+
+            let split = rng.gen_range(0, expected_len + 1);
+            let extra = rng.gen_range(0, 20);
+            let mut first = vec![0xA5; split];
+            let mut second = vec![0xA5; expected_len - split + extra];
+
+            assert_eq!(
+                Some(expected_len),
+                encode_split::<E>(&nums, &mut first, &mut second)
+            );
+            let mut actual = first.clone();
+            actual.extend_from_slice(&second);
+            assert_eq!(&expected[..], &actual[0..expected_len]);
+            assert!(actual[expected_len..].iter().all(|&b| b == 0xA5));
+        }
+    }
+}
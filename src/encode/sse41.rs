@@ -1,14 +1,18 @@
-extern crate stdsimd;
-
-use self::stdsimd::simd;
-use self::stdsimd::vendor::{__m128i, _mm_loadu_si128, _mm_min_epu8, _mm_mullo_epi32,
-                            _mm_shuffle_epi8, _mm_storeu_si128};
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m128i, _mm_extract_epi8, _mm_loadu_si128, _mm_min_epu8, _mm_mullo_epi32,
+                     _mm_shuffle_epi8, _mm_storeu_si128};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, _mm_extract_epi8, _mm_loadu_si128, _mm_min_epu8,
+                        _mm_mullo_epi32, _mm_shuffle_epi8, _mm_storeu_si128};
 
 use tables;
 
 use super::Encoder;
 
 /// Encoder using SSE4.1 instructions.
+///
+/// Encoding panics if the CPU doesn't support SSE4.1. The check is done once per call to
+/// `encode_quads()`, and left out entirely when compiling with the `sse4.1` target feature.
 pub struct Sse41;
 
 const ONES: [u8; 16] = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
@@ -41,103 +45,105 @@ const AGGREGATORS: [u32; 4] = [CONCAT, SUM, 0, 0];
 
 impl Encoder for Sse41 {
     fn encode_quads(input: &[u32], control_bytes: &mut [u8], output: &mut [u8]) -> (usize, usize) {
-        let mut nums_encoded: usize = 0;
-        let mut bytes_encoded: usize = 0;
-
-        // TODO these load unaligned once https://github.com/rust-lang/rust/issues/33626
-        // hits stable
-        let ones = simd::u8x16::from(unsafe { _mm_loadu_si128(ONES.as_ptr() as *const __m128i) });
-        let shifts = simd::i32x4::from(unsafe {
-            _mm_loadu_si128(SHIFTS.as_ptr() as *const __m128i)
-        });
-        let lanecodes = simd::u8x16::from(unsafe {
-            _mm_loadu_si128(LANECODES.as_ptr() as *const __m128i)
-        });
-        let gather_hi = simd::u8x16::from(unsafe {
-            _mm_loadu_si128(GATHER_HI.as_ptr() as *const __m128i)
-        });
-        let aggregators = simd::i32x4::from(unsafe {
-            _mm_loadu_si128(AGGREGATORS.as_ptr() as *const __m128i)
-        });
-
-        // Encoding writes 16 bytes at a time, but if numbers are encoded with 1 byte each, that
-        // means the last 3 quads could write past what is actually necessary. So, don't process
-        // the last few control bytes.
-        let control_byte_limit = control_bytes.len().saturating_sub(3);
-
-        for control_byte in &mut control_bytes[0..control_byte_limit].iter_mut() {
-            let to_encode = simd::u8x16::from(unsafe {
-                _mm_loadu_si128(input[nums_encoded..(nums_encoded + 4)].as_ptr()
-                    as *const __m128i)
-            });
-
-            // clamp each byte to 1 if nonzero
-            let mins = simd::i32x4::from(unsafe { _mm_min_epu8(to_encode, ones) });
-
-            // Apply shifts to clamped bytes. e.g. u32::max_value() would be (little endian):
-            // 00000001 00000001 00000001 00000001
-            // and after multiplication aka shifting:
-            // 00000001 00000011 00000111 00000111
-            // 1 << 16 | 1 would be:
-            // 00000001 00000000 00000001 00000000
-            // and shifted:
-            // 00000001 00000010 00000101 00000010
-            // At most the bottom 3 bits of each byte will be set by shifting.
-            // What we care about is the bottom 3 bits of the high byte in each num.
-            // A 1-byte number (clamped to 0x01000000) will accumulate to 0x00 in the top byte
-            // because there isn't a 3-byte shift to get that set bit into the top byte.
-            // A 2-byte number (clamped to 0x00010000) will accumulate to 0x04 in the top byte
-            // because the set bit would have been shifted 2 bytes + 2 bits higher.
-            // A 3-byte number will have the 0x02 bit set in the top byte, and possibly the 0x04
-            // bit set as well if the 2nd byte was non-zero.
-            // A 4-byte number will have the 0x01 bit set in the top byte, and possibly 0x02 and
-            // 0x04.
-            // In summary, byte lengths -> high byte:
-            // 1-byte -> 0x00
-            // 2-byte -> 0x04
-            // 3-byte -> 0x02, 0x06
-            // 4-byte -> 0x01, 0x05, 0x03, 0x07
-            let bytemaps = simd::u8x16::from(unsafe { _mm_mullo_epi32(mins, shifts) });
-
-            // Map high bytes to the corresponding lane codes. (Other bytes are mapped as well
-            // but are not used.)
-            let shuffled_lanecodes = unsafe { _mm_shuffle_epi8(lanecodes, bytemaps) };
-
-            // Assemble 2 copies of the high byte from each of the 4 numbers.
-            // The first copy will be used to calculate the control byte, the second the length.
-            let hi_bytes =
-                simd::i32x4::from(unsafe { _mm_shuffle_epi8(shuffled_lanecodes, gather_hi) });
-
-            // use CONCAT to shift the lane code bits from bytes 0-3 into 1 byte (byte 3)
-            // use SUM to sum lane code bits from bytes 4-7 into 1 byte (byte 7)
-            let code_and_length = unsafe { _mm_mullo_epi32(hi_bytes, aggregators) };
-
-            let bytes = simd::u8x16::from(code_and_length);
-            let code = bytes.extract(3);
-            let length = bytes.extract(7) + 4;
-
-            let mask_bytes = tables::X86_ENCODE_SHUFFLE_TABLE[code as usize];
-            let encode_mask = simd::u8x16::from(unsafe {
-                _mm_loadu_si128(mask_bytes.as_ptr() as *const __m128i)
-            });
-
-            let encoded = unsafe { _mm_shuffle_epi8(to_encode, encode_mask) };
-
-            unsafe {
-                _mm_storeu_si128(
-                    output[bytes_encoded..(bytes_encoded + 16)].as_ptr() as *mut __m128i,
-                    simd::i8x16::from(encoded),
-                );
-            }
-
-            *control_byte = code;
-
-            bytes_encoded += length as usize;
-            nums_encoded += 4;
-        }
+        assert!(
+            cfg!(target_feature = "sse4.1") || is_x86_feature_detected!("sse4.1"),
+            "x86::Sse41 needs a CPU with SSE4.1"
+        );
+
+        // Safe because the CPU supports SSE4.1, and so SSSE3.
+        unsafe { encode_quads_sse41(input, control_bytes, output) }
+    }
+}
 
-        (nums_encoded, bytes_encoded)
+/// The encode loop, compiled with SSE4.1 enabled whatever the target features.
+///
+/// # Safety
+///
+/// The CPU must support SSE4.1.
+#[target_feature(enable = "ssse3,sse4.1")]
+unsafe fn encode_quads_sse41(
+    input: &[u32],
+    control_bytes: &mut [u8],
+    output: &mut [u8],
+) -> (usize, usize) {
+    let mut nums_encoded: usize = 0;
+    let mut bytes_encoded: usize = 0;
+
+    let ones = _mm_loadu_si128(ONES.as_ptr() as *const __m128i);
+    let shifts = _mm_loadu_si128(SHIFTS.as_ptr() as *const __m128i);
+    let lanecodes = _mm_loadu_si128(LANECODES.as_ptr() as *const __m128i);
+    let gather_hi = _mm_loadu_si128(GATHER_HI.as_ptr() as *const __m128i);
+    let aggregators = _mm_loadu_si128(AGGREGATORS.as_ptr() as *const __m128i);
+
+    // Encoding writes 16 bytes at a time, but if numbers are encoded with 1 byte each, that
+    // means the last 3 quads could write past what is actually necessary. So, don't process
+    // the last few control bytes.
+    let control_byte_limit = control_bytes.len().saturating_sub(3);
+
+    for control_byte in &mut control_bytes[0..control_byte_limit].iter_mut() {
+        let to_encode =
+            _mm_loadu_si128(input[nums_encoded..(nums_encoded + 4)].as_ptr() as *const __m128i);
+
+        // clamp each byte to 1 if nonzero
+        let mins = _mm_min_epu8(to_encode, ones);
+
+        // Apply shifts to clamped bytes. e.g. u32::max_value() would be (little endian):
+        // 00000001 00000001 00000001 00000001
+        // and after multiplication aka shifting:
+        // 00000001 00000011 00000111 00000111
+        // 1 << 16 | 1 would be:
+        // 00000001 00000000 00000001 00000000
+        // and shifted:
+        // 00000001 00000010 00000101 00000010
+        // At most the bottom 3 bits of each byte will be set by shifting.
+        // What we care about is the bottom 3 bits of the high byte in each num.
+        // A 1-byte number (clamped to 0x01000000) will accumulate to 0x00 in the top byte
+        // because there isn't a 3-byte shift to get that set bit into the top byte.
+        // A 2-byte number (clamped to 0x00010000) will accumulate to 0x04 in the top byte
+        // because the set bit would have been shifted 2 bytes + 2 bits higher.
+        // A 3-byte number will have the 0x02 bit set in the top byte, and possibly the 0x04
+        // bit set as well if the 2nd byte was non-zero.
+        // A 4-byte number will have the 0x01 bit set in the top byte, and possibly 0x02 and
+        // 0x04.
+        // In summary, byte lengths -> high byte:
+        // 1-byte -> 0x00
+        // 2-byte -> 0x04
+        // 3-byte -> 0x02, 0x06
+        // 4-byte -> 0x01, 0x05, 0x03, 0x07
+        let bytemaps = _mm_mullo_epi32(mins, shifts);
+
+        // Map high bytes to the corresponding lane codes. (Other bytes are mapped as well
+        // but are not used.)
+        let shuffled_lanecodes = _mm_shuffle_epi8(lanecodes, bytemaps);
+
+        // Assemble 2 copies of the high byte from each of the 4 numbers.
+        // The first copy will be used to calculate the control byte, the second the length.
+        let hi_bytes = _mm_shuffle_epi8(shuffled_lanecodes, gather_hi);
+
+        // use CONCAT to shift the lane code bits from bytes 0-3 into 1 byte (byte 3)
+        // use SUM to sum lane code bits from bytes 4-7 into 1 byte (byte 7)
+        let code_and_length = _mm_mullo_epi32(hi_bytes, aggregators);
+
+        let code = _mm_extract_epi8(code_and_length, 3) as u8;
+        let length = _mm_extract_epi8(code_and_length, 7) as u8 + 4;
+
+        let mask_bytes = tables::X86_ENCODE_SHUFFLE_TABLE[code as usize];
+        let encode_mask = _mm_loadu_si128(mask_bytes.as_ptr() as *const __m128i);
+
+        let encoded = _mm_shuffle_epi8(to_encode, encode_mask);
+
+        _mm_storeu_si128(
+            output[bytes_encoded..(bytes_encoded + 16)].as_mut_ptr() as *mut __m128i,
+            encoded,
+        );
+
+        *control_byte = code;
+
+        bytes_encoded += length as usize;
+        nums_encoded += 4;
     }
+
+    (nums_encoded, bytes_encoded)
 }
 
 #[cfg(test)]
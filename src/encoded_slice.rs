@@ -0,0 +1,365 @@
+use std::fmt;
+#[cfg(feature = "nightly")]
+use std::iter::TrustedLen;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use {cumulative_encoded_len, encoded_shape, lengths, DecodeCursor, Decoder};
+use decode::decode_num_scalar;
+
+/// A borrowed view of encoded numbers that keeps track of how many numbers there are, so that
+/// encoded bytes and their count don't need to be passed around separately.
+///
+/// An `EncodedSlice` can be narrowed with `slice()` without decoding or copying anything. Since a
+/// sub-range need not start at a quad boundary, a slice may begin partway into its first quad.
+#[derive(Clone, Copy)]
+pub struct EncodedSlice<'a> {
+    /// Control bytes for every quad that overlaps the slice
+    control_bytes: &'a [u8],
+    /// Encoded numbers starting at the first quad that overlaps the slice
+    encoded_nums: &'a [u8],
+    /// How many numbers in the first quad precede the slice
+    head: usize,
+    len: usize,
+}
+
+impl<'a> EncodedSlice<'a> {
+    /// Create a view of `count` numbers encoded in `input`.
+    pub fn new(input: &'a [u8], count: usize) -> EncodedSlice<'a> {
+        let control_bytes_len = encoded_shape(count).control_bytes_len;
+
+        EncodedSlice {
+            control_bytes: &input[0..control_bytes_len],
+            encoded_nums: &input[control_bytes_len..],
+            head: 0,
+            len: count,
+        }
+    }
+
+    /// The number of numbers in the slice.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over the numbers in the slice, decoding them a chunk at a time with `D`.
+    pub fn iter<D: Decoder>(&self) -> DecodeIter<'a, D> {
+        DecodeIter::new(self)
+    }
+
+    /// Iterate over the numbers in the slice from last to first, decoding them a chunk at a time
+    /// with `D`.
+    ///
+    /// This scans the control bytes once up front to find where each chunk starts, and then
+    /// decodes only the chunks that are reached, so taking the last few numbers of a long slice
+    /// is cheap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "encode")] {
+    /// use stream_vbyte::{encode, EncodedSlice, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..1000).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    ///
+    /// let most_recent: Vec<u32> = EncodedSlice::new(&encoded, nums.len())
+    ///     .iter_rev::<Scalar>()
+    ///     .take(3)
+    ///     .collect();
+    /// assert_eq!(vec![999, 998, 997], most_recent);
+    /// # }
+    /// ```
+    pub fn iter_rev<D: Decoder>(&self) -> DecodeRevIter<'a, D> {
+        DecodeRevIter::new(self)
+    }
+
+    /// Returns the number at `index`, or `None` if `index` is out of range.
+    ///
+    /// This only decodes the one number, but finding it requires scanning the control bytes that
+    /// precede it, so iterate rather than calling this repeatedly.
+    pub fn get(&self, index: usize) -> Option<u32> {
+        if index >= self.len {
+            return None;
+        }
+
+        let position = self.head + index;
+        let quad_index = position / 4;
+        let control_byte = self.control_bytes[quad_index];
+
+        let offset = cumulative_encoded_len(&self.control_bytes[0..quad_index])
+            + lengths::partial_quad_len(control_byte, position % 4);
+
+        Some(decode_num_scalar(
+            lengths::num_len(control_byte, position % 4),
+            &self.encoded_nums[offset..],
+        ))
+    }
+
+    /// Returns a view of the numbers in `range`, which is relative to the start of this slice.
+    ///
+    /// Only the control bytes that precede the range are scanned; nothing is decoded.
+    pub fn slice(&self, range: Range<usize>) -> EncodedSlice<'a> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "Range out of bounds"
+        );
+
+        if range.start == range.end {
+            return EncodedSlice {
+                control_bytes: &[],
+                encoded_nums: &[],
+                head: 0,
+                len: 0,
+            };
+        }
+
+        let start = self.head + range.start;
+        let end = self.head + range.end;
+        let first_quad = start / 4;
+        let end_quad = (end + 3) / 4;
+        let skipped_len = cumulative_encoded_len(&self.control_bytes[0..first_quad]);
+
+        EncodedSlice {
+            control_bytes: &self.control_bytes[first_quad..end_quad],
+            encoded_nums: &self.encoded_nums[skipped_len..],
+            head: start % 4,
+            len: range.end - range.start,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for EncodedSlice<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncodedSlice")
+            .field("len", &self.len)
+            .field("quads", &self.control_bytes.len())
+            .field("head", &self.head)
+            .finish()
+    }
+}
+
+/// The number of numbers decoded at a time by `DecodeIter`.
+const ITER_BUF_LEN: usize = 64;
+
+/// Iterator over the numbers in an `EncodedSlice`.
+///
+/// Numbers can also be taken from the back, e.g. with `.rev()`, which works like
+/// `EncodedSlice.iter_rev()`.
+pub struct DecodeIter<'a, D: Decoder> {
+    slice: EncodedSlice<'a>,
+    /// Created the first time a number is taken from the back
+    back: Option<DecodeRevIter<'a, D>>,
+    cursor: DecodeCursor<'a>,
+    buf: [u32; ITER_BUF_LEN],
+    buf_pos: usize,
+    buf_len: usize,
+    remaining: usize,
+    decoder: PhantomData<D>,
+}
+
+impl<'a, D: Decoder> DecodeIter<'a, D> {
+    fn new(slice: &EncodedSlice<'a>) -> DecodeIter<'a, D> {
+        let mut iter = DecodeIter {
+            slice: *slice,
+            back: None,
+            cursor: DecodeCursor::from_parts(
+                slice.control_bytes,
+                slice.encoded_nums,
+                slice.head + slice.len,
+            ),
+            buf: [0; ITER_BUF_LEN],
+            buf_pos: 0,
+            buf_len: 0,
+            remaining: slice.len,
+            decoder: PhantomData,
+        };
+
+        if slice.head > 0 {
+            // discard the numbers in the first quad that precede the slice
+            iter.fill_buf();
+            iter.buf_pos = slice.head;
+        }
+
+        iter
+    }
+
+    fn fill_buf(&mut self) {
+        self.buf_len = self.cursor.decode_slice::<D>(&mut self.buf);
+        self.buf_pos = 0;
+    }
+}
+
+impl<'a, D: Decoder> Iterator for DecodeIter<'a, D> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        if self.buf_pos == self.buf_len {
+            self.fill_buf();
+        }
+
+        let num = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+        self.remaining -= 1;
+
+        Some(num)
+    }
+
+    /// Skips whole quads with `DecodeCursor.skip()` rather than decoding them, so e.g.
+    /// `iter.skip(1_000_000).next()` only decodes the quad it lands in.
+    fn nth(&mut self, n: usize) -> Option<u32> {
+        if n >= self.remaining {
+            self.remaining = 0;
+            return None;
+        }
+
+        let buffered = self.buf_len - self.buf_pos;
+        if n < buffered {
+            self.buf_pos += n;
+        } else {
+            // the cursor is at a quad boundary, so skip to the quad holding the number we want
+            let to_skip = n - buffered;
+            let quad_aligned = to_skip / 4 * 4;
+            self.cursor.skip(quad_aligned);
+            self.fill_buf();
+            self.buf_pos = to_skip - quad_aligned;
+        }
+
+        self.remaining -= n;
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, D: Decoder> DoubleEndedIterator for DecodeIter<'a, D> {
+    fn next_back(&mut self) -> Option<u32> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        // `remaining` keeps the front and back from passing each other
+        let slice = &self.slice;
+        self.back
+            .get_or_insert_with(|| DecodeRevIter::new(slice))
+            .next()
+    }
+}
+
+impl<'a, D: Decoder> ExactSizeIterator for DecodeIter<'a, D> {}
+
+impl<'a, D: Decoder> FusedIterator for DecodeIter<'a, D> {}
+
+/// `size_hint()` is always exact, so e.g. `collect()` allocates once.
+#[cfg(feature = "nightly")]
+unsafe impl<'a, D: Decoder> TrustedLen for DecodeIter<'a, D> {}
+
+/// Iterator over the numbers in an `EncodedSlice` from last to first.
+pub struct DecodeRevIter<'a, D: Decoder> {
+    control_bytes: &'a [u8],
+    encoded_nums: &'a [u8],
+    /// Offset into `encoded_nums` of the start of each chunk
+    chunk_offsets: Vec<usize>,
+    /// Position (counting from the start of the first quad) of the first number in the slice
+    head: usize,
+    /// Position of the first number in the buffer
+    buf_start: usize,
+    buf: [u32; ITER_BUF_LEN],
+    /// How many numbers at the start of the buffer haven't been returned yet
+    buf_len: usize,
+    remaining: usize,
+    decoder: PhantomData<D>,
+}
+
+impl<'a, D: Decoder> DecodeRevIter<'a, D> {
+    fn new(slice: &EncodedSlice<'a>) -> DecodeRevIter<'a, D> {
+        let quads_per_chunk = ITER_BUF_LEN / 4;
+        let end = slice.head + slice.len;
+
+        let mut chunk_offsets = Vec::with_capacity(end / ITER_BUF_LEN + 1);
+        let mut offset = 0;
+        for chunk in slice.control_bytes[0..(end / 4)].chunks(quads_per_chunk) {
+            chunk_offsets.push(offset);
+            offset += cumulative_encoded_len(chunk);
+        }
+        if chunk_offsets.len() < (end + ITER_BUF_LEN - 1) / ITER_BUF_LEN {
+            // a last chunk with only a partial quad
+            chunk_offsets.push(offset);
+        }
+
+        DecodeRevIter {
+            control_bytes: slice.control_bytes,
+            encoded_nums: slice.encoded_nums,
+            chunk_offsets,
+            head: slice.head,
+            buf_start: end,
+            buf: [0; ITER_BUF_LEN],
+            buf_len: 0,
+            remaining: slice.len,
+            decoder: PhantomData,
+        }
+    }
+
+    /// Decode the chunk before the one in the buffer.
+    fn fill_buf(&mut self) {
+        let chunk = (self.buf_start - 1) / ITER_BUF_LEN;
+        let chunk_start = chunk * ITER_BUF_LEN;
+        let chunk_len = self.buf_start - chunk_start;
+
+        let mut cursor = DecodeCursor::from_parts(
+            &self.control_bytes[(chunk_start / 4)..],
+            &self.encoded_nums[self.chunk_offsets[chunk]..],
+            chunk_len,
+        );
+        let nums_decoded = cursor.decode_slice::<D>(&mut self.buf);
+        debug_assert_eq!(chunk_len, nums_decoded);
+
+        self.buf_start = chunk_start;
+        self.buf_len = chunk_len;
+    }
+}
+
+impl<'a, D: Decoder> Iterator for DecodeRevIter<'a, D> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        if self.buf_len == 0 {
+            self.fill_buf();
+        }
+
+        self.buf_len -= 1;
+        self.remaining -= 1;
+
+        debug_assert!(self.buf_start + self.buf_len >= self.head);
+        Some(self.buf[self.buf_len])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, D: Decoder> ExactSizeIterator for DecodeRevIter<'a, D> {}
+
+impl<'a, D: Decoder> FusedIterator for DecodeRevIter<'a, D> {}
+
+/// `size_hint()` is always exact, so e.g. `collect()` allocates once.
+#[cfg(feature = "nightly")]
+unsafe impl<'a, D: Decoder> TrustedLen for DecodeRevIter<'a, D> {}
@@ -0,0 +1,177 @@
+//! Encoding of sorted `f32`s, such as quantiles or sorted scores.
+//!
+//! Each float's bits are mapped to a `u32` so that the order of the `u32`s matches the order of
+//! the floats: positive floats have their sign bit set, and negative floats have all their bits
+//! flipped. Sorted floats then become sorted `u32`s, which are delta encoded so that each is
+//! stored as its (usually small) difference from the previous one.
+//!
+//! The mapping covers every bit pattern and is exactly undone when decoding, so floats come back
+//! bit for bit, including the sign of zero and NaN payloads. The order it imposes is that of
+//! IEEE 754 `totalOrder`: negative NaNs, then negative infinity, the negative numbers, -0.0, +0.0,
+//! the positive numbers, positive infinity, and finally positive NaNs. Input sorted with
+//! `partial_cmp()` is in this order as long as it has no NaNs and doesn't put +0.0 before -0.0.
+
+use std::cmp;
+
+use {DecodeCursor, Decoder, Encoder};
+use encode::chunked::ChunkedEncoder;
+
+/// How many numbers to map at a time, in a buffer on the stack.
+const BATCH_LEN: usize = 64;
+
+/// Encode `input`, which must be sorted as described in the module docs, with `E` into `output`,
+/// which should be sized as for `encode()`.
+///
+/// Returns the number of bytes written.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{decode_f32_sorted, encode_f32_sorted, Scalar};
+///
+/// let quantiles: Vec<f32> = (0..100).map(|i| (i as f32 / 10.0).exp()).collect();
+/// let mut encoded = vec![0; quantiles.len() * 5];
+/// let encoded_len = encode_f32_sorted::<Scalar>(&quantiles, &mut encoded);
+///
+/// let mut decoded = vec![0.0; quantiles.len()];
+/// decode_f32_sorted::<Scalar>(&encoded[0..encoded_len], quantiles.len(), &mut decoded);
+/// assert_eq!(quantiles, decoded);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `input` isn't sorted.
+pub fn encode_f32_sorted<E: Encoder>(input: &[f32], output: &mut [u8]) -> usize {
+    let mut encoder = ChunkedEncoder::new(output, input.len());
+    let mut batch = [0_u32; BATCH_LEN];
+    let mut prev = 0;
+
+    for chunk in input.chunks(BATCH_LEN) {
+        for (delta, &float) in batch.iter_mut().zip(chunk.iter()) {
+            let ordered = to_ordered(float);
+            assert!(ordered >= prev, "Input must be sorted");
+            *delta = ordered - prev;
+            prev = ordered;
+        }
+        encoder.push::<E>(&batch[0..chunk.len()]);
+    }
+
+    encoder.finish()
+}
+
+/// Decode `count` floats encoded with `encode_f32_sorted()` from `input` with `D` into `output`.
+///
+/// Returns the number of bytes read.
+pub fn decode_f32_sorted<D: Decoder>(input: &[u8], count: usize, output: &mut [f32]) -> usize {
+    assert!(output.len() >= count, "Output must have room for every number");
+
+    let mut cursor = DecodeCursor::new(input, count);
+    let mut batch = [0_u32; BATCH_LEN];
+    let mut nums_decoded = 0;
+    let mut prev = 0_u32;
+
+    while cursor.has_more() {
+        let batch_len = cmp::min(BATCH_LEN, count - nums_decoded);
+        let batch_decoded = cursor.decode_slice::<D>(&mut batch[0..batch_len]);
+
+        for (float, &delta) in output[nums_decoded..].iter_mut().zip(&batch[0..batch_decoded]) {
+            // malformed input can't make this overflow panic, just decode to garbage
+            prev = prev.wrapping_add(delta);
+            *float = from_ordered(prev);
+        }
+        nums_decoded += batch_decoded;
+    }
+
+    cursor.input_consumed()
+}
+
+/// Map `float`'s bits to a `u32` with the same order.
+fn to_ordered(float: f32) -> u32 {
+    let bits = float.to_bits();
+    if bits & SIGN_BIT != 0 {
+        !bits
+    } else {
+        bits | SIGN_BIT
+    }
+}
+
+/// Undo `to_ordered()`.
+fn from_ordered(ordered: u32) -> f32 {
+    let bits = if ordered & SIGN_BIT != 0 {
+        ordered & !SIGN_BIT
+    } else {
+        !ordered
+    };
+
+    f32::from_bits(bits)
+}
+
+const SIGN_BIT: u32 = 0x8000_0000;
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::f32;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn mapping_preserves_order() {
+        let ordered = [
+            f32::from_bits(0xFFC0_0000), // negative NaN
+            f32::NEG_INFINITY,
+            f32::MIN,
+            -1.0,
+            -f32::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f32::MIN_POSITIVE,
+            1.0,
+            f32::MAX,
+            f32::INFINITY,
+            f32::NAN,
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(to_ordered(pair[0]) < to_ordered(pair[1]), "{:?}", pair);
+        }
+        for &float in &ordered {
+            assert_eq!(float.to_bits(), from_ordered(to_ordered(float)).to_bits());
+        }
+    }
+
+    #[test]
+    fn random_roundtrip_is_bit_exact() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let mut floats: Vec<f32> = (0..rng.gen_range(0, 300))
+                .map(|_| f32::from_bits(rng.gen()))
+                .collect();
+            floats.sort_by_key(|&f| to_ordered(f));
+
+            let mut encoded = vec![0; floats.len() * 5];
+            let encoded_len = encode_f32_sorted::<Scalar>(&floats, &mut encoded);
+
+            let mut decoded = vec![0.0; floats.len()];
+            assert_eq!(
+                encoded_len,
+                decode_f32_sorted::<Scalar>(&encoded, floats.len(), &mut decoded)
+            );
+
+            let expected_bits: Vec<u32> = floats.iter().map(|f| f.to_bits()).collect();
+            let decoded_bits: Vec<u32> = decoded.iter().map(|f| f.to_bits()).collect();
+            assert_eq!(expected_bits, decoded_bits);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Input must be sorted")]
+    fn unsorted_input_panics() {
+        encode_f32_sorted::<Scalar>(&[0.0, -0.0], &mut [0; 10]);
+    }
+}
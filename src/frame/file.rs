@@ -0,0 +1,348 @@
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use {Decoder, Encoder};
+use super::{decode_frame, encode_frame, FrameError, FrameHeader, HeaderError, FRAME_HEADER_LEN,
+            VERSIONED_FRAME_HEADER_LEN};
+
+/// How many numbers `FileEncoder` puts in each frame by default.
+pub const DEFAULT_FRAME_LEN: usize = 64 * 1024;
+
+/// Encodes any number of numbers into frames written to a `Write`, typically a (temporary) file,
+/// using a bounded amount of memory.
+///
+/// Numbers are buffered until there are enough to fill a frame, so memory use is proportional to
+/// the frame length rather than to the total number of numbers. Read the result back with
+/// `FileDecoder`.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{FileDecoder, FileEncoder};
+///
+/// // a Vec<u8> stands in for a File here
+/// let mut encoder = FileEncoder::with_frame_len(Vec::new(), 1000);
+/// encoder.extend::<Scalar, _>((0..10_000).map(|i| i * 3)).unwrap();
+/// let file = encoder.finish::<Scalar>().unwrap();
+///
+/// let decoder = FileDecoder::<_, Scalar>::new(Cursor::new(file));
+/// let decoded: Vec<u32> = decoder.map(|r| r.unwrap()).collect();
+/// assert_eq!((0..10_000).map(|i| i * 3).collect::<Vec<u32>>(), decoded);
+/// ```
+#[derive(Debug)]
+pub struct FileEncoder<W: Write> {
+    writer: W,
+    frame_len: usize,
+    nums: Vec<u32>,
+    encoded: Vec<u8>,
+}
+
+impl<W: Write> FileEncoder<W> {
+    /// Create an encoder with frames of `DEFAULT_FRAME_LEN` numbers.
+    pub fn new(writer: W) -> FileEncoder<W> {
+        FileEncoder::with_frame_len(writer, DEFAULT_FRAME_LEN)
+    }
+
+    /// Create an encoder with frames of `frame_len` numbers (except perhaps the last).
+    pub fn with_frame_len(writer: W, frame_len: usize) -> FileEncoder<W> {
+        assert!(frame_len > 0, "Frame length must be non-zero");
+
+        FileEncoder {
+            writer,
+            frame_len,
+            nums: Vec::with_capacity(frame_len),
+            encoded: Vec::new(),
+        }
+    }
+
+    /// Add a number, writing a frame if one is full.
+    pub fn push<E: Encoder>(&mut self, num: u32) -> io::Result<()> {
+        self.nums.push(num);
+
+        if self.nums.len() == self.frame_len {
+            self.write_frame::<E>()?;
+        }
+
+        Ok(())
+    }
+
+    /// Add all of `nums`, writing frames as they fill up.
+    pub fn extend<E: Encoder, I: IntoIterator<Item = u32>>(&mut self, nums: I) -> io::Result<()> {
+        for num in nums {
+            self.push::<E>(num)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write any buffered numbers as a final frame, flush, and return the writer.
+    pub fn finish<E: Encoder>(mut self) -> io::Result<W> {
+        if !self.nums.is_empty() {
+            self.write_frame::<E>()?;
+        }
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn write_frame<E: Encoder>(&mut self) -> io::Result<()> {
+        self.encoded.resize(FRAME_HEADER_LEN + self.nums.len() * 5, 0);
+        let frame_len = encode_frame::<E>(&self.nums, &mut self.encoded);
+        self.writer.write_all(&self.encoded[0..frame_len])?;
+        self.nums.clear();
+
+        Ok(())
+    }
+}
+
+/// Iterates over the numbers in frames read from a `Read`, such as those written by `FileEncoder`.
+///
+/// One frame is held in memory at a time. If the input ends partway through a frame, the
+/// iterator yields an `UnexpectedEof` error. Frames with a versioned header are untransformed as
+/// the header describes, and a checksum mismatch or unsupported format is an `InvalidData` error.
+#[derive(Debug)]
+pub struct FileDecoder<R: Read, D: Decoder> {
+    reader: R,
+    encoded: Vec<u8>,
+    nums: Vec<u32>,
+    position: usize,
+    done: bool,
+    decoder: PhantomData<D>,
+}
+
+impl<R: Read, D: Decoder> FileDecoder<R, D> {
+    pub fn new(reader: R) -> FileDecoder<R, D> {
+        FileDecoder {
+            reader,
+            encoded: Vec::new(),
+            nums: Vec::new(),
+            position: 0,
+            done: false,
+            decoder: PhantomData,
+        }
+    }
+
+    /// Read and decode the next frame. Returns false at the end of the input.
+    fn read_frame(&mut self) -> io::Result<bool> {
+        self.encoded.resize(FRAME_HEADER_LEN, 0);
+        let header_bytes_read = read_fully(&mut self.reader, &mut self.encoded)?;
+        if header_bytes_read == 0 {
+            return Ok(false);
+        } else if header_bytes_read < FRAME_HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Input ended inside a frame header",
+            ));
+        }
+
+        let header = match FrameHeader::parse(&self.encoded) {
+            Ok(h) => h,
+            Err(HeaderError::NeedsMoreInput(_)) => {
+                // the rest of a versioned header
+                self.encoded.resize(VERSIONED_FRAME_HEADER_LEN, 0);
+                self.reader
+                    .read_exact(&mut self.encoded[FRAME_HEADER_LEN..])?;
+                FrameHeader::parse(&self.encoded).map_err(|_| unsupported_format())?
+            }
+            Err(HeaderError::UnsupportedFormat) => return Err(unsupported_format()),
+        };
+        // read the rest of the frame as it arrives rather than allocating all of it up front, so a
+        // corrupt header can't make this allocate much more than the input holds
+        let rest_len = header.frame_len() - self.encoded.len();
+        let rest_read = (&mut self.reader)
+            .take(rest_len as u64)
+            .read_to_end(&mut self.encoded)?;
+        if rest_read < rest_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Input ended inside a frame",
+            ));
+        }
+
+        self.nums.clear();
+        self.position = 0;
+        match decode_frame::<D>(&header, &self.encoded, &mut self.nums) {
+            Ok(()) => {}
+            Err(FrameError::Malformed) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Frame header length does not match encoded numbers",
+                ))
+            }
+            Err(FrameError::ChecksumMismatch) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Frame checksum does not match encoded numbers",
+                ))
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Read, D: Decoder> Iterator for FileDecoder<R, D> {
+    type Item = io::Result<u32>;
+
+    fn next(&mut self) -> Option<io::Result<u32>> {
+        // loop to get past empty frames
+        while self.position == self.nums.len() {
+            if self.done {
+                return None;
+            }
+
+            match self.read_frame() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let num = self.nums[self.position];
+        self.position += 1;
+
+        Some(Ok(num))
+    }
+}
+
+fn unsupported_format() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Frame header is in an unsupported format",
+    )
+}
+
+/// Like `read_exact()`, but a clean end of input before anything is read is not an error.
+///
+/// Returns the number of bytes read, which is less than `buf.len()` only at the end of input.
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match reader.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(n) => bytes_read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(bytes_read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use super::*;
+    use super::super::{encode_frame_with_format, encode_frame_with_metadata};
+    use Scalar;
+
+    #[test]
+    fn roundtrip_through_file() {
+        let path = ::std::env::temp_dir().join(format!(
+            "stream-vbyte-file-encoder-test-{}",
+            ::std::process::id()
+        ));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let mut encoder = FileEncoder::with_frame_len(file, 777);
+        encoder.extend::<Scalar, _>(0..100_000).unwrap();
+        let mut file = encoder.finish::<Scalar>().unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let decoded: Vec<u32> = FileDecoder::<_, Scalar>::new(file)
+            .map(|r| r.unwrap())
+            .collect();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((0..100_000).collect::<Vec<u32>>(), decoded);
+    }
+
+    #[test]
+    fn empty_input_has_no_frames() {
+        let encoded = FileEncoder::new(Vec::new()).finish::<Scalar>().unwrap();
+        assert!(encoded.is_empty());
+
+        assert_eq!(
+            0,
+            FileDecoder::<_, Scalar>::new(Cursor::new(encoded)).count()
+        );
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let mut encoder = FileEncoder::with_frame_len(Vec::new(), 10);
+        encoder.extend::<Scalar, _>(0..25).unwrap();
+        let mut encoded = encoder.finish::<Scalar>().unwrap();
+        encoded.pop();
+
+        let results: Vec<io::Result<u32>> =
+            FileDecoder::<_, Scalar>::new(Cursor::new(encoded)).collect();
+
+        assert_eq!(21, results.len());
+        assert!(results[0..20].iter().all(|r| r.is_ok()));
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            results[20].as_ref().unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn huge_frame_length_in_short_input_is_an_error() {
+        let mut encoded = vec![0; FRAME_HEADER_LEN];
+        encoded[0..4].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0x0F]);
+        encoded[4..8].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+        encoded.extend_from_slice(&[0; 100]);
+
+        let mut decoder = FileDecoder::<_, Scalar>::new(Cursor::new(encoded));
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            decoder.next().unwrap().unwrap_err().kind()
+        );
+        assert!(decoder.encoded.capacity() < 1024);
+    }
+
+    #[test]
+    fn versioned_frames_are_untransformed_and_checked() {
+        let format = ::frame::FrameFormat {
+            delta: true,
+            zigzag: true,
+            checksum: true,
+            one_byte: false,
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[10, 5, 20], format, &mut encoded);
+        encode_frame_with_metadata::<Scalar>(&[7, 8], format, b"meta", &mut encoded);
+
+        let decoded: Vec<u32> = FileDecoder::<_, Scalar>::new(Cursor::new(&encoded[..]))
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(vec![10, 5, 20, 7, 8], decoded);
+
+        // corrupt the second frame's checksum
+        let last = encoded.len() - 1;
+        encoded[last] ^= 0x01;
+        let results: Vec<io::Result<u32>> =
+            FileDecoder::<_, Scalar>::new(Cursor::new(encoded)).collect();
+        assert_eq!(4, results.len());
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            results[3].as_ref().unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,1045 @@
+//! A simple framing for sending encoded numbers over a stream without knowing the total count up
+//! front.
+//!
+//! Each frame is a header followed by a normal Stream VByte encoding of the frame's numbers. The
+//! header holds the number of numbers in the frame and the length of the encoded data, both as
+//! little-endian `u32`s, so a reader can tell when it has a complete frame (or skip one) without
+//! looking at its contents.
+//!
+//! # Versioned frames
+//!
+//! `encode_frame()` writes the original 8-byte header: the count, then the encoded length.
+//! `encode_frame_with_format()` writes a 16-byte versioned header instead, which records how the
+//! numbers were transformed before encoding, so a reader can undo it without being told out of
+//! band. It starts with `0xFFFFFFFF`, which can't be the count in an original header, then a
+//! format version byte (currently `FORMAT_VERSION`), a flags byte (0x01 for delta, 0x02 for
+//! zigzag, 0x04 for checksum, 0x20 for one byte numbers), the length of the frame's metadata as a
+//! little-endian `u16`, and finally the count and encoded length as in the original header. The
+//! metadata, an arbitrary byte string such as a column ID or epoch that the application can read
+//! without decoding the frame, follows the header.
+//!
+//! With the one byte flag, the numbers are stored as one byte each, with no control bytes, rather
+//! than in Stream VByte format. That saves a quarter of a byte per number for frames whose
+//! numbers (after transforms) are all less than 256, such as the gaps between dense sorted IDs.
+//! Writers detect such frames themselves: `config::EncoderConfig` uses the flag wherever every
+//! number fits unless told not to, and `encode_frame_with_format()` does when
+//! `FrameFormat.one_byte` allows it.
+//!
+//! With the checksum flag, the encoded numbers are followed by a little-endian 32-bit FNV-1a hash
+//! of them (not including the metadata). Frames in either format can be mixed freely. A reader
+//! that finds a version or flag it doesn't know about stops rather than returning the wrong
+//! numbers, so new options can be added in later versions of this crate without older versions
+//! misreading them.
+//!
+//! Two flags are reserved for options this version doesn't write: 0x08 for numbers encoded with
+//! the alternative 0, 1, 2, 4 byte length codes, and 0x10 for frames split into blocks whose size
+//! follows the metadata. A frame with either is reported as an unsupported format until a version
+//! that understands them is released.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {decode, encode, encode_append, validate, Decoder, Encoder};
+
+mod pairs;
+pub use self::pairs::{decode_pairs, encode_pairs, PairsFormat};
+
+mod reader;
+pub use self::reader::{Frame, FrameReader};
+
+mod resumable;
+pub use self::resumable::{FeedError, ResumableDecoder};
+
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "std")]
+pub use self::file::{FileDecoder, FileEncoder, DEFAULT_FRAME_LEN};
+
+/// The length of the original header written by `encode_frame()`.
+pub const FRAME_HEADER_LEN: usize = 8;
+
+/// The length of the versioned header written by `encode_frame_with_format()`.
+pub const VERSIONED_FRAME_HEADER_LEN: usize = 16;
+
+/// The newest versioned header format, which is the one this crate writes.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The length of the checksum after the encoded numbers of a frame with `FrameFormat.checksum`.
+pub const FRAME_CHECKSUM_LEN: usize = 4;
+
+/// Marks a versioned header, in place of the count in an original header.
+const VERSIONED_MARKER: u32 = 0xFFFF_FFFF;
+
+const FLAG_DELTA: u8 = 0x01;
+const FLAG_ZIGZAG: u8 = 0x02;
+const FLAG_CHECKSUM: u8 = 0x04;
+/// Reserved for the 0, 1, 2, 4 byte length codes. Not yet supported.
+const FLAG_CODES_0124: u8 = 0x08;
+/// Reserved for frames split into blocks, with the block size after the metadata. Not yet
+/// supported.
+const FLAG_BLOCK_SIZE: u8 = 0x10;
+const FLAG_ONE_BYTE: u8 = 0x20;
+
+/// How a frame's numbers were transformed before encoding, and whether a checksum follows them.
+///
+/// The default, with everything off, is what frames with the original header use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameFormat {
+    /// Each number is stored as its difference from the previous one in the frame.
+    pub delta: bool,
+    /// Numbers (or differences) are treated as `i32`s and zigzag encoded, so that small negative
+    /// numbers take few bytes.
+    pub zigzag: bool,
+    /// The encoded numbers are followed by a checksum.
+    pub checksum: bool,
+    /// The numbers (or differences) are stored as one byte each, with no control bytes.
+    ///
+    /// When encoding, this allows rather than requires that: a frame with a number that doesn't
+    /// fit in a byte is written in Stream VByte format as usual, and its header doesn't have the
+    /// flag.
+    pub one_byte: bool,
+}
+
+impl FrameFormat {
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.delta {
+            flags |= FLAG_DELTA;
+        }
+        if self.zigzag {
+            flags |= FLAG_ZIGZAG;
+        }
+        if self.checksum {
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.one_byte {
+            flags |= FLAG_ONE_BYTE;
+        }
+
+        flags
+    }
+
+    /// `None` if any flags are set that this version doesn't know about.
+    fn from_flags(flags: u8) -> Option<FrameFormat> {
+        if flags & (FLAG_CODES_0124 | FLAG_BLOCK_SIZE) != 0 {
+            // reserved, but can't be decoded yet
+            return None;
+        }
+        if flags & !(FLAG_DELTA | FLAG_ZIGZAG | FLAG_CHECKSUM | FLAG_ONE_BYTE) != 0 {
+            return None;
+        }
+
+        Some(FrameFormat {
+            delta: flags & FLAG_DELTA != 0,
+            zigzag: flags & FLAG_ZIGZAG != 0,
+            checksum: flags & FLAG_CHECKSUM != 0,
+            one_byte: flags & FLAG_ONE_BYTE != 0,
+        })
+    }
+
+    /// Apply the delta and zigzag transforms, in that order.
+    pub(crate) fn transform(&self, nums: &mut [u32]) {
+        if self.delta {
+            let mut prev = 0;
+            for num in nums.iter_mut() {
+                let delta = num.wrapping_sub(prev);
+                prev = *num;
+                *num = delta;
+            }
+        }
+
+        if self.zigzag {
+            for num in nums.iter_mut() {
+                let n = *num as i32;
+                *num = ((n << 1) ^ (n >> 31)) as u32;
+            }
+        }
+    }
+
+    /// Undo `transform()`.
+    pub(crate) fn untransform(&self, nums: &mut [u32]) {
+        self.untransform_after(0, nums);
+    }
+
+    /// Undo `transform()` for numbers that follow others in the same frame, the last of which
+    /// untransformed to `prev` (0 at the start of a frame). Returns the last untransformed number,
+    /// or `prev` if `nums` is empty.
+    pub(crate) fn untransform_after(&self, prev: u32, nums: &mut [u32]) -> u32 {
+        if self.zigzag {
+            for num in nums.iter_mut() {
+                *num = (*num >> 1) ^ (-((*num & 1) as i32)) as u32;
+            }
+        }
+
+        let mut prev = prev;
+        if self.delta {
+            for num in nums.iter_mut() {
+                prev = prev.wrapping_add(*num);
+                *num = prev;
+            }
+        } else if let Some(&last) = nums.last() {
+            prev = last;
+        }
+
+        prev
+    }
+}
+
+/// The contents of a frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// The number of numbers in the frame.
+    pub count: usize,
+    /// The length of the encoded numbers following the header.
+    pub encoded_len: usize,
+    /// The length of the metadata following the versioned header. Always 0 for the original
+    /// header.
+    pub metadata_len: usize,
+    /// 0 for the original header, otherwise the version of the versioned header.
+    pub version: u8,
+    /// How the numbers were transformed. Always the default for the original header.
+    pub format: FrameFormat,
+}
+
+/// Why `FrameHeader::parse()` couldn't read a header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The input ended partway through the header. At least this many more bytes are needed.
+    NeedsMoreInput(usize),
+    /// The header is a version, or uses flags, that this version of the crate doesn't support.
+    UnsupportedFormat,
+}
+
+impl FrameHeader {
+    /// Read a header in either format from the start of `input`.
+    ///
+    /// Only the header itself needs to be present, not any metadata after it.
+    pub fn parse(input: &[u8]) -> Result<FrameHeader, HeaderError> {
+        if input.len() < FRAME_HEADER_LEN {
+            return Err(HeaderError::NeedsMoreInput(FRAME_HEADER_LEN - input.len()));
+        }
+
+        let first = LittleEndian::read_u32(&input[0..4]);
+        if first != VERSIONED_MARKER {
+            return Ok(FrameHeader {
+                count: first as usize,
+                encoded_len: LittleEndian::read_u32(&input[4..8]) as usize,
+                metadata_len: 0,
+                version: 0,
+                format: FrameFormat::default(),
+            });
+        }
+
+        let version = input[4];
+        let format = match FrameFormat::from_flags(input[5]) {
+            Some(f) if version == FORMAT_VERSION => f,
+            _ => return Err(HeaderError::UnsupportedFormat),
+        };
+
+        if input.len() < VERSIONED_FRAME_HEADER_LEN {
+            return Err(HeaderError::NeedsMoreInput(
+                VERSIONED_FRAME_HEADER_LEN - input.len(),
+            ));
+        }
+
+        Ok(FrameHeader {
+            count: LittleEndian::read_u32(&input[8..12]) as usize,
+            encoded_len: LittleEndian::read_u32(&input[12..16]) as usize,
+            metadata_len: LittleEndian::read_u16(&input[6..8]) as usize,
+            version,
+            format,
+        })
+    }
+
+    /// Read a header from the start of `input`, or `None` if `input` is shorter than the header or
+    /// the header is in an unsupported format.
+    pub fn read(input: &[u8]) -> Option<FrameHeader> {
+        FrameHeader::parse(input).ok()
+    }
+
+    /// The length of the header itself, including any metadata.
+    pub fn header_len(&self) -> usize {
+        if self.version == 0 {
+            FRAME_HEADER_LEN
+        } else {
+            VERSIONED_FRAME_HEADER_LEN + self.metadata_len
+        }
+    }
+
+    /// The metadata of the frame at the start of `input`, which this header was read from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` is shorter than `header_len()`.
+    pub fn metadata<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        &input[(self.header_len() - self.metadata_len)..self.header_len()]
+    }
+
+    /// The length of the whole frame, including the header and any checksum.
+    pub fn frame_len(&self) -> usize {
+        let checksum_len = if self.format.checksum {
+            FRAME_CHECKSUM_LEN
+        } else {
+            0
+        };
+
+        self.header_len() + self.encoded_len + checksum_len
+    }
+}
+
+/// Encode `input` as a single frame with the original header.
+///
+/// `output` should be `FRAME_HEADER_LEN` bytes longer than it would need to be for `encode()`.
+///
+/// Returns the number of bytes written to `output`.
+///
+/// # Panics
+///
+/// Panics if `input` has `u32::max_value()` or more numbers, or if they encode to more than
+/// `u32::max_value()` bytes, since the header couldn't record that.
+pub fn encode_frame<E: Encoder>(input: &[u32], output: &mut [u8]) -> usize {
+    assert!(
+        input.len() < u32::MAX as usize,
+        "Frames can hold fewer than u32::max_value() numbers"
+    );
+
+    let encoded_len = encode::<E>(input, &mut output[FRAME_HEADER_LEN..]);
+    assert!(
+        encoded_len <= u32::MAX as usize,
+        "Frames can hold at most u32::max_value() bytes of encoded numbers"
+    );
+    LittleEndian::write_u32(&mut output[0..4], input.len() as u32);
+    LittleEndian::write_u32(&mut output[4..8], encoded_len as u32);
+
+    FRAME_HEADER_LEN + encoded_len
+}
+
+/// Encode `input` as a single frame with a versioned header, transforming the numbers as
+/// `format` describes, and append it to `output`.
+///
+/// Returns the number of bytes appended to `output`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{decode_framed, encode_frame_with_format, FrameFormat};
+///
+/// let format = FrameFormat {
+///     delta: true,
+///     checksum: true,
+///     ..FrameFormat::default()
+/// };
+/// let mut encoded = Vec::new();
+/// encode_frame_with_format::<Scalar>(&[1000, 1001, 1003, 1006], format, &mut encoded);
+///
+/// // the header says how to undo the transform
+/// let mut decoded = Vec::new();
+/// decode_framed::<Scalar>(&encoded, &mut decoded);
+/// assert_eq!(vec![1000, 1001, 1003, 1006], decoded);
+/// ```
+pub fn encode_frame_with_format<E: Encoder>(
+    input: &[u32],
+    format: FrameFormat,
+    output: &mut Vec<u8>,
+) -> usize {
+    encode_frame_with_metadata::<E>(input, format, &[], output)
+}
+
+/// Like `encode_frame_with_format()`, but with `metadata` stored after the header, where
+/// `FrameHeader.metadata()` can read it without decoding the frame.
+///
+/// Returns the number of bytes appended to `output`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{encode_frame_with_metadata, FrameFormat, FrameHeader};
+///
+/// let mut encoded = Vec::new();
+/// let format = FrameFormat::default();
+/// encode_frame_with_metadata::<Scalar>(&[1, 2, 3], format, b"col-7", &mut encoded);
+///
+/// let header = FrameHeader::parse(&encoded).unwrap();
+/// assert_eq!(b"col-7", header.metadata(&encoded));
+/// assert_eq!(3, header.count);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `metadata` is longer than `u16::max_value()` bytes, or in the same cases as
+/// `encode_frame()`.
+pub fn encode_frame_with_metadata<E: Encoder>(
+    input: &[u32],
+    format: FrameFormat,
+    metadata: &[u8],
+    output: &mut Vec<u8>,
+) -> usize {
+    assert!(
+        metadata.len() <= u16::MAX as usize,
+        "Frame metadata can be at most u16::max_value() bytes"
+    );
+    assert!(
+        input.len() < u32::MAX as usize,
+        "Frames can hold fewer than u32::max_value() numbers"
+    );
+
+    let start = output.len();
+    output.resize(start + VERSIONED_FRAME_HEADER_LEN, 0);
+    output.extend_from_slice(metadata);
+    let encoded_start = output.len();
+
+    let mut format = format;
+    let encoded_len = if format.delta || format.zigzag {
+        let mut transformed = input.to_vec();
+        format.transform(&mut transformed);
+        encode_nums::<E>(&transformed, &mut format, output)
+    } else {
+        encode_nums::<E>(input, &mut format, output)
+    };
+    assert!(
+        encoded_len <= u32::MAX as usize,
+        "Frames can hold at most u32::max_value() bytes of encoded numbers"
+    );
+
+    {
+        let header = &mut output[start..(start + VERSIONED_FRAME_HEADER_LEN)];
+        LittleEndian::write_u32(&mut header[0..4], VERSIONED_MARKER);
+        header[4] = FORMAT_VERSION;
+        header[5] = format.flags();
+        LittleEndian::write_u16(&mut header[6..8], metadata.len() as u16);
+        LittleEndian::write_u32(&mut header[8..12], input.len() as u32);
+        LittleEndian::write_u32(&mut header[12..16], encoded_len as u32);
+    }
+
+    if format.checksum {
+        let checksum = fnv1a(&output[encoded_start..]);
+        let mut buf = [0; FRAME_CHECKSUM_LEN];
+        LittleEndian::write_u32(&mut buf, checksum);
+        output.extend_from_slice(&buf);
+    }
+
+    output.len() - start
+}
+
+/// Append `nums` to `output` one byte each if `format.one_byte` allows it and they all fit,
+/// otherwise with `E`, clearing `format.one_byte` to match.
+///
+/// Returns the number of bytes appended.
+fn encode_nums<E: Encoder>(nums: &[u32], format: &mut FrameFormat, output: &mut Vec<u8>) -> usize {
+    if format.one_byte && nums.iter().all(|&n| n <= u32::from(u8::MAX)) {
+        output.extend(nums.iter().map(|&n| n as u8));
+        return nums.len();
+    }
+
+    format.one_byte = false;
+    encode_append::<E>(nums, output)
+}
+
+/// Why a complete frame couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameError {
+    /// The encoded numbers don't match the header's count and length.
+    Malformed,
+    ChecksumMismatch,
+}
+
+/// Decode the complete frame `frame` (header included), whose header is `header`, appending its
+/// numbers to `output`. On error, `output` is left as it was.
+pub(crate) fn decode_frame<D: Decoder>(
+    header: &FrameHeader,
+    frame: &[u8],
+    output: &mut Vec<u32>,
+) -> Result<(), FrameError> {
+    debug_assert_eq!(header.frame_len(), frame.len());
+
+    let encoded_end = header.header_len() + header.encoded_len;
+    let encoded = &frame[header.header_len()..encoded_end];
+    if header.format.checksum && fnv1a(encoded) != LittleEndian::read_u32(&frame[encoded_end..]) {
+        return Err(FrameError::ChecksumMismatch);
+    }
+
+    let start = output.len();
+    if header.format.one_byte {
+        if encoded.len() != header.count {
+            return Err(FrameError::Malformed);
+        }
+
+        output.extend(encoded.iter().map(|&b| u32::from(b)));
+    } else {
+        if validate(encoded, header.count) != Some(encoded.len()) {
+            return Err(FrameError::Malformed);
+        }
+
+        output.resize(start + header.count, 0);
+        decode::<D>(encoded, header.count, &mut output[start..]);
+    }
+    header.format.untransform(&mut output[start..]);
+
+    Ok(())
+}
+
+/// The FNV-1a hash of no bytes, to start `fnv1a_extend()` from.
+pub(crate) const FNV1A_EMPTY: u32 = 0x811C_9DC5;
+
+/// 32-bit FNV-1a, which is simple and good enough to catch corruption, though not tampering.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    fnv1a_extend(FNV1A_EMPTY, bytes)
+}
+
+/// The FNV-1a hash of the bytes that hashed to `hash` followed by `bytes`.
+pub(crate) fn fnv1a_extend(hash: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(hash, |hash, &b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Why `decode_framed()` stopped: because it used up its input, because the input ended partway
+/// through a frame, or because it found a frame it can't decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramedStatus {
+    /// The input consisted entirely of complete frames, all of which were decoded.
+    Exhausted,
+    /// The input ended partway through a frame. At least this many more bytes are needed before
+    /// that frame can be decoded.
+    NeedsMoreInput(usize),
+    /// The next frame's encoded numbers don't match the count and length in its header.
+    Malformed,
+    /// The next frame's checksum doesn't match its encoded numbers.
+    ChecksumMismatch,
+    /// The next frame has a versioned header in a format this version of the crate doesn't
+    /// support.
+    UnsupportedFormat,
+}
+
+/// The outcome of a `decode_framed()` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramedProgress {
+    /// The number of bytes of complete frames that were read from the input. Any partial frame
+    /// after that should be provided again along with more input. If decoding stopped at a frame
+    /// that can't be decoded, that frame starts here.
+    pub bytes_read: usize,
+    /// The number of numbers appended to the output.
+    pub nums_decoded: usize,
+    pub status: FramedStatus,
+}
+
+/// Decode every complete frame at the start of `input`, appending the numbers to `output`.
+///
+/// This lets a consumer decode whatever has arrived so far without knowing how many numbers are
+/// coming in total: decode what's available, then call again with the unread remainder of the
+/// input plus whatever else has arrived.
+///
+/// Frames with a versioned header are untransformed and have their checksums verified as the
+/// header describes.
+pub fn decode_framed<D: Decoder>(input: &[u8], output: &mut Vec<u32>) -> FramedProgress {
+    let mut bytes_read = 0;
+    let mut nums_decoded = 0;
+
+    let status = loop {
+        let remaining = &input[bytes_read..];
+        if remaining.is_empty() {
+            break FramedStatus::Exhausted;
+        }
+
+        let header = match FrameHeader::parse(remaining) {
+            Ok(h) => h,
+            Err(HeaderError::NeedsMoreInput(n)) => break FramedStatus::NeedsMoreInput(n),
+            Err(HeaderError::UnsupportedFormat) => break FramedStatus::UnsupportedFormat,
+        };
+
+        if remaining.len() < header.frame_len() {
+            break FramedStatus::NeedsMoreInput(header.frame_len() - remaining.len());
+        }
+
+        match decode_frame::<D>(&header, &remaining[0..header.frame_len()], output) {
+            Ok(()) => {}
+            Err(FrameError::ChecksumMismatch) => break FramedStatus::ChecksumMismatch,
+            Err(FrameError::Malformed) => break FramedStatus::Malformed,
+        }
+
+        bytes_read += header.frame_len();
+        nums_decoded += header.count;
+    };
+
+    FramedProgress {
+        bytes_read,
+        nums_decoded,
+        status,
+    }
+}
+
+/// Iterates over the frames in a buffer of concatenated frames by reading only their headers.
+///
+/// Each item is `(offset, encoded_len, count)`, where `input[offset..(offset + encoded_len)]` holds
+/// the `count` encoded numbers of a frame (just as produced by `encode()`), so an index of many
+/// frames can be built without decoding any of them.
+///
+/// For frames with a versioned header, the encoded numbers are as stored, before undoing any
+/// transforms, and don't include the checksum.
+///
+/// Iteration stops at the end of the input, at a frame that is cut off by the end of the input, at
+/// a frame in an unsupported format, or at a frame with one byte numbers, which aren't in Stream
+/// VByte format. Use `bytes_read()` to tell the first apart from the others.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{decode, Scalar};
+/// use stream_vbyte::frame::{encode_frame, StreamDirectoryIter};
+///
+/// let mut encoded = vec![0; 100];
+/// let mut len = encode_frame::<Scalar>(&[1, 2, 3], &mut encoded);
+/// len += encode_frame::<Scalar>(&[4, 5, 6, 7, 8], &mut encoded[len..]);
+///
+/// let directory: Vec<(usize, usize, usize)> =
+///     StreamDirectoryIter::new(&encoded[0..len]).collect();
+/// assert_eq!(2, directory.len());
+///
+/// // decode just the second frame
+/// let (offset, encoded_len, count) = directory[1];
+/// let mut decoded = vec![0; count];
+/// decode::<Scalar>(&encoded[offset..(offset + encoded_len)], count, &mut decoded);
+/// assert_eq!(vec![4, 5, 6, 7, 8], decoded);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamDirectoryIter<'a> {
+    input: &'a [u8],
+    bytes_read: usize,
+}
+
+impl<'a> StreamDirectoryIter<'a> {
+    pub fn new(input: &'a [u8]) -> StreamDirectoryIter<'a> {
+        StreamDirectoryIter {
+            input,
+            bytes_read: 0,
+        }
+    }
+
+    /// The length of the complete frames iterated over so far.
+    ///
+    /// Once iteration has finished, this is less than the length of the input iff iteration stopped
+    /// at a frame that is cut off or can't be described.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+impl<'a> Iterator for StreamDirectoryIter<'a> {
+    type Item = (usize, usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize, usize)> {
+        let remaining = &self.input[self.bytes_read..];
+        let header = FrameHeader::read(remaining)?;
+
+        if remaining.len() < header.frame_len() || header.format.one_byte {
+            return None;
+        }
+
+        let offset = self.bytes_read + header.header_len();
+        self.bytes_read += header.frame_len();
+
+        Some((offset, header.encoded_len, header.count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn decode_framed_multiple_frames() {
+        let frames: Vec<Vec<u32>> = vec![(0..10).collect(), vec![], (100..103).collect()];
+        let encoded = encode_frames(&frames);
+
+        let mut output = Vec::new();
+        let progress = decode_framed::<Scalar>(&encoded, &mut output);
+
+        assert_eq!(
+            FramedProgress {
+                bytes_read: encoded.len(),
+                nums_decoded: 13,
+                status: FramedStatus::Exhausted,
+            },
+            progress
+        );
+        assert_eq!(frames.concat(), output);
+    }
+
+    #[test]
+    fn decode_framed_partial_header() {
+        let encoded = encode_frames(&[vec![1, 2, 3]]);
+
+        let mut output = Vec::new();
+        let progress = decode_framed::<Scalar>(&encoded[0..3], &mut output);
+
+        assert_eq!(0, progress.bytes_read);
+        assert_eq!(FramedStatus::NeedsMoreInput(5), progress.status);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn decode_framed_partial_body() {
+        let encoded = encode_frames(&[vec![1, 2, 3], vec![4, 5]]);
+        // first frame is 8 + 1 + 3 bytes, second is 8 + 1 + 2 bytes
+        let progress = decode_framed::<Scalar>(&encoded[0..20], &mut Vec::new());
+
+        assert_eq!(12, progress.bytes_read);
+        assert_eq!(3, progress.nums_decoded);
+        assert_eq!(FramedStatus::NeedsMoreInput(3), progress.status);
+    }
+
+    #[test]
+    fn decode_framed_malformed() {
+        let mut encoded = encode_frames(&[vec![1, 2, 3], vec![4, 5, 6]]);
+        // second frame's control byte claims a 4-byte first number, overrunning its length
+        encoded[12 + 8] = 0x03;
+
+        let mut output = Vec::new();
+        assert_eq!(
+            FramedProgress {
+                bytes_read: 12,
+                nums_decoded: 3,
+                status: FramedStatus::Malformed,
+            },
+            decode_framed::<Scalar>(&encoded, &mut output)
+        );
+        assert_eq!(vec![1, 2, 3], output);
+    }
+
+    #[test]
+    fn decode_framed_random_arrival() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let frames: Vec<Vec<u32>> = (0..rng.gen_range(0, 10))
+                .map(|_| {
+                    (0..rng.gen_range(0, 100))
+                        .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                        .collect()
+                })
+                .collect();
+            let encoded = encode_frames(&frames);
+
+            // bytes arrive in random-sized pieces
+            let mut pending = Vec::new();
+            let mut output = Vec::new();
+            let mut arrived = 0;
+            while arrived < encoded.len() {
+                let piece_end = rng.gen_range(arrived + 1, encoded.len() + 1);
+                pending.extend_from_slice(&encoded[arrived..piece_end]);
+                arrived = piece_end;
+
+                let progress = decode_framed::<Scalar>(&pending, &mut output);
+                pending.drain(0..progress.bytes_read);
+                assert_eq!(
+                    pending.is_empty(),
+                    progress.status == FramedStatus::Exhausted
+                );
+            }
+
+            assert!(pending.is_empty());
+            assert_eq!(frames.concat(), output);
+        }
+    }
+
+    #[test]
+    fn directory_lists_each_frame() {
+        let frames: Vec<Vec<u32>> = vec![(0..10).collect(), vec![], vec![1 << 20; 7]];
+        let encoded = encode_frames(&frames);
+
+        let mut iter = StreamDirectoryIter::new(&encoded);
+        let directory: Vec<(usize, usize, usize)> = iter.by_ref().collect();
+
+        assert_eq!(encoded.len(), iter.bytes_read());
+        assert_eq!(
+            vec![(8, 13, 10), (29, 0, 0), (37, 23, 7)],
+            directory
+        );
+
+        for ((offset, encoded_len, count), frame) in directory.into_iter().zip(frames.iter()) {
+            let mut decoded = vec![0; count];
+            decode::<Scalar>(&encoded[offset..(offset + encoded_len)], count, &mut decoded);
+            assert_eq!(frame, &decoded);
+        }
+    }
+
+    #[test]
+    fn directory_stops_at_truncated_frame() {
+        let encoded = encode_frames(&[vec![1, 2, 3], vec![4, 5]]);
+
+        let mut iter = StreamDirectoryIter::new(&encoded[0..(encoded.len() - 1)]);
+        assert_eq!(Some((8, 4, 3)), iter.next());
+        assert_eq!(None, iter.next());
+        assert_eq!(12, iter.bytes_read());
+    }
+
+    #[test]
+    fn decode_framed_mixed_formats_random_arrival() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let frames: Vec<Vec<u32>> = (0..rng.gen_range(0, 10))
+                .map(|_| {
+                    (0..rng.gen_range(0, 100))
+                        .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                        .collect()
+                })
+                .collect();
+
+            let mut encoded = Vec::new();
+            for frame in &frames {
+                if rng.gen() {
+                    let format = FrameFormat {
+                        delta: rng.gen(),
+                        zigzag: rng.gen(),
+                        checksum: rng.gen(),
+                        one_byte: rng.gen(),
+                    };
+                    encode_frame_with_format::<Scalar>(frame, format, &mut encoded);
+                } else {
+                    encoded.extend_from_slice(&encode_frames(std::slice::from_ref(frame)));
+                }
+            }
+
+            let mut pending = Vec::new();
+            let mut output = Vec::new();
+            let mut arrived = 0;
+            while arrived < encoded.len() {
+                let piece_end = rng.gen_range(arrived + 1, encoded.len() + 1);
+                pending.extend_from_slice(&encoded[arrived..piece_end]);
+                arrived = piece_end;
+
+                let progress = decode_framed::<Scalar>(&pending, &mut output);
+                pending.drain(0..progress.bytes_read);
+                match progress.status {
+                    FramedStatus::Exhausted => assert!(pending.is_empty()),
+                    FramedStatus::NeedsMoreInput(n) => assert!(n <= encoded.len() - arrived),
+                    s => panic!("Unexpected status {:?}", s),
+                }
+            }
+
+            assert!(pending.is_empty());
+            assert_eq!(frames.concat(), output);
+        }
+    }
+
+    #[test]
+    fn versioned_header_roundtrip() {
+        let format = FrameFormat {
+            delta: false,
+            zigzag: true,
+            checksum: true,
+            one_byte: false,
+        };
+        let mut encoded = Vec::new();
+        let frame_len = encode_frame_with_format::<Scalar>(&[1, 2, 3], format, &mut encoded);
+
+        assert_eq!(16 + 1 + 3 + 4, frame_len);
+        assert_eq!(
+            Ok(FrameHeader {
+                count: 3,
+                encoded_len: 4,
+                metadata_len: 0,
+                version: FORMAT_VERSION,
+                format,
+            }),
+            FrameHeader::parse(&encoded)
+        );
+        assert_eq!(
+            Err(HeaderError::NeedsMoreInput(3)),
+            FrameHeader::parse(&encoded[0..13])
+        );
+    }
+
+    #[test]
+    fn unknown_version_or_flags_are_unsupported() {
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[1, 2, 3], FrameFormat::default(), &mut encoded);
+
+        let unsupported = [
+            (4, FORMAT_VERSION + 1),
+            (5, FLAG_CODES_0124),
+            (5, FLAG_BLOCK_SIZE),
+            (5, 0x80),
+        ];
+        for &(index, value) in &unsupported {
+            let mut modified = encoded.clone();
+            modified[index] = value;
+
+            assert_eq!(
+                Err(HeaderError::UnsupportedFormat),
+                FrameHeader::parse(&modified)
+            );
+
+            let mut input = encode_frames(&[vec![4, 5]]);
+            input.extend_from_slice(&modified);
+            let mut output = Vec::new();
+            assert_eq!(
+                FramedProgress {
+                    bytes_read: 11,
+                    nums_decoded: 2,
+                    status: FramedStatus::UnsupportedFormat,
+                },
+                decode_framed::<Scalar>(&input, &mut output)
+            );
+            assert_eq!(vec![4, 5], output);
+        }
+    }
+
+    #[test]
+    fn one_byte_frames_skip_control_bytes() {
+        let format = FrameFormat {
+            delta: true,
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let nums: Vec<u32> = (0..100).map(|i| 200 + i * 3).collect();
+        let mut encoded = Vec::new();
+        // most numbers don't fit in a byte until they're delta encoded
+        let frame_len = encode_frame_with_format::<Scalar>(&nums, format, &mut encoded);
+
+        assert_eq!(16 + 100, frame_len);
+        let header = FrameHeader::parse(&encoded).unwrap();
+        assert_eq!(format, header.format);
+        assert_eq!(100, header.encoded_len);
+        assert_eq!(&[200, 3, 3], &encoded[16..19]);
+
+        let mut output = Vec::new();
+        assert_eq!(
+            FramedStatus::Exhausted,
+            decode_framed::<Scalar>(&encoded, &mut output).status
+        );
+        assert_eq!(nums, output);
+
+        // not a Stream VByte encoding, so it's not in the directory
+        assert_eq!(None, StreamDirectoryIter::new(&encoded).next());
+    }
+
+    #[test]
+    fn one_byte_frames_fall_back_when_a_number_does_not_fit() {
+        let format = FrameFormat {
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[1, 256, 3], format, &mut encoded);
+        encode_frame_with_format::<Scalar>(&[1, 255, 3], format, &mut encoded);
+
+        let first = FrameHeader::parse(&encoded).unwrap();
+        assert_eq!(FrameFormat::default(), first.format);
+        assert_eq!(1 + 4, first.encoded_len);
+        let second = FrameHeader::parse(&encoded[first.frame_len()..]).unwrap();
+        assert_eq!(format, second.format);
+        assert_eq!(3, second.encoded_len);
+
+        let mut output = Vec::new();
+        decode_framed::<Scalar>(&encoded, &mut output);
+        assert_eq!(vec![1, 256, 3, 1, 255, 3], output);
+    }
+
+    #[test]
+    fn one_byte_frame_with_wrong_length_is_malformed() {
+        let format = FrameFormat {
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[1, 2, 3], format, &mut encoded);
+        encoded[8] = 2;
+
+        assert_eq!(
+            FramedStatus::Malformed,
+            decode_framed::<Scalar>(&encoded, &mut Vec::new()).status
+        );
+    }
+
+    #[test]
+    fn decode_framed_checksum_mismatch() {
+        let format = FrameFormat {
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[1, 2, 3], format, &mut encoded);
+        encode_frame_with_format::<Scalar>(&[4, 5, 6], format, &mut encoded);
+        // second frame's first number
+        encoded[24 + 16 + 1] = 40;
+
+        let mut output = Vec::new();
+        assert_eq!(
+            FramedProgress {
+                bytes_read: 24,
+                nums_decoded: 3,
+                status: FramedStatus::ChecksumMismatch,
+            },
+            decode_framed::<Scalar>(&encoded, &mut output)
+        );
+        assert_eq!(vec![1, 2, 3], output);
+    }
+
+    #[test]
+    fn directory_skips_versioned_headers_and_checksums() {
+        let format = FrameFormat {
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = encode_frames(&[vec![1, 2]]);
+        encode_frame_with_format::<Scalar>(&[3, 4, 5], format, &mut encoded);
+        encoded.extend_from_slice(&encode_frames(&[vec![6]]));
+
+        let directory: Vec<(usize, usize, usize)> = StreamDirectoryIter::new(&encoded).collect();
+        assert_eq!(vec![(8, 3, 2), (27, 4, 3), (43, 2, 1)], directory);
+    }
+
+    #[test]
+    fn metadata_is_readable_before_decoding() {
+        let format = FrameFormat {
+            delta: true,
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = encode_frames(&[vec![1]]);
+        encode_frame_with_metadata::<Scalar>(&[10, 20, 30], format, b"epoch 42", &mut encoded);
+        encode_frame_with_metadata::<Scalar>(&[40], format, &[], &mut encoded);
+
+        let header = FrameHeader::parse(&encoded[10..]).unwrap();
+        assert_eq!(8, header.metadata_len);
+        assert_eq!(24, header.header_len());
+        assert_eq!(b"epoch 42", header.metadata(&encoded[10..]));
+        assert_eq!(
+            b"",
+            FrameHeader::parse(&encoded).unwrap().metadata(&encoded)
+        );
+
+        // metadata is skipped when decoding
+        let mut output = Vec::new();
+        let progress = decode_framed::<Scalar>(&encoded, &mut output);
+        assert_eq!(FramedStatus::Exhausted, progress.status);
+        assert_eq!(vec![1, 10, 20, 30, 40], output);
+
+        let directory: Vec<(usize, usize, usize)> = StreamDirectoryIter::new(&encoded).collect();
+        assert_eq!((10 + 24, 4, 3), directory[1]);
+
+        // metadata arriving later is still part of the frame
+        let progress = decode_framed::<Scalar>(&encoded[0..(10 + 20)], &mut Vec::new());
+        assert_eq!(FramedStatus::NeedsMoreInput(4 + 4 + 4), progress.status);
+    }
+
+    fn encode_frames(frames: &[Vec<u32>]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for frame in frames {
+            let start = encoded.len();
+            encoded.resize(start + FRAME_HEADER_LEN + frame.len() * 5, 0);
+            let frame_len = encode_frame::<Scalar>(frame, &mut encoded[start..]);
+            encoded.truncate(start + frame_len);
+        }
+
+        encoded
+    }
+}
@@ -0,0 +1,202 @@
+use {Decoder, Encoder};
+use super::{decode_frame, encode_frame_with_metadata, FrameFormat, FrameHeader};
+
+/// The first metadata byte of a pairs frame.
+const PAIRS_MARKER: u8 = b'P';
+
+const FLAG_ZIGZAG_VALUES: u8 = 0x01;
+
+/// How `encode_pairs()` stores values, and whether the frame has a checksum. Keys are always
+/// delta encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PairsFormat {
+    /// Values are treated as `i32`s and zigzag encoded, so that small negative values take few
+    /// bytes.
+    pub zigzag_values: bool,
+    /// The encoded numbers are followed by a checksum.
+    pub checksum: bool,
+}
+
+/// Encode key-value pairs as a single versioned frame, appending it to `output`.
+///
+/// The frame holds all the keys, delta encoded, followed by all the values. Delta encoding makes
+/// sorted keys (row IDs, timestamps...) small, but any keys will do. The frame's metadata marks it
+/// as pairs and records `format`, so `decode_pairs()` needs nothing else to decode it.
+///
+/// Returns the number of bytes appended to `output`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{decode_pairs, encode_pairs, PairsFormat};
+///
+/// let postings: Vec<(u32, u32)> = vec![(3, 1), (17, 4), (18, 2), (1000, 1)];
+/// let mut encoded = Vec::new();
+/// let encoded_len = encode_pairs::<Scalar>(&postings, PairsFormat::default(), &mut encoded);
+///
+/// let mut decoded = Vec::new();
+/// assert_eq!(Some(encoded_len), decode_pairs::<Scalar>(&encoded, &mut decoded));
+/// assert_eq!(postings, decoded);
+/// ```
+///
+/// # Panics
+///
+/// Panics if there are `u32::MAX / 2` or more pairs.
+pub fn encode_pairs<E: Encoder>(
+    pairs: &[(u32, u32)],
+    format: PairsFormat,
+    output: &mut Vec<u8>,
+) -> usize {
+    assert!(
+        pairs.len() < u32::MAX as usize / 2,
+        "Frames can hold fewer than u32::MAX / 2 pairs"
+    );
+
+    let mut nums = Vec::with_capacity(pairs.len() * 2);
+    nums.extend(pairs.iter().map(|&(k, _)| k));
+    nums.extend(pairs.iter().map(|&(_, v)| v));
+    {
+        let (keys, values) = nums.split_at_mut(pairs.len());
+        keys_format().transform(keys);
+        values_format(format).transform(values);
+    }
+
+    let flags = if format.zigzag_values {
+        FLAG_ZIGZAG_VALUES
+    } else {
+        0
+    };
+    let frame_format = FrameFormat {
+        checksum: format.checksum,
+        ..FrameFormat::default()
+    };
+
+    encode_frame_with_metadata::<E>(&nums, frame_format, &[PAIRS_MARKER, flags], output)
+}
+
+/// Decode the pairs frame at the start of `input`, written by `encode_pairs()`, with `D`,
+/// appending the pairs to `output`.
+///
+/// Returns the length of the frame, or `None` if `input` doesn't start with a complete, valid
+/// pairs frame (including if its checksum doesn't match), in which case `output` is unchanged.
+pub fn decode_pairs<D: Decoder>(input: &[u8], output: &mut Vec<(u32, u32)>) -> Option<usize> {
+    let header = FrameHeader::parse(input).ok()?;
+    if input.len() < header.frame_len() || header.count % 2 != 0 {
+        return None;
+    }
+
+    let metadata = header.metadata(input);
+    if metadata.len() != 2 || metadata[0] != PAIRS_MARKER || metadata[1] & !FLAG_ZIGZAG_VALUES != 0
+        || header.format.delta || header.format.zigzag
+    {
+        return None;
+    }
+    let format = PairsFormat {
+        zigzag_values: metadata[1] & FLAG_ZIGZAG_VALUES != 0,
+        checksum: header.format.checksum,
+    };
+
+    let mut nums = Vec::new();
+    decode_frame::<D>(&header, &input[0..header.frame_len()], &mut nums).ok()?;
+
+    let (keys, values) = nums.split_at_mut(header.count / 2);
+    keys_format().untransform(keys);
+    values_format(format).untransform(values);
+    output.extend(keys.iter().cloned().zip(values.iter().cloned()));
+
+    Some(header.frame_len())
+}
+
+fn keys_format() -> FrameFormat {
+    FrameFormat {
+        delta: true,
+        ..FrameFormat::default()
+    }
+}
+
+fn values_format(format: PairsFormat) -> FrameFormat {
+    FrameFormat {
+        zigzag: format.zigzag_values,
+        ..FrameFormat::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+    use frame::{encode_frame_with_format, VERSIONED_FRAME_HEADER_LEN};
+
+    #[test]
+    fn random_roundtrip_every_format() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let mut pairs: Vec<(u32, u32)> = (0..rng.gen_range(0, 300))
+                .map(|_| {
+                    (
+                        rng.gen::<u32>() >> rng.gen_range(0, 32),
+                        rng.gen::<u32>() >> rng.gen_range(0, 32),
+                    )
+                })
+                .collect();
+            if rng.gen() {
+                pairs.sort();
+            }
+
+            for &zigzag_values in &[false, true] {
+                for &checksum in &[false, true] {
+                    let format = PairsFormat {
+                        zigzag_values,
+                        checksum,
+                    };
+                    let mut encoded = vec![0xFF];
+                    let encoded_len = encode_pairs::<Scalar>(&pairs, format, &mut encoded);
+                    assert_eq!(encoded.len() - 1, encoded_len);
+
+                    let mut decoded = vec![(1, 2)];
+                    assert_eq!(
+                        Some(encoded_len),
+                        decode_pairs::<Scalar>(&encoded[1..], &mut decoded)
+                    );
+                    assert_eq!((1, 2), decoded[0]);
+                    assert_eq!(pairs, &decoded[1..]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sorted_keys_are_small() {
+        let pairs: Vec<(u32, u32)> = (0..1000).map(|i| (1_000_000 + i * 3, 7)).collect();
+        let mut encoded = Vec::new();
+        let encoded_len = encode_pairs::<Scalar>(&pairs, PairsFormat::default(), &mut encoded);
+
+        // header, metadata, control bytes, the first key, then one byte per other key and value
+        assert_eq!(16 + 2 + 500 + 3 + 999 + 1000, encoded_len);
+    }
+
+    #[test]
+    fn other_frames_are_rejected() {
+        let mut decoded = Vec::new();
+
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[1, 2], FrameFormat::default(), &mut encoded);
+        assert_eq!(None, decode_pairs::<Scalar>(&encoded, &mut decoded));
+
+        encoded.clear();
+        encode_pairs::<Scalar>(&[(1, 2), (3, 4)], PairsFormat::default(), &mut encoded);
+        assert_eq!(None, decode_pairs::<Scalar>(&encoded[..(encoded.len() - 1)], &mut decoded));
+
+        // unknown flags
+        encoded[VERSIONED_FRAME_HEADER_LEN + 1] = 0x80;
+        assert_eq!(None, decode_pairs::<Scalar>(&encoded, &mut decoded));
+
+        assert!(decoded.is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use {encoded_shape, EncodedSlice};
+use super::{fnv1a, FrameHeader};
+
+/// Iterates over the frames in a buffer of concatenated frames, such as a memory-mapped file,
+/// without copying or decoding anything.
+///
+/// Each frame's header and metadata are available as soon as it is reached, and its numbers as an
+/// `EncodedSlice`, so frames that aren't needed cost nothing beyond reading their headers.
+///
+/// Iteration stops at the end of the input, at a frame that is cut off by the end of the input, at
+/// a frame in an unsupported format, or at a frame too short to hold the control bytes its header
+/// implies (or, for a frame with one byte numbers, whose length isn't its count). Use
+/// `bytes_read()` to tell the first apart from the others.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{encode_frame_with_metadata, FrameFormat, FrameReader};
+///
+/// let mut encoded = Vec::new();
+/// encode_frame_with_metadata::<Scalar>(&[1, 2, 3], FrameFormat::default(), b"a", &mut encoded);
+/// encode_frame_with_metadata::<Scalar>(&[4, 5], FrameFormat::default(), b"b", &mut encoded);
+///
+/// let frame = FrameReader::new(&encoded)
+///     .find(|f| f.metadata() == b"b")
+///     .unwrap();
+/// assert_eq!(vec![4, 5], frame.encoded().iter::<Scalar>().collect::<Vec<u32>>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameReader<'a> {
+    input: &'a [u8],
+    bytes_read: usize,
+}
+
+impl<'a> FrameReader<'a> {
+    pub fn new(input: &'a [u8]) -> FrameReader<'a> {
+        FrameReader {
+            input,
+            bytes_read: 0,
+        }
+    }
+
+    /// The length of the complete frames iterated over so far.
+    ///
+    /// Once iteration has finished, this is less than the length of the input iff iteration stopped
+    /// at a frame that is cut off or can't be read.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+impl<'a> Iterator for FrameReader<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Frame<'a>> {
+        let remaining = &self.input[self.bytes_read..];
+        let header = FrameHeader::read(remaining)?;
+
+        let malformed = if header.format.one_byte {
+            header.encoded_len != header.count
+        } else {
+            header.encoded_len < encoded_shape(header.count).control_bytes_len
+        };
+        if remaining.len() < header.frame_len() || malformed {
+            return None;
+        }
+
+        let frame = Frame {
+            header,
+            offset: self.bytes_read,
+            bytes: &remaining[0..header.frame_len()],
+        };
+        self.bytes_read += header.frame_len();
+
+        Some(frame)
+    }
+}
+
+/// A frame found by `FrameReader`, borrowed from its input.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    header: FrameHeader,
+    offset: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    pub fn header(&self) -> &FrameHeader {
+        &self.header
+    }
+
+    /// Where the frame starts in the `FrameReader`'s input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The frame's metadata, which is empty unless it has a versioned header with metadata.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.header.metadata(self.bytes)
+    }
+
+    /// The frame's encoded numbers, just as produced by `encode()`, or one byte per number if the
+    /// header's format has `one_byte`.
+    pub fn encoded_bytes(&self) -> &'a [u8] {
+        let start = self.header.header_len();
+        &self.bytes[start..(start + self.header.encoded_len)]
+    }
+
+    /// The frame's numbers, as stored. If the header's format has transforms, the numbers need to
+    /// be untransformed after decoding.
+    ///
+    /// Frames aren't validated, so if the input isn't trusted, check `encoded_bytes()` with
+    /// `validate()` first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header's format has `one_byte`, since those numbers aren't in Stream VByte
+    /// format. Use `encoded_bytes()` instead.
+    pub fn encoded(&self) -> EncodedSlice<'a> {
+        assert!(
+            !self.header.format.one_byte,
+            "Frames with one byte numbers aren't Stream VByte encoded"
+        );
+
+        EncodedSlice::new(self.encoded_bytes(), self.header.count)
+    }
+
+    /// True if the frame has no checksum, or its checksum matches its encoded numbers.
+    pub fn checksum_matches(&self) -> bool {
+        if !self.header.format.checksum {
+            return true;
+        }
+
+        let encoded = self.encoded_bytes();
+        let checksum = &self.bytes[(self.header.header_len() + encoded.len())..];
+        fnv1a(encoded) == LittleEndian::read_u32(checksum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{encode_frame, encode_frame_with_format, FrameFormat};
+    use Scalar;
+
+    #[test]
+    fn reads_each_frame_without_copying() {
+        let format = FrameFormat {
+            zigzag: true,
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = vec![0; 8 + 2 * 5];
+        let len = encode_frame::<Scalar>(&[1, 1 << 20], &mut encoded);
+        encoded.truncate(len);
+        encode_frame_with_format::<Scalar>(&[3, 4, 5], format, &mut encoded);
+
+        let mut reader = FrameReader::new(&encoded);
+        let frames: Vec<Frame> = reader.by_ref().collect();
+        assert_eq!(encoded.len(), reader.bytes_read());
+        assert_eq!(2, frames.len());
+
+        assert_eq!(0, frames[0].offset());
+        assert_eq!(
+            vec![1, 1 << 20],
+            frames[0].encoded().iter::<Scalar>().collect::<Vec<u32>>()
+        );
+        assert!(frames[0].checksum_matches());
+
+        assert_eq!(len, frames[1].offset());
+        assert_eq!(format, frames[1].header().format);
+        // zigzag encoded, as stored
+        assert_eq!(
+            vec![6, 8, 10],
+            frames[1].encoded().iter::<Scalar>().collect::<Vec<u32>>()
+        );
+        assert!(frames[1].checksum_matches());
+        assert_eq!(
+            encoded.as_ptr() as usize + len + 16,
+            frames[1].encoded_bytes().as_ptr() as usize
+        );
+
+        let last = encoded.len() - 1;
+        encoded[last] ^= 0x01;
+        assert!(!FrameReader::new(&encoded).nth(1).unwrap().checksum_matches());
+    }
+
+    #[test]
+    fn one_byte_frames_have_their_bytes_but_no_encoded_slice() {
+        let format = FrameFormat {
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[7, 8, 9], format, &mut encoded);
+
+        let frame = FrameReader::new(&encoded).next().unwrap();
+        assert!(frame.header().format.one_byte);
+        assert_eq!(&[7, 8, 9], frame.encoded_bytes());
+        assert!(frame.checksum_matches());
+    }
+
+    #[test]
+    #[should_panic(expected = "Frames with one byte numbers aren't Stream VByte encoded")]
+    fn one_byte_frame_encoded_panics() {
+        let format = FrameFormat {
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_format::<Scalar>(&[7, 8, 9], format, &mut encoded);
+
+        FrameReader::new(&encoded).next().unwrap().encoded();
+    }
+
+    #[test]
+    fn stops_at_frame_too_short_for_control_bytes() {
+        let mut encoded = vec![0; 8 + 9];
+        // 9 numbers need 3 control bytes, but only 2 bytes are claimed
+        encoded[0] = 9;
+        encoded[4] = 2;
+        encoded.truncate(8 + 2);
+
+        let mut reader = FrameReader::new(&encoded);
+        assert!(reader.next().is_none());
+        assert_eq!(0, reader.bytes_read());
+    }
+}
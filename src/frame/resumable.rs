@@ -0,0 +1,653 @@
+use std::cmp;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {encoded_shape, lengths, DecodeCursor, Decoder};
+use super::{fnv1a_extend, FrameFormat, FrameHeader, HeaderError, FNV1A_EMPTY, FRAME_CHECKSUM_LEN,
+            FRAME_HEADER_LEN, VERSIONED_FRAME_HEADER_LEN};
+
+/// Decodes framed input that arrives in arbitrarily sized pieces, such as reads from a socket
+/// into a ring buffer.
+///
+/// Unlike `decode_framed()`, input doesn't need to be held until a whole frame is available: the
+/// only state kept between calls to `feed()` is the current frame's header and control bytes (a
+/// quarter of a byte per number), plus the bytes of at most one quad that was split across pieces
+/// of input. Decoded numbers are appended to the output as soon as their quad has arrived.
+///
+/// Frames with either header are supported. Metadata after a versioned header is skipped, and
+/// transforms are undone as the numbers are decoded. A frame's checksum can only be verified once
+/// the whole frame has arrived, which is after its numbers have been appended to the output, so a
+/// checksum mismatch means that the numbers appended since the frame started can't be trusted.
+/// Use `is_at_frame_boundary()` to keep track of where that is if it matters.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::frame::{encode_frame, ResumableDecoder};
+///
+/// let nums: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; 8 + nums.len() * 5];
+/// let encoded_len = encode_frame::<Scalar>(&nums, &mut encoded);
+///
+/// let mut decoder = ResumableDecoder::new();
+/// let mut decoded = Vec::new();
+/// for piece in encoded[0..encoded_len].chunks(7) {
+///     decoder.feed::<Scalar>(piece, &mut decoded).unwrap();
+/// }
+///
+/// assert!(decoder.is_at_frame_boundary());
+/// assert_eq!(nums, decoded);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResumableDecoder {
+    state: State,
+    header_buf: [u8; VERSIONED_FRAME_HEADER_LEN],
+    header_buf_len: usize,
+    /// How much of the header is needed to parse it, which is only known to be the versioned
+    /// header's length once the start of it has been read
+    header_len_needed: usize,
+    header: FrameHeader,
+    metadata_read: usize,
+    control_bytes: Vec<u8>,
+    /// Bytes of a quad that was split across calls to `feed()`
+    quad_buf: [u8; 16],
+    quad_buf_len: usize,
+    quads_decoded: usize,
+    encoded_bytes_read: usize,
+    /// The last number decoded in the current frame, after undoing transforms
+    prev: u32,
+    /// The hash of the encoded numbers read so far, for frames with a checksum
+    hash: u32,
+    checksum_buf: [u8; FRAME_CHECKSUM_LEN],
+    checksum_buf_len: usize,
+}
+
+impl Default for ResumableDecoder {
+    fn default() -> ResumableDecoder {
+        ResumableDecoder::new()
+    }
+}
+
+/// Why `ResumableDecoder::feed()` couldn't decode a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedError {
+    /// A frame has a versioned header in a format this version of the crate doesn't support.
+    UnsupportedFormat,
+    /// A frame's encoded numbers don't match the count and length in its header.
+    Malformed,
+    /// A frame's checksum doesn't match its encoded numbers.
+    ChecksumMismatch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Metadata,
+    ControlBytes,
+    EncodedNums,
+    Checksum,
+    /// A frame couldn't be decoded, so there's no telling where the next one starts.
+    Failed(FeedError),
+}
+
+impl ResumableDecoder {
+    pub fn new() -> ResumableDecoder {
+        ResumableDecoder {
+            state: State::Header,
+            header_buf: [0; VERSIONED_FRAME_HEADER_LEN],
+            header_buf_len: 0,
+            header_len_needed: FRAME_HEADER_LEN,
+            header: FrameHeader {
+                count: 0,
+                encoded_len: 0,
+                metadata_len: 0,
+                version: 0,
+                format: FrameFormat::default(),
+            },
+            metadata_read: 0,
+            control_bytes: Vec::new(),
+            quad_buf: [0; 16],
+            quad_buf_len: 0,
+            quads_decoded: 0,
+            encoded_bytes_read: 0,
+            prev: 0,
+            hash: FNV1A_EMPTY,
+            checksum_buf: [0; FRAME_CHECKSUM_LEN],
+            checksum_buf_len: 0,
+        }
+    }
+
+    /// Consume all of `input`, appending any numbers that can now be decoded to `output`.
+    ///
+    /// Returns the number of numbers appended to `output`, or an error if a frame can't be
+    /// decoded. Numbers from earlier frames in `input` are appended to `output` even so, as are
+    /// those of a frame whose checksum doesn't match. There's no way to find the start of the
+    /// frame after one that can't be decoded, so once this has returned an error, it returns the
+    /// same error for any further input.
+    pub fn feed<D: Decoder>(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u32>,
+    ) -> Result<usize, FeedError> {
+        let start_len = output.len();
+        let mut input = input;
+
+        if let State::Failed(e) = self.state {
+            return Err(e);
+        }
+
+        while !input.is_empty() {
+            let consumed = match self.state {
+                State::Header => self.feed_header(input),
+                State::Metadata => self.feed_metadata(input),
+                State::ControlBytes => self.feed_control_bytes(input),
+                State::EncodedNums if self.header.format.one_byte => {
+                    self.feed_one_byte_nums(input, output)
+                }
+                State::EncodedNums => self.feed_encoded_nums::<D>(input, output),
+                State::Checksum => self.feed_checksum(input),
+                State::Failed(e) => Err(e),
+            };
+
+            match consumed {
+                Ok(consumed) => input = &input[consumed..],
+                Err(e) => {
+                    self.state = State::Failed(e);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(output.len() - start_len)
+    }
+
+    /// Returns true iff all input fed so far consisted of complete frames.
+    pub fn is_at_frame_boundary(&self) -> bool {
+        self.state == State::Header && self.header_buf_len == 0
+    }
+
+    fn feed_header(&mut self, input: &[u8]) -> Result<usize, FeedError> {
+        let to_copy = cmp::min(self.header_len_needed - self.header_buf_len, input.len());
+        self.header_buf[self.header_buf_len..(self.header_buf_len + to_copy)]
+            .copy_from_slice(&input[0..to_copy]);
+        self.header_buf_len += to_copy;
+
+        if self.header_buf_len < self.header_len_needed {
+            return Ok(to_copy);
+        }
+
+        self.header = match FrameHeader::parse(&self.header_buf[0..self.header_buf_len]) {
+            Ok(h) => h,
+            Err(HeaderError::NeedsMoreInput(n)) => {
+                // the rest of a versioned header
+                self.header_len_needed += n;
+                return Ok(to_copy);
+            }
+            Err(HeaderError::UnsupportedFormat) => return Err(FeedError::UnsupportedFormat),
+        };
+
+        let malformed = if self.header.format.one_byte {
+            self.header.encoded_len != self.header.count
+        } else {
+            encoded_shape(self.header.count).control_bytes_len > self.header.encoded_len
+        };
+        if malformed {
+            return Err(FeedError::Malformed);
+        }
+
+        self.header_buf_len = 0;
+        self.header_len_needed = FRAME_HEADER_LEN;
+        self.metadata_read = 0;
+        self.control_bytes.clear();
+        self.quads_decoded = 0;
+        self.encoded_bytes_read = 0;
+        self.prev = 0;
+        self.hash = FNV1A_EMPTY;
+        self.checksum_buf_len = 0;
+        self.state = State::Metadata;
+        self.skip_finished_states()?;
+
+        Ok(to_copy)
+    }
+
+    fn feed_metadata(&mut self, input: &[u8]) -> Result<usize, FeedError> {
+        let to_skip = cmp::min(self.header.metadata_len - self.metadata_read, input.len());
+        self.metadata_read += to_skip;
+        self.skip_finished_states()?;
+
+        Ok(to_skip)
+    }
+
+    fn feed_control_bytes(&mut self, input: &[u8]) -> Result<usize, FeedError> {
+        let control_bytes_len = self.control_bytes_len();
+        let to_copy = cmp::min(control_bytes_len - self.control_bytes.len(), input.len());
+        self.control_bytes.extend_from_slice(&input[0..to_copy]);
+        if self.header.format.checksum {
+            self.hash = fnv1a_extend(self.hash, &input[0..to_copy]);
+        }
+        self.skip_finished_states()?;
+
+        Ok(to_copy)
+    }
+
+    fn feed_encoded_nums<D: Decoder>(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u32>,
+    ) -> Result<usize, FeedError> {
+        let consumed = if self.quad_buf_len > 0 {
+            // finish the quad that was split across calls
+            let quad_len = self.quad_len(self.quads_decoded);
+            let to_copy = cmp::min(quad_len - self.quad_buf_len, input.len());
+            self.quad_buf[self.quad_buf_len..(self.quad_buf_len + to_copy)]
+                .copy_from_slice(&input[0..to_copy]);
+            self.quad_buf_len += to_copy;
+
+            if self.quad_buf_len == quad_len {
+                let quad_buf = self.quad_buf;
+                self.decode_quads::<D>(&quad_buf[0..quad_len], 1, output);
+                self.quad_buf_len = 0;
+            }
+
+            to_copy
+        } else {
+            // decode as many whole quads as are available, without going past the encoded length
+            // in the header
+            let encoded_nums_len = self.header.encoded_len - self.control_bytes.len();
+            let mut quads = 0;
+            let mut available_len = 0;
+            while self.quads_decoded + quads < self.control_bytes.len() {
+                let quad_len = self.quad_len(self.quads_decoded + quads);
+                if self.encoded_bytes_read + available_len + quad_len > encoded_nums_len {
+                    return Err(FeedError::Malformed);
+                }
+                if available_len + quad_len > input.len() {
+                    break;
+                }
+                available_len += quad_len;
+                quads += 1;
+            }
+
+            if quads > 0 {
+                self.decode_quads::<D>(&input[0..available_len], quads, output);
+                available_len
+            } else {
+                self.quad_buf[0..input.len()].copy_from_slice(input);
+                self.quad_buf_len = input.len();
+                input.len()
+            }
+        };
+
+        if self.header.format.checksum {
+            self.hash = fnv1a_extend(self.hash, &input[0..consumed]);
+        }
+        self.encoded_bytes_read += consumed;
+        self.skip_finished_states()?;
+
+        Ok(consumed)
+    }
+
+    /// Like `feed_encoded_nums()`, for a frame whose numbers are one byte each, which needs
+    /// nothing kept between calls.
+    fn feed_one_byte_nums(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u32>,
+    ) -> Result<usize, FeedError> {
+        let to_decode = cmp::min(self.header.count - self.encoded_bytes_read, input.len());
+        let start = output.len();
+        output.extend(input[0..to_decode].iter().map(|&b| u32::from(b)));
+        self.prev = self
+            .header
+            .format
+            .untransform_after(self.prev, &mut output[start..]);
+
+        if self.header.format.checksum {
+            self.hash = fnv1a_extend(self.hash, &input[0..to_decode]);
+        }
+        self.encoded_bytes_read += to_decode;
+        self.skip_finished_states()?;
+
+        Ok(to_decode)
+    }
+
+    fn feed_checksum(&mut self, input: &[u8]) -> Result<usize, FeedError> {
+        let to_copy = cmp::min(FRAME_CHECKSUM_LEN - self.checksum_buf_len, input.len());
+        self.checksum_buf[self.checksum_buf_len..(self.checksum_buf_len + to_copy)]
+            .copy_from_slice(&input[0..to_copy]);
+        self.checksum_buf_len += to_copy;
+
+        if self.checksum_buf_len == FRAME_CHECKSUM_LEN {
+            if LittleEndian::read_u32(&self.checksum_buf) != self.hash {
+                return Err(FeedError::ChecksumMismatch);
+            }
+            self.state = State::Header;
+        }
+
+        Ok(to_copy)
+    }
+
+    /// Move on from each state that doesn't need any more input, such as the metadata of a frame
+    /// without any.
+    fn skip_finished_states(&mut self) -> Result<(), FeedError> {
+        let control_bytes_len = self.control_bytes_len();
+        let encoded_nums_done = if self.header.format.one_byte {
+            self.encoded_bytes_read == self.header.count
+        } else {
+            self.quads_decoded == control_bytes_len
+        };
+
+        loop {
+            self.state = match self.state {
+                State::Metadata if self.metadata_read == self.header.metadata_len => {
+                    State::ControlBytes
+                }
+                State::ControlBytes if self.control_bytes.len() == control_bytes_len => {
+                    State::EncodedNums
+                }
+                State::EncodedNums if encoded_nums_done => {
+                    if self.header.encoded_len != control_bytes_len + self.encoded_bytes_read {
+                        return Err(FeedError::Malformed);
+                    }
+
+                    if self.header.format.checksum {
+                        State::Checksum
+                    } else {
+                        State::Header
+                    }
+                }
+                _ => return Ok(()),
+            };
+        }
+    }
+
+    /// Decode `quads` quads starting at `quads_decoded` from `encoded_nums`.
+    fn decode_quads<D: Decoder>(
+        &mut self,
+        encoded_nums: &[u8],
+        quads: usize,
+        output: &mut Vec<u32>,
+    ) {
+        let first_num = self.quads_decoded * 4;
+        let count = cmp::min(first_num + quads * 4, self.header.count) - first_num;
+        let start = output.len();
+        output.resize(start + count, 0);
+
+        let mut cursor = DecodeCursor::from_parts(
+            &self.control_bytes[self.quads_decoded..(self.quads_decoded + quads)],
+            encoded_nums,
+            count,
+        );
+        assert_eq!(count, cursor.decode_slice::<D>(&mut output[start..]));
+        self.prev = self
+            .header
+            .format
+            .untransform_after(self.prev, &mut output[start..]);
+
+        self.quads_decoded += quads;
+    }
+
+    /// The number of control bytes in the current frame, which is 0 if its numbers are one byte
+    /// each.
+    fn control_bytes_len(&self) -> usize {
+        if self.header.format.one_byte {
+            0
+        } else {
+            encoded_shape(self.header.count).control_bytes_len
+        }
+    }
+
+    /// The encoded length of the numbers in quad `quad_index`, which may be the frame's trailing
+    /// partial quad.
+    fn quad_len(&self, quad_index: usize) -> usize {
+        let control_byte = self.control_bytes[quad_index];
+        let nums_in_quad = cmp::min(4, self.header.count - quad_index * 4);
+
+        lengths::partial_quad_len(control_byte, nums_in_quad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use super::super::{encode_frame, encode_frame_with_metadata, FORMAT_VERSION};
+    use Scalar;
+
+    #[test]
+    fn resumable_random_pieces_scalar() {
+        do_resumable_random_pieces::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn resumable_random_pieces_ssse3() {
+        do_resumable_random_pieces::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn resumable_one_byte_at_a_time() {
+        let frames: Vec<Vec<u32>> = vec![
+            vec![1, 1 << 8, 1 << 16, 1 << 24, 5, 6],
+            vec![],
+            vec![u32::MAX],
+        ];
+        let encoded = encode_frames(&frames);
+
+        let mut decoder = ResumableDecoder::new();
+        let mut output = Vec::new();
+        for b in encoded.chunks(1) {
+            decoder.feed::<Scalar>(b, &mut output).unwrap();
+        }
+
+        assert!(decoder.is_at_frame_boundary());
+        assert_eq!(frames.concat(), output);
+    }
+
+    #[test]
+    fn resumable_mid_frame_is_not_at_boundary() {
+        let encoded = encode_frames(&[vec![1, 2, 3]]);
+
+        let mut decoder = ResumableDecoder::new();
+        let mut output = Vec::new();
+        assert_eq!(Ok(0), decoder.feed::<Scalar>(&encoded[0..9], &mut output));
+        assert!(!decoder.is_at_frame_boundary());
+
+        assert_eq!(Ok(3), decoder.feed::<Scalar>(&encoded[9..], &mut output));
+        assert!(decoder.is_at_frame_boundary());
+    }
+
+    #[test]
+    fn resumable_checksummed_frame_is_decoded_before_verified() {
+        let format = FrameFormat {
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_metadata::<Scalar>(&[1, 2, 3, 4, 5], format, b"meta", &mut encoded);
+
+        let mut decoder = ResumableDecoder::new();
+        let mut output = Vec::new();
+        let checksum_start = encoded.len() - FRAME_CHECKSUM_LEN;
+        assert_eq!(
+            Ok(5),
+            decoder.feed::<Scalar>(&encoded[0..checksum_start], &mut output)
+        );
+        assert_eq!(vec![1, 2, 3, 4, 5], output);
+        assert!(!decoder.is_at_frame_boundary());
+
+        assert_eq!(
+            Ok(0),
+            decoder.feed::<Scalar>(&encoded[checksum_start..], &mut output)
+        );
+        assert!(decoder.is_at_frame_boundary());
+    }
+
+    #[test]
+    fn resumable_one_byte_frames() {
+        let format = FrameFormat {
+            delta: true,
+            checksum: true,
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let frames: Vec<Vec<u32>> = vec![
+            (0..50).map(|i| 200 + i * 7).collect(),
+            vec![],
+            // too big for one byte
+            vec![1, 100_000],
+            vec![255],
+        ];
+        let mut encoded = Vec::new();
+        for frame in &frames {
+            encode_frame_with_metadata::<Scalar>(frame, format, b"m", &mut encoded);
+        }
+
+        let mut decoder = ResumableDecoder::new();
+        let mut output = Vec::new();
+        for b in encoded.chunks(1) {
+            decoder.feed::<Scalar>(b, &mut output).unwrap();
+        }
+
+        assert!(decoder.is_at_frame_boundary());
+        assert_eq!(frames.concat(), output);
+    }
+
+    #[test]
+    fn resumable_one_byte_frame_with_wrong_length_is_malformed() {
+        let format = FrameFormat {
+            one_byte: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = Vec::new();
+        encode_frame_with_metadata::<Scalar>(&[1, 2, 3], format, &[], &mut encoded);
+        encoded[12] = 4;
+
+        assert_eq!(
+            Err(FeedError::Malformed),
+            ResumableDecoder::new().feed::<Scalar>(&encoded, &mut Vec::new())
+        );
+    }
+
+    #[test]
+    fn resumable_checksum_mismatch() {
+        let format = FrameFormat {
+            checksum: true,
+            ..FrameFormat::default()
+        };
+        let mut encoded = encode_frames(&[vec![1, 2]]);
+        encode_frame_with_metadata::<Scalar>(&[3, 4, 5], format, &[], &mut encoded);
+        // second frame's first number
+        encoded[11 + 16 + 1] = 30;
+
+        let mut decoder = ResumableDecoder::new();
+        let mut output = Vec::new();
+        assert_eq!(
+            Err(FeedError::ChecksumMismatch),
+            decoder.feed::<Scalar>(&encoded, &mut output)
+        );
+        // the corrupt frame's numbers were decoded before its checksum was read
+        assert_eq!(vec![1, 2, 30, 4, 5], output);
+        assert!(!decoder.is_at_frame_boundary());
+
+        // no way to carry on
+        assert_eq!(
+            Err(FeedError::ChecksumMismatch),
+            decoder.feed::<Scalar>(&encode_frames(&[vec![6]]), &mut output)
+        );
+        assert_eq!(vec![1, 2, 30, 4, 5], output);
+    }
+
+    #[test]
+    fn resumable_unsupported_format() {
+        let mut encoded = Vec::new();
+        encode_frame_with_metadata::<Scalar>(&[1], FrameFormat::default(), &[], &mut encoded);
+        encoded[4] = FORMAT_VERSION + 1;
+
+        let mut decoder = ResumableDecoder::new();
+        assert_eq!(
+            Err(FeedError::UnsupportedFormat),
+            decoder.feed::<Scalar>(&encoded, &mut Vec::new())
+        );
+    }
+
+    #[test]
+    fn resumable_malformed() {
+        let encoded = encode_frames(&[vec![1, 2, 3]]);
+
+        // header's length doesn't match the control byte's, either way
+        for &encoded_len in &[0_u8, 3, 5] {
+            let mut modified = encoded.clone();
+            modified[4] = encoded_len;
+            modified.extend_from_slice(&encode_frames(&[vec![4, 5]]));
+
+            let mut decoder = ResumableDecoder::new();
+            let mut output = Vec::new();
+            assert_eq!(
+                Err(FeedError::Malformed),
+                decoder.feed::<Scalar>(&modified, &mut output)
+            );
+        }
+    }
+
+    fn do_resumable_random_pieces<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let frames: Vec<Vec<u32>> = (0..rng.gen_range(0, 10))
+                .map(|_| {
+                    (0..rng.gen_range(0, 200))
+                        .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                        .collect()
+                })
+                .collect();
+
+            // a mix of original and versioned headers, with every combination of options
+            let mut encoded = Vec::new();
+            for frame in &frames {
+                if rng.gen() {
+                    encoded.extend_from_slice(&encode_frames(std::slice::from_ref(frame)));
+                } else {
+                    let format = FrameFormat {
+                        delta: rng.gen(),
+                        zigzag: rng.gen(),
+                        checksum: rng.gen(),
+                        one_byte: rng.gen(),
+                    };
+                    let metadata: Vec<u8> = (0..rng.gen_range(0, 20)).map(|_| rng.gen()).collect();
+                    encode_frame_with_metadata::<Scalar>(frame, format, &metadata, &mut encoded);
+                }
+            }
+
+            let mut decoder = ResumableDecoder::new();
+            let mut output = Vec::new();
+            let mut fed = 0;
+            while fed < encoded.len() {
+                let piece_end = rng.gen_range(fed + 1, cmp::min(fed + 100, encoded.len()) + 1);
+                decoder
+                    .feed::<D>(&encoded[fed..piece_end], &mut output)
+                    .unwrap();
+                fed = piece_end;
+            }
+
+            assert!(decoder.is_at_frame_boundary());
+            assert_eq!(frames.concat(), output);
+        }
+    }
+
+    fn encode_frames(frames: &[Vec<u32>]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for frame in frames {
+            let start = encoded.len();
+            encoded.resize(start + FRAME_HEADER_LEN + frame.len() * 5, 0);
+            let frame_len = encode_frame::<Scalar>(frame, &mut encoded[start..]);
+            encoded.truncate(start + frame_len);
+        }
+
+        encoded
+    }
+}
@@ -0,0 +1,197 @@
+//! Generators for random numbers with various distributions, for use in tests and benchmarks.
+//!
+//! These are the same generators this crate uses in its own tests and benchmarks, so downstream
+//! crates can compare their results with ours. Enable the `gen` feature to use them.
+
+extern crate rand;
+
+use self::rand::Rng;
+
+pub use random_varint::RandomVarintEncodedLengthIter;
+
+/// Emits ascending runs of numbers, like a sorted column or a posting list, where each run starts
+/// at a random number and climbs by random gaps.
+///
+/// Run lengths are in `[1, max_run_len]` and gaps are in `[0, max_gap]`. A run ends early rather
+/// than overflow.
+pub struct SortedRunsIter<R: Rng> {
+    rng: R,
+    max_run_len: usize,
+    max_gap: u32,
+    remaining_in_run: usize,
+    prev: u32,
+}
+
+impl<R: Rng> SortedRunsIter<R> {
+    pub fn new(rng: R, max_run_len: usize, max_gap: u32) -> SortedRunsIter<R> {
+        assert!(max_run_len > 0, "Run length must be non-zero");
+
+        SortedRunsIter {
+            rng,
+            max_run_len,
+            max_gap,
+            remaining_in_run: 0,
+            prev: 0,
+        }
+    }
+}
+
+impl<R: Rng> Iterator for SortedRunsIter<R> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let gap = self.rng.gen_range(0, u64::from(self.max_gap) + 1) as u32;
+
+        let num = match self.prev.checked_add(gap) {
+            Some(n) if self.remaining_in_run > 0 => n,
+            _ => {
+                // start a new run
+                self.remaining_in_run = self.rng.gen_range(1, self.max_run_len + 1);
+                self.rng.gen()
+            }
+        };
+
+        self.remaining_in_run -= 1;
+        self.prev = num;
+
+        Some(num)
+    }
+}
+
+/// Emits clusters of numbers that are close to one another, like timestamps or ids that arrive in
+/// bursts.
+///
+/// Each cluster has `cluster_len` numbers within `spread` of a random center.
+pub struct ClusteredIter<R: Rng> {
+    rng: R,
+    cluster_len: usize,
+    spread: u32,
+    remaining_in_cluster: usize,
+    low: u32,
+    high: u32,
+}
+
+impl<R: Rng> ClusteredIter<R> {
+    pub fn new(rng: R, cluster_len: usize, spread: u32) -> ClusteredIter<R> {
+        assert!(cluster_len > 0, "Cluster length must be non-zero");
+
+        ClusteredIter {
+            rng,
+            cluster_len,
+            spread,
+            remaining_in_cluster: 0,
+            low: 0,
+            high: 0,
+        }
+    }
+}
+
+impl<R: Rng> Iterator for ClusteredIter<R> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining_in_cluster == 0 {
+            let center: u32 = self.rng.gen();
+            self.low = center.saturating_sub(self.spread);
+            self.high = center.saturating_add(self.spread);
+            self.remaining_in_cluster = self.cluster_len;
+        }
+
+        self.remaining_in_cluster -= 1;
+
+        Some(self.rng.gen_range(u64::from(self.low), u64::from(self.high) + 1) as u32)
+    }
+}
+
+/// Emits numbers in `[1, num_ranks]` following Zipf's law: the probability of `k` is proportional
+/// to `1 / k^exponent`, so small numbers are much more common than large ones, as with word
+/// frequencies or term ids.
+///
+/// Sampling looks up a table of `num_ranks` cumulative probabilities, so keep `num_ranks` to a
+/// size that fits comfortably in memory.
+pub struct ZipfIter<R: Rng> {
+    rng: R,
+    cumulative: Vec<f64>,
+}
+
+impl<R: Rng> ZipfIter<R> {
+    pub fn new(rng: R, num_ranks: u32, exponent: f64) -> ZipfIter<R> {
+        assert!(num_ranks > 0, "Must have at least one rank");
+
+        let mut cumulative = Vec::with_capacity(num_ranks as usize);
+        let mut total = 0.0;
+        for rank in 1..(u64::from(num_ranks) + 1) {
+            total += 1.0 / (rank as f64).powf(exponent);
+            cumulative.push(total);
+        }
+
+        ZipfIter { rng, cumulative }
+    }
+}
+
+impl<R: Rng> Iterator for ZipfIter<R> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let total = self.cumulative[self.cumulative.len() - 1];
+        let target = self.rng.next_f64() * total;
+        let index = match self.cumulative
+            .binary_search_by(|c| c.partial_cmp(&target).unwrap())
+        {
+            Ok(i) | Err(i) => i,
+        };
+
+        // rounding could put target past the last entry
+        Some(index.min(self.cumulative.len() - 1) as u32 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_runs_ascend_within_runs() {
+        let nums: Vec<u32> = SortedRunsIter::new(rand::weak_rng(), 1, 10)
+            .take(1000)
+            .collect();
+        assert_eq!(1000, nums.len());
+
+        let mut iter = SortedRunsIter::new(rand::weak_rng(), 100, 10);
+        let mut ascending = 0;
+        let mut prev = iter.next().unwrap();
+        for n in iter.take(10_000) {
+            if n >= prev {
+                ascending += 1;
+            }
+            prev = n;
+        }
+        // about one in 50 numbers starts a new run, half of which happen to be larger anyway
+        assert!(ascending > 9_500);
+    }
+
+    #[test]
+    fn clustered_stays_near_center() {
+        let nums: Vec<u32> = ClusteredIter::new(rand::weak_rng(), 10, 100)
+            .take(10_000)
+            .collect();
+
+        for cluster in nums.chunks(10) {
+            let min = cluster.iter().min().unwrap();
+            let max = cluster.iter().max().unwrap();
+            assert!(max - min <= 200);
+        }
+    }
+
+    #[test]
+    fn zipf_in_range_and_skewed() {
+        let nums: Vec<u32> = ZipfIter::new(rand::weak_rng(), 1000, 1.0)
+            .take(10_000)
+            .collect();
+
+        assert!(nums.iter().all(|&n| n >= 1 && n <= 1000));
+        // rank 1 has probability 1 / H(1000) ≈ 13%
+        let ones = nums.iter().filter(|&&n| n == 1).count();
+        assert!(ones > 1000 && ones < 1700);
+    }
+}
@@ -0,0 +1,194 @@
+//! Reference encodings for checking other implementations of Stream VByte against this one.
+//!
+//! `golden_cases()` produces a fixed matrix of inputs that exercise every encoded length, partial
+//! final quads, long runs of zeros, and maximum values, each with its encoding. The inputs are
+//! generated deterministically, so they are the same on every machine and in every version.
+//!
+//! To hand the cases to another language, write each case's numbers as little-endian `u32`s to
+//! `<name>.u32le` and its encoding to `<name>.bin`, and describe them with `write_manifest()` in
+//! `manifest.txt`. The manifest is plain text with one case per line: the name, the count of
+//! numbers, and the encoded length, separated by spaces. Lines starting with `#` are comments. The
+//! CLI example's `golden` subcommand does all of this.
+
+use std::fmt;
+
+use {encode, Scalar};
+
+/// One input and its expected encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenCase {
+    /// A short name, usable as a file name.
+    pub name: &'static str,
+    pub nums: Vec<u32>,
+    pub encoded: Vec<u8>,
+}
+
+/// Returns the full matrix of golden cases.
+pub fn golden_cases() -> Vec<GoldenCase> {
+    let boundaries = [
+        0,
+        1,
+        (1 << 8) - 1,
+        1 << 8,
+        (1 << 16) - 1,
+        1 << 16,
+        (1 << 24) - 1,
+        1 << 24,
+        u32::MAX - 1,
+        u32::MAX,
+    ];
+
+    let mut rng = XorShift32(0x9E37_79B9);
+
+    let inputs: Vec<(&'static str, Vec<u32>)> = vec![
+        ("empty", vec![]),
+        ("length_1", (0..1000).map(|i| i % (1 << 8)).collect()),
+        ("length_2", (0..1000).map(|i| (1 << 8) + i * 61).collect()),
+        ("length_3", (0..1000).map(|i| (1 << 16) + i * 16_693).collect()),
+        (
+            "length_4",
+            (0..1000).map(|i| (1 << 24) + i * 4_278_190).collect(),
+        ),
+        // every combination of lengths within a quad
+        (
+            "all_control_bytes",
+            (0..1024)
+                .map(|i: usize| {
+                    let len = ((i / 4) >> ((i % 4) * 2)) & 0x03;
+                    boundaries[1 + len * 2]
+                })
+                .collect(),
+        ),
+        ("boundaries", boundaries.to_vec()),
+        ("partial_quad_1", vec![1 << 24]),
+        ("partial_quad_2", vec![1, 1 << 16]),
+        ("partial_quad_3", vec![1 << 8, 1 << 24, 3]),
+        (
+            "partial_quad_after_complete",
+            vec![1, 1 << 8, 1 << 16, 1 << 24, u32::MAX, 0, 1 << 8],
+        ),
+        (
+            "zero_heavy",
+            (0..1000)
+                .map(|i| if i % 97 == 0 { rng.next() } else { 0 })
+                .collect(),
+        ),
+        ("max_values", vec![u32::MAX; 1001]),
+        ("mixed", (0..10_001).map(|_| rng.next() >> (rng.next() % 32)).collect()),
+        // the same numbers as tests/data/data.bin, which came from the C reference implementation
+        ("reference", (0..5000).map(|i| i * 100).collect()),
+    ];
+
+    inputs
+        .into_iter()
+        .map(|(name, nums)| {
+            let mut encoded = vec![0; nums.len() * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            GoldenCase {
+                name,
+                nums,
+                encoded,
+            }
+        })
+        .collect()
+}
+
+/// Write a manifest describing `cases` to `out`.
+pub fn write_manifest<W: fmt::Write>(cases: &[GoldenCase], out: &mut W) -> fmt::Result {
+    writeln!(out, "# stream-vbyte golden cases v1")?;
+    writeln!(out, "# name count encoded_len")?;
+
+    for case in cases {
+        writeln!(out, "{} {} {}", case.name, case.nums.len(), case.encoded.len())?;
+    }
+
+    Ok(())
+}
+
+/// A tiny deterministic generator, so the cases don't depend on any particular RNG crate.
+struct XorShift32(u32);
+
+impl XorShift32 {
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+
+        x
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use std::fs::File;
+    use std::io::Read;
+
+    use super::*;
+    use decode;
+
+    #[test]
+    fn cases_roundtrip() {
+        for case in golden_cases() {
+            let mut decoded = Vec::new();
+            decoded.resize(case.nums.len(), 0);
+            assert_eq!(
+                case.encoded.len(),
+                decode::<Scalar>(&case.encoded, case.nums.len(), &mut decoded),
+                "Case {}",
+                case.name
+            );
+            assert_eq!(case.nums, decoded, "Case {}", case.name);
+        }
+    }
+
+    #[test]
+    fn cases_are_deterministic() {
+        assert_eq!(golden_cases(), golden_cases());
+    }
+
+    #[test]
+    fn all_control_bytes_present() {
+        let cases = golden_cases();
+        let case = cases.iter().find(|c| c.name == "all_control_bytes").unwrap();
+
+        let mut seen = [false; 256];
+        for &b in &case.encoded[0..(case.nums.len() / 4)] {
+            seen[b as usize] = true;
+        }
+
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn reference_matches_c_implementation() {
+        let mut ref_data = Vec::new();
+        File::open("tests/data/data.bin")
+            .unwrap()
+            .read_to_end(&mut ref_data)
+            .unwrap();
+
+        let cases = golden_cases();
+        let case = cases.iter().find(|c| c.name == "reference").unwrap();
+
+        assert_eq!(ref_data, case.encoded);
+    }
+
+    #[test]
+    fn manifest_lists_cases() {
+        let cases = golden_cases();
+        let mut manifest = String::new();
+        write_manifest(&cases[0..2], &mut manifest).unwrap();
+
+        assert_eq!(
+            "# stream-vbyte golden cases v1\n\
+             # name count encoded_len\n\
+             empty 0 0\n\
+             length_1 1000 1250\n",
+            manifest
+        );
+    }
+}
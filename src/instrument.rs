@@ -0,0 +1,223 @@
+//! Optional `tracing` instrumentation for the top-level entry points.
+//!
+//! With the `tracing` feature, each call gets a debug-level span with the operation, codec, and
+//! count of numbers, and an event when it finishes with the encoded length and how long it took.
+//! Whether or not any subscriber is interested, each call is also added to process-wide counters
+//! (see `counters()`). Without the feature, this compiles down to just calling the wrapped
+//! function.
+
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// Totals of the `encode()` and `decode()` calls made so far by this process, for exporting to a
+/// metrics system.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub encode_calls: u64,
+    /// The number of numbers encoded.
+    pub nums_encoded: u64,
+    /// The number of bytes the encoded numbers took.
+    pub bytes_encoded: u64,
+    pub decode_calls: u64,
+    /// The number of numbers decoded.
+    pub nums_decoded: u64,
+    /// The number of bytes the decoded numbers were read from.
+    pub bytes_decoded: u64,
+}
+
+/// The `encode()` and `decode()` calls made so far by this process, with the `tracing` feature.
+///
+/// The counters are updated independently of one another, so a snapshot taken while other threads
+/// are encoding or decoding may include part of a call.
+#[cfg(feature = "tracing")]
+pub fn counters() -> Counters {
+    Counters {
+        encode_calls: ENCODE.calls.load(Ordering::Relaxed),
+        nums_encoded: ENCODE.nums.load(Ordering::Relaxed),
+        bytes_encoded: ENCODE.bytes.load(Ordering::Relaxed),
+        decode_calls: DECODE.calls.load(Ordering::Relaxed),
+        nums_decoded: DECODE.nums.load(Ordering::Relaxed),
+        bytes_decoded: DECODE.bytes.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(feature = "tracing")]
+struct OperationCounters {
+    calls: AtomicU64,
+    nums: AtomicU64,
+    bytes: AtomicU64,
+}
+
+#[cfg(feature = "tracing")]
+impl OperationCounters {
+    const fn new() -> OperationCounters {
+        OperationCounters {
+            calls: AtomicU64::new(0),
+            nums: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    fn add(&self, count: usize, encoded_len: usize) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.nums.fetch_add(count as u64, Ordering::Relaxed);
+        self.bytes.fetch_add(encoded_len as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "tracing")]
+static ENCODE: OperationCounters = OperationCounters::new();
+#[cfg(feature = "tracing")]
+static DECODE: OperationCounters = OperationCounters::new();
+
+/// Run `f`, which encodes or decodes `count` numbers with `codec` and returns the encoded length,
+/// in a span.
+#[cfg(feature = "tracing")]
+#[inline]
+pub fn instrument<F: FnOnce() -> usize>(
+    operation: &'static str,
+    codec: &'static str,
+    count: usize,
+    f: F,
+) -> usize {
+    let span = tracing::debug_span!("stream_vbyte", operation, codec, count);
+
+    let encoded_len = if span.is_disabled() {
+        // don't bother timing calls no one will hear about
+        f()
+    } else {
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let encoded_len = f();
+        let elapsed = start.elapsed();
+
+        tracing::debug!(
+            encoded_len,
+            duration_ns = (elapsed.as_secs() * 1_000_000_000 + u64::from(elapsed.subsec_nanos())),
+            "finished"
+        );
+
+        encoded_len
+    };
+
+    if operation == "encode" {
+        ENCODE.add(count, encoded_len);
+    } else {
+        DECODE.add(count, encoded_len);
+    }
+
+    encoded_len
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub fn instrument<F: FnOnce() -> usize>(
+    _operation: &'static str,
+    _codec: &'static str,
+    _count: usize,
+    f: F,
+) -> usize {
+    f()
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{field, span, Event, Metadata, Subscriber};
+
+    use super::*;
+    use {decode, encode, Scalar};
+
+    /// Records the fields of every span and event as strings.
+    struct RecordingSubscriber {
+        records: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut String);
+
+    impl<'a> field::Visit for FieldVisitor<'a> {
+        fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+            self.0.push_str(&format!("{}={:?} ", field.name(), value));
+        }
+    }
+
+    impl Subscriber for RecordingSubscriber {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes) -> span::Id {
+            let mut record = String::new();
+            attrs.record(&mut FieldVisitor(&mut record));
+            let mut records = self.records.lock().unwrap();
+            records.push(record);
+
+            span::Id::from_u64(records.len() as u64)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut record = String::new();
+            event.record(&mut FieldVisitor(&mut record));
+            self.records.lock().unwrap().push(record);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn encode_and_decode_emit_spans_and_events() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = RecordingSubscriber {
+            records: records.clone(),
+        };
+
+        let nums: Vec<u32> = (0..100).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let mut decoded = vec![0; nums.len()];
+        ::tracing::subscriber::with_default(subscriber, || {
+            encode::<Scalar>(&nums, &mut encoded);
+            decode::<Scalar>(&encoded, nums.len(), &mut decoded);
+        });
+
+        let records = records.lock().unwrap();
+        assert_eq!(4, records.len());
+        assert!(records[0].starts_with("operation=\"encode\""));
+        assert!(records[0].contains("codec=\"stream_vbyte::scalar::Scalar\""));
+        assert!(records[0].contains("count=100"));
+        assert!(records[1].contains("encoded_len=125"));
+        assert!(records[2].starts_with("operation=\"decode\""));
+        assert!(records[3].contains("encoded_len=125"));
+    }
+
+    #[test]
+    fn calls_are_counted_without_a_subscriber() {
+        let nums: Vec<u32> = (0..100).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let mut decoded = vec![0; nums.len()];
+
+        // other tests encode and decode concurrently, so only lower bounds can be checked
+        let before = counters();
+        encode::<Scalar>(&nums, &mut encoded);
+        decode::<Scalar>(&encoded, nums.len(), &mut decoded);
+        let after = counters();
+
+        assert!(after.encode_calls >= before.encode_calls + 1);
+        assert!(after.nums_encoded >= before.nums_encoded + 100);
+        assert!(after.bytes_encoded >= before.bytes_encoded + 125);
+        assert!(after.decode_calls >= before.decode_calls + 1);
+        assert!(after.nums_decoded >= before.nums_decoded + 100);
+        assert!(after.bytes_decoded >= before.bytes_decoded + 125);
+    }
+}
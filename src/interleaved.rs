@@ -0,0 +1,144 @@
+//! An alternate layout for multiple streams of the same length that interleaves their quads.
+//!
+//! Quad `q` of every stream is stored next to quad `q` of all the other streams (quad 0 of stream
+//! 0, quad 0 of stream 1, ..., quad 1 of stream 0, ...), so reading one logical row touches
+//! adjacent bytes rather than `k` distant regions of memory.
+//!
+//! The result is itself a normal Stream VByte encoding of `num_streams * padded_count` numbers,
+//! where `padded_count` is `count` rounded up to a multiple of 4 (a trailing partial quad in each
+//! stream is padded with zeros). That means that decoding still goes through a `DecodeCursor` and
+//! any `Decoder` can be used.
+
+use std::cmp;
+
+use {encoded_shape, DecodeCursor, Decoder, Encoder};
+use encode::encode_complete_quads;
+
+/// How many numbers to buffer at a time while interleaving or de-interleaving.
+const CHUNK_LEN: usize = 256;
+
+/// Encode several streams of the same length with their quads interleaved.
+///
+/// `output` should be at least `5 * streams.len() * padded_count` bytes, where `padded_count` is
+/// the length of each stream rounded up to a multiple of 4.
+///
+/// Returns the number of bytes written to `output`.
+pub fn encode_interleaved<E: Encoder>(streams: &[&[u32]], output: &mut [u8]) -> usize {
+    let count = streams.first().map(|s| s.len()).unwrap_or(0);
+    assert!(
+        streams.iter().all(|s| s.len() == count),
+        "All streams must have the same length"
+    );
+
+    let quads_per_stream = encoded_shape(count).control_bytes_len;
+    let total_quads = quads_per_stream * streams.len();
+
+    let (control_bytes, encoded_nums) = output.split_at_mut(total_quads);
+
+    let mut scratch = [0_u32; CHUNK_LEN];
+    let mut quads_buffered = 0;
+    let mut quads_encoded = 0;
+    let mut bytes_written = 0;
+
+    for quad_index in 0..quads_per_stream {
+        let start = quad_index * 4;
+        let end = cmp::min(start + 4, count);
+
+        for stream in streams {
+            let slot = &mut scratch[(quads_buffered * 4)..(quads_buffered * 4 + 4)];
+            slot[0..(end - start)].copy_from_slice(&stream[start..end]);
+            for n in slot[(end - start)..].iter_mut() {
+                *n = 0;
+            }
+            quads_buffered += 1;
+
+            if quads_buffered * 4 == CHUNK_LEN {
+                bytes_written += encode_complete_quads::<E>(
+                    &scratch,
+                    &mut control_bytes[quads_encoded..(quads_encoded + quads_buffered)],
+                    &mut encoded_nums[bytes_written..],
+                );
+                quads_encoded += quads_buffered;
+                quads_buffered = 0;
+            }
+        }
+    }
+
+    bytes_written += encode_complete_quads::<E>(
+        &scratch[0..(quads_buffered * 4)],
+        &mut control_bytes[quads_encoded..(quads_encoded + quads_buffered)],
+        &mut encoded_nums[bytes_written..],
+    );
+
+    total_quads + bytes_written
+}
+
+/// Decode a single stream out of interleaved input produced by `encode_interleaved()`.
+///
+/// `count` is the length of each stream, and `output` must be at least as big as `count`.
+///
+/// Returns the number of bytes read from `input`.
+pub fn decode_interleaved_stream<D: Decoder>(
+    input: &[u8],
+    count: usize,
+    num_streams: usize,
+    stream_index: usize,
+    output: &mut [u32],
+) -> usize {
+    assert!(stream_index < num_streams, "Stream index out of range");
+    assert!(output.len() >= count, "output buffer was not large enough");
+
+    let quads_per_stream = encoded_shape(count).control_bytes_len;
+    let mut cursor = DecodeCursor::new(input, quads_per_stream * num_streams * 4);
+    let mut quad = [0_u32; 4];
+
+    for quad_index in 0..quads_per_stream {
+        let start = quad_index * 4;
+        let end = cmp::min(start + 4, count);
+
+        cursor.skip(stream_index * 4);
+        assert_eq!(4, cursor.decode_slice::<D>(&mut quad));
+        output[start..end].copy_from_slice(&quad[0..(end - start)]);
+        cursor.skip((num_streams - stream_index - 1) * 4);
+    }
+
+    cursor.input_consumed()
+}
+
+/// Decode all streams out of interleaved input produced by `encode_interleaved()` into rows, where
+/// the `k`th element of each row comes from the `k`th stream.
+///
+/// `count` is the length of each stream, and `output` must be at least as big as `count`.
+///
+/// Returns the number of bytes read from `input`.
+pub fn decode_interleaved_rows<D: Decoder, const K: usize>(
+    input: &[u8],
+    count: usize,
+    output: &mut [[u32; K]],
+) -> usize {
+    assert!(output.len() >= count, "output buffer was not large enough");
+
+    let quads_per_stream = encoded_shape(count).control_bytes_len;
+    let mut cursor = DecodeCursor::new(input, quads_per_stream * K * 4);
+    let mut scratch = [0_u32; CHUNK_LEN];
+    let mut nums_decoded = 0;
+
+    while cursor.has_more() {
+        let chunk_len = cursor.decode_slice::<D>(&mut scratch);
+
+        for (i, &num) in scratch[0..chunk_len].iter().enumerate() {
+            let position = nums_decoded + i;
+            let quad_index = position / 4;
+            let row = (quad_index / K) * 4 + position % 4;
+
+            // skip padding in each stream's trailing partial quad
+            if row < count {
+                output[row][quad_index % K] = num;
+            }
+        }
+
+        nums_decoded += chunk_len;
+    }
+
+    cursor.input_consumed()
+}
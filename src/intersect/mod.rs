@@ -0,0 +1,239 @@
+use std::cmp;
+
+use {Decoder, SortedCursor};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2;
+
+/// How many quads in a row one list can move past while the other stays put before the quad by
+/// quad comparison gives way to galloping.
+const MAX_STREAK: i32 = 4;
+
+/// Append the numbers that are in both of two encoded lists of strictly ascending numbers, such
+/// as posting lists, to `output`, returning how many there were.
+///
+/// Each list gallops ahead to the other's next number (see `SortedCursor.advance_to()`), which
+/// skips quickly over stretches of one list with nothing from the other in them. Where the lists
+/// are dense in the same range, a quad of each is compared at a time, all 16 pairs at once: with
+/// SSE2 on x86 CPUs that support it (every x86_64 CPU does), by comparing one quad with each
+/// rotation of the other.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, intersect_encoded, Scalar};
+///
+/// let evens: Vec<u32> = (0..1000).map(|i| i * 2).collect();
+/// let threes: Vec<u32> = (0..1000).map(|i| i * 3).collect();
+/// let mut encoded_evens = vec![0; evens.len() * 5];
+/// encode::<Scalar>(&evens, &mut encoded_evens);
+/// let mut encoded_threes = vec![0; threes.len() * 5];
+/// encode::<Scalar>(&threes, &mut encoded_threes);
+///
+/// let mut both = Vec::new();
+/// let len = intersect_encoded::<Scalar>(
+///     &encoded_evens,
+///     evens.len(),
+///     &encoded_threes,
+///     threes.len(),
+///     &mut both,
+/// );
+///
+/// assert_eq!(334, len);
+/// assert_eq!((0..334).map(|i| i * 6).collect::<Vec<u32>>(), both);
+/// # }
+/// ```
+pub fn intersect_encoded<D: Decoder>(
+    a: &[u8],
+    a_count: usize,
+    b: &[u8],
+    b_count: usize,
+    output: &mut Vec<u32>,
+) -> usize {
+    let start_len = output.len();
+    let mut a = SortedCursor::<D>::new(a, a_count);
+    let mut b = SortedCursor::<D>::new(b, b_count);
+    let mut target = 0;
+    // checked once here rather than for every quad
+    let sse2 = sse2_supported();
+
+    while let Some(x) = a.advance_to(target) {
+        let y = match b.advance_to(x) {
+            Some(y) => y,
+            None => break,
+        };
+        if x == y {
+            output.push(x);
+            a.consume(1);
+            b.consume(1);
+        } else {
+            target = y;
+        }
+
+        // Compare whole quads of what's decoded so far. Each comparison moves past the quad
+        // that ends first (or both), so every pair of quads that could share a number is
+        // compared once, and any quad not moved past hasn't been compared with the other's
+        // current quad yet. Once one list has moved past several quads in a row, the other is
+        // sparse here, so go back to galloping.
+        let (a_quads, b_quads) = {
+            let a_nums = a.buffered();
+            let b_nums = b.buffered();
+            let mut i = 0;
+            let mut j = 0;
+            // how many quads in a row only one list has moved past: positive for `a`
+            let mut streak: i32 = 0;
+
+            while i + 4 <= a_nums.len() && j + 4 <= b_nums.len() && streak.abs() < MAX_STREAK {
+                let matches = quad_matches(sse2, &a_nums[i..(i + 4)], &b_nums[j..(j + 4)]);
+                for k in 0..4 {
+                    if matches & (1 << k) != 0 {
+                        output.push(a_nums[i + k]);
+                    }
+                }
+
+                let a_last = a_nums[i + 3];
+                let b_last = b_nums[j + 3];
+                if a_last <= b_last {
+                    i += 4;
+                    streak = cmp::max(streak, 0) + 1;
+                }
+                if b_last <= a_last {
+                    j += 4;
+                    streak = if a_last == b_last {
+                        0
+                    } else {
+                        cmp::min(streak, 0) - 1
+                    };
+                }
+            }
+
+            (i, j)
+        };
+        a.consume(a_quads);
+        b.consume(b_quads);
+    }
+
+    output.len() - start_len
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn sse2_supported() -> bool {
+    sse2::is_supported()
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn sse2_supported() -> bool {
+    false
+}
+
+/// `quad_matches_scalar()`, or its SSE2 equivalent if `sse2` is true, which it must only be if
+/// `sse2_supported()` is.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[inline]
+fn quad_matches(sse2: bool, a: &[u32], b: &[u32]) -> u32 {
+    if sse2 {
+        unsafe { sse2::quad_matches(a, b) }
+    } else {
+        quad_matches_scalar(a, b)
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[inline]
+fn quad_matches(_sse2: bool, a: &[u32], b: &[u32]) -> u32 {
+    quad_matches_scalar(a, b)
+}
+
+/// Which of the 4 numbers in `a` are also among the 4 in `b`, as a bitmask with bit `i` set if
+/// `a[i]` is.
+#[inline]
+fn quad_matches_scalar(a: &[u32], b: &[u32]) -> u32 {
+    let mut matches = 0;
+    for (i, num) in a[0..4].iter().enumerate() {
+        if b[0..4].contains(num) {
+            matches |= 1 << i;
+        }
+    }
+
+    matches
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use std::collections::BTreeSet;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn intersect_random_lists_of_varying_density() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..300 {
+            let universe = rng.gen_range(1, 20_000);
+            let a = random_list(&mut rng, universe);
+            let b = random_list(&mut rng, universe);
+            let expected: Vec<u32> = a.intersection(&b).cloned().collect();
+
+            let a: Vec<u32> = a.into_iter().collect();
+            let b: Vec<u32> = b.into_iter().collect();
+            let mut encoded_a = vec![0; a.len() * 5];
+            encode::<Scalar>(&a, &mut encoded_a);
+            let mut encoded_b = vec![0; b.len() * 5];
+            encode::<Scalar>(&b, &mut encoded_b);
+
+            // existing contents are kept
+            let mut output = vec![7];
+            let len =
+                intersect_encoded::<Scalar>(&encoded_a, a.len(), &encoded_b, b.len(), &mut output);
+            assert_eq!(expected.len(), len);
+            assert_eq!(7, output[0]);
+            assert_eq!(expected, &output[1..]);
+        }
+    }
+
+    fn random_list<R: Rng>(rng: &mut R, universe: u32) -> BTreeSet<u32> {
+        // anywhere from nearly every number to a handful
+        let density = rng.gen_range(1, 1000);
+        (0..rng.gen_range(0, universe / density + 2))
+            .map(|_| rng.gen_range(0, universe))
+            .collect()
+    }
+
+    #[test]
+    fn quad_matches_scalar_finds_every_pair() {
+        for i in 0..4 {
+            for j in 0..4 {
+                let a = [10, 20, 30, 40];
+                let mut b = [1, 2, 3, 4];
+                b[j] = a[i];
+                assert_eq!(1 << i, quad_matches_scalar(&a, &b));
+            }
+        }
+
+        assert_eq!(0b1011, quad_matches_scalar(&[1, 2, 3, 4], &[4, 1, 2, 5]));
+        assert_eq!(0, quad_matches_scalar(&[1, 2, 3, 4], &[5, 6, 7, 8]));
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn quad_matches_sse2_matches_scalar() {
+        if !sse2::is_supported() {
+            return;
+        }
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..10_000 {
+            let a: Vec<u32> = (0..4).map(|_| rng.gen_range(0, 8)).collect();
+            let b: Vec<u32> = (0..4).map(|_| rng.gen_range(0, 8)).collect();
+            assert_eq!(quad_matches_scalar(&a, &b), unsafe {
+                sse2::quad_matches(&a, &b)
+            });
+        }
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m128i, _mm_castsi128_ps, _mm_cmpeq_epi32, _mm_loadu_si128, _mm_movemask_ps,
+                     _mm_or_si128, _mm_shuffle_epi32};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, _mm_castsi128_ps, _mm_cmpeq_epi32, _mm_loadu_si128,
+                        _mm_movemask_ps, _mm_or_si128, _mm_shuffle_epi32};
+
+/// Whether the CPU supports SSE2, which `quad_matches()` needs. Always true when compiling with
+/// the `sse2` target feature, as is the default on x86_64.
+#[inline]
+pub fn is_supported() -> bool {
+    cfg!(target_feature = "sse2") || is_x86_feature_detected!("sse2")
+}
+
+/// Like `quad_matches_scalar()`: which of the 4 numbers in `a` are also among the 4 in `b`, as a
+/// bitmask with bit `i` set if `a[i]` is.
+///
+/// All 16 pairs are compared with 4 comparisons of `a` against `b` rotated by 0, 1, 2, and 3
+/// lanes.
+///
+/// # Safety
+///
+/// The CPU must support SSE2, which `is_supported()` checks.
+#[inline]
+#[target_feature(enable = "sse2")]
+pub unsafe fn quad_matches(a: &[u32], b: &[u32]) -> u32 {
+    let a = _mm_loadu_si128(a[0..4].as_ptr() as *const __m128i);
+    let b = _mm_loadu_si128(b[0..4].as_ptr() as *const __m128i);
+
+    let equal = _mm_or_si128(
+        _mm_or_si128(
+            _mm_cmpeq_epi32(a, b),
+            _mm_cmpeq_epi32(a, _mm_shuffle_epi32(b, 0b00_11_10_01)),
+        ),
+        _mm_or_si128(
+            _mm_cmpeq_epi32(a, _mm_shuffle_epi32(b, 0b01_00_11_10)),
+            _mm_cmpeq_epi32(a, _mm_shuffle_epi32(b, 0b10_01_00_11)),
+        ),
+    );
+
+    // the top bit of each lane, which is set in every byte of a lane that matched
+    _mm_movemask_ps(_mm_castsi128_ps(equal)) as u32
+}
@@ -0,0 +1,212 @@
+use {decode_to_new_vec, encode_append, Decoder, Encoder};
+
+/// LEB128 (protobuf-style varint) encoding of `u32`s, for reading data written in that format,
+/// comparing sizes, and transcoding to and from Stream VByte.
+///
+/// Each number is stored in 7-bit groups, least significant first, with the high bit of each byte
+/// set if more bytes follow. Unlike Stream VByte, lengths aren't stored separately, so decoding
+/// can't be done a quad at a time and there's no `Encoder` or `Decoder` implementation.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{Leb128, Scalar};
+///
+/// let nums: Vec<u32> = (0..1000).map(|i| i * 300).collect();
+/// let mut leb = vec![0; nums.len() * 5];
+/// let leb_len = Leb128::encode(&nums, &mut leb);
+///
+/// // move old data over to Stream VByte
+/// let mut svb = Vec::new();
+/// assert_eq!(Some(leb_len), Leb128::to_stream_vbyte::<Scalar>(&leb, nums.len(), &mut svb));
+/// println!("LEB128: {} bytes, Stream VByte: {} bytes", leb_len, svb.len());
+///
+/// let mut decoded = vec![0; nums.len()];
+/// stream_vbyte::decode::<Scalar>(&svb, nums.len(), &mut decoded);
+/// assert_eq!(nums, decoded);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Leb128;
+
+impl Leb128 {
+    /// The number of bytes `encode()` would write for `input`.
+    pub fn encoded_len(input: &[u32]) -> usize {
+        input.iter().map(|&n| num_len(n)).sum()
+    }
+
+    /// Encode `input` into `output`, which must be at least `encoded_len(input)` bytes long
+    /// (`5 * input.len()` is always enough).
+    ///
+    /// Returns the number of bytes written.
+    pub fn encode(input: &[u32], output: &mut [u8]) -> usize {
+        let mut bytes_written = 0;
+
+        for &num in input {
+            let mut rest = num;
+            while rest >= 0x80 {
+                output[bytes_written] = (rest as u8) | 0x80;
+                rest >>= 7;
+                bytes_written += 1;
+            }
+            output[bytes_written] = rest as u8;
+            bytes_written += 1;
+        }
+
+        bytes_written
+    }
+
+    /// Decode `count` numbers from `input` into `output`.
+    ///
+    /// Returns the number of bytes read, or `None` if `input` ends partway through, or any number
+    /// is longer than 5 bytes or doesn't fit in a `u32`.
+    pub fn decode(input: &[u8], count: usize, output: &mut [u32]) -> Option<usize> {
+        let mut bytes_read = 0;
+
+        for out in output[0..count].iter_mut() {
+            let mut num = 0_u32;
+            let mut shift = 0;
+            loop {
+                let byte = *input.get(bytes_read)?;
+                bytes_read += 1;
+
+                if shift == 28 && byte > 0x0F {
+                    // a 5th byte may only hold the top 4 bits
+                    return None;
+                }
+                num |= u32::from(byte & 0x7F) << shift;
+
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            *out = num;
+        }
+
+        Some(bytes_read)
+    }
+
+    /// Transcode `count` LEB128 numbers from `input` to Stream VByte with `E`, appending them to
+    /// `output`.
+    ///
+    /// Returns the number of bytes read from `input`, or `None` if it couldn't be decoded (see
+    /// `decode()`), in which case `output` is unchanged.
+    pub fn to_stream_vbyte<E: Encoder>(
+        input: &[u8],
+        count: usize,
+        output: &mut Vec<u8>,
+    ) -> Option<usize> {
+        let mut nums = vec![0; count];
+        let bytes_read = Leb128::decode(input, count, &mut nums)?;
+        encode_append::<E>(&nums, output);
+
+        Some(bytes_read)
+    }
+
+    /// Transcode `count` Stream VByte numbers from `input`, decoded with `D`, to LEB128,
+    /// appending them to `output`.
+    ///
+    /// Returns the number of bytes appended to `output`.
+    pub fn from_stream_vbyte<D: Decoder>(
+        input: &[u8],
+        count: usize,
+        output: &mut Vec<u8>,
+    ) -> usize {
+        let nums = decode_to_new_vec::<D>(input, count);
+        let start = output.len();
+        output.resize(start + Leb128::encoded_len(&nums), 0);
+
+        Leb128::encode(&nums, &mut output[start..])
+    }
+}
+
+fn num_len(num: u32) -> usize {
+    if num < 1 << 7 {
+        1
+    } else if num < 1 << 14 {
+        2
+    } else if num < 1 << 21 {
+        3
+    } else if num < 1 << 28 {
+        4
+    } else {
+        5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn matches_protobuf_examples() {
+        let mut output = [0; 10];
+        assert_eq!(2, Leb128::encode(&[300], &mut output));
+        assert_eq!([0xAC, 0x02], output[0..2]);
+
+        assert_eq!(5, Leb128::encode(&[u32::max_value()], &mut output));
+        assert_eq!([0xFF, 0xFF, 0xFF, 0xFF, 0x0F], output[0..5]);
+    }
+
+    #[test]
+    fn random_roundtrip_and_transcode() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 300))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut leb = vec![0; nums.len() * 5];
+            let leb_len = Leb128::encode(&nums, &mut leb);
+            assert_eq!(Leb128::encoded_len(&nums), leb_len);
+
+            let mut decoded = vec![0; nums.len()];
+            assert_eq!(
+                Some(leb_len),
+                Leb128::decode(&leb[0..leb_len], nums.len(), &mut decoded)
+            );
+            assert_eq!(nums, decoded);
+
+            let mut svb = Vec::new();
+            assert_eq!(
+                Some(leb_len),
+                Leb128::to_stream_vbyte::<Scalar>(&leb[0..leb_len], nums.len(), &mut svb)
+            );
+            let mut back = Vec::new();
+            assert_eq!(
+                leb_len,
+                Leb128::from_stream_vbyte::<Scalar>(&svb, nums.len(), &mut back)
+            );
+            assert_eq!(&leb[0..leb_len], &back[..]);
+        }
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let mut output = [0; 2];
+        // ends partway through a number
+        assert_eq!(None, Leb128::decode(&[0x01, 0x80], 2, &mut output));
+        // too big for a u32
+        assert_eq!(
+            None,
+            Leb128::decode(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F], 1, &mut output)
+        );
+        // more than 5 bytes
+        assert_eq!(
+            None,
+            Leb128::decode(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00], 1, &mut output)
+        );
+
+        let mut svb = vec![1, 2, 3];
+        assert_eq!(
+            None,
+            Leb128::to_stream_vbyte::<Scalar>(&[0x80], 1, &mut svb)
+        );
+        assert_eq!(vec![1, 2, 3], svb);
+    }
+}
@@ -0,0 +1,101 @@
+//! The lengths of the numbers in a quad, given its control byte.
+//!
+//! By default these are looked up in the 256-entry tables in `tables`. With the `compact-tables`
+//! feature, they're computed from the control byte's bits instead, which is a little slower but
+//! leaves the tables out of the binary, for targets where every kilobyte of flash counts.
+
+#[cfg(not(feature = "compact-tables"))]
+use tables;
+
+/// The length of each of the 4 numbers in a quad.
+#[cfg(all(any(feature = "encode", feature = "decode"), not(feature = "compact-tables")))]
+#[inline]
+pub fn per_num(control_byte: u8) -> (u8, u8, u8, u8) {
+    tables::DECODE_LENGTH_PER_NUM_TABLE[control_byte as usize]
+}
+
+/// The length of each of the 4 numbers in a quad.
+#[cfg(all(any(feature = "encode", feature = "decode"), feature = "compact-tables"))]
+#[inline]
+pub fn per_num(control_byte: u8) -> (u8, u8, u8, u8) {
+    computed_per_num(control_byte)
+}
+
+/// The total length of the numbers in a quad.
+#[cfg(not(feature = "compact-tables"))]
+#[inline]
+pub fn per_quad(control_byte: u8) -> u8 {
+    tables::DECODE_LENGTH_PER_QUAD_TABLE[control_byte as usize]
+}
+
+/// The total length of the numbers in a quad.
+#[cfg(feature = "compact-tables")]
+#[inline]
+pub fn per_quad(control_byte: u8) -> u8 {
+    computed_per_quad(control_byte)
+}
+
+/// The length of the number at `index` (0-3) in a quad.
+#[cfg(any(feature = "decode", all(feature = "proptest", feature = "encode")))]
+#[inline]
+pub fn num_len(control_byte: u8, index: usize) -> usize {
+    ((control_byte >> (index * 2)) & 0x03) as usize + 1
+}
+
+/// The total length of the first `n` (0-4) numbers in a quad, e.g. for the trailing partial quad
+/// when the count isn't a multiple of 4.
+#[inline]
+pub fn partial_quad_len(control_byte: u8, n: usize) -> usize {
+    debug_assert!(n <= 4);
+    // clear the lengths of the numbers after the first n, so each of those counts as 1 byte
+    let mask = ((1_u16 << (n * 2)) - 1) as u8;
+    per_quad(control_byte & mask) as usize - (4 - n)
+}
+
+#[cfg(all(any(feature = "encode", feature = "decode"), any(test, feature = "compact-tables")))]
+#[inline]
+fn computed_per_num(control_byte: u8) -> (u8, u8, u8, u8) {
+    (
+        (control_byte & 0x03) + 1,
+        ((control_byte >> 2) & 0x03) + 1,
+        ((control_byte >> 4) & 0x03) + 1,
+        (control_byte >> 6) + 1,
+    )
+}
+
+#[cfg(any(all(test, any(feature = "encode", feature = "decode")), feature = "compact-tables"))]
+#[inline]
+fn computed_per_quad(control_byte: u8) -> u8 {
+    // add adjacent 2-bit lengths into 4-bit sums, then add the two sums
+    let pairs = (control_byte & 0x33) + ((control_byte >> 2) & 0x33);
+    (pairs & 0x0F) + (pairs >> 4) + 4
+}
+
+#[cfg(all(test, any(feature = "encode", feature = "decode"), not(feature = "compact-tables")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computed_lengths_match_tables() {
+        for control_byte in 0..256 {
+            let control_byte = control_byte as u8;
+
+            assert_eq!(per_num(control_byte), computed_per_num(control_byte));
+            assert_eq!(per_quad(control_byte), computed_per_quad(control_byte));
+        }
+    }
+
+    #[test]
+    fn partial_quad_len_sums_the_first_numbers() {
+        for control_byte in 0..256 {
+            let control_byte = control_byte as u8;
+
+            for n in 0..5 {
+                let expected: usize = (0..n)
+                    .map(|i| ((control_byte >> (i * 2)) & 0x03) as usize + 1)
+                    .sum();
+                assert_eq!(expected, partial_quad_len(control_byte, n));
+            }
+        }
+    }
+}
@@ -16,18 +16,17 @@
 //!
 //! # The more complex, really fast way
 //!
-//! If you can use nightly Rust (currently needed for SIMD) and you know which hardware you'll be
-//! running on, or you can add runtime detection of CPU features, you can choose to use an
-//! implementation that takes advantage of your hardware. Something like
-//! [raw-cpuid](https://crates.io/crates/raw-cpuid) or [auxv](https://crates.io/crates/auxv) will
-//! probably be useful for runtime CPU feature detection.
+//! If you know which hardware you'll be running on, you can choose to use an implementation that
+//! takes advantage of it. The SIMD implementations check that the CPU supports them once per call
+//! and panic if it doesn't, so it's safe to pick one at runtime after checking with
+//! `is_x86_feature_detected!`. If the `target_feature` is enabled at compile time, the check
+//! compiles away.
 //!
 //! Performance numbers are calculated on an E5-1650v3 on encoding/decoding 1 million random numbers
 //! at a time. You can run the benchmarks yourself to see how your hardware does.
 //!
-//! Both `feature`s and `target_feature`s are used because `target_feature` is not in stable Rust
-//! yet and this library should remain usable by stable Rust, so non-stable-friendly things are
-//! hidden behind `feature`s.
+//! The SIMD implementations are behind `feature`s so that builds that don't want them, or that
+//! target other architectures, needn't compile them.
 //!
 //! ## Encoders
 //!
@@ -43,7 +42,7 @@
 //! | `Scalar`       | ≈140 million/s | All                                        | none             | none        |
 //! | `x86::Ssse3`   | ≈2.7 billion/s | x86 with SSSE3 (Woodcrest and above, 2006) | `ssse3`          | `x86_ssse3` |
 //!
-//! If you have a modern x86 and you want to use the all SIMD accelerated versions, you would use
+//! If you have a modern x86 and you want to skip the runtime checks, you would use
 //! `target_feature` in a compiler invocation like this:
 //!
 //! ```sh
@@ -53,11 +52,31 @@
 //! Meanwhile, `feature`s for your dependency on this crate are specified
 //! [in your project's Cargo.toml](http://doc.crates.io/manifest.html#the-features-section).
 //!
+//! If you'd rather not select types with `cfg` in your own code, `DefaultEncoder` and
+//! `DefaultDecoder` resolve to the fastest implementations enabled by this crate's `feature`s.
+//!
+//! The `encode` and `decode` features, both enabled by default, can be turned off separately to
+//! leave out code that a program never uses, e.g. a reader on a microcontroller that only ever
+//! decodes. Anything that needs both, such as framing, transforms, and codec selection, is only
+//! available when both are enabled.
+//!
+//! # Allocation
+//!
+//! `encode()`, `decode()`, `decode_unchecked()`, and `DecodeCursor` (including decoding into
+//! slices and into your own sinks) never allocate on the heap, so they're safe to call on
+//! real-time threads. They only write into the buffers you give them. The `tracing` feature breaks
+//! this guarantee, since `tracing` subscribers may allocate.
+//!
+//! Functions that return or grow a `Vec`, such as `decode_to_new_vec()`, `encode_append()`, and
+//! the framing and transform APIs, allocate, as do types that own their buffers, like
+//! `DecodeContext`, `SkipIndex`, and `EncodedSegment`.
+//!
 //! # Examples
 //!
 //! Encode some numbers to bytes, then decode them in different ways.
 //!
 //! ```
+//! # #[cfg(all(feature = "encode", feature = "decode"))] {
 //! use stream_vbyte::*;
 //!
 //! let nums: Vec<u32> = (0..12_345).collect();
@@ -76,6 +95,14 @@
 //! assert_eq!(nums, decoded_nums);
 //! assert_eq!(encoded_len, bytes_decoded);
 //!
+//! // or just get a new Vec
+//! assert_eq!(nums, decode_to_new_vec::<Scalar>(&encoded_data, nums.len()));
+//!
+//! // or skip sizing the output yourself
+//! let mut appended = Vec::new();
+//! assert_eq!(encoded_len, encode_append::<Scalar>(&nums, &mut appended));
+//! assert_eq!(&encoded_data[0..encoded_len], &appended[..]);
+//!
 //! // or maybe you want to skip some of the numbers while decoding
 //! decoded_nums.clear();
 //! decoded_nums.resize(nums.len(), 0);
@@ -85,8 +112,16 @@
 //! assert_eq!(12_345 - 10_000, count);
 //! assert_eq!(&nums[10_000..], &decoded_nums[0..count]);
 //! assert_eq!(encoded_len, cursor.input_consumed());
+//! # }
 //! ```
 //!
+//! # Byte order
+//!
+//! Encoded numbers are always little-endian, as in the reference C implementation, so data encoded
+//! on one target decodes identically on any other, big-endian ones like s390x included. `Scalar`
+//! uses explicitly little-endian reads and writes, and the SIMD implementations are only available
+//! on x86, which is little-endian.
+//!
 //! # Panics
 //!
 //! If you use undersized slices (e.g. encoding 10 numbers into 5 bytes), you will get the normal
@@ -102,23 +137,250 @@
 //! mitigate those risks, there are various forms of randomized testing in the test suite to shake
 //! out any lurking bugs.
 //!
-//! The `Scalar` codec does not use unsafe.
+//! The `Scalar` codec does not use unsafe, except in `decode_unchecked()`, which leaves bounds
+//! checking to the caller.
+
+#![cfg_attr(feature = "nightly", feature(trusted_len))]
 
 extern crate byteorder;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(all(test, loom))]
+extern crate loom;
 
 mod tables;
 
+mod lengths;
+
+#[cfg(any(feature = "encode", feature = "decode"))]
+mod instrument;
+#[cfg(all(feature = "tracing", any(feature = "encode", feature = "decode")))]
+pub use instrument::{counters, Counters};
+
 mod scalar;
 pub use scalar::Scalar;
 
 pub mod x86;
 
+#[cfg(feature = "encode")]
 mod encode;
-pub use encode::{encode, Encoder};
+#[cfg(feature = "encode")]
+pub use encode::{encode, encode_append, Encoder, NoOverwrite};
+#[cfg(feature = "encode")]
+pub use encode::chunked::{encode_chunks, ChunkedEncoder};
+#[cfg(feature = "encode")]
+pub use encode::source::{encode_source, EncodeSource};
+#[cfg(feature = "encode")]
+pub use encode::split::encode_split;
 
+#[cfg(feature = "decode")]
 mod decode;
-pub use decode::{decode, DecodeQuadSink, DecodeSingleSink, Decoder, SliceDecodeSink};
-pub use decode::cursor::DecodeCursor;
+#[cfg(feature = "decode")]
+pub use decode::{decode, decode_to_new_vec, DecodeQuadSink, DecodeSingleSink, Decoder,
+                 WriteQuadToSlice};
+#[cfg(feature = "decode")]
+pub use decode::context::DecodeContext;
+#[cfg(feature = "decode")]
+pub use decode::cursor::{DecodeCursor, DecodeProgress, DecodeStop, SeekError, SkipError};
+#[cfg(feature = "decode")]
+pub use decode::lanes::decode_four;
+#[cfg(feature = "decode")]
+pub use decode::owned::OwnedDecodeCursor;
+#[cfg(feature = "decode")]
+pub use decode::plan::DecodePlan;
+#[cfg(feature = "decode")]
+pub use decode::zip::ZipCursor;
+#[cfg(feature = "decode")]
+pub use decode::sinks::{HistogramSink, TopKSink};
+#[cfg(feature = "decode")]
+pub use decode::unchecked::{decode_unchecked, validate};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod affine;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use affine::Affine;
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod float;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use float::{decode_f32_sorted, encode_f32_sorted};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod wide;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use wide::{decode_u64, encode_u64};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod leb128;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use leb128::Leb128;
+
+mod single;
+pub use single::encoded_num_len;
+#[cfg(feature = "encode")]
+pub use single::{encode_num, try_encode_num};
+#[cfg(feature = "decode")]
+pub use single::{decode_num, try_decode_num};
+
+/// The fastest `Encoder` enabled by this crate's features: `x86::Sse41` if the `x86_sse41`
+/// feature is enabled, otherwise `Scalar`.
+#[cfg(feature = "x86_sse41")]
+pub type DefaultEncoder = x86::Sse41;
+
+/// The fastest `Encoder` enabled by this crate's features: `x86::Sse41` if the `x86_sse41`
+/// feature is enabled, otherwise `Scalar`.
+#[cfg(all(feature = "encode", not(feature = "x86_sse41")))]
+pub type DefaultEncoder = Scalar;
+
+/// The fastest `Decoder` enabled by this crate's features: `x86::Ssse3` if the `x86_ssse3`
+/// feature is enabled, otherwise `Scalar`.
+#[cfg(feature = "x86_ssse3")]
+pub type DefaultDecoder = x86::Ssse3;
+
+/// The fastest `Decoder` enabled by this crate's features: `x86::Ssse3` if the `x86_ssse3`
+/// feature is enabled, otherwise `Scalar`.
+#[cfg(all(feature = "decode", not(feature = "x86_ssse3")))]
+pub type DefaultDecoder = Scalar;
+
+#[cfg(feature = "encode")]
+mod stats;
+#[cfg(feature = "encode")]
+pub use stats::{encode_with_stats, length_histogram, EncodeStats};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod advise;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use advise::{advise, CodecAdvice, Transform};
+
+#[cfg(feature = "decode")]
+mod scan;
+#[cfg(feature = "decode")]
+pub use scan::{count_less_than, filter_to_bitmap, CmpOp};
+
+#[cfg(feature = "decode")]
+mod sparse;
+#[cfg(feature = "decode")]
+pub use sparse::{iter_nonzero, NonZeroIter};
+
+mod bitvec;
+pub use bitvec::{BitVec, Ones};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod nullable;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use nullable::{decode_nullable, decode_nullable_present, encode_nullable};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod codecs;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use codecs::{available_codecs, CodecInfo, CodecKind};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod bench;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use bench::{bench_decode, bench_encode, bench_skip};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod selftest;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use selftest::{self_test, verify_cross_codec, Mismatch, SelfTestError};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod auto;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use auto::{decode_auto, global_decoder, set_global_decoder_chain};
+
+#[cfg(feature = "decode")]
+mod encoded_slice;
+#[cfg(feature = "decode")]
+pub use encoded_slice::{DecodeIter, DecodeRevIter, EncodedSlice};
+
+#[cfg(feature = "decode")]
+mod dump;
+#[cfg(feature = "decode")]
+pub use dump::dump;
+
+mod shard;
+pub use shard::shard;
+
+#[cfg(feature = "decode")]
+mod canonical;
+#[cfg(feature = "decode")]
+pub use canonical::{decode_strict, is_canonical};
+
+#[cfg(feature = "encode")]
+pub mod golden;
+
+#[cfg(any(feature = "gen", feature = "conformance"))]
+mod random_varint;
+#[cfg(feature = "gen")]
+pub mod gen;
+
+#[cfg(all(feature = "proptest", feature = "encode"))]
+pub mod strategies;
+
+#[cfg(feature = "conformance")]
+pub mod conformance;
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub mod frame;
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub mod config;
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub mod packer;
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub mod interleaved;
+
+#[cfg(feature = "decode")]
+mod block_max;
+#[cfg(feature = "decode")]
+pub use block_max::BlockMaxIndex;
+
+#[cfg(feature = "decode")]
+mod sorted;
+#[cfg(feature = "decode")]
+pub use sorted::SortedCursor;
+
+#[cfg(feature = "decode")]
+mod merged;
+#[cfg(feature = "decode")]
+pub use merged::MergedCursor;
+
+#[cfg(feature = "decode")]
+mod intersect;
+#[cfg(feature = "decode")]
+pub use intersect::intersect_encoded;
+
+#[cfg(feature = "decode")]
+mod union;
+#[cfg(feature = "decode")]
+pub use union::{union_encoded, union_encoded_payloads, union_encoded_sink, UnionSink};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod skip_index;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use skip_index::{SkipIndex, SkipIndexBuilder, SkipIndexReport};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod segment;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use segment::{EncodedSegment, SegmentCursor};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod concurrent;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use concurrent::{ConcurrentCursor, ConcurrentSegmentReader, SegmentAppender, SegmentSnapshot,
+                     SnapshotIter};
+
+#[cfg(all(feature = "encode", feature = "decode"))]
+mod append;
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub use append::{AppendSegment, AppendSegmentReader};
 
 #[derive(Debug, PartialEq)]
 struct EncodedShape {
@@ -141,10 +403,10 @@ fn cumulative_encoded_len(control_bytes: &[u8]) -> usize {
     control_bytes
         .iter()
         .map({
-            |&b| tables::DECODE_LENGTH_PER_QUAD_TABLE[b as usize] as usize
+            |&b| lengths::per_quad(b) as usize
         })
         .sum()
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "encode", feature = "decode"))]
 mod tests;
@@ -0,0 +1,180 @@
+use {Decoder, SortedCursor};
+
+/// The key of a stream with no numbers left, greater than any number's.
+const EXHAUSTED: u64 = u64::MAX;
+
+/// An `Iterator` over the numbers of many `SortedCursor`s merged into one ascending sequence:
+/// the streaming primitive behind compacting segments or iterating a query's matches across
+/// segments.
+///
+/// A number that occurs in several streams, or several times in one, is yielded once per
+/// occurrence, so nothing is lost when compacting; numbers that are equal are yielded in the order
+/// of the streams they're from.
+///
+/// The streams' current numbers are kept in a tournament tree, so each number costs
+/// `log2(streams)` comparisons to yield. Each stream is decoded a block at a time as its numbers
+/// are needed, so only a block per stream is decoded ahead.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, MergedCursor, Scalar, SortedCursor};
+///
+/// let lists: Vec<Vec<u32>> = vec![vec![1, 4, 9], vec![2, 3, 10, 11], vec![], vec![4, 5]];
+/// let encoded: Vec<Vec<u8>> = lists
+///     .iter()
+///     .map(|nums| {
+///         let mut encoded = vec![0; nums.len() * 5];
+///         encode::<Scalar>(nums, &mut encoded);
+///         encoded
+///     })
+///     .collect();
+///
+/// let cursors = lists
+///     .iter()
+///     .zip(encoded.iter())
+///     .map(|(nums, encoded)| SortedCursor::<Scalar>::new(encoded, nums.len()))
+///     .collect();
+///
+/// let merged: Vec<u32> = MergedCursor::new(cursors).collect();
+/// assert_eq!(vec![1, 2, 3, 4, 4, 5, 9, 10, 11], merged);
+/// # }
+/// ```
+pub struct MergedCursor<'a, D: Decoder> {
+    cursors: Vec<SortedCursor<'a, D>>,
+    /// The current number of each stream, or `EXHAUSTED`, padded with `EXHAUSTED` to the number of
+    /// leaves.
+    keys: Vec<u64>,
+    /// A complete binary tree of the index of the stream with the smallest key in each subtree,
+    /// with the root at 1 and the leaves starting at `keys.len()`.
+    tree: Vec<usize>,
+}
+
+impl<'a, D: Decoder> MergedCursor<'a, D> {
+    /// Create a cursor over the numbers of every one of `cursors`, from each one's current number
+    /// on.
+    pub fn new(mut cursors: Vec<SortedCursor<'a, D>>) -> MergedCursor<'a, D> {
+        let leaves = cursors.len().next_power_of_two();
+        let mut keys = vec![EXHAUSTED; leaves];
+        for (key, cursor) in keys.iter_mut().zip(cursors.iter_mut()) {
+            *key = cursor.next().map_or(EXHAUSTED, u64::from);
+        }
+
+        let mut tree = vec![0; leaves * 2];
+        for (i, node) in tree[leaves..].iter_mut().enumerate() {
+            *node = i;
+        }
+        for node in (1..leaves).rev() {
+            tree[node] = winner(&keys, tree[node * 2], tree[node * 2 + 1]);
+        }
+
+        MergedCursor {
+            cursors,
+            keys,
+            tree,
+        }
+    }
+}
+
+impl<'a, D: Decoder> Iterator for MergedCursor<'a, D> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let stream = self.tree[1];
+        let key = self.keys[stream];
+        if key == EXHAUSTED {
+            return None;
+        }
+
+        self.keys[stream] = self.cursors[stream].next().map_or(EXHAUSTED, u64::from);
+
+        // replay the matches on the way from the stream's leaf to the root
+        let mut node = (self.keys.len() + stream) / 2;
+        while node > 0 {
+            self.tree[node] = winner(&self.keys, self.tree[node * 2], self.tree[node * 2 + 1]);
+            node /= 2;
+        }
+
+        Some(key as u32)
+    }
+}
+
+/// Whichever of streams `left` and `right` has the smaller key, with `left` winning ties so that
+/// equal numbers come out in stream order.
+#[inline]
+fn winner(keys: &[u64], left: usize, right: usize) -> usize {
+    if keys[right] < keys[left] {
+        right
+    } else {
+        left
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn merges_random_streams() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let lists: Vec<Vec<u32>> = (0..rng.gen_range(0, 20))
+                .map(|_| {
+                    let mut nums: Vec<u32> = (0..rng.gen_range(0, 300))
+                        .map(|_| rng.gen_range(0, 1000))
+                        .collect();
+                    nums.sort();
+                    nums
+                })
+                .collect();
+            let encoded: Vec<Vec<u8>> = lists
+                .iter()
+                .map(|nums| {
+                    let mut encoded = vec![0; nums.len() * 5];
+                    encode::<Scalar>(nums, &mut encoded);
+                    encoded
+                })
+                .collect();
+
+            let mut expected: Vec<u32> = lists.iter().flat_map(|nums| nums.clone()).collect();
+            expected.sort();
+
+            let cursors = lists
+                .iter()
+                .zip(encoded.iter())
+                .map(|(nums, encoded)| SortedCursor::<Scalar>::new(encoded, nums.len()))
+                .collect();
+            assert_eq!(expected, MergedCursor::new(cursors).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn merges_from_each_cursors_current_number() {
+        let nums: Vec<u32> = (0..100).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let mut first = SortedCursor::<Scalar>::new(&encoded, nums.len());
+        first.advance_to(90);
+        let mut second = SortedCursor::<Scalar>::new(&encoded, nums.len());
+        second.advance_to(97);
+
+        let merged = MergedCursor::new(vec![first, second]);
+        assert_eq!(
+            vec![90, 91, 92, 93, 94, 95, 96, 97, 97, 98, 98, 99, 99],
+            merged.collect::<Vec<u32>>()
+        );
+    }
+
+    #[test]
+    fn no_streams() {
+        assert_eq!(None, MergedCursor::<Scalar>::new(Vec::new()).next());
+    }
+}
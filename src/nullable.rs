@@ -0,0 +1,282 @@
+//! Encoding of nullable columns: numbers where some slots have no value.
+//!
+//! The validity of each slot is stored as the lengths of alternating runs of present and null
+//! slots (starting with present, possibly an empty run), which are themselves encoded as numbers,
+//! so columns with few nulls, or long stretches of them, take little space for their validity.
+//! Only the present values are stored after that.
+//!
+//! The layout is the number of slots and the number of runs, as little-endian `u32`s, then the
+//! encoded runs, then the encoded present values.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {decode, encode_append, validate, BitVec, Decoder, Encoder};
+
+const HEADER_LEN: usize = 8;
+
+/// Encode the slots of `values` whose bits are set in `validity`, along with `validity` itself,
+/// appending them to `output`. Values in null slots are ignored.
+///
+/// Returns the number of bytes appended to `output`.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{decode_nullable, decode_nullable_present, encode_nullable, BitVec, Scalar};
+///
+/// let values = [10, 0, 0, 13, 14];
+/// let mut validity = BitVec::new(values.len());
+/// for &i in &[0, 3, 4] {
+///     validity.set(i);
+/// }
+///
+/// let mut encoded = Vec::new();
+/// let encoded_len = encode_nullable::<Scalar>(&values, &validity, &mut encoded);
+///
+/// let mut expanded = Vec::new();
+/// assert_eq!(Some(encoded_len), decode_nullable::<Scalar>(&encoded, 999, &mut expanded));
+/// assert_eq!(vec![10, 999, 999, 13, 14], expanded);
+///
+/// let mut present = Vec::new();
+/// assert_eq!(Some(encoded_len), decode_nullable_present::<Scalar>(&encoded, &mut present));
+/// assert_eq!(vec![(0, 10), (3, 13), (4, 14)], present);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `validity` isn't as long as `values`, or there are `u32::MAX` or more
+/// values.
+pub fn encode_nullable<E: Encoder>(
+    values: &[u32],
+    validity: &BitVec,
+    output: &mut Vec<u8>,
+) -> usize {
+    assert_eq!(
+        values.len(),
+        validity.len(),
+        "Validity must have a bit for every value"
+    );
+    assert!(
+        values.len() < u32::MAX as usize,
+        "Can encode fewer than u32::MAX values"
+    );
+
+    let mut runs = Vec::new();
+    let mut present = Vec::with_capacity(validity.count_ones());
+    let mut present_run = 0;
+    let mut next_index = 0;
+
+    for index in validity.ones() {
+        if index > next_index {
+            runs.push(present_run);
+            runs.push((index - next_index) as u32);
+            present_run = 0;
+        }
+        present_run += 1;
+        next_index = index + 1;
+        present.push(values[index]);
+    }
+    runs.push(present_run);
+    if next_index < values.len() {
+        runs.push((values.len() - next_index) as u32);
+    }
+
+    let start = output.len();
+    let mut header = [0; HEADER_LEN];
+    LittleEndian::write_u32(&mut header[0..4], values.len() as u32);
+    LittleEndian::write_u32(&mut header[4..8], runs.len() as u32);
+    output.extend_from_slice(&header);
+
+    encode_append::<E>(&runs, output);
+    encode_append::<E>(&present, output);
+
+    output.len() - start
+}
+
+/// Decode the column at the start of `input`, written by `encode_nullable()`, with `D`, appending
+/// a number for every slot to `output`, with `default` for null slots.
+///
+/// Returns the number of bytes read, or `None` if `input` doesn't start with a complete, valid
+/// column, in which case `output` is unchanged.
+pub fn decode_nullable<D: Decoder>(
+    input: &[u8],
+    default: u32,
+    output: &mut Vec<u32>,
+) -> Option<usize> {
+    let column = read_column::<D>(input)?;
+
+    let start = output.len();
+    output.reserve(column.count);
+    let mut present = column.present.iter();
+    for (run_index, &run) in column.runs.iter().enumerate() {
+        if run_index % 2 == 0 {
+            output.extend(present.by_ref().take(run as usize));
+        } else {
+            let len = output.len();
+            output.resize(len + run as usize, default);
+        }
+    }
+    debug_assert_eq!(start + column.count, output.len());
+
+    Some(column.encoded_len)
+}
+
+/// Decode the column at the start of `input`, written by `encode_nullable()`, with `D`, appending
+/// only the present values to `output`, as `(slot index, value)` pairs.
+///
+/// Returns the number of bytes read, or `None` if `input` doesn't start with a complete, valid
+/// column, in which case `output` is unchanged.
+pub fn decode_nullable_present<D: Decoder>(
+    input: &[u8],
+    output: &mut Vec<(usize, u32)>,
+) -> Option<usize> {
+    let column = read_column::<D>(input)?;
+
+    output.reserve(column.present.len());
+    let mut present = column.present.iter().cloned();
+    let mut index = 0;
+    for (run_index, &run) in column.runs.iter().enumerate() {
+        if run_index % 2 == 0 {
+            output.extend((index..(index + run as usize)).zip(present.by_ref()));
+        }
+        index += run as usize;
+    }
+
+    Some(column.encoded_len)
+}
+
+/// A decoded column, before the nulls are filled in.
+struct Column {
+    count: usize,
+    runs: Vec<u32>,
+    present: Vec<u32>,
+    encoded_len: usize,
+}
+
+/// Decode the runs and present values, checking that they agree with each other and the header.
+fn read_column<D: Decoder>(input: &[u8]) -> Option<Column> {
+    if input.len() < HEADER_LEN {
+        return None;
+    }
+    let count = LittleEndian::read_u32(&input[0..4]) as usize;
+    let runs_count = LittleEndian::read_u32(&input[4..8]) as usize;
+
+    let runs_input = &input[HEADER_LEN..];
+    let (runs, runs_len) = decode_checked::<D>(runs_input, runs_count)?;
+
+    // sum as u64, so that malformed runs can't overflow
+    let total: u64 = runs.iter().map(|&r| u64::from(r)).sum();
+    if total != count as u64 {
+        return None;
+    }
+    let present_count = runs.iter()
+        .enumerate()
+        .filter(|&(i, _)| i % 2 == 0)
+        .map(|(_, &r)| r as usize)
+        .sum();
+
+    let (present, present_len) = decode_checked::<D>(&runs_input[runs_len..], present_count)?;
+
+    Some(Column {
+        count,
+        runs,
+        present,
+        encoded_len: HEADER_LEN + runs_len + present_len,
+    })
+}
+
+/// Decode `count` numbers if `input` is long enough to hold them, returning them and their
+/// encoded length.
+fn decode_checked<D: Decoder>(input: &[u8], count: usize) -> Option<(Vec<u32>, usize)> {
+    let encoded_len = validate(input, count)?;
+
+    let mut nums = vec![0; count];
+    decode::<D>(input, count, &mut nums);
+
+    Some((nums, encoded_len))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use Scalar;
+
+    #[test]
+    fn random_roundtrip() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let count = rng.gen_range(0, 300);
+            let null_chance = rng.gen_range(0.0, 1.0);
+            let values: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut validity = BitVec::new(count);
+            for i in 0..count {
+                if rng.gen_range(0.0, 1.0) >= null_chance {
+                    validity.set(i);
+                }
+            }
+
+            let mut encoded = vec![0xFF];
+            let encoded_len = encode_nullable::<Scalar>(&values, &validity, &mut encoded);
+            assert_eq!(encoded.len() - 1, encoded_len);
+
+            let mut expanded = Vec::new();
+            assert_eq!(
+                Some(encoded_len),
+                decode_nullable::<Scalar>(&encoded[1..], 7, &mut expanded)
+            );
+            let expected: Vec<u32> = (0..count)
+                .map(|i| if validity.get(i) { values[i] } else { 7 })
+                .collect();
+            assert_eq!(expected, expanded);
+
+            let mut present = Vec::new();
+            assert_eq!(
+                Some(encoded_len),
+                decode_nullable_present::<Scalar>(&encoded[1..], &mut present)
+            );
+            let expected: Vec<(usize, u32)> = validity.ones().map(|i| (i, values[i])).collect();
+            assert_eq!(expected, present);
+        }
+    }
+
+    #[test]
+    fn mostly_null_column_is_small() {
+        let values = vec![5; 10_000];
+        let mut validity = BitVec::new(values.len());
+        validity.set(5000);
+
+        let mut encoded = Vec::new();
+        // header, then runs of 0 present, 5000 null, 1 present, and 4999 null, then the value
+        assert_eq!(
+            8 + 1 + 1 + 2 + 1 + 2 + 1 + 1,
+            encode_nullable::<Scalar>(&values, &validity, &mut encoded)
+        );
+    }
+
+    #[test]
+    fn truncated_or_inconsistent_input_is_rejected() {
+        let values = [1, 2, 3, 4, 5];
+        let mut validity = BitVec::new(values.len());
+        validity.set(1);
+        validity.set(4);
+        let mut encoded = Vec::new();
+        encode_nullable::<Scalar>(&values, &validity, &mut encoded);
+
+        let mut output = Vec::new();
+        for len in 0..encoded.len() {
+            assert_eq!(None, decode_nullable::<Scalar>(&encoded[0..len], 0, &mut output));
+        }
+
+        // runs that don't add up to the count
+        encoded[0] += 1;
+        assert_eq!(None, decode_nullable::<Scalar>(&encoded, 0, &mut output));
+        assert!(output.is_empty());
+    }
+}
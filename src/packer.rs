@@ -0,0 +1,316 @@
+//! Pack several encoded sequences back to back into one buffer with a directory describing them.
+//!
+//! The packed layout is each sequence's encoding (padded so each one starts at a multiple of the
+//! chosen alignment), followed by the directory, followed by a 12-byte footer. The directory is
+//! itself Stream VByte encoded: the count and encoded length of each sequence, in order. The footer
+//! holds the number of sequences, the length of the directory, and the alignment, each as a
+//! little-endian `u32`.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {decode, encode, encoded_shape, validate, EncodedSlice, Encoder, Scalar};
+
+const FOOTER_LEN: usize = 12;
+
+/// Where one sequence is in a packed buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedStream {
+    /// The offset of the encoded sequence from the start of the buffer.
+    pub offset: usize,
+    pub encoded_len: usize,
+    pub count: usize,
+}
+
+/// Encodes sequences one after another into a single buffer.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::Scalar;
+/// use stream_vbyte::packer::{PackedStreams, StreamPacker};
+///
+/// let mut packer = StreamPacker::with_alignment(16);
+/// packer.push::<Scalar>(&[1, 2, 3]);
+/// packer.push::<Scalar>(&[1000, 2000]);
+/// let packed = packer.finish();
+///
+/// let streams = PackedStreams::new(&packed).unwrap();
+/// assert_eq!(2, streams.len());
+/// assert_eq!(16, streams.entries()[1].offset);
+/// assert_eq!(
+///     vec![1000, 2000],
+///     streams.get(1).unwrap().iter::<Scalar>().collect::<Vec<u32>>()
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamPacker {
+    buf: Vec<u8>,
+    alignment: usize,
+    directory: Vec<u32>,
+}
+
+impl StreamPacker {
+    /// Create a packer that puts sequences immediately after one another.
+    pub fn new() -> StreamPacker {
+        StreamPacker::with_alignment(1)
+    }
+
+    /// Create a packer that pads with zeros so that each sequence starts at a multiple of
+    /// `alignment` bytes from the start of the buffer. `alignment` must be a power of two.
+    ///
+    /// An alignment of 16 keeps each sequence's first control byte on a 16-byte boundary, as long
+    /// as the buffer itself is so aligned when it is read.
+    pub fn with_alignment(alignment: usize) -> StreamPacker {
+        assert!(
+            alignment.is_power_of_two(),
+            "Alignment must be a power of two"
+        );
+
+        StreamPacker {
+            buf: Vec::new(),
+            alignment,
+            directory: Vec::new(),
+        }
+    }
+
+    /// Encode `nums` as the next sequence.
+    ///
+    /// Returns the index of the sequence.
+    pub fn push<E: Encoder>(&mut self, nums: &[u32]) -> usize {
+        assert!(
+            nums.len() <= u32::MAX as usize,
+            "Sequences can hold at most u32::MAX numbers"
+        );
+
+        let start = align_up(self.buf.len(), self.alignment);
+        self.buf.resize(start + nums.len() * 5, 0);
+        let encoded_len = encode::<E>(nums, &mut self.buf[start..]);
+        self.buf.truncate(start + encoded_len);
+
+        self.directory.push(nums.len() as u32);
+        self.directory.push(encoded_len as u32);
+
+        self.directory.len() / 2 - 1
+    }
+
+    /// Append the directory and footer, and return the packed buffer.
+    pub fn finish(self) -> Vec<u8> {
+        let mut buf = self.buf;
+
+        let directory_start = buf.len();
+        buf.resize(directory_start + self.directory.len() * 5, 0);
+        let directory_len = encode::<Scalar>(&self.directory, &mut buf[directory_start..]);
+        buf.truncate(directory_start + directory_len);
+
+        let mut footer = [0; FOOTER_LEN];
+        LittleEndian::write_u32(&mut footer[0..4], (self.directory.len() / 2) as u32);
+        LittleEndian::write_u32(&mut footer[4..8], directory_len as u32);
+        LittleEndian::write_u32(&mut footer[8..12], self.alignment as u32);
+        buf.extend_from_slice(&footer);
+
+        buf
+    }
+}
+
+impl Default for StreamPacker {
+    fn default() -> StreamPacker {
+        StreamPacker::new()
+    }
+}
+
+/// Reads sequences out of a buffer produced by `StreamPacker`.
+#[derive(Debug, Clone)]
+pub struct PackedStreams<'a> {
+    input: &'a [u8],
+    entries: Vec<PackedStream>,
+}
+
+impl<'a> PackedStreams<'a> {
+    /// Read the directory of a packed buffer.
+    ///
+    /// Returns `None` if `input` isn't a packed buffer: if the footer or directory is malformed,
+    /// or if the sequences it describes don't fit before the directory. The sequences' encoded
+    /// numbers aren't checked until they're read.
+    pub fn new(input: &'a [u8]) -> Option<PackedStreams<'a>> {
+        if input.len() < FOOTER_LEN {
+            return None;
+        }
+
+        let footer = &input[(input.len() - FOOTER_LEN)..];
+        let num_streams = LittleEndian::read_u32(&footer[0..4]) as usize;
+        let directory_len = LittleEndian::read_u32(&footer[4..8]) as usize;
+        let alignment = LittleEndian::read_u32(&footer[8..12]) as usize;
+        if !alignment.is_power_of_two() || directory_len > input.len() - FOOTER_LEN {
+            return None;
+        }
+
+        let directory_start = input.len() - FOOTER_LEN - directory_len;
+        let directory_bytes = &input[directory_start..(directory_start + directory_len)];
+        let directory_count = num_streams.checked_mul(2)?;
+        // before allocating, so that a corrupt count can't make the allocation huge
+        if validate(directory_bytes, directory_count) != Some(directory_len) {
+            return None;
+        }
+
+        let mut directory = vec![0; directory_count];
+        decode::<Scalar>(directory_bytes, directory_count, &mut directory);
+
+        let mut entries = Vec::with_capacity(num_streams);
+        let mut offset = 0;
+        for pair in directory.chunks(2) {
+            offset = align_up(offset, alignment);
+            let entry = PackedStream {
+                offset,
+                count: pair[0] as usize,
+                encoded_len: pair[1] as usize,
+            };
+            if encoded_shape(entry.count).control_bytes_len > entry.encoded_len {
+                return None;
+            }
+            offset = offset
+                .checked_add(entry.encoded_len)
+                .filter(|&end| end <= directory_start)?;
+            entries.push(entry);
+        }
+
+        Some(PackedStreams {
+            input: &input[0..directory_start],
+            entries,
+        })
+    }
+
+    /// The number of sequences.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Where each sequence is in the buffer.
+    pub fn entries(&self) -> &[PackedStream] {
+        &self.entries
+    }
+
+    /// The sequence at `index`, or `None` if `index` is out of range.
+    pub fn get(&self, index: usize) -> Option<EncodedSlice<'a>> {
+        self.entries.get(index).map(|e| {
+            EncodedSlice::new(&self.input[e.offset..(e.offset + e.encoded_len)], e.count)
+        })
+    }
+}
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn random_sequences_roundtrip_with_alignment() {
+        let mut rng = rand::weak_rng();
+
+        for &alignment in &[1, 4, 16, 64] {
+            for _ in 0..100 {
+                let seqs: Vec<Vec<u32>> = (0..rng.gen_range(0, 20))
+                    .map(|_| {
+                        (0..rng.gen_range(0, 100))
+                            .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                            .collect()
+                    })
+                    .collect();
+
+                let mut packer = StreamPacker::with_alignment(alignment);
+                for (i, seq) in seqs.iter().enumerate() {
+                    assert_eq!(i, packer.push::<Scalar>(seq));
+                }
+                let packed = packer.finish();
+
+                let streams = PackedStreams::new(&packed).unwrap();
+                assert_eq!(seqs.len(), streams.len());
+                for (i, seq) in seqs.iter().enumerate() {
+                    assert_eq!(0, streams.entries()[i].offset % alignment);
+                    let decoded: Vec<u32> = streams.get(i).unwrap().iter::<Scalar>().collect();
+                    assert_eq!(seq, &decoded);
+                }
+                assert!(streams.get(seqs.len()).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn empty_packer_is_just_footer() {
+        let packed = StreamPacker::new().finish();
+
+        assert_eq!(FOOTER_LEN, packed.len());
+        assert!(PackedStreams::new(&packed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unaligned_streams_are_adjacent() {
+        let mut packer = StreamPacker::new();
+        packer.push::<Scalar>(&[1, 2, 3]);
+        packer.push::<Scalar>(&[4]);
+        let packed = packer.finish();
+
+        let streams = PackedStreams::new(&packed).unwrap();
+        assert_eq!(
+            &[
+                PackedStream {
+                    offset: 0,
+                    encoded_len: 4,
+                    count: 3,
+                },
+                PackedStream {
+                    offset: 4,
+                    encoded_len: 2,
+                    count: 1,
+                },
+            ],
+            streams.entries()
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let mut packer = StreamPacker::with_alignment(4);
+        packer.push::<Scalar>(&[1, 2, 3]);
+        packer.push::<Scalar>(&[4, 5]);
+        let packed = packer.finish();
+        let footer_start = packed.len() - FOOTER_LEN;
+
+        assert!(PackedStreams::new(&packed[0..(FOOTER_LEN - 1)]).is_none());
+
+        for &(index, value) in &[
+            // alignment of 0, and not a power of two
+            (footer_start + 8, 0),
+            (footer_start + 8, 3),
+            // more streams than the directory holds
+            (footer_start, 200),
+            (footer_start + 3, 0x40),
+            // directory longer than the input
+            (footer_start + 6, 1),
+            // first stream's encoded length, past the directory
+            (footer_start - 3, 100),
+            // first stream's encoded length, shorter than its control bytes
+            (footer_start - 3, 0),
+        ] {
+            let mut modified = packed.clone();
+            modified[index] = value;
+            assert!(PackedStreams::new(&modified).is_none(), "{} = {}", index, value);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Alignment must be a power of two")]
+    fn non_power_of_two_alignment_panics() {
+        StreamPacker::with_alignment(12);
+    }
+}
@@ -1,13 +1,20 @@
+#[cfg(feature = "decode")]
 use std::cmp;
 
-use {tables, SliceDecodeSink};
-use decode::{decode_num_scalar, DecodeQuadSink, Decoder};
-use encode::{encode_num_scalar, Encoder};
+#[cfg(feature = "decode")]
+use lengths;
+#[cfg(feature = "decode")]
+use decode::{decode_num_scalar, DecodeQuadSink, Decoder, WriteQuadToSlice};
+#[cfg(feature = "decode")]
+use decode::unchecked::decode_quads_unchecked_scalar;
+#[cfg(feature = "encode")]
+use encode::{encode_num_scalar, Encoder, NoOverwrite};
 
 /// Encoder/Decoder that works on every platform, at the cost of speed compared to the SIMD
 /// accelerated versions.
 pub struct Scalar;
 
+#[cfg(feature = "encode")]
 impl Encoder for Scalar {
     // This implementation encodes all provided input numbers.
     fn encode_quads(
@@ -24,6 +31,18 @@ impl Encoder for Scalar {
             let num2 = input[nums_encoded + 2];
             let num3 = input[nums_encoded + 3];
 
+            if num0 < 256 && num0 == num1 && num0 == num2 && num0 == num3 {
+                // Runs of equal small numbers, like the gaps in dense sorted input after delta
+                // encoding, can be written all at once.
+                let byte = num0 as u8;
+                encoded_nums[bytes_written..(bytes_written + 4)].copy_from_slice(&[byte; 4]);
+                control_bytes[quads_encoded] = 0;
+
+                bytes_written += 4;
+                nums_encoded += 4;
+                continue;
+            }
+
             let len0 = encode_num_scalar(num0, &mut encoded_nums[bytes_written..]);
             let len1 = encode_num_scalar(num1, &mut encoded_nums[bytes_written + len0..]);
             let len2 = encode_num_scalar(num2, &mut encoded_nums[bytes_written + len0 + len1..]);
@@ -44,6 +63,10 @@ impl Encoder for Scalar {
     }
 }
 
+#[cfg(feature = "encode")]
+impl NoOverwrite for Scalar {}
+
+#[cfg(feature = "decode")]
 impl Decoder for Scalar {
     // Quads are decoded one at a time anyway so no need to bundle them up only to un-bundle them.
     // Instead, we just call on_number for each decoded number.
@@ -62,8 +85,21 @@ impl Decoder for Scalar {
         let control_byte_limit = cmp::min(control_bytes.len(), control_bytes_to_decode);
 
         for &control_byte in control_bytes[0..control_byte_limit].iter() {
-            let (len0, len1, len2, len3) =
-                tables::DECODE_LENGTH_PER_NUM_TABLE[control_byte as usize];
+            if control_byte == 0 {
+                // Four 1-byte numbers, by far the most common quad in delta encoded data, so skip
+                // the length lookups.
+                let quad = &encoded_nums[bytes_read..(bytes_read + 4)];
+                sink.on_number(u32::from(quad[0]), nums_decoded);
+                sink.on_number(u32::from(quad[1]), nums_decoded + 1);
+                sink.on_number(u32::from(quad[2]), nums_decoded + 2);
+                sink.on_number(u32::from(quad[3]), nums_decoded + 3);
+
+                bytes_read += 4;
+                nums_decoded += 4;
+                continue;
+            }
+
+            let (len0, len1, len2, len3) = lengths::per_num(control_byte);
             let len0 = len0 as usize;
             let len1 = len1 as usize;
             let len2 = len2 as usize;
@@ -92,10 +128,32 @@ impl Decoder for Scalar {
 
         (nums_decoded - nums_already_decoded, bytes_read)
     }
+
+    unsafe fn decode_quads_unchecked<S: DecodeQuadSink<Self::DecodedQuad>>(
+        control_bytes: &[u8],
+        encoded_nums: &[u8],
+        control_bytes_to_decode: usize,
+        nums_already_decoded: usize,
+        sink: &mut S,
+    ) -> (usize, usize) {
+        let control_byte_limit = cmp::min(control_bytes.len(), control_bytes_to_decode);
+
+        decode_quads_unchecked_scalar(
+            control_bytes.get_unchecked(0..control_byte_limit),
+            encoded_nums,
+            nums_already_decoded,
+            sink,
+        )
+    }
 }
 
-impl<'a> DecodeQuadSink<()> for SliceDecodeSink<'a> {
-    fn on_quad(&mut self, _: (), _: usize) {
-        unreachable!()
+// `Scalar`, and other decoders that hand every number to `DecodeSingleSink.on_number()`, use `()`
+// as their `DecodedQuad` and never produce one, so there is never a `()` quad to write. This only
+// exists to satisfy the `Decoder::DecodedQuad` bound, so it's left out of the docs.
+#[cfg(feature = "decode")]
+#[doc(hidden)]
+impl WriteQuadToSlice for () {
+    fn write_to_slice(self, _: &mut [u32]) {
+        unreachable!("() is the quad type of decoders that never decode whole quads")
     }
 }
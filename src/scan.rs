@@ -0,0 +1,212 @@
+//! Evaluate simple predicates over encoded numbers without materializing them into a buffer.
+
+use {BitVec, DecodeCursor, DecodeQuadSink, DecodeSingleSink, Decoder, WriteQuadToSlice};
+
+/// A comparison to evaluate against each number with `filter_to_bitmap()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    /// Equal to the operand.
+    Eq(u32),
+    /// Less than the operand.
+    Lt(u32),
+    /// Greater than or equal to the operand.
+    Ge(u32),
+    /// Between the two operands, inclusive.
+    Between(u32, u32),
+}
+
+impl CmpOp {
+    #[inline]
+    fn matches(&self, num: u32) -> bool {
+        match *self {
+            CmpOp::Eq(operand) => num == operand,
+            CmpOp::Lt(operand) => num < operand,
+            CmpOp::Ge(operand) => num >= operand,
+            CmpOp::Between(low, high) => num >= low && num <= high,
+        }
+    }
+}
+
+/// Count how many of the `count` numbers encoded in `input` are less than `threshold`.
+///
+/// Numbers are compared as they are decoded, a quad at a time where the `Decoder` supports it, so
+/// this never needs more than 4 numbers' worth of buffer space.
+pub fn count_less_than<D: Decoder>(input: &[u8], count: usize, threshold: u32) -> usize {
+    let mut sink = CountLessThanSink {
+        threshold,
+        matches: 0,
+    };
+    DecodeCursor::new(input, count).decode_sink::<D, _>(&mut sink, count);
+
+    sink.matches
+}
+
+/// Evaluate `predicate` against each of the `count` numbers encoded in `input`, returning a
+/// bitmap with bit `i` set iff the `i`th number matched.
+///
+/// Like `count_less_than()`, this evaluates quads as they are decoded, so only the numbers that
+/// survive the filter need to be decoded again later.
+pub fn filter_to_bitmap<D: Decoder>(input: &[u8], count: usize, predicate: CmpOp) -> BitVec {
+    let mut sink = FilterSink {
+        predicate,
+        bits: BitVec::new(count),
+    };
+    DecodeCursor::new(input, count).decode_sink::<D, _>(&mut sink, count);
+
+    sink.bits
+}
+
+struct CountLessThanSink {
+    threshold: u32,
+    matches: usize,
+}
+
+impl DecodeSingleSink for CountLessThanSink {
+    #[inline]
+    fn on_number(&mut self, num: u32, _nums_decoded: usize) {
+        self.matches += (num < self.threshold) as usize;
+    }
+}
+
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for CountLessThanSink {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, _nums_decoded: usize) {
+        let mut nums = [0_u32; 4];
+        quad.write_to_slice(&mut nums);
+
+        // branchless so that the 4 comparisons can be done as one vector compare
+        self.matches += nums
+            .iter()
+            .map(|&n| (n < self.threshold) as usize)
+            .sum::<usize>();
+    }
+}
+
+struct FilterSink {
+    predicate: CmpOp,
+    bits: BitVec,
+}
+
+impl DecodeSingleSink for FilterSink {
+    #[inline]
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        if self.predicate.matches(num) {
+            self.bits.set(nums_decoded);
+        }
+    }
+}
+
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for FilterSink {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        let mut nums = [0_u32; 4];
+        quad.write_to_slice(&mut nums);
+
+        let mask = nums
+            .iter()
+            .enumerate()
+            .fold(0_u64, |mask, (i, &n)| mask | (self.predicate.matches(n) as u64) << i);
+
+        // quads start at multiples of 4, so all 4 bits are in the same word
+        self.bits.or_bits(nums_decoded, mask);
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode_append, Scalar};
+
+    #[test]
+    fn count_less_than_random_scalar() {
+        do_count_less_than_random::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn count_less_than_random_ssse3() {
+        do_count_less_than_random::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn filter_to_bitmap_random_scalar() {
+        do_filter_to_bitmap_random::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn filter_to_bitmap_random_ssse3() {
+        do_filter_to_bitmap_random::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn filter_to_bitmap_each_op() {
+        let nums: Vec<u32> = vec![10, 20, 30, 40, 50, 20];
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+
+        let ones = |op| {
+            filter_to_bitmap::<Scalar>(&encoded, nums.len(), op)
+                .ones()
+                .collect::<Vec<usize>>()
+        };
+
+        assert_eq!(vec![1, 5], ones(CmpOp::Eq(20)));
+        assert_eq!(vec![0, 1, 5], ones(CmpOp::Lt(30)));
+        assert_eq!(vec![2, 3, 4], ones(CmpOp::Ge(30)));
+        assert_eq!(vec![1, 2, 3, 5], ones(CmpOp::Between(20, 40)));
+        assert_eq!(Vec::<usize>::new(), ones(CmpOp::Between(41, 49)));
+    }
+
+    #[test]
+    fn count_less_than_extreme_thresholds() {
+        let nums: Vec<u32> = vec![0, 1, 2, u32::MAX, 5, 6, 7];
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+
+        assert_eq!(0, count_less_than::<Scalar>(&encoded, nums.len(), 0));
+        assert_eq!(6, count_less_than::<Scalar>(&encoded, nums.len(), u32::MAX));
+        assert_eq!(0, count_less_than::<Scalar>(&[], 0, 100));
+    }
+
+    fn do_count_less_than_random<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 500);
+            let nums: Vec<u32> = (0..count).map(|_| rng.gen_range(0, 10_000)).collect();
+            let mut encoded = Vec::new();
+            encode_append::<Scalar>(&nums, &mut encoded);
+            let threshold = rng.gen_range(0, 10_001);
+
+            let expected = nums.iter().filter(|&&n| n < threshold).count();
+            assert_eq!(expected, count_less_than::<D>(&encoded, count, threshold));
+        }
+    }
+
+    fn do_filter_to_bitmap_random<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 500);
+            let nums: Vec<u32> = (0..count).map(|_| rng.gen_range(0, 100)).collect();
+            let mut encoded = Vec::new();
+            encode_append::<Scalar>(&nums, &mut encoded);
+            let a = rng.gen_range(0, 100);
+            let b = rng.gen_range(a, 100);
+
+            for &op in &[CmpOp::Eq(a), CmpOp::Lt(a), CmpOp::Ge(a), CmpOp::Between(a, b)] {
+                let bits = filter_to_bitmap::<D>(&encoded, count, op);
+
+                assert_eq!(count, bits.len());
+                for (i, &n) in nums.iter().enumerate() {
+                    assert_eq!(op.matches(n), bits.get(i), "index {} op {:?}", i, op);
+                }
+            }
+        }
+    }
+}
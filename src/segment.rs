@@ -0,0 +1,857 @@
+use std::cmp::{self, Ordering};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use decode::cursor::CursorPosition;
+use {encode_append, encoded_shape, is_canonical, validate, DecodeCursor, DecodeQuadSink,
+     DecodeSingleSink, Decoder, Scalar, SkipIndex};
+
+/// How many numbers to decode at a time when comparing or hashing segments.
+const BATCH_LEN: usize = 64;
+
+/// Encoded numbers in shared memory, along with their count and optionally a `SkipIndex`, so
+/// that one copy can serve any number of readers at once, on any thread.
+///
+/// Cloning a segment, or creating a cursor over it, only bumps a reference count. Each cursor has
+/// its own position, so readers don't need to coordinate with each other.
+///
+/// A segment made by `edit()` shares the memory of every quad it didn't change with the segment
+/// it was made from, so its numbers may be spread over several pieces of memory. Cursors move
+/// from one piece to the next as they go, and `to_bytes()` gathers them into one encoding.
+///
+/// Segments compare and hash by the numbers they hold, not how they're encoded, and order like
+/// `Vec<u32>`s of their numbers, so they can be used as map keys or sorted. Comparing segments
+/// with canonical encodings (see `is_canonical()`) in one piece of memory only needs to decode
+/// them to find the order, not to find whether they're equal.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use stream_vbyte::{encode, EncodedSegment, Scalar, SkipIndex};
+///
+/// let nums: Vec<u32> = (0..10_000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+/// encoded.truncate(encoded_len);
+///
+/// let index = SkipIndex::build(&encoded, nums.len());
+/// let segment = EncodedSegment::new(Arc::from(encoded), nums.len()).with_skip_index(index);
+///
+/// let readers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let mut cursor = segment.cursor_at(i * 2500);
+///         thread::spawn(move || {
+///             let mut decoded = vec![0; 10];
+///             cursor.decode_slice::<Scalar>(&mut decoded);
+///             decoded[0]
+///         })
+///     })
+///     .collect();
+///
+/// let firsts: Vec<u32> = readers.into_iter().map(|r| r.join().unwrap()).collect();
+/// assert_eq!(vec![0, 2_500_000, 5_000_000, 7_500_000], firsts);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EncodedSegment {
+    /// The segment's numbers, in order. There's always at least one piece, and none are empty
+    /// unless the segment is.
+    pieces: Arc<[Piece]>,
+    count: usize,
+}
+
+impl EncodedSegment {
+    /// Create a segment of the `count` numbers encoded in `input`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` is too short to hold `count` encoded numbers.
+    pub fn new(input: Arc<[u8]>, count: usize) -> EncodedSegment {
+        let encoded_len =
+            validate(&input, count).expect("Input is too short for the encoded numbers");
+
+        EncodedSegment {
+            pieces: Arc::from(vec![Piece::whole(input, count, encoded_len, None)]),
+            count,
+        }
+    }
+
+    /// Use `index` to position cursors created by `cursor_at()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` wasn't built for the same count of numbers, or if the segment was made
+    /// by `edit()` and is spread over several pieces of memory.
+    pub fn with_skip_index(mut self, index: SkipIndex) -> EncodedSegment {
+        assert_eq!(
+            self.count,
+            index.len(),
+            "Skip index must be for the same count of numbers"
+        );
+
+        let mut piece = match *self.pieces {
+            [ref piece] if piece.count == piece.chunk_count => piece.clone(),
+            _ => panic!("Skip index must be added before editing"),
+        };
+        piece.skip_index = Some(Arc::new(index));
+        self.pieces = Arc::from(vec![piece]);
+
+        self
+    }
+
+    /// The number of numbers in the segment.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The encoded numbers, gathered from every piece of memory they're in.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for piece in self.pieces.iter() {
+            bytes.extend_from_slice(piece.control_bytes());
+        }
+        for piece in self.pieces.iter() {
+            bytes.extend_from_slice(piece.encoded_nums());
+        }
+
+        bytes
+    }
+
+    /// Whether the segment has a skip index, set with `with_skip_index()` or kept by `edit()`.
+    pub fn is_indexed(&self) -> bool {
+        self.pieces.iter().any(|p| p.skip_index.is_some())
+    }
+
+    /// Returns a cursor at the first number.
+    pub fn cursor(&self) -> SegmentCursor {
+        SegmentCursor {
+            pieces: self.pieces.clone(),
+            piece: 0,
+            position: CursorPosition::default(),
+        }
+    }
+
+    /// Returns a cursor positioned at number `index`, which must be a multiple of 4 and no more
+    /// than the count of numbers.
+    ///
+    /// With a skip index, this scans at most one entry's worth of control bytes; without one, it
+    /// scans every control byte before `index` in the same piece of memory.
+    pub fn cursor_at(&self, index: usize) -> SegmentCursor {
+        assert_eq!(index % 4, 0, "Must be a multiple of 4");
+        assert!(index <= self.count, "Index out of range");
+
+        // the last piece starting at or before `index`
+        let piece = match self.pieces.binary_search_by_key(&index, |p| p.start) {
+            Ok(piece) => piece,
+            Err(next) => next - 1,
+        };
+
+        let mut cursor = SegmentCursor {
+            pieces: self.pieces.clone(),
+            piece,
+            position: self.pieces[piece].position_at(index - self.pieces[piece].start),
+        };
+        cursor.next_piece_if_done();
+
+        cursor
+    }
+
+    /// Returns a new segment with the numbers starting at `start` replaced by `values`, leaving
+    /// this segment as it was, so that readers of it keep seeing the old numbers.
+    ///
+    /// Only the quads that `values` overlap are decoded and re-encoded, into new memory. The new
+    /// segment shares the encoded bytes of all other quads with this one rather than copying
+    /// them, so an edit only allocates in proportion to `values`, however large the segment is.
+    /// If this segment has a skip index, the re-encoded quads get an index with the same spacing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use stream_vbyte::{encode, EncodedSegment, Scalar};
+    ///
+    /// let nums: Vec<u32> = (0..100).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+    /// encoded.truncate(encoded_len);
+    ///
+    /// let v1 = EncodedSegment::new(Arc::from(encoded), nums.len());
+    /// let v2 = v1.edit(50, &[1_000_000, 1_000_001]);
+    ///
+    /// let mut decoded = vec![0; 4];
+    /// v1.cursor_at(48).decode_slice::<Scalar>(&mut decoded);
+    /// assert_eq!(vec![48, 49, 50, 51], decoded);
+    /// v2.cursor_at(48).decode_slice::<Scalar>(&mut decoded);
+    /// assert_eq!(vec![48, 49, 1_000_000, 1_000_001], decoded);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` would extend past the end of the segment.
+    pub fn edit(&self, start: usize, values: &[u32]) -> EncodedSegment {
+        let end = start + values.len();
+        assert!(end <= self.count, "Edit must be within the segment");
+        if values.is_empty() {
+            return self.clone();
+        }
+
+        // the affected quads
+        let first = start / 4 * 4;
+        let last = cmp::min((end + 3) / 4 * 4, self.count);
+
+        let mut nums = vec![0; last - first];
+        let decoded = self.cursor_at(first).decode_slice::<Scalar>(&mut nums);
+        debug_assert_eq!(nums.len(), decoded);
+        nums[(start - first)..(end - first)].copy_from_slice(values);
+
+        let mut encoded = Vec::new();
+        let encoded_len = encode_append::<Scalar>(&nums, &mut encoded);
+        let skip_index = self.pieces
+            .iter()
+            .filter_map(|p| p.skip_index.as_ref())
+            .next()
+            .map(|index| {
+                Arc::new(
+                    SkipIndex::builder()
+                        .quads_per_entry(index.quads_per_entry())
+                        .fences(index.fences().is_some())
+                        .build(&encoded, nums.len()),
+                )
+            });
+        let mut region = Some(Piece::whole(
+            Arc::from(encoded),
+            nums.len(),
+            encoded_len,
+            skip_index,
+        ));
+
+        let mut pieces = Vec::with_capacity(self.pieces.len() + 2);
+        for piece in self.pieces.iter() {
+            let piece_end = piece.start + piece.count;
+            if piece_end <= first || piece.start >= last {
+                pieces.push(piece.clone());
+                continue;
+            }
+
+            // keep the quads on either side of the edit
+            if piece.start < first {
+                pieces.push(piece.slice(0, first - piece.start));
+            }
+            if piece.start <= first {
+                pieces.extend(region.take());
+            }
+            if piece_end > last {
+                pieces.push(piece.slice(last - piece.start, piece.count));
+            }
+        }
+
+        let mut piece_start = 0;
+        for piece in &mut pieces {
+            piece.start = piece_start;
+            piece_start += piece.count;
+        }
+
+        EncodedSegment {
+            pieces: Arc::from(pieces),
+            count: self.count,
+        }
+    }
+
+    /// The encoded numbers, without anything after them, if they're all in one piece of memory.
+    fn contiguous(&self) -> Option<&[u8]> {
+        match *self.pieces {
+            [ref piece] if piece.count == piece.chunk_count => {
+                Some(&piece.chunk[0..piece.nums_end])
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for EncodedSegment {
+    fn eq(&self, other: &EncodedSegment) -> bool {
+        if self.count != other.count {
+            return false;
+        }
+        if let (Some(encoded), Some(other_encoded)) = (self.contiguous(), other.contiguous()) {
+            if encoded == other_encoded {
+                return true;
+            }
+            // there's only one canonical encoding of any numbers
+            if is_canonical(encoded, self.count) && is_canonical(other_encoded, other.count) {
+                return false;
+            }
+        }
+
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for EncodedSegment {}
+
+impl PartialOrd for EncodedSegment {
+    fn partial_cmp(&self, other: &EncodedSegment) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EncodedSegment {
+    fn cmp(&self, other: &EncodedSegment) -> Ordering {
+        let mut cursor = self.cursor();
+        let mut other_cursor = other.cursor();
+        let mut batch = [0; BATCH_LEN];
+        let mut other_batch = [0; BATCH_LEN];
+
+        loop {
+            let len = cursor.decode_slice::<Scalar>(&mut batch);
+            let other_len = other_cursor.decode_slice::<Scalar>(&mut other_batch);
+
+            match batch[0..len].cmp(&other_batch[0..other_len]) {
+                Ordering::Equal if len == BATCH_LEN && other_len == BATCH_LEN => {}
+                ordering => return ordering,
+            }
+        }
+    }
+}
+
+impl Hash for EncodedSegment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.count.hash(state);
+
+        let mut cursor = self.cursor();
+        let mut batch = [0; BATCH_LEN];
+        loop {
+            let len = cursor.decode_slice::<Scalar>(&mut batch);
+            u32::hash_slice(&batch[0..len], state);
+            if len < BATCH_LEN {
+                break;
+            }
+        }
+    }
+}
+
+/// A cursor over an `EncodedSegment`, created by `cursor()` or `cursor_at()`.
+///
+/// It holds a reference to the segment's memory rather than borrowing the segment, so it can be
+/// sent to another thread or outlive the segment.
+#[derive(Debug, Clone)]
+pub struct SegmentCursor {
+    pieces: Arc<[Piece]>,
+    /// The piece the cursor is in. It only stays at the end of a piece if that's the last one.
+    piece: usize,
+    /// The position in the current piece.
+    position: CursorPosition,
+}
+
+impl SegmentCursor {
+    /// See `DecodeCursor.skip()`.
+    pub fn skip(&mut self, to_skip: usize) {
+        let mut to_skip = to_skip;
+        while to_skip > 0 && self.piece + 1 < self.pieces.len() {
+            let left = self.pieces[self.piece].count - self.position.nums_decoded;
+            if to_skip < left {
+                break;
+            }
+            to_skip -= left;
+            self.piece += 1;
+            self.position = CursorPosition::default();
+        }
+
+        self.with_cursor(|c| c.skip(to_skip))
+    }
+
+    /// See `DecodeCursor.decode_slice()`. Unlike that, this carries on from one piece of the
+    /// segment's memory to the next, until `output` is full, there are no numbers left, or
+    /// there isn't room for the next quad.
+    pub fn decode_slice<D: Decoder>(&mut self, output: &mut [u32]) -> usize {
+        let mut decoded = 0;
+        while decoded < output.len() && self.has_more() {
+            let piece_decoded = self.with_cursor(|c| c.decode_slice::<D>(&mut output[decoded..]));
+            if piece_decoded == 0 {
+                break;
+            }
+            decoded += piece_decoded;
+        }
+
+        decoded
+    }
+
+    /// See `DecodeCursor.decode_sink()`. Unlike that, this carries on from one piece of the
+    /// segment's memory to the next, until `max_numbers_to_decode` are decoded, there are no
+    /// numbers left, or the sink won't take more. The indices the sink is handed count from the
+    /// start of the call, whichever piece the numbers are in.
+    pub fn decode_sink<D, S>(&mut self, sink: &mut S, max_numbers_to_decode: usize) -> usize
+    where
+        D: Decoder,
+        S: DecodeQuadSink<D::DecodedQuad> + DecodeQuadSink<<Scalar as Decoder>::DecodedQuad>,
+    {
+        let mut decoded = 0;
+        while decoded < max_numbers_to_decode && self.has_more() {
+            let mut offset_sink = OffsetSink {
+                sink: &mut *sink,
+                offset: decoded,
+            };
+            let piece_decoded = self.with_cursor(|c| {
+                c.decode_sink::<D, _>(&mut offset_sink, max_numbers_to_decode - decoded)
+            });
+            if piece_decoded == 0 {
+                break;
+            }
+            decoded += piece_decoded;
+        }
+
+        decoded
+    }
+
+    /// See `DecodeCursor.has_more()`.
+    pub fn has_more(&self) -> bool {
+        self.cursor().has_more()
+    }
+
+    /// A borrowing cursor at the same position in the current piece.
+    fn cursor<'a>(&'a self) -> DecodeCursor<'a> {
+        let mut cursor = self.pieces[self.piece].cursor();
+        cursor.set_position(self.position);
+
+        cursor
+    }
+
+    fn with_cursor<R, F: FnOnce(&mut DecodeCursor) -> R>(&mut self, f: F) -> R {
+        let mut cursor = self.cursor();
+        let result = f(&mut cursor);
+        self.position = cursor.position();
+        self.next_piece_if_done();
+
+        result
+    }
+
+    fn next_piece_if_done(&mut self) {
+        if self.position.nums_decoded == self.pieces[self.piece].count
+            && self.piece + 1 < self.pieces.len()
+        {
+            self.piece += 1;
+            self.position = CursorPosition::default();
+        }
+    }
+}
+
+/// Passes numbers on to `sink` with `offset` added to their indices, so that sinks see the
+/// indices continue from one piece to the next within one `SegmentCursor.decode_sink()` call.
+struct OffsetSink<'a, S: 'a> {
+    sink: &'a mut S,
+    offset: usize,
+}
+
+impl<'a, S: DecodeSingleSink> DecodeSingleSink for OffsetSink<'a, S> {
+    #[inline]
+    fn on_number(&mut self, num: u32, nums_decoded: usize) {
+        self.sink.on_number(num, self.offset + nums_decoded);
+    }
+
+    fn capacity_hint(&self) -> Option<usize> {
+        self.sink.capacity_hint()
+    }
+}
+
+impl<'a, Q, S: DecodeQuadSink<Q>> DecodeQuadSink<Q> for OffsetSink<'a, S> {
+    #[inline]
+    fn on_quad(&mut self, quad: Q, nums_decoded: usize) {
+        self.sink.on_quad(quad, self.offset + nums_decoded);
+    }
+}
+
+/// A run of a segment's numbers, encoded in a chunk of memory that other segments made by
+/// `edit()` may share.
+#[derive(Debug, Clone)]
+struct Piece {
+    /// Encoded numbers, of which the piece's are a run of whole quads.
+    chunk: Arc<[u8]>,
+    /// The count of numbers encoded in `chunk`.
+    chunk_count: usize,
+    /// An index over all of `chunk`.
+    skip_index: Option<Arc<SkipIndex>>,
+    /// The index in `chunk` of the piece's first number, a multiple of 4.
+    first: usize,
+    /// The count of the piece's numbers. Only the segment's last piece may end in a partial quad.
+    count: usize,
+    /// Where in `chunk` the piece's encoded numbers (not control bytes) start and end.
+    nums_start: usize,
+    nums_end: usize,
+    /// The index in the segment of the piece's first number.
+    start: usize,
+}
+
+impl Piece {
+    /// A piece of all `count` numbers in `chunk`, which must be valid.
+    fn whole(
+        chunk: Arc<[u8]>,
+        count: usize,
+        encoded_len: usize,
+        skip_index: Option<Arc<SkipIndex>>,
+    ) -> Piece {
+        Piece {
+            chunk,
+            chunk_count: count,
+            skip_index,
+            first: 0,
+            count,
+            nums_start: encoded_shape(count).control_bytes_len,
+            nums_end: encoded_len,
+            start: 0,
+        }
+    }
+
+    fn control_bytes(&self) -> &[u8] {
+        let start = self.first / 4;
+        &self.chunk[start..(start + encoded_shape(self.count).control_bytes_len)]
+    }
+
+    fn encoded_nums(&self) -> &[u8] {
+        &self.chunk[self.nums_start..self.nums_end]
+    }
+
+    fn cursor<'a>(&'a self) -> DecodeCursor<'a> {
+        DecodeCursor::from_parts(self.control_bytes(), self.encoded_nums(), self.count)
+    }
+
+    /// The position of a cursor over the piece at its number `index`, a multiple of 4.
+    fn position_at(&self, index: usize) -> CursorPosition {
+        let mut cursor = self.cursor();
+        if let Some(ref skip_index) = self.skip_index {
+            let entry = skip_index.entry_position(self.first + index);
+            // entries before the piece starts are no help
+            if entry.nums_decoded > self.first {
+                let chunk_nums_start = encoded_shape(self.chunk_count).control_bytes_len;
+                cursor.set_position(CursorPosition {
+                    nums_decoded: entry.nums_decoded - self.first,
+                    control_bytes_read: entry.control_bytes_read - self.first / 4,
+                    encoded_bytes_read: chunk_nums_start + entry.encoded_bytes_read
+                        - self.nums_start,
+                });
+            }
+        }
+        let nums_decoded = cursor.position().nums_decoded;
+        cursor.skip(index - nums_decoded);
+
+        cursor.position()
+    }
+
+    /// The piece's numbers from `from`, a multiple of 4, to `to`, which must also be a multiple
+    /// of 4 unless it's the end of the piece, in the same chunk.
+    fn slice(&self, from: usize, to: usize) -> Piece {
+        let offset = |index| {
+            if index == self.count {
+                self.nums_end
+            } else {
+                self.nums_start + self.position_at(index).encoded_bytes_read
+            }
+        };
+
+        Piece {
+            first: self.first + from,
+            count: to - from,
+            nums_start: offset(from),
+            nums_end: offset(to),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use std::collections::hash_map::DefaultHasher;
+    use std::thread;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn cursors_at_every_position_with_and_without_index() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..20 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 1000))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; nums.len() * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            let index = SkipIndex::builder()
+                .quads_per_entry(rng.gen_range(1, 10))
+                .build(&encoded, nums.len());
+            let plain = EncodedSegment::new(Arc::from(encoded), nums.len());
+            let indexed = plain.clone().with_skip_index(index);
+
+            for start in (0..(nums.len() / 4 + 1)).map(|q| q * 4) {
+                for segment in &[&plain, &indexed] {
+                    let mut cursor = segment.cursor_at(start);
+                    let mut decoded = vec![0; nums.len() - start];
+                    assert_eq!(decoded.len(), cursor.decode_slice::<Scalar>(&mut decoded));
+                    assert_eq!(&nums[start..], &decoded[..]);
+                    assert!(!cursor.has_more());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cursors_are_independent_across_threads() {
+        let nums: Vec<u32> = (0..4000).map(|i| i * 7919).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        encoded.truncate(encoded_len);
+        let segment = EncodedSegment::new(Arc::from(encoded), nums.len());
+
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let segment = segment.clone();
+                thread::spawn(move || {
+                    let mut decoded = vec![0; segment.len()];
+                    segment.cursor().decode_slice::<Scalar>(&mut decoded);
+                    decoded
+                })
+            })
+            .collect();
+
+        for reader in readers {
+            assert_eq!(nums, reader.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn random_edits() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let mut nums: Vec<u32> = (0..rng.gen_range(0, 300))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; nums.len() * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            let mut segment = EncodedSegment::new(Arc::from(&encoded[..]), nums.len());
+            if rng.gen() {
+                let index = SkipIndex::builder()
+                    .quads_per_entry(rng.gen_range(1, 4))
+                    .fences(rng.gen())
+                    .build(&encoded, nums.len());
+                segment = segment.with_skip_index(index);
+            }
+            let original = segment.clone();
+
+            // edit the edits, so that they're spread over more and more pieces
+            for _ in 0..rng.gen_range(1, 6) {
+                let start = rng.gen_range(0, nums.len() + 1);
+                let len = rng.gen_range(0, nums.len() - start + 1);
+                let values: Vec<u32> = (0..len)
+                    .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                    .collect();
+                segment = segment.edit(start, &values);
+                nums[start..(start + len)].copy_from_slice(&values);
+
+                let mut expected = vec![0; nums.len() * 5];
+                let expected_len = encode::<Scalar>(&nums, &mut expected);
+                assert_eq!(&expected[0..expected_len], &segment.to_bytes()[..]);
+                assert_eq!(original.is_indexed(), segment.is_indexed());
+
+                for index in (0..(nums.len() / 4 + 1)).map(|q| q * 4) {
+                    let mut skipped = segment.cursor();
+                    skipped.skip(index);
+
+                    for mut cursor in vec![segment.cursor_at(index), skipped] {
+                        let mut decoded = vec![0; nums.len() - index];
+                        assert_eq!(decoded.len(), cursor.decode_slice::<Scalar>(&mut decoded));
+                        assert_eq!(&nums[index..], &decoded[..]);
+                        assert!(!cursor.has_more());
+                    }
+                }
+            }
+
+            // decoding a few at a time stops short of quads that don't fit, piece or no piece
+            let mut cursor = segment.cursor();
+            let mut decoded = Vec::new();
+            let mut buf = [0; 6];
+            while cursor.has_more() {
+                let len = cursor.decode_slice::<Scalar>(&mut buf);
+                assert!(len > 0);
+                decoded.extend_from_slice(&buf[0..len]);
+            }
+            assert_eq!(nums, decoded);
+
+            assert_eq!(encoded, original.to_bytes());
+        }
+    }
+
+    #[test]
+    fn decode_sink_indices_continue_across_pieces() {
+        let nums: Vec<u32> = (0..40).collect();
+        let edited = segment_of(&nums, false).edit(20, &[1000]);
+        assert_eq!(3, edited.pieces.len());
+
+        let mut sink = IndexSink(Vec::new());
+        assert_eq!(40, edited.cursor().decode_sink::<Scalar, _>(&mut sink, 40));
+
+        let expected: Vec<(usize, u32)> = (0..40)
+            .map(|i| (i, if i == 20 { 1000 } else { i as u32 }))
+            .collect();
+        assert_eq!(expected, sink.0);
+
+        // indices count from where each call starts
+        let mut sink = IndexSink(Vec::new());
+        assert_eq!(12, edited.cursor_at(16).decode_sink::<Scalar, _>(&mut sink, 12));
+        let shifted: Vec<(usize, u32)> = sink.0.iter().map(|&(i, num)| (i + 16, num)).collect();
+        assert_eq!(&expected[16..28], &shifted[..]);
+    }
+
+    /// Records each number with the index it was handed.
+    struct IndexSink(Vec<(usize, u32)>);
+
+    impl DecodeSingleSink for IndexSink {
+        fn on_number(&mut self, num: u32, nums_decoded: usize) {
+            self.0.push((nums_decoded, num));
+        }
+    }
+
+    impl DecodeQuadSink<()> for IndexSink {
+        fn on_quad(&mut self, _quad: (), _nums_decoded: usize) {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn edits_share_unchanged_bytes() {
+        let nums: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+        let segment = segment_of(&nums, false);
+
+        let edited = segment.edit(502, &[7, 8, 9]);
+
+        assert_eq!(3, edited.pieces.len());
+        assert!(Arc::ptr_eq(&segment.pieces[0].chunk, &edited.pieces[0].chunk));
+        assert!(Arc::ptr_eq(&segment.pieces[0].chunk, &edited.pieces[2].chunk));
+        // only the quads of numbers 500 to 507 were re-encoded
+        assert_eq!(8, edited.pieces[1].count);
+        assert!(edited.pieces[1].chunk.len() <= 2 + 8 * 4);
+
+        // editing the edit only replaces the piece it touches
+        let edited_again = edited.edit(0, &[1]);
+        assert_eq!(4, edited_again.pieces.len());
+        assert!(Arc::ptr_eq(&edited.pieces[1].chunk, &edited_again.pieces[2].chunk));
+        assert!(Arc::ptr_eq(&segment.pieces[0].chunk, &edited_again.pieces[1].chunk));
+    }
+
+    #[test]
+    fn compares_and_hashes_by_numbers() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let a = random_nums(&mut rng);
+            let b = if rng.gen() {
+                a.clone()
+            } else {
+                random_nums(&mut rng)
+            };
+            let segment_a = segment_of(&a, rng.gen());
+            let mut segment_b = segment_of(&b, rng.gen());
+            if rng.gen() {
+                // the same numbers, in several pieces of memory
+                let start = rng.gen_range(0, b.len() + 1);
+                segment_b = segment_b.edit(start, &b[start..(start + (b.len() - start) / 2)]);
+            }
+
+            assert_eq!(a == b, segment_a == segment_b);
+            assert_eq!(a.cmp(&b), segment_a.cmp(&segment_b));
+            if a == b {
+                assert_eq!(hash_of(&segment_a), hash_of(&segment_b));
+            }
+        }
+    }
+
+    #[test]
+    fn sorts_like_vecs() {
+        let mut rng = rand::weak_rng();
+        let mut vecs: Vec<Vec<u32>> = (0..100).map(|_| random_nums(&mut rng)).collect();
+        let mut segments: Vec<EncodedSegment> = vecs.iter().map(|v| segment_of(v, false)).collect();
+
+        vecs.sort();
+        segments.sort();
+
+        assert_eq!(
+            vecs.iter().map(|v| segment_of(v, false)).collect::<Vec<_>>(),
+            segments
+        );
+    }
+
+    fn random_nums<R: Rng>(rng: &mut R) -> Vec<u32> {
+        // short and small, so that some are prefixes of others
+        (0..rng.gen_range(0, 150))
+            .map(|_| rng.gen_range(0, 3) << (rng.gen_range(0, 4) * 8))
+            .collect()
+    }
+
+    /// A segment of `nums`, optionally with every number encoded in 4 bytes.
+    fn segment_of(nums: &[u32], non_canonical: bool) -> EncodedSegment {
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(nums, &mut encoded);
+        encoded.truncate(encoded_len);
+
+        if non_canonical {
+            let control_bytes_len = encoded_shape(nums.len()).control_bytes_len;
+            encoded = vec![0xFF; control_bytes_len];
+            let leftover = nums.len() % 4;
+            if leftover > 0 {
+                encoded[control_bytes_len - 1] = 0xFF >> ((4 - leftover) * 2);
+            }
+            for &num in nums {
+                encoded.extend_from_slice(&[
+                    num as u8,
+                    (num >> 8) as u8,
+                    (num >> 16) as u8,
+                    (num >> 24) as u8,
+                ]);
+            }
+        }
+
+        EncodedSegment::new(Arc::from(encoded), nums.len())
+    }
+
+    fn hash_of(segment: &EncodedSegment) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        segment.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    #[should_panic(expected = "Skip index must be for the same count of numbers")]
+    fn mismatched_skip_index_panics() {
+        let mut encoded = vec![0; 100];
+        encode::<Scalar>(&[1; 20], &mut encoded);
+        let index = SkipIndex::build(&encoded, 16);
+
+        EncodedSegment::new(Arc::from(encoded), 20).with_skip_index(index);
+    }
+
+    #[test]
+    #[should_panic(expected = "Skip index must be added before editing")]
+    fn skip_index_after_edit_panics() {
+        let mut encoded = vec![0; 100];
+        let encoded_len = encode::<Scalar>(&[1; 20], &mut encoded);
+        let index = SkipIndex::build(&encoded[0..encoded_len], 20);
+
+        EncodedSegment::new(Arc::from(encoded), 20)
+            .edit(8, &[2])
+            .with_skip_index(index);
+    }
+}
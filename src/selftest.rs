@@ -0,0 +1,208 @@
+use std::error;
+use std::fmt;
+
+use {available_codecs, bench_decode, bench_encode, decode_auto, encode, global_decoder,
+     CodecKind, Scalar};
+
+/// The first disagreement found by `verify_cross_codec()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The `CodecInfo` name of the encoder.
+    pub encoder: &'static str,
+    /// The `CodecInfo` name of the decoder.
+    pub decoder: &'static str,
+    /// The index of the first number that didn't decode to its original value, or the count of
+    /// numbers if they all did, but the decoder read a different number of bytes than the encoder
+    /// wrote.
+    pub index: usize,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Encoding with {} and decoding with {} failed at index {}",
+            self.encoder, self.decoder, self.index
+        )
+    }
+}
+
+impl error::Error for Mismatch {}
+
+/// Encode `nums` with every encoder in `available_codecs()` that the current CPU supports, decode
+/// each result with every supported decoder, and check that the numbers survive every round trip.
+///
+/// This is meant as a self-test at startup, to check that the SIMD implementations work on the
+/// hardware at hand before trusting them with real data. Pairs are checked in the order of
+/// `available_codecs()`, so the same input always reports the same mismatch.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::verify_cross_codec;
+///
+/// let nums: Vec<u32> = (0..1000).map(|i| i * 7919).collect();
+/// verify_cross_codec(&nums).expect("Codecs disagree on this hardware");
+/// ```
+pub fn verify_cross_codec(nums: &[u32]) -> Result<(), Mismatch> {
+    let supported = || available_codecs().iter().filter(|c| c.is_supported());
+    let mut encoded = vec![0; nums.len() * 5];
+    let mut decoded = vec![0; nums.len()];
+
+    for encoder in supported().filter(|c| c.kind == CodecKind::Encoder) {
+        let encoded_len = bench_encode(encoder, nums, &mut encoded);
+
+        for decoder in supported().filter(|c| c.kind == CodecKind::Decoder) {
+            for d in decoded.iter_mut() {
+                *d = 0;
+            }
+            let bytes_read =
+                bench_decode(decoder, &encoded[0..encoded_len], nums.len(), &mut decoded);
+
+            let mut mismatch_index = nums.iter().zip(decoded.iter()).position(|(n, d)| n != d);
+            if mismatch_index.is_none() && bytes_read != encoded_len {
+                mismatch_index = Some(nums.len());
+            }
+
+            if let Some(index) = mismatch_index {
+                return Err(Mismatch {
+                    encoder: encoder.name,
+                    decoder: decoder.name,
+                    index,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Why `self_test()` failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestError {
+    /// An encoder and decoder disagreed.
+    CodecMismatch(Mismatch),
+    /// `decode_auto()`, with the decoder named here, didn't decode correctly.
+    AutoDecoder(&'static str),
+}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SelfTestError::CodecMismatch(ref mismatch) => mismatch.fmt(f),
+            SelfTestError::AutoDecoder(decoder) => {
+                write!(f, "decode_auto() with {} failed", decoder)
+            }
+        }
+    }
+}
+
+impl error::Error for SelfTestError {}
+
+/// Run a fixed battery of inputs through every encoder and decoder the current CPU supports, and
+/// through `decode_auto()`, to check they all work before trusting them with real data.
+///
+/// The battery covers every control byte, each with numbers at both ends of the range for their
+/// lengths, in inputs with counts around the boundaries where decoders switch between SIMD and
+/// scalar code. It takes well under a millisecond, so it's cheap to call once at startup in a
+/// deployment that enables `target_feature`s.
+///
+/// # Examples
+///
+/// ```
+/// stream_vbyte::self_test().expect("Stream VByte doesn't work on this hardware");
+/// ```
+pub fn self_test() -> Result<(), SelfTestError> {
+    let nums = every_control_byte();
+
+    for &count in &[
+        0, 1, 2, 3, 4, 5, 7, 8, 9, 15, 16, 17, 31, 32, 33, 63, 64, 65, 127, 128, 129
+    ] {
+        verify_cross_codec(&nums[0..count]).map_err(SelfTestError::CodecMismatch)?;
+    }
+    verify_cross_codec(&nums).map_err(SelfTestError::CodecMismatch)?;
+
+    let mut encoded = vec![0; nums.len() * 5];
+    let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+    let mut decoded = vec![0; nums.len()];
+    if decode_auto(&encoded[0..encoded_len], nums.len(), &mut decoded) != encoded_len
+        || decoded != nums
+    {
+        return Err(SelfTestError::AutoDecoder(global_decoder().name));
+    }
+
+    Ok(())
+}
+
+/// A quad for every control byte, alternating between the smallest and largest numbers of each
+/// length.
+fn every_control_byte() -> Vec<u32> {
+    let mut nums = Vec::new();
+
+    for control_byte in 0..256_usize {
+        for i in 0..4 {
+            let len = ((control_byte >> (i * 2)) & 0x03) + 1;
+            let num = if control_byte % 2 == 1 {
+                (u64::MAX >> (64 - len * 8)) as u32
+            } else if len == 1 {
+                0
+            } else {
+                1 << ((len - 1) * 8)
+            };
+            nums.push(num);
+        }
+    }
+
+    nums
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn enabled_codecs_agree_on_random_input() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let nums: Vec<u32> = (0..rng.gen_range(0, 500))
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+
+            assert_eq!(Ok(()), verify_cross_codec(&nums));
+        }
+    }
+
+    #[test]
+    fn self_test_passes() {
+        assert_eq!(Ok(()), self_test());
+    }
+
+    #[test]
+    fn battery_covers_every_control_byte() {
+        let nums = every_control_byte();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let control_bytes: Vec<usize> = encoded[0..256].iter().map(|&b| b as usize).collect();
+        assert_eq!((0..256).collect::<Vec<usize>>(), control_bytes);
+    }
+
+    #[test]
+    fn mismatch_display() {
+        let mismatch = Mismatch {
+            encoder: "x86::Sse41",
+            decoder: "Scalar",
+            index: 17,
+        };
+
+        assert_eq!(
+            "Encoding with x86::Sse41 and decoding with Scalar failed at index 17",
+            mismatch.to_string()
+        );
+    }
+}
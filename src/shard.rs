@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use {cumulative_encoded_len, encoded_shape, lengths};
+
+/// Split `count` numbers encoded in `input` into at most `num_shards` pieces of roughly equal
+/// length that can be decoded independently, looking only at control bytes.
+///
+/// Each shard is a tuple of the range of `input` holding its control bytes, the range of `input`
+/// holding its encoded numbers, and how many numbers it contains. Pass the corresponding parts of
+/// `input` to `DecodeCursor::from_parts()` to decode a shard.
+///
+/// Shards are split on quad boundaries, so there are fewer than `num_shards` shards if there
+/// aren't enough quads to go around. Empty input produces no shards.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(all(feature = "encode", feature = "decode"))] {
+/// use stream_vbyte::{encode, shard, DecodeCursor, Scalar};
+///
+/// let nums: Vec<u32> = (0..100).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// encode::<Scalar>(&nums, &mut encoded);
+///
+/// let mut decoded = Vec::new();
+/// for (control_bytes, encoded_nums, count) in shard(&encoded, nums.len(), 3) {
+///     let mut cursor =
+///         DecodeCursor::from_parts(&encoded[control_bytes], &encoded[encoded_nums], count);
+///     let mut buf = vec![0; count];
+///     cursor.decode_slice::<Scalar>(&mut buf);
+///     decoded.extend_from_slice(&buf);
+/// }
+///
+/// assert_eq!(nums, decoded);
+/// # }
+/// ```
+pub fn shard(
+    input: &[u8],
+    count: usize,
+    num_shards: usize,
+) -> Vec<(Range<usize>, Range<usize>, usize)> {
+    assert!(num_shards > 0, "Must have at least one shard");
+
+    let shape = encoded_shape(count);
+    let control_bytes = &input[0..shape.control_bytes_len];
+
+    let total_quads = shape.control_bytes_len;
+    let quads_per_shard = total_quads / num_shards;
+    // the first `extra_quads` shards get one more quad than the rest
+    let extra_quads = total_quads % num_shards;
+
+    let mut shards = Vec::with_capacity(num_shards);
+    let mut quad_start = 0;
+    let mut encoded_start = shape.control_bytes_len;
+
+    for shard_index in 0..num_shards {
+        let shard_quads = quads_per_shard + if shard_index < extra_quads { 1 } else { 0 };
+        if shard_quads == 0 {
+            break;
+        }
+
+        let quad_end = quad_start + shard_quads;
+
+        let (shard_count, encoded_len) = if quad_end == total_quads && shape.leftover_numbers > 0 {
+            // trailing partial quad: only count the lengths of numbers actually present
+            let control_byte = control_bytes[quad_end - 1];
+            let partial_len = lengths::partial_quad_len(control_byte, shape.leftover_numbers);
+
+            (
+                (shard_quads - 1) * 4 + shape.leftover_numbers,
+                cumulative_encoded_len(&control_bytes[quad_start..(quad_end - 1)]) + partial_len,
+            )
+        } else {
+            (
+                shard_quads * 4,
+                cumulative_encoded_len(&control_bytes[quad_start..quad_end]),
+            )
+        };
+
+        shards.push((
+            quad_start..quad_end,
+            encoded_start..(encoded_start + encoded_len),
+            shard_count,
+        ));
+
+        quad_start = quad_end;
+        encoded_start += encoded_len;
+    }
+
+    shards
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, DecodeCursor, Scalar};
+
+    #[test]
+    fn shards_are_contiguous_and_decode_to_input() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let count = rng.gen_range(0, 300);
+            let num_shards = rng.gen_range(1, 20);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            let shards = shard(&encoded, count, num_shards);
+            assert!(shards.len() <= num_shards);
+
+            let mut decoded = Vec::new();
+            let mut next_control_byte = 0;
+            let mut next_encoded_byte = encoded_shape(count).control_bytes_len;
+            for (control_bytes, encoded_nums, shard_count) in shards {
+                assert!(shard_count > 0);
+                assert_eq!(next_control_byte, control_bytes.start);
+                assert_eq!(next_encoded_byte, encoded_nums.start);
+                next_control_byte = control_bytes.end;
+                next_encoded_byte = encoded_nums.end;
+
+                let mut cursor = DecodeCursor::from_parts(
+                    &encoded[control_bytes],
+                    &encoded[encoded_nums],
+                    shard_count,
+                );
+                let mut buf = vec![0; shard_count + 4];
+                assert_eq!(shard_count, cursor.decode_slice::<Scalar>(&mut buf));
+                decoded.extend_from_slice(&buf[0..shard_count]);
+            }
+
+            assert_eq!(encoded_len, next_encoded_byte);
+            assert_eq!(nums, decoded);
+        }
+    }
+
+    #[test]
+    fn fewer_quads_than_shards() {
+        let nums: Vec<u32> = (0..9).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let counts: Vec<usize> = shard(&encoded, nums.len(), 5)
+            .into_iter()
+            .map(|(_, _, c)| c)
+            .collect();
+
+        assert_eq!(vec![4, 4, 1], counts);
+    }
+
+    #[test]
+    fn empty_input_no_shards() {
+        assert!(shard(&[], 0, 4).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! Encoding of single numbers outside of a quad, for protocols that embed the occasional
+//! standalone number alongside encoded streams.
+
+#[cfg(feature = "decode")]
+use decode::decode_num_scalar;
+#[cfg(feature = "encode")]
+use encode::encode_num_scalar;
+
+/// The number of bytes `encode_num()` uses for `num`, from 1 to 4.
+pub fn encoded_num_len(num: u32) -> usize {
+    if num < 1 << 8 {
+        1
+    } else if num < 1 << 16 {
+        2
+    } else if num < 1 << 24 {
+        3
+    } else {
+        4
+    }
+}
+
+/// Encode `num` into the start of `output` the same way numbers are encoded within a quad: as its
+/// `encoded_num_len(num)` least significant bytes, in little-endian order, on every platform.
+///
+/// The length isn't stored, so the caller must record it somehow (as a quad's control byte does)
+/// to pass to `decode_num()`.
+///
+/// Returns the number of bytes written.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "decode")] {
+/// use stream_vbyte::{decode_num, encode_num};
+///
+/// let mut buf = [0; 4];
+/// assert_eq!(3, encode_num(0x01_02_03, &mut buf));
+/// assert_eq!([0x03, 0x02, 0x01, 0], buf);
+/// assert_eq!(0x01_02_03, decode_num(3, &buf));
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `output` is shorter than `encoded_num_len(num)`.
+#[cfg(feature = "encode")]
+pub fn encode_num(num: u32, output: &mut [u8]) -> usize {
+    try_encode_num(num, output).expect("Output is too short for the encoded number")
+}
+
+/// Like `encode_num()`, but returns `None` rather than panicking if `output` is too short.
+#[cfg(feature = "encode")]
+pub fn try_encode_num(num: u32, output: &mut [u8]) -> Option<usize> {
+    if output.len() < encoded_num_len(num) {
+        return None;
+    }
+
+    Some(encode_num_scalar(num, output))
+}
+
+/// Decode a `len`-byte number encoded with `encode_num()` from the start of `input`.
+///
+/// # Panics
+///
+/// Panics if `len` isn't between 1 and 4, or `input` is shorter than `len`.
+#[cfg(feature = "decode")]
+pub fn decode_num(len: usize, input: &[u8]) -> u32 {
+    assert!((1..=4).contains(&len), "Length must be between 1 and 4");
+    try_decode_num(len, input).expect("Input is too short for the encoded number")
+}
+
+/// Like `decode_num()`, but returns `None` rather than panicking if `len` isn't between 1 and 4
+/// or `input` is shorter than `len`.
+#[cfg(feature = "decode")]
+pub fn try_decode_num(len: usize, input: &[u8]) -> Option<u32> {
+    if !(1..=4).contains(&len) || input.len() < len {
+        return None;
+    }
+
+    Some(decode_num_scalar(len, input))
+}
+
+#[cfg(all(test, feature = "encode", feature = "decode"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_each_length_boundary() {
+        let mut buf = [0xFF; 5];
+        for &(num, len) in &[
+            (0, 1),
+            (0xFF, 1),
+            (0x100, 2),
+            (0xFFFF, 2),
+            (0x1_0000, 3),
+            (0xFF_FFFF, 3),
+            (0x100_0000, 4),
+            (u32::max_value(), 4),
+        ] {
+            assert_eq!(len, encoded_num_len(num));
+            assert_eq!(len, encode_num(num, &mut buf));
+            assert_eq!(num, decode_num(len, &buf));
+        }
+    }
+
+    #[test]
+    fn encode_writes_only_its_bytes() {
+        let mut buf = [0xFF; 4];
+        assert_eq!(Some(2), try_encode_num(0x1234, &mut buf));
+        assert_eq!([0x34, 0x12, 0xFF, 0xFF], buf);
+    }
+
+    #[test]
+    fn short_buffers_and_bad_lengths_are_rejected() {
+        assert_eq!(None, try_encode_num(0x1234, &mut [0; 1]));
+        assert_eq!(None, try_decode_num(3, &[1, 2]));
+        assert_eq!(None, try_decode_num(0, &[1, 2, 3, 4]));
+        assert_eq!(None, try_decode_num(5, &[1, 2, 3, 4, 5]));
+        assert_eq!(Some(0x0201), try_decode_num(2, &[1, 2, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Output is too short for the encoded number")]
+    fn encode_num_panics_on_short_output() {
+        encode_num(1 << 24, &mut [0; 3]);
+    }
+}
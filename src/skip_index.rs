@@ -0,0 +1,623 @@
+use std::cmp;
+use std::mem;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use {cumulative_encoded_len, decode, encode, encoded_shape, validate, DecodeCursor, Decoder,
+     Scalar};
+use decode::cursor::CursorPosition;
+
+/// The number of quads between index entries, unless configured otherwise with
+/// `SkipIndexBuilder.quads_per_entry()`.
+const DEFAULT_QUADS_PER_ENTRY: usize = 16;
+
+/// The length of the header at the start of a serialized index.
+const HEADER_LEN: usize = 12;
+
+/// The offsets of every 16th quad in some encoded numbers, so that a cursor can be positioned
+/// anywhere in the input after scanning at most 15 control bytes.
+///
+/// Unlike `DecodePlan`, which records every quad's offset and borrows the input it was built
+/// from, a skip index is small (one offset per 64 numbers by default) and owns its data, so it can
+/// be kept around, or serialized with `to_bytes()` and stored next to the encoded numbers so that
+/// it needn't be rebuilt every time they're loaded.
+///
+/// Use `SkipIndex::builder()` to choose a different spacing between entries: closer entries make
+/// positioning faster at the cost of a bigger index. `report()` shows what the choice costs.
+///
+/// For numbers in ascending order, the builder can also record fences: the first number of each
+/// block of numbers between entries. Then `lower_bound()` finds a number by binary searching the
+/// fences and decoding just one block, rather than decoding up to the number.
+///
+/// # Examples
+///
+/// ```
+/// use stream_vbyte::{encode, Scalar, SkipIndex};
+///
+/// let nums: Vec<u32> = (0..10_000).map(|i| i * 1000).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// encode::<Scalar>(&nums, &mut encoded);
+///
+/// let bytes = SkipIndex::build(&encoded, nums.len()).to_bytes();
+/// // ... later, when the numbers are loaded again
+/// let index = SkipIndex::from_bytes(&bytes).unwrap();
+///
+/// let mut decoded = vec![0; 100];
+/// let mut cursor = index.cursor_at(&encoded, 5000);
+/// assert_eq!(100, cursor.decode_slice::<Scalar>(&mut decoded));
+/// assert_eq!(&nums[5000..5100], &decoded[..]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipIndex {
+    count: usize,
+    quads_per_entry: usize,
+    /// Offset into the encoded numbers (i.e. after the control bytes) of every
+    /// `quads_per_entry`th complete quad, starting with the first
+    offsets: Vec<usize>,
+    /// The first number at each offset that has any numbers after it, if recorded
+    fences: Option<Vec<u32>>,
+}
+
+impl SkipIndex {
+    /// Scan the control bytes of `count` numbers encoded in `input`, with the default spacing
+    /// between entries.
+    pub fn build(input: &[u8], count: usize) -> SkipIndex {
+        SkipIndex::builder().build(input, count)
+    }
+
+    /// A builder for an index with a different spacing between entries.
+    pub fn builder() -> SkipIndexBuilder {
+        SkipIndexBuilder {
+            quads_per_entry: DEFAULT_QUADS_PER_ENTRY,
+            fences: false,
+        }
+    }
+
+    /// The number of numbers in the indexed input.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The number of quads between entries.
+    pub fn quads_per_entry(&self) -> usize {
+        self.quads_per_entry
+    }
+
+    /// The offset of every `quads_per_entry()`th quad from the start of the encoded numbers (i.e.
+    /// after the control bytes).
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// The first number of each block of `quads_per_entry() * 4` numbers, if the index was built
+    /// with fences.
+    pub fn fences(&self) -> Option<&[u32]> {
+        self.fences.as_ref().map(|f| &f[..])
+    }
+
+    /// The block (of `quads_per_entry() * 4` numbers) that the first number at least `target`
+    /// is in, or, if it's the first number of the next block, the block before that: either way,
+    /// the last block whose first number is less than `target`, or the first block if there isn't
+    /// one. The indexed numbers must be in ascending order.
+    ///
+    /// Only the fences are searched, so this looks at `log2(blocks)` of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index was built without fences.
+    pub fn block_for(&self, target: u32) -> usize {
+        let fences = self.fences
+            .as_ref()
+            .expect("Index must be built with fences");
+
+        // the number of fences less than target
+        let mut low = 0;
+        let mut high = fences.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if fences[mid] < target {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        low.saturating_sub(1)
+    }
+
+    /// The index of the first number at least `target` in `input`, which must be the input the
+    /// index was built from, or the count of numbers if there isn't one. The indexed numbers must
+    /// be in ascending order.
+    ///
+    /// Only the block from `block_for()` is decoded, with `D`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use stream_vbyte::{encode, Scalar, SkipIndex};
+    ///
+    /// let nums: Vec<u32> = (0..100_000).map(|i| i * 3).collect();
+    /// let mut encoded = vec![0; nums.len() * 5];
+    /// encode::<Scalar>(&nums, &mut encoded);
+    ///
+    /// let index = SkipIndex::builder().fences(true).build(&encoded, nums.len());
+    /// assert_eq!(20_000, index.lower_bound::<Scalar>(&encoded, 60_000));
+    /// assert_eq!(20_001, index.lower_bound::<Scalar>(&encoded, 60_001));
+    /// assert_eq!(100_000, index.lower_bound::<Scalar>(&encoded, 300_000));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index was built without fences.
+    pub fn lower_bound<D: Decoder>(&self, input: &[u8], target: u32) -> usize {
+        let block = self.block_for(target);
+        let block_len = self.quads_per_entry * 4;
+        let start = block * block_len;
+
+        let mut nums = vec![0; cmp::min(block_len, self.count - start)];
+        let decoded = self.cursor_at(input, start).decode_slice::<D>(&mut nums);
+        debug_assert_eq!(nums.len(), decoded);
+
+        // if nothing in this block is at least target, the next block's first number is
+        let in_block = nums.iter().position(|&num| num >= target).unwrap_or(nums.len());
+
+        start + in_block
+    }
+
+    /// Returns a cursor over `input`, which must be the input the index was built from,
+    /// positioned at number `index`. `index` must be a multiple of 4 and no more than the count of
+    /// numbers.
+    pub fn cursor_at<'a>(&self, input: &'a [u8], index: usize) -> DecodeCursor<'a> {
+        assert_eq!(index % 4, 0, "Must be a multiple of 4");
+        assert!(index <= self.count, "Index out of range");
+
+        let shape = encoded_shape(self.count);
+        let entry = self.entry_position(index);
+
+        let mut cursor = DecodeCursor::from_parts(
+            &input[entry.control_bytes_read..shape.control_bytes_len],
+            &input[(shape.control_bytes_len + entry.encoded_bytes_read)..],
+            self.count - entry.nums_decoded,
+        );
+        cursor.skip(index - entry.nums_decoded);
+
+        cursor
+    }
+
+    /// The position of a cursor over the whole input at the last entry at or before number
+    /// `index`.
+    pub(crate) fn entry_position(&self, index: usize) -> CursorPosition {
+        let entry = cmp::min(index / 4 / self.quads_per_entry, self.offsets.len() - 1);
+        let entry_start = entry * self.quads_per_entry * 4;
+
+        CursorPosition {
+            nums_decoded: entry_start,
+            control_bytes_read: entry_start / 4,
+            encoded_bytes_read: self.offsets[entry],
+        }
+    }
+
+    /// Report the size of the index and how much scanning it leaves to do.
+    pub fn report(&self) -> SkipIndexReport {
+        let complete_quads = encoded_shape(self.count).complete_control_bytes_len;
+
+        SkipIndexReport {
+            entries: self.offsets.len(),
+            heap_bytes: self.offsets.len() * mem::size_of::<usize>()
+                + self.fences.as_ref().map_or(0, |f| f.len() * mem::size_of::<u32>()),
+            serialized_len: self.to_bytes().len(),
+            max_control_bytes_scanned: cmp::min(self.quads_per_entry - 1, complete_quads),
+        }
+    }
+
+    /// Serialize the index.
+    ///
+    /// The layout is a 12-byte header (the count of numbers, the number of quads per entry, and
+    /// the length of the encoded offsets, each as a little-endian `u32`), followed by the
+    /// differences between consecutive offsets, Stream VByte encoded. Since every difference is
+    /// the length of a few quads, they usually take 1 or 2 bytes each.
+    ///
+    /// If the index has fences, they come next: the length of the encoded fences as a
+    /// little-endian `u32`, followed by the differences between consecutive fences (the first
+    /// from 0), Stream VByte encoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        assert!(
+            self.count <= u32::MAX as usize,
+            "Serialized indexes can cover at most u32::max_value() numbers"
+        );
+
+        let deltas: Vec<u32> = self.offsets
+            .windows(2)
+            .map(|pair| {
+                let delta = pair[1] - pair[0];
+                assert!(delta <= u32::MAX as usize, "Offset delta too large");
+                delta as u32
+            })
+            .collect();
+
+        let mut bytes = vec![0; HEADER_LEN + deltas.len() * 5];
+        let encoded_len = encode::<Scalar>(&deltas, &mut bytes[HEADER_LEN..]);
+        bytes.truncate(HEADER_LEN + encoded_len);
+
+        LittleEndian::write_u32(&mut bytes[0..4], self.count as u32);
+        LittleEndian::write_u32(&mut bytes[4..8], self.quads_per_entry as u32);
+        LittleEndian::write_u32(&mut bytes[8..12], encoded_len as u32);
+
+        if let Some(ref fences) = self.fences {
+            let mut prev = 0;
+            let deltas: Vec<u32> = fences
+                .iter()
+                .map(|&fence| {
+                    let delta = fence.wrapping_sub(prev);
+                    prev = fence;
+                    delta
+                })
+                .collect();
+
+            let fences_start = bytes.len();
+            bytes.resize(fences_start + 4 + deltas.len() * 5, 0);
+            let encoded_len = encode::<Scalar>(&deltas, &mut bytes[(fences_start + 4)..]);
+            bytes.truncate(fences_start + 4 + encoded_len);
+            LittleEndian::write_u32(
+                &mut bytes[fences_start..(fences_start + 4)],
+                encoded_len as u32,
+            );
+        }
+
+        bytes
+    }
+
+    /// Deserialize an index written by `to_bytes()`.
+    ///
+    /// Returns `None` if `bytes` is not a serialized index.
+    pub fn from_bytes(bytes: &[u8]) -> Option<SkipIndex> {
+        if bytes.len() < HEADER_LEN {
+            return None;
+        }
+
+        let count = LittleEndian::read_u32(&bytes[0..4]) as usize;
+        let quads_per_entry = LittleEndian::read_u32(&bytes[4..8]) as usize;
+        let encoded_len = LittleEndian::read_u32(&bytes[8..12]) as usize;
+        if quads_per_entry == 0 {
+            return None;
+        }
+
+        let num_deltas = encoded_shape(count).complete_control_bytes_len / quads_per_entry;
+        if bytes.len() < HEADER_LEN + encoded_len {
+            return None;
+        }
+        let (encoded, rest) = bytes[HEADER_LEN..].split_at(encoded_len);
+        if validate(encoded, num_deltas) != Some(encoded_len) {
+            return None;
+        }
+
+        let mut deltas = vec![0; num_deltas];
+        decode::<Scalar>(encoded, num_deltas, &mut deltas);
+
+        let mut offsets = Vec::with_capacity(num_deltas + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for delta in deltas {
+            offset += delta as usize;
+            offsets.push(offset);
+        }
+
+        let fences = if rest.is_empty() {
+            None
+        } else {
+            Some(fences_from_bytes(rest, num_fences(count, quads_per_entry))?)
+        };
+
+        Some(SkipIndex {
+            count,
+            quads_per_entry,
+            offsets,
+            fences,
+        })
+    }
+}
+
+/// The number of blocks of `quads_per_entry * 4` numbers that `count` numbers are split into.
+fn num_fences(count: usize, quads_per_entry: usize) -> usize {
+    let block_len = quads_per_entry * 4;
+    (count + block_len - 1) / block_len
+}
+
+/// Deserialize the fences section of `SkipIndex.to_bytes()`, which must be all of `bytes`.
+fn fences_from_bytes(bytes: &[u8], count: usize) -> Option<Vec<u32>> {
+    if bytes.len() < 4 {
+        return None;
+    }
+
+    let encoded_len = LittleEndian::read_u32(&bytes[0..4]) as usize;
+    let encoded = &bytes[4..];
+    if encoded.len() != encoded_len || validate(encoded, count) != Some(encoded_len) {
+        return None;
+    }
+
+    let mut fences = vec![0; count];
+    decode::<Scalar>(encoded, count, &mut fences);
+
+    let mut prev: u32 = 0;
+    for fence in fences.iter_mut() {
+        prev = prev.wrapping_add(*fence);
+        *fence = prev;
+    }
+
+    Some(fences)
+}
+
+/// Configures a `SkipIndex`.
+#[derive(Debug, Clone)]
+pub struct SkipIndexBuilder {
+    quads_per_entry: usize,
+    fences: bool,
+}
+
+impl SkipIndexBuilder {
+    /// Record an offset every `quads_per_entry` quads. Must be non-zero.
+    ///
+    /// Positioning a cursor scans up to `quads_per_entry - 1` control bytes, and the index holds
+    /// one offset per `quads_per_entry` quads.
+    pub fn quads_per_entry(mut self, quads_per_entry: usize) -> SkipIndexBuilder {
+        assert!(quads_per_entry > 0, "Quads per entry must be non-zero");
+        self.quads_per_entry = quads_per_entry;
+        self
+    }
+
+    /// Also record the first number of each entry's block of numbers, for `SkipIndex.block_for()`
+    /// and `SkipIndex.lower_bound()`. Off by default.
+    ///
+    /// That's one more number per entry, and building the index then decodes the first quad of
+    /// each block too.
+    pub fn fences(mut self, fences: bool) -> SkipIndexBuilder {
+        self.fences = fences;
+        self
+    }
+
+    /// Scan the control bytes of `count` numbers encoded in `input`, and decode the first quad
+    /// of each block if recording fences.
+    pub fn build(&self, input: &[u8], count: usize) -> SkipIndex {
+        let shape = encoded_shape(count);
+        let control_bytes = &input[0..shape.complete_control_bytes_len];
+
+        let mut offsets = Vec::with_capacity(control_bytes.len() / self.quads_per_entry + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+
+        for entry in control_bytes.chunks(self.quads_per_entry) {
+            if entry.len() < self.quads_per_entry {
+                break;
+            }
+            offset += cumulative_encoded_len(entry);
+            offsets.push(offset);
+        }
+
+        let mut index = SkipIndex {
+            count,
+            quads_per_entry: self.quads_per_entry,
+            offsets,
+            fences: None,
+        };
+
+        if self.fences {
+            let block_len = self.quads_per_entry * 4;
+            let fences = (0..num_fences(count, self.quads_per_entry))
+                .map(|block| {
+                    let mut quad = [0; 4];
+                    index
+                        .cursor_at(input, block * block_len)
+                        .decode_slice::<Scalar>(&mut quad);
+                    quad[0]
+                })
+                .collect();
+            index.fences = Some(fences);
+        }
+
+        index
+    }
+}
+
+/// The costs of a `SkipIndex`, from `SkipIndex.report()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipIndexReport {
+    /// The number of offsets in the index.
+    pub entries: usize,
+    /// The memory used by the offsets.
+    pub heap_bytes: usize,
+    /// The length of the index's `to_bytes()` serialization.
+    pub serialized_len: usize,
+    /// The most control bytes that positioning a cursor with `cursor_at()` scans.
+    pub max_control_bytes_scanned: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn random_cursor_at_and_roundtrip_bytes() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let count = rng.gen_range(0, 1000);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+            encoded.truncate(encoded_len);
+
+            let index = SkipIndex::build(&encoded, count);
+            assert_eq!(Some(index.clone()), SkipIndex::from_bytes(&index.to_bytes()));
+
+            for start in (0..(count + 1)).filter(|i| i % 4 == 0) {
+                let mut cursor = index.cursor_at(&encoded, start);
+                let mut decoded = vec![0; count - start + 4];
+                assert_eq!(count - start, cursor.decode_slice::<Scalar>(&mut decoded));
+                assert_eq!(&nums[start..], &decoded[0..(count - start)]);
+            }
+        }
+    }
+
+    #[test]
+    fn offsets_are_every_16th_quad() {
+        // every number is 2 bytes, so every quad is 8 bytes
+        let nums = vec![1000; 4 * 40 + 3];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let index = SkipIndex::build(&encoded, nums.len());
+        assert_eq!(&[0, 128, 256], index.offsets());
+    }
+
+    #[test]
+    fn random_quads_per_entry_cursor_at() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let count = rng.gen_range(0, 300);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut encoded = vec![0; count * 5];
+            encode::<Scalar>(&nums, &mut encoded);
+
+            let index = SkipIndex::builder()
+                .quads_per_entry(rng.gen_range(1, 40))
+                .build(&encoded, count);
+            assert_eq!(Some(index.clone()), SkipIndex::from_bytes(&index.to_bytes()));
+
+            for start in (0..(count + 1)).filter(|i| i % 4 == 0) {
+                let mut cursor = index.cursor_at(&encoded, start);
+                let mut decoded = vec![0; count - start + 4];
+                assert_eq!(count - start, cursor.decode_slice::<Scalar>(&mut decoded));
+                assert_eq!(&nums[start..], &decoded[0..(count - start)]);
+            }
+        }
+    }
+
+    #[test]
+    fn report_reflects_quads_per_entry() {
+        let nums = vec![1000; 4 * 100];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let coarse = SkipIndex::builder()
+            .quads_per_entry(50)
+            .build(&encoded, nums.len())
+            .report();
+        let fine = SkipIndex::builder()
+            .quads_per_entry(4)
+            .build(&encoded, nums.len())
+            .report();
+
+        assert_eq!(3, coarse.entries);
+        assert_eq!(49, coarse.max_control_bytes_scanned);
+        assert_eq!(26, fine.entries);
+        assert_eq!(3, fine.max_control_bytes_scanned);
+        assert!(fine.heap_bytes > coarse.heap_bytes);
+        assert!(fine.serialized_len > coarse.serialized_len);
+    }
+
+    #[test]
+    fn random_fences_lower_bound_and_roundtrip_bytes() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let count = rng.gen_range(0, 2000);
+            let mut nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            nums.sort();
+            let mut encoded = vec![0; count * 5];
+            encode::<Scalar>(&nums, &mut encoded);
+
+            let quads_per_entry = rng.gen_range(1, 40);
+            let index = SkipIndex::builder()
+                .quads_per_entry(quads_per_entry)
+                .fences(true)
+                .build(&encoded, count);
+            let expected_fences: Vec<u32> = nums.iter()
+                .cloned()
+                .step_by(quads_per_entry * 4)
+                .collect();
+            assert_eq!(Some(&expected_fences[..]), index.fences());
+            assert_eq!(Some(index.clone()), SkipIndex::from_bytes(&index.to_bytes()));
+
+            let mut targets: Vec<u32> = (0..100).map(|_| rng.gen()).collect();
+            targets.extend(nums.iter().take(100).cloned());
+            targets.push(0);
+            targets.push(u32::max_value());
+            for target in targets {
+                let expected = nums.iter().position(|&num| num >= target).unwrap_or(count);
+                assert_eq!(expected, index.lower_bound::<Scalar>(&encoded, target));
+            }
+        }
+    }
+
+    #[test]
+    fn block_for_finds_last_block_starting_before_target() {
+        // blocks of 4 numbers: [10, 10, 10, 10], [10, 20, 20, 20], [30]
+        let nums = [10, 10, 10, 10, 10, 20, 20, 20, 30];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let index = SkipIndex::builder()
+            .quads_per_entry(1)
+            .fences(true)
+            .build(&encoded, nums.len());
+        assert_eq!(Some(&[10, 10, 30][..]), index.fences());
+
+        assert_eq!(0, index.block_for(0));
+        assert_eq!(0, index.block_for(10));
+        assert_eq!(1, index.block_for(11));
+        assert_eq!(1, index.block_for(30));
+        assert_eq!(2, index.block_for(31));
+
+        assert_eq!(0, index.lower_bound::<Scalar>(&encoded, 10));
+        assert_eq!(5, index.lower_bound::<Scalar>(&encoded, 11));
+        assert_eq!(8, index.lower_bound::<Scalar>(&encoded, 21));
+        assert_eq!(9, index.lower_bound::<Scalar>(&encoded, 31));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index must be built with fences")]
+    fn block_for_without_fences_panics() {
+        SkipIndex::build(&[], 0).block_for(0);
+    }
+
+    #[test]
+    fn from_bytes_rejects_malformed_input() {
+        let nums = vec![1000; 200];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+        let bytes = SkipIndex::build(&encoded, nums.len()).to_bytes();
+
+        assert_eq!(None, SkipIndex::from_bytes(&bytes[0..(HEADER_LEN - 1)]));
+        assert_eq!(None, SkipIndex::from_bytes(&bytes[0..(bytes.len() - 1)]));
+
+        let mut zero_quads_per_entry = bytes.clone();
+        zero_quads_per_entry[4..8].copy_from_slice(&[0; 4]);
+        assert_eq!(None, SkipIndex::from_bytes(&zero_quads_per_entry));
+
+        let with_fences = SkipIndex::builder()
+            .fences(true)
+            .build(&encoded, nums.len())
+            .to_bytes();
+        assert!(SkipIndex::from_bytes(&with_fences).is_some());
+        assert_eq!(None, SkipIndex::from_bytes(&with_fences[0..(bytes.len() + 3)]));
+        assert_eq!(
+            None,
+            SkipIndex::from_bytes(&with_fences[0..(with_fences.len() - 1)])
+        );
+    }
+}
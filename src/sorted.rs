@@ -0,0 +1,312 @@
+use std::cmp;
+use std::marker::PhantomData;
+
+use {DecodeCursor, Decoder};
+
+/// How many numbers `SortedCursor` decodes at a time once it has found where to look.
+const BLOCK_LEN: usize = 64;
+
+/// A cursor over numbers in ascending order, for finding the first number at or past a target
+/// quickly: the inner loop of intersecting sorted lists such as posting lists.
+///
+/// Numbers can be encoded as they are (`new()`) or as differences from the previous number
+/// (`delta()`, as with `FrameFormat.delta`). Differences compress sorted numbers much better, but
+/// the only way to know a number is to add up every difference before it, so quads can't be
+/// skipped without decoding them. Plain numbers can, so `advance_to()` gallops over them with
+/// `DecodeCursor.skip()`, 1, 2, 4, ... quads at a time, decoding only the last quad of each jump,
+/// then binary searches the quads of the last jump. Either way, the block of numbers with the
+/// target in it is binary searched.
+///
+/// It's also an `Iterator` over the numbers from the current one on.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, Scalar, SortedCursor};
+///
+/// let nums: Vec<u32> = (0..10_000).map(|i| i * 3).collect();
+/// let mut encoded = vec![0; nums.len() * 5];
+/// encode::<Scalar>(&nums, &mut encoded);
+///
+/// let mut cursor = SortedCursor::<Scalar>::new(&encoded, nums.len());
+/// assert_eq!(Some(6_000), cursor.advance_to(6_000));
+/// // the cursor stays on the number it found
+/// assert_eq!(Some(6_000), cursor.advance_to(10));
+/// assert_eq!(Some(20_001), cursor.advance_to(20_000));
+/// assert_eq!(Some(20_001), cursor.next());
+/// assert_eq!(Some(20_004), cursor.next());
+/// assert_eq!(None, cursor.advance_to(30_000));
+/// # }
+/// ```
+pub struct SortedCursor<'a, D: Decoder> {
+    cursor: DecodeCursor<'a>,
+    count: usize,
+    delta: bool,
+    /// The last number decoded, which differences are added to.
+    prev: u32,
+    buf: [u32; BLOCK_LEN],
+    buf_pos: usize,
+    buf_len: usize,
+    decoder: PhantomData<D>,
+}
+
+impl<'a, D: Decoder> SortedCursor<'a, D> {
+    /// Create a cursor over `count` numbers in ascending order, encoded as they are.
+    pub fn new(input: &'a [u8], count: usize) -> SortedCursor<'a, D> {
+        SortedCursor::with_delta(input, count, false)
+    }
+
+    /// Create a cursor over `count` numbers in ascending order, each encoded as its difference
+    /// from the previous one (the first from 0).
+    pub fn delta(input: &'a [u8], count: usize) -> SortedCursor<'a, D> {
+        SortedCursor::with_delta(input, count, true)
+    }
+
+    fn with_delta(input: &'a [u8], count: usize, delta: bool) -> SortedCursor<'a, D> {
+        SortedCursor {
+            cursor: DecodeCursor::new(input, count),
+            count,
+            delta,
+            prev: 0,
+            buf: [0; BLOCK_LEN],
+            buf_pos: 0,
+            buf_len: 0,
+            decoder: PhantomData,
+        }
+    }
+
+    /// Move to the first number that's at least `target`, starting from the current number, and
+    /// return it, or `None` if there isn't one.
+    ///
+    /// The cursor stays on the number found, so the next `advance_to()` or `next()` starts there.
+    /// If the current number is already at least `target`, the cursor doesn't move.
+    pub fn advance_to(&mut self, target: u32) -> Option<u32> {
+        loop {
+            if self.buf_pos < self.buf_len {
+                if self.buf[self.buf_len - 1] >= target {
+                    self.buf_pos += lower_bound(&self.buf[self.buf_pos..self.buf_len], target);
+                    return Some(self.buf[self.buf_pos]);
+                }
+                self.buf_pos = self.buf_len;
+            }
+
+            if !self.delta {
+                self.gallop(target);
+            }
+            if !self.fill_buf() {
+                return None;
+            }
+        }
+    }
+
+    /// Skip the complete quads whose numbers are all less than `target`.
+    fn gallop(&mut self, target: u32) {
+        let mut quad = [0; 4];
+        let mut stride = 1;
+
+        loop {
+            let complete_quads_left = (self.count - self.cursor.position().nums_decoded) / 4;
+            let stride_quads = cmp::min(stride, complete_quads_left);
+            if stride_quads == 0 {
+                return;
+            }
+
+            self.cursor.skip((stride_quads - 1) * 4);
+            self.cursor.decode_slice::<D>(&mut quad);
+            if quad[3] >= target {
+                // the target is in one of the quads of this jump
+                self.cursor.skip_back(stride_quads * 4);
+                self.binary_search_quads(stride_quads, target);
+                return;
+            }
+
+            stride *= 2;
+        }
+    }
+
+    /// Move to the first of the next `quads` quads whose last number is at least `target`, given
+    /// that the last of them is.
+    fn binary_search_quads(&mut self, quads: usize, target: u32) {
+        let mut quad = [0; 4];
+        let mut low = 0;
+        let mut high = quads - 1;
+        // which quad the cursor is at the start of
+        let mut at = 0;
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            self.move_to_quad(&mut at, mid);
+            self.cursor.decode_slice::<D>(&mut quad);
+            at = mid + 1;
+
+            if quad[3] < target {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        self.move_to_quad(&mut at, low);
+    }
+
+    fn move_to_quad(&mut self, at: &mut usize, quad: usize) {
+        if quad > *at {
+            self.cursor.skip((quad - *at) * 4);
+        } else {
+            self.cursor.skip_back((*at - quad) * 4);
+        }
+        *at = quad;
+    }
+
+    /// The decoded numbers from the current one to the end of the block, decoding the next block
+    /// if there are none left in this one. Empty once every number has been read.
+    pub(crate) fn buffered(&mut self) -> &[u32] {
+        if self.buf_pos == self.buf_len {
+            self.fill_buf();
+        }
+
+        &self.buf[self.buf_pos..self.buf_len]
+    }
+
+    /// Move past `n` of the numbers returned by `buffered()`.
+    pub(crate) fn consume(&mut self, n: usize) {
+        debug_assert!(self.buf_pos + n <= self.buf_len);
+        self.buf_pos += n;
+    }
+
+    /// Decode the next block of numbers into the buffer, returning false if there are none left.
+    fn fill_buf(&mut self) -> bool {
+        let len = self.cursor.decode_slice::<D>(&mut self.buf);
+        if len == 0 {
+            return false;
+        }
+
+        if self.delta {
+            for num in self.buf[0..len].iter_mut() {
+                self.prev = self.prev.wrapping_add(*num);
+                *num = self.prev;
+            }
+        }
+        self.buf_pos = 0;
+        self.buf_len = len;
+
+        true
+    }
+}
+
+impl<'a, D: Decoder> Iterator for SortedCursor<'a, D> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.buf_pos == self.buf_len && !self.fill_buf() {
+            return None;
+        }
+
+        let num = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+
+        Some(num)
+    }
+}
+
+/// The index of the first number in `sorted` that's at least `target`, or its length if there
+/// isn't one.
+fn lower_bound(sorted: &[u32], target: u32) -> usize {
+    let mut low = 0;
+    let mut high = sorted.len();
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if sorted[mid] < target {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn advance_to_random_targets_plain_and_delta() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..200 {
+            let mut nums: Vec<u32> = (0..rng.gen_range(0, 2000))
+                .map(|_| rng.gen_range(0, 100_000))
+                .collect();
+            nums.sort();
+            let mut deltas = nums.clone();
+            for i in (1..deltas.len()).rev() {
+                deltas[i] -= deltas[i - 1];
+            }
+
+            let mut encoded = vec![0; nums.len() * 5];
+            encode::<Scalar>(&nums, &mut encoded);
+            let mut encoded_deltas = vec![0; nums.len() * 5];
+            encode::<Scalar>(&deltas, &mut encoded_deltas);
+
+            let mut plain = SortedCursor::<Scalar>::new(&encoded, nums.len());
+            let mut delta = SortedCursor::<Scalar>::delta(&encoded_deltas, nums.len());
+            let mut pos = 0;
+            let mut target: u32 = 0;
+
+            while pos < nums.len() {
+                // mostly small jumps, and now and then a big one or a step back
+                target = match rng.gen_range(0, 10) {
+                    0 => target + rng.gen_range(0, 50_000),
+                    1 => target.saturating_sub(rng.gen_range(0, 100)),
+                    _ => target + rng.gen_range(0, 200),
+                };
+                pos += lower_bound(&nums[pos..], target);
+                let expected = nums.get(pos).cloned();
+
+                assert_eq!(expected, plain.advance_to(target));
+                assert_eq!(expected, delta.advance_to(target));
+
+                if rng.gen_range(0, 4) == 0 && pos < nums.len() {
+                    assert_eq!(expected, plain.next());
+                    assert_eq!(expected, delta.next());
+                    pos += 1;
+                }
+            }
+
+            assert_eq!(None, plain.next());
+            assert_eq!(None, delta.next());
+        }
+    }
+
+    #[test]
+    fn iterates_every_number() {
+        let nums: Vec<u32> = (0..1003).map(|i| i * 7).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        assert_eq!(
+            nums,
+            SortedCursor::<Scalar>::new(&encoded, nums.len()).collect::<Vec<u32>>()
+        );
+    }
+
+    #[test]
+    fn lower_bound_finds_first_of_duplicates() {
+        let sorted = [1, 3, 3, 3, 5];
+
+        assert_eq!(0, lower_bound(&sorted, 0));
+        assert_eq!(1, lower_bound(&sorted, 2));
+        assert_eq!(1, lower_bound(&sorted, 3));
+        assert_eq!(4, lower_bound(&sorted, 4));
+        assert_eq!(5, lower_bound(&sorted, 6));
+        assert_eq!(0, lower_bound(&[], 6));
+    }
+}
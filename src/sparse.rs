@@ -0,0 +1,192 @@
+use std::marker::PhantomData;
+
+use {cumulative_encoded_len, encoded_shape, DecodeCursor, Decoder};
+
+/// The most quads decoded at a time by `NonZeroIter`.
+const RUN_QUADS: usize = 16;
+
+/// Iterate over the nonzero numbers among the `count` numbers encoded in `input`, as
+/// `(index, number)` pairs.
+///
+/// Complete quads of four zeros are recognized from their control byte (0x00) and their four
+/// encoded bytes, and skipped without decoding, so long runs of zeros, as are common after delta
+/// encoding, cost little more than reading them.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, iter_nonzero, Scalar};
+///
+/// let mut nums = vec![0; 1000];
+/// nums[3] = 7;
+/// nums[900] = 100_000;
+/// let mut encoded = vec![0; nums.len() * 5];
+/// encode::<Scalar>(&nums, &mut encoded);
+///
+/// let nonzero: Vec<(usize, u32)> = iter_nonzero::<Scalar>(&encoded, nums.len()).collect();
+/// assert_eq!(vec![(3, 7), (900, 100_000)], nonzero);
+/// # }
+/// ```
+pub fn iter_nonzero<'a, D: Decoder>(input: &'a [u8], count: usize) -> NonZeroIter<'a, D> {
+    let shape = encoded_shape(count);
+
+    NonZeroIter {
+        control_bytes: &input[0..shape.control_bytes_len],
+        encoded_nums: &input[shape.control_bytes_len..],
+        complete_quads: shape.complete_control_bytes_len,
+        count,
+        quad_index: 0,
+        nums_offset: 0,
+        buf: [0; RUN_QUADS * 4],
+        buf_start: 0,
+        buf_pos: 0,
+        buf_len: 0,
+        decoder: PhantomData,
+    }
+}
+
+/// Iterator over the nonzero numbers in encoded input. See `iter_nonzero()`.
+pub struct NonZeroIter<'a, D: Decoder> {
+    control_bytes: &'a [u8],
+    encoded_nums: &'a [u8],
+    complete_quads: usize,
+    count: usize,
+    /// The next quad to examine
+    quad_index: usize,
+    /// Where that quad's numbers start in `encoded_nums`
+    nums_offset: usize,
+    buf: [u32; RUN_QUADS * 4],
+    /// The index of the number in `buf[0]`
+    buf_start: usize,
+    buf_pos: usize,
+    buf_len: usize,
+    decoder: PhantomData<D>,
+}
+
+impl<'a, D: Decoder> NonZeroIter<'a, D> {
+    fn is_zero_quad(&self, quad_index: usize, nums_offset: usize) -> bool {
+        quad_index < self.complete_quads && self.control_bytes[quad_index] == 0
+            && self.encoded_nums[nums_offset..(nums_offset + 4)] == [0; 4]
+    }
+
+    /// Skip any zero quads, then decode the run of quads up to the next zero quad (or at most
+    /// `RUN_QUADS`) into `buf`. Returns false if there are no more quads.
+    fn fill_buf(&mut self) -> bool {
+        while self.is_zero_quad(self.quad_index, self.nums_offset) {
+            self.quad_index += 1;
+            self.nums_offset += 4;
+        }
+
+        if self.quad_index == self.control_bytes.len() {
+            return false;
+        }
+
+        let mut run = 1;
+        let mut run_offset = self.nums_offset
+            + cumulative_encoded_len(&self.control_bytes[self.quad_index..(self.quad_index + 1)]);
+        while run < RUN_QUADS && self.quad_index + run < self.control_bytes.len()
+            && !self.is_zero_quad(self.quad_index + run, run_offset)
+        {
+            run_offset += cumulative_encoded_len(
+                &self.control_bytes[(self.quad_index + run)..(self.quad_index + run + 1)],
+            );
+            run += 1;
+        }
+
+        let first_num = self.quad_index * 4;
+        let nums = ::std::cmp::min(run * 4, self.count - first_num);
+        let mut cursor = DecodeCursor::from_parts(
+            &self.control_bytes[self.quad_index..],
+            &self.encoded_nums[self.nums_offset..],
+            nums,
+        );
+        cursor.decode_slice::<D>(&mut self.buf[0..nums]);
+
+        self.buf_start = first_num;
+        self.buf_pos = 0;
+        self.buf_len = nums;
+        self.quad_index += run;
+        self.nums_offset = run_offset;
+
+        true
+    }
+}
+
+impl<'a, D: Decoder> Iterator for NonZeroIter<'a, D> {
+    type Item = (usize, u32);
+
+    fn next(&mut self) -> Option<(usize, u32)> {
+        loop {
+            while self.buf_pos < self.buf_len {
+                let num = self.buf[self.buf_pos];
+                self.buf_pos += 1;
+                if num != 0 {
+                    return Some((self.buf_start + self.buf_pos - 1, num));
+                }
+            }
+
+            if !self.fill_buf() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn iter_nonzero_random_scalar() {
+        do_iter_nonzero_random::<Scalar>();
+    }
+
+    #[cfg(feature = "x86_ssse3")]
+    #[test]
+    fn iter_nonzero_random_ssse3() {
+        do_iter_nonzero_random::<::x86::Ssse3>();
+    }
+
+    #[test]
+    fn quads_with_zero_control_byte_but_nonzero_numbers_are_decoded() {
+        let nums = vec![0, 0, 0, 0, 0, 0, 5, 0, 1, 2, 3, 4];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let nonzero: Vec<(usize, u32)> = iter_nonzero::<Scalar>(&encoded, nums.len()).collect();
+        assert_eq!(vec![(6, 5), (8, 1), (9, 2), (10, 3), (11, 4)], nonzero);
+    }
+
+    fn do_iter_nonzero_random<D: Decoder>() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..500 {
+            let density = rng.gen_range(0.0, 1.0);
+            let nums: Vec<u32> = (0..rng.gen_range(0, 500))
+                .map(|_| {
+                    if rng.gen_range(0.0, 1.0) < density {
+                        rng.gen::<u32>() >> rng.gen_range(0, 32)
+                    } else {
+                        0
+                    }
+                })
+                .collect();
+            let mut encoded = vec![0; nums.len() * 5];
+            encode::<Scalar>(&nums, &mut encoded);
+
+            let expected: Vec<(usize, u32)> = nums.iter()
+                .cloned()
+                .enumerate()
+                .filter(|&(_, n)| n != 0)
+                .collect();
+            let actual: Vec<(usize, u32)> = iter_nonzero::<D>(&encoded, nums.len()).collect();
+            assert_eq!(expected, actual);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::any;
+
+use encode::{encode_complete_quads, encode_partial_quad};
+use instrument::instrument;
+use {encoded_shape, lengths, Encoder};
+
+#[cfg(any(feature = "x86_ssse3", feature = "x86_sse41"))]
+mod ssse3;
+
+/// Statistics about one invocation of `encode_with_stats()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodeStats {
+    /// The number of numbers encoded.
+    pub input_count: usize,
+    /// The number of bytes written to the output, including control bytes.
+    pub output_bytes: usize,
+    /// How many numbers were encoded with 1, 2, 3, and 4 bytes respectively.
+    pub len_histogram: [usize; 4],
+    /// `output_bytes` divided by the 4 bytes per number the input occupied, so smaller is better.
+    /// Empty input has a ratio of 0.
+    pub ratio: f64,
+    /// The number of numbers in each frame, in order, for encodings that write frames, such as
+    /// `EncoderConfig.encode_with_stats()`. Empty for `encode_with_stats()`, which doesn't.
+    pub block_sizes: Vec<usize>,
+}
+
+/// Quads encoded between counts of the control bytes just written in `encode_with_stats()`: few
+/// enough that they're still in L1 cache when they're counted.
+const QUADS_PER_CHUNK: usize = 256;
+
+/// Encode the `input` slice into the `output` slice just like `encode()`, and also report
+/// statistics about the encoded form.
+///
+/// The input is encoded a chunk at a time, and each chunk's control bytes are counted as soon as
+/// they're written, so the statistics cost much less than the encoding itself.
+pub fn encode_with_stats<E: Encoder>(input: &[u32], output: &mut [u8]) -> EncodeStats {
+    let mut len_histogram = [0; 4];
+    let output_bytes = instrument("encode", any::type_name::<E>(), input.len(), || {
+        let encoded_len = encode_counting_lengths::<E>(input, output, &mut len_histogram);
+        #[cfg(feature = "debug-verify")]
+        ::encode::verify(input, &output[0..encoded_len]);
+
+        encoded_len
+    });
+
+    EncodeStats {
+        input_count: input.len(),
+        output_bytes,
+        len_histogram,
+        ratio: ratio(input.len(), output_bytes),
+        block_sizes: Vec::new(),
+    }
+}
+
+/// Like `encode()`, but also add how many numbers were encoded with 1, 2, 3, and 4 bytes to
+/// `len_histogram`.
+fn encode_counting_lengths<E: Encoder>(
+    input: &[u32],
+    output: &mut [u8],
+    len_histogram: &mut [usize; 4],
+) -> usize {
+    let shape = encoded_shape(input.len());
+    let (control_bytes, encoded_nums) = output.split_at_mut(shape.control_bytes_len);
+    let complete_nums = shape.complete_control_bytes_len * 4;
+    let mut nums_len = 0;
+
+    for (chunk_index, chunk) in input[0..complete_nums]
+        .chunks(QUADS_PER_CHUNK * 4)
+        .enumerate()
+    {
+        let chunk_start = chunk_index * QUADS_PER_CHUNK;
+        let chunk_control_bytes = &mut control_bytes[chunk_start..(chunk_start + chunk.len() / 4)];
+        nums_len += encode_complete_quads::<E>(
+            chunk,
+            chunk_control_bytes,
+            &mut encoded_nums[nums_len..],
+        );
+
+        let chunk_histogram = length_histogram(chunk_control_bytes);
+        for (count, &chunk_count) in len_histogram.iter_mut().zip(chunk_histogram.iter()) {
+            *count += chunk_count as usize;
+        }
+    }
+
+    if shape.leftover_numbers > 0 {
+        let (control_byte, len) =
+            encode_partial_quad(&input[complete_nums..], &mut encoded_nums[nums_len..]);
+        control_bytes[shape.complete_control_bytes_len] = control_byte;
+        nums_len += len;
+
+        for i in 0..shape.leftover_numbers {
+            len_histogram[((control_byte >> (i * 2)) & 0x03) as usize] += 1;
+        }
+    }
+
+    shape.control_bytes_len + nums_len
+}
+
+/// Count how many of the `count` numbers encoded in `encoded` take 1, 2, 3, and 4 bytes
+/// respectively, including any in a partial quad.
+#[cfg(all(feature = "encode", feature = "decode"))]
+pub(crate) fn encoded_len_histogram(encoded: &[u8], count: usize) -> [usize; 4] {
+    let shape = encoded_shape(count);
+
+    let complete_histogram = length_histogram(&encoded[0..shape.complete_control_bytes_len]);
+    let mut len_histogram = [0; 4];
+    for (count, &complete_count) in len_histogram.iter_mut().zip(complete_histogram.iter()) {
+        *count = complete_count as usize;
+    }
+
+    if shape.leftover_numbers > 0 {
+        let control_byte = encoded[shape.complete_control_bytes_len];
+        for i in 0..shape.leftover_numbers {
+            len_histogram[((control_byte >> (i * 2)) & 0x03) as usize] += 1;
+        }
+    }
+
+    len_histogram
+}
+
+/// Encoded bytes per input byte, or 0 for empty input.
+pub(crate) fn ratio(input_count: usize, output_bytes: usize) -> f64 {
+    if input_count == 0 {
+        0.0
+    } else {
+        output_bytes as f64 / (input_count * 4) as f64
+    }
+}
+
+/// Count how many numbers are encoded with 1, 2, 3, and 4 bytes respectively, looking only at
+/// control bytes.
+///
+/// This lets you characterize existing encoded data without touching any of the encoded numbers.
+/// Every control byte is counted as a complete quad, so if the encoded data ends in a partial quad
+/// either leave its control byte out or subtract the unused slots, which are counted as 1 byte
+/// numbers.
+///
+/// With either of the x86 features enabled, control bytes are counted 16 at a time with SSSE3
+/// shuffles if the CPU supports them. Otherwise, they're processed 8 at a time as a `u64`, using a
+/// popcount per length rather than a table lookup per byte.
+pub fn length_histogram(control_bytes: &[u8]) -> [u64; 4] {
+    #[cfg(any(feature = "x86_ssse3", feature = "x86_sse41"))]
+    {
+        if ssse3::is_supported() {
+            return unsafe { ssse3::length_histogram(control_bytes) };
+        }
+    }
+
+    length_histogram_portable(control_bytes)
+}
+
+/// `length_histogram()` without SIMD.
+fn length_histogram_portable(control_bytes: &[u8]) -> [u64; 4] {
+    const LOW_BITS: u64 = 0x5555_5555_5555_5555;
+
+    let mut histogram = [0_u64; 4];
+
+    let chunks = control_bytes.chunks_exact(8);
+    let remainder = chunks.remainder();
+
+    for chunk in chunks {
+        let mut word_bytes = [0_u8; 8];
+        word_bytes.copy_from_slice(chunk);
+        let word = u64::from_le_bytes(word_bytes);
+
+        // each 2-bit length code is split into its high and low bit, each in the low bit position
+        let high = (word >> 1) & LOW_BITS;
+        let low = word & LOW_BITS;
+
+        let fours = (high & low).count_ones() as u64;
+        let threes = (high & !low).count_ones() as u64;
+        let twos = (!high & low & LOW_BITS).count_ones() as u64;
+
+        histogram[1] += twos;
+        histogram[2] += threes;
+        histogram[3] += fours;
+        histogram[0] += 32 - twos - threes - fours;
+    }
+
+    for &control_byte in remainder {
+        let (len0, len1, len2, len3) = lengths::per_num(control_byte);
+        histogram[len0 as usize - 1] += 1;
+        histogram[len1 as usize - 1] += 1;
+        histogram[len2 as usize - 1] += 1;
+        histogram[len3 as usize - 1] += 1;
+    }
+
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate rand;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, Scalar};
+
+    #[test]
+    fn stats_count_lengths_including_partial_quad() {
+        // 1, 2, 3, 4 bytes, then 2 leftovers of 1 and 4 bytes
+        let nums = vec![1, 1 << 8, 1 << 16, 1 << 24, 0, u32::MAX];
+        let mut encoded = vec![0; nums.len() * 5];
+
+        let stats = encode_with_stats::<Scalar>(&nums, &mut encoded);
+
+        let expected_len = encode::<Scalar>(&nums, &mut vec![0; nums.len() * 5]);
+        assert_eq!(6, stats.input_count);
+        assert_eq!(expected_len, stats.output_bytes);
+        assert_eq!([2, 1, 1, 2], stats.len_histogram);
+        assert_eq!(expected_len as f64 / 24.0, stats.ratio);
+    }
+
+    #[test]
+    fn stats_encode_like_encode_across_chunks() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..100 {
+            let count = rng.gen_range(0, QUADS_PER_CHUNK * 4 * 3);
+            let nums: Vec<u32> = (0..count)
+                .map(|_| rng.gen::<u32>() >> rng.gen_range(0, 32))
+                .collect();
+            let mut expected = vec![0; count * 5];
+            let expected_len = encode::<Scalar>(&nums, &mut expected);
+
+            let mut encoded = vec![0; count * 5];
+            let stats = encode_with_stats::<Scalar>(&nums, &mut encoded);
+
+            assert_eq!(expected_len, stats.output_bytes);
+            assert_eq!(&expected[0..expected_len], &encoded[0..expected_len]);
+            #[cfg(all(feature = "encode", feature = "decode"))]
+            assert_eq!(
+                encoded_len_histogram(&expected, count),
+                stats.len_histogram
+            );
+        }
+    }
+
+    #[test]
+    fn length_histogram_matches_table_lookup() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..1000 {
+            let len = rng.gen_range(0, 100);
+            let control_bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut expected = [0_u64; 4];
+            for &b in &control_bytes {
+                for i in 0..4 {
+                    expected[((b >> (i * 2)) & 0x03) as usize] += 1;
+                }
+            }
+
+            assert_eq!(expected, length_histogram(&control_bytes));
+        }
+    }
+
+    #[cfg(any(feature = "x86_ssse3", feature = "x86_sse41"))]
+    #[test]
+    fn length_histogram_ssse3_matches_portable() {
+        if !ssse3::is_supported() {
+            return;
+        }
+        let mut rng = rand::weak_rng();
+
+        // long enough to need several sums of the per-byte counters
+        for _ in 0..300 {
+            let len = rng.gen_range(0, 5000);
+            let control_bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            assert_eq!(length_histogram_portable(&control_bytes), unsafe {
+                ssse3::length_histogram(&control_bytes)
+            });
+        }
+
+        let all_fours = vec![0xFF; 3000];
+        assert_eq!([0, 0, 0, 12_000], unsafe {
+            ssse3::length_histogram(&all_fours)
+        });
+    }
+
+    #[test]
+    fn length_histogram_every_byte_value() {
+        let control_bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+
+        // every length appears equally often in each of the 4 slots
+        assert_eq!([256, 256, 256, 256], length_histogram(&control_bytes));
+    }
+
+    #[test]
+    fn stats_empty_input() {
+        let stats = encode_with_stats::<Scalar>(&[], &mut []);
+
+        assert_eq!(
+            EncodeStats {
+                input_count: 0,
+                output_bytes: 0,
+                len_histogram: [0; 4],
+                ratio: 0.0,
+                block_sizes: Vec::new(),
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn stats_all_single_byte() {
+        let nums: Vec<u32> = (0..1000).map(|i| i % 256).collect();
+        let mut encoded = vec![0; nums.len() * 5];
+
+        let stats = encode_with_stats::<Scalar>(&nums, &mut encoded);
+
+        assert_eq!([1000, 0, 0, 0], stats.len_histogram);
+        assert_eq!(1250, stats.output_bytes);
+        assert_eq!(0.3125, stats.ratio);
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m128i, _mm_add_epi8, _mm_and_si128, _mm_loadu_si128, _mm_sad_epu8,
+                     _mm_set1_epi8, _mm_setzero_si128, _mm_shuffle_epi8, _mm_srli_epi16,
+                     _mm_storeu_si128};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, _mm_add_epi8, _mm_and_si128, _mm_loadu_si128, _mm_sad_epu8,
+                        _mm_set1_epi8, _mm_setzero_si128, _mm_shuffle_epi8, _mm_srli_epi16,
+                        _mm_storeu_si128};
+
+use super::length_histogram_portable;
+
+/// How many 16-byte chunks are counted into the per-byte counters before they're summed. Each
+/// chunk adds at most 4 to a counter, so any more could overflow a byte.
+const CHUNKS_PER_SUM: usize = 63;
+
+/// How many of the 2 length codes in each nibble value are 1 (2 byte numbers), 2 (3 byte numbers),
+/// and 3 (4 byte numbers), for use with `_mm_shuffle_epi8()`.
+const TWOS: [u8; 16] = [0, 1, 0, 0, 1, 2, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0];
+const THREES: [u8; 16] = [0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 2, 1, 0, 0, 1, 0];
+const FOURS: [u8; 16] = [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 1, 2];
+
+/// Whether the CPU supports SSSE3, which `length_histogram()` needs.
+#[inline]
+pub fn is_supported() -> bool {
+    cfg!(target_feature = "ssse3") || is_x86_feature_detected!("ssse3")
+}
+
+/// `super::length_histogram()`, 16 control bytes at a time.
+///
+/// Each nibble of a control byte holds 2 length codes, so looking up both nibbles in a table of how
+/// many codes of a given length each nibble value has counts that length in the byte. The per-byte
+/// counts are summed every `CHUNKS_PER_SUM` chunks.
+///
+/// # Safety
+///
+/// The CPU must support SSSE3, which `is_supported()` checks.
+#[target_feature(enable = "ssse3")]
+pub unsafe fn length_histogram(control_bytes: &[u8]) -> [u64; 4] {
+    let twos_table = _mm_loadu_si128(TWOS.as_ptr() as *const __m128i);
+    let threes_table = _mm_loadu_si128(THREES.as_ptr() as *const __m128i);
+    let fours_table = _mm_loadu_si128(FOURS.as_ptr() as *const __m128i);
+    let low_nibbles = _mm_set1_epi8(0x0F);
+
+    let chunks_len = control_bytes.len() / 16 * 16;
+    let mut histogram = length_histogram_portable(&control_bytes[chunks_len..]);
+
+    for batch in control_bytes[0..chunks_len].chunks(CHUNKS_PER_SUM * 16) {
+        let mut twos = _mm_setzero_si128();
+        let mut threes = _mm_setzero_si128();
+        let mut fours = _mm_setzero_si128();
+
+        for chunk in batch.chunks(16) {
+            let bytes = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let low = _mm_and_si128(bytes, low_nibbles);
+            // there's no 8-bit shift, but the mask drops the bits shifted in from the next byte
+            let high = _mm_and_si128(_mm_srli_epi16(bytes, 4), low_nibbles);
+
+            twos = _mm_add_epi8(twos, count(twos_table, low, high));
+            threes = _mm_add_epi8(threes, count(threes_table, low, high));
+            fours = _mm_add_epi8(fours, count(fours_table, low, high));
+        }
+
+        let twos = sum_bytes(twos);
+        let threes = sum_bytes(threes);
+        let fours = sum_bytes(fours);
+        histogram[1] += twos;
+        histogram[2] += threes;
+        histogram[3] += fours;
+        histogram[0] += (batch.len() * 4) as u64 - twos - threes - fours;
+    }
+
+    histogram
+}
+
+/// Look up both nibbles of each byte in `table` and add the results.
+#[inline]
+#[target_feature(enable = "ssse3")]
+unsafe fn count(table: __m128i, low: __m128i, high: __m128i) -> __m128i {
+    _mm_add_epi8(_mm_shuffle_epi8(table, low), _mm_shuffle_epi8(table, high))
+}
+
+/// Add up all 16 bytes of `counts`.
+#[inline]
+#[target_feature(enable = "ssse3")]
+unsafe fn sum_bytes(counts: __m128i) -> u64 {
+    // sums each half into the low 16 bits of a 64-bit lane
+    let sums = _mm_sad_epu8(counts, _mm_setzero_si128());
+    let mut lanes = [0_u64; 2];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, sums);
+
+    lanes[0] + lanes[1]
+}
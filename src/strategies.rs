@@ -0,0 +1,164 @@
+//! `proptest` strategies that generate encoded numbers, for property testing code that stores or
+//! parses Stream VByte data. Enable the `proptest` feature to use them.
+//!
+//! `encoded()` and `encoded_with_nums()` produce valid encodings. The other strategies produce
+//! encodings that are subtly wrong in the ways that data from outside sources tends to be, so you
+//! can check that your code rejects or tolerates them as it should.
+//!
+//! # Examples
+//!
+//! ```
+//! #[macro_use]
+//! extern crate proptest;
+//! extern crate stream_vbyte;
+//!
+//! use stream_vbyte::{validate, strategies};
+//!
+//! proptest! {
+//!     // add #[test] to run it with your tests
+//!     fn valid_encodings_validate((encoded, count) in strategies::encoded(100)) {
+//!         prop_assert_eq!(Some(encoded.len()), validate(&encoded, count));
+//!     }
+//! }
+//!
+//! fn main() {
+//!     valid_encodings_validate();
+//! }
+//! ```
+
+use proptest::collection;
+use proptest::prelude::*;
+
+use {cumulative_encoded_len, encode_append, encoded_shape, lengths, Scalar};
+
+/// Numbers with every encoded length about equally likely.
+fn num() -> impl Strategy<Value = u32> {
+    (any::<u32>(), 0_u32..4).prop_map(|(n, bytes_to_drop)| n >> (bytes_to_drop * 8))
+}
+
+/// Up to `max_count` numbers and their encoding.
+pub fn encoded_with_nums(max_count: usize) -> impl Strategy<Value = (Vec<u32>, Vec<u8>)> {
+    collection::vec(num(), 0..(max_count + 1)).prop_map(|nums| {
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+        (nums, encoded)
+    })
+}
+
+/// A valid encoding of up to `max_count` numbers, and the count.
+pub fn encoded(max_count: usize) -> impl Strategy<Value = (Vec<u8>, usize)> {
+    encoded_with_nums(max_count).prop_map(|(nums, encoded)| (encoded, nums.len()))
+}
+
+/// A valid encoding of between 1 and `max_count` numbers with at least one byte cut off the end,
+/// and the original count. `validate()` always rejects these.
+pub fn truncated(max_count: usize) -> impl Strategy<Value = (Vec<u8>, usize)> {
+    assert!(max_count > 0, "Must allow at least one number");
+
+    (encoded(max_count), any::<prop::sample::Index>())
+        .prop_filter("Need at least one number", |&((_, count), _)| count > 0)
+        .prop_map(|((mut encoded, count), cut)| {
+            let new_len = cut.index(encoded.len());
+            encoded.truncate(new_len);
+            (encoded, count)
+        })
+}
+
+/// An encoding of between 1 and `max_count` numbers where one number is encoded with more bytes
+/// than it needs, and the count. These decode to the right numbers, but aren't canonical (see
+/// `is_canonical()`), so they break the assumption that equal numbers have equal encodings.
+pub fn overlong(max_count: usize) -> impl Strategy<Value = (Vec<u8>, usize)> {
+    assert!(max_count > 0, "Must allow at least one number");
+
+    (
+        collection::vec(num(), 1..(max_count + 1)),
+        any::<prop::sample::Index>(),
+    ).prop_filter_map("Need a number shorter than 4 bytes", |(nums, index)| {
+            let shorter: Vec<usize> = nums.iter()
+                .enumerate()
+                .filter(|&(_, &n)| n < 1 << 24)
+                .map(|(i, _)| i)
+                .collect();
+            if shorter.is_empty() {
+                return None;
+            }
+
+            Some((encode_overlong(&nums, *index.get(&shorter)), nums.len()))
+        })
+}
+
+/// A valid encoding of between 1 and `max_count` numbers with one control byte replaced by a
+/// random byte, and the count. These may or may not be long enough to decode, but if they do,
+/// they decode to the wrong numbers.
+pub fn corrupted_control_byte(max_count: usize) -> impl Strategy<Value = (Vec<u8>, usize)> {
+    assert!(max_count > 0, "Must allow at least one number");
+
+    (encoded(max_count), any::<prop::sample::Index>(), any::<u8>())
+        .prop_filter("Need at least one number", |&((_, count), _, _)| count > 0)
+        .prop_map(|((mut encoded, count), index, control_byte)| {
+            let control_bytes_len = encoded_shape(count).control_bytes_len;
+            encoded[index.index(control_bytes_len)] = control_byte;
+            (encoded, count)
+        })
+}
+
+/// Any of `truncated()`, `overlong()`, or `corrupted_control_byte()`.
+pub fn near_valid(max_count: usize) -> impl Strategy<Value = (Vec<u8>, usize)> {
+    prop_oneof![
+        truncated(max_count),
+        overlong(max_count),
+        corrupted_control_byte(max_count),
+    ]
+}
+
+/// Encode `nums`, using one more byte than necessary for the number at `index`.
+fn encode_overlong(nums: &[u32], index: usize) -> Vec<u8> {
+    let shape = encoded_shape(nums.len());
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(nums, &mut encoded);
+
+    let quad = index / 4;
+    let shift = (index % 4) * 2;
+    let len = lengths::num_len(encoded[quad], index % 4);
+    debug_assert!(len < 4);
+
+    // find where the number is and insert a zero most significant byte
+    let offset = shape.control_bytes_len + cumulative_encoded_len(&encoded[0..quad])
+        + lengths::partial_quad_len(encoded[quad], index % 4);
+    encoded.insert(offset + len, 0);
+    encoded[quad] += 1 << shift;
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {decode, is_canonical, validate};
+
+    proptest! {
+        #[test]
+        fn encoded_with_nums_decodes((nums, encoded) in encoded_with_nums(50)) {
+            let mut decoded = vec![0; nums.len()];
+            prop_assert_eq!(encoded.len(), decode::<Scalar>(&encoded, nums.len(), &mut decoded));
+            prop_assert_eq!(nums, decoded);
+        }
+
+        #[test]
+        fn encoded_is_valid_and_canonical((encoded, count) in encoded(50)) {
+            prop_assert_eq!(Some(encoded.len()), validate(&encoded, count));
+            prop_assert!(is_canonical(&encoded, count));
+        }
+
+        #[test]
+        fn truncated_is_invalid((encoded, count) in truncated(50)) {
+            prop_assert_eq!(None, validate(&encoded, count));
+        }
+
+        #[test]
+        fn overlong_is_valid_but_not_canonical((encoded, count) in overlong(50)) {
+            prop_assert_eq!(Some(encoded.len()), validate(&encoded, count));
+            prop_assert!(!is_canonical(&encoded, count));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 #[cfg_attr(rustfmt, rustfmt_skip)]
+#[cfg(all(any(feature = "encode", feature = "decode"), not(feature = "compact-tables")))]
 pub const DECODE_LENGTH_PER_NUM_TABLE: &'static [(u8, u8, u8, u8); 256] = &[
     (1, 1, 1, 1), // 0 = 0x0 = 0b00000000, lengths 1 1 1 1
     (2, 1, 1, 1), // 1 = 0x1 = 0b00000001, lengths 2 1 1 1
@@ -259,6 +260,7 @@ pub const DECODE_LENGTH_PER_NUM_TABLE: &'static [(u8, u8, u8, u8); 256] = &[
 ];
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
+#[cfg(not(feature = "compact-tables"))]
 pub const DECODE_LENGTH_PER_QUAD_TABLE: &'static [u8; 256] = &[
     4, // 0 = 0x0 = 0b00000000, lengths 1 1 1 1
     5, // 1 = 0x1 = 0b00000001, lengths 2 1 1 1
@@ -23,6 +23,22 @@ fn encode_decode_roundtrip_random() {
     }
 }
 
+#[test]
+fn default_encoder_decoder_roundtrip() {
+    let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+        .take(1_001)
+        .collect();
+    let mut encoded = vec![0; nums.len() * 5];
+    let mut decoded = vec![0; nums.len()];
+
+    let encoded_len = encode::<DefaultEncoder>(&nums, &mut encoded);
+    assert_eq!(
+        encoded_len,
+        decode::<DefaultDecoder>(&encoded[0..encoded_len], nums.len(), &mut decoded)
+    );
+    assert_eq!(nums, decoded);
+}
+
 #[test]
 fn encoded_shape_len_0() {
     let shape = encoded_shape(0);
@@ -112,3 +128,40 @@ fn cumulative_encoded_len_accurate_complete_quad() {
         );
     }
 }
+
+#[test]
+fn scalar_constant_and_zero_quads_match_general_encoding() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..1000 {
+        // runs of equal numbers mixed with other numbers, so some quads are constant
+        let mut nums: Vec<u32> = Vec::new();
+        while nums.len() < 100 {
+            let num = rng.gen::<u32>() >> rng.gen_range(0, 32);
+            for _ in 0..rng.gen_range(1, 10) {
+                nums.push(num);
+            }
+        }
+
+        // encode each number separately, bypassing the constant quad path
+        let shape = encoded_shape(nums.len());
+        let mut expected = vec![0; shape.control_bytes_len];
+        let mut buf = [0; 4];
+        for (i, &num) in nums.iter().enumerate() {
+            let len = encode_num_scalar(num, &mut buf);
+            expected[i / 4] |= ((len - 1) << ((i % 4) * 2)) as u8;
+            expected.extend_from_slice(&buf[0..len]);
+        }
+
+        let mut encoded = vec![0; nums.len() * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        assert_eq!(expected, &encoded[0..encoded_len]);
+
+        let mut decoded = vec![0; nums.len()];
+        assert_eq!(
+            encoded_len,
+            decode::<Scalar>(&encoded[0..encoded_len], nums.len(), &mut decoded)
+        );
+        assert_eq!(nums, decoded);
+    }
+}
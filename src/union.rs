@@ -0,0 +1,465 @@
+use {Decoder, SortedCursor, ZipCursor};
+
+/// How many rows of each list `union_encoded_payloads()` decodes at a time.
+const BLOCK_LEN: usize = 64;
+
+/// Receives the numbers in the union of two sorted lists from `union_encoded_sink()`, in
+/// ascending order, each once along with how many times it occurs in the two lists combined.
+pub trait UnionSink {
+    fn on_number(&mut self, num: u32, multiplicity: usize);
+}
+
+/// Collects `(number, multiplicity)` pairs.
+impl UnionSink for Vec<(u32, usize)> {
+    fn on_number(&mut self, num: u32, multiplicity: usize) {
+        self.push((num, multiplicity));
+    }
+}
+
+/// Append the numbers that are in either of two encoded lists of ascending numbers, such as
+/// posting lists, to `output` in ascending order, each only once. Returns how many there were.
+///
+/// Numbers may repeat within a list too; use `union_encoded_sink()` to find out how many times
+/// each number occurs rather than dropping the duplicates.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, union_encoded, Scalar};
+///
+/// let a = [1, 3, 5, 7];
+/// let b = [2, 3, 4, 5, 5];
+/// let mut encoded_a = vec![0; a.len() * 5];
+/// encode::<Scalar>(&a, &mut encoded_a);
+/// let mut encoded_b = vec![0; b.len() * 5];
+/// encode::<Scalar>(&b, &mut encoded_b);
+///
+/// let mut either = Vec::new();
+/// let len = union_encoded::<Scalar>(&encoded_a, a.len(), &encoded_b, b.len(), &mut either);
+///
+/// assert_eq!(6, len);
+/// assert_eq!(vec![1, 2, 3, 4, 5, 7], either);
+/// # }
+/// ```
+pub fn union_encoded<D: Decoder>(
+    a: &[u8],
+    a_count: usize,
+    b: &[u8],
+    b_count: usize,
+    output: &mut Vec<u32>,
+) -> usize {
+    union_encoded_sink::<D, _>(a, a_count, b, b_count, &mut Distinct { output })
+}
+
+/// Hand each number that's in either of two encoded lists of ascending numbers to `sink`, in
+/// ascending order, along with how many times it occurs in the two lists combined. Returns how
+/// many distinct numbers there were.
+///
+/// This is for merging lists where each occurrence of a number counts for something, like
+/// frequency-bearing posting lists, so that duplicates are added up rather than dropped.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, union_encoded_sink, Scalar};
+///
+/// let a = [1, 3, 3, 7];
+/// let b = [3, 4, 7];
+/// let mut encoded_a = vec![0; a.len() * 5];
+/// encode::<Scalar>(&a, &mut encoded_a);
+/// let mut encoded_b = vec![0; b.len() * 5];
+/// encode::<Scalar>(&b, &mut encoded_b);
+///
+/// let mut counts: Vec<(u32, usize)> = Vec::new();
+/// let len =
+///     union_encoded_sink::<Scalar, _>(&encoded_a, a.len(), &encoded_b, b.len(), &mut counts);
+///
+/// assert_eq!(4, len);
+/// assert_eq!(vec![(1, 1), (3, 3), (4, 1), (7, 2)], counts);
+/// # }
+/// ```
+pub fn union_encoded_sink<D: Decoder, S: UnionSink>(
+    a: &[u8],
+    a_count: usize,
+    b: &[u8],
+    b_count: usize,
+    sink: &mut S,
+) -> usize {
+    let mut a = SortedCursor::<D>::new(a, a_count);
+    let mut b = SortedCursor::<D>::new(b, b_count);
+    let mut run = Run {
+        num: 0,
+        multiplicity: 0,
+        distinct: 0,
+    };
+
+    loop {
+        let (a_used, b_used) = {
+            let a_nums = a.buffered();
+            let b_nums = b.buffered();
+            if a_nums.is_empty() && b_nums.is_empty() {
+                break;
+            }
+
+            let mut i = 0;
+            let mut j = 0;
+            while i < a_nums.len() && j < b_nums.len() {
+                if a_nums[i] <= b_nums[j] {
+                    run.add(a_nums[i], sink);
+                    i += 1;
+                } else {
+                    run.add(b_nums[j], sink);
+                    j += 1;
+                }
+            }
+
+            // Only once one list has run out can the other's block be used up without knowing
+            // what comes after the first's block.
+            if b_nums.is_empty() {
+                for &num in &a_nums[i..] {
+                    run.add(num, sink);
+                }
+                i = a_nums.len();
+            } else if a_nums.is_empty() {
+                for &num in &b_nums[j..] {
+                    run.add(num, sink);
+                }
+                j = b_nums.len();
+            }
+
+            (i, j)
+        };
+        a.consume(a_used);
+        b.consume(b_used);
+    }
+
+    run.finish(sink)
+}
+
+/// Append the union of two lists of doc IDs in ascending order, each with a parallel stream of
+/// payloads (term frequencies, scores...), to `output` as `(doc ID, payload)` pairs, so that
+/// merging indexes doesn't lose the payloads.
+///
+/// Each list is a `ZipCursor` over two streams of the same length: the doc IDs, then their
+/// payloads. A doc ID that occurs more than once, in either list, is output once, with the
+/// payloads of its occurrences folded together with `combine(so_far, next)`: those from `a`
+/// first, then those from `b`, each in order. A doc ID that occurs once keeps its payload as it
+/// is.
+///
+/// Returns the number of pairs appended.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "encode")] {
+/// use stream_vbyte::{encode, union_encoded_payloads, DecodeCursor, Scalar, ZipCursor};
+///
+/// fn encoded(nums: &[u32]) -> Vec<u8> {
+///     let mut encoded = vec![0; nums.len() * 5];
+///     encode::<Scalar>(nums, &mut encoded);
+///     encoded
+/// }
+///
+/// let (a_ids, a_freqs) = (encoded(&[1, 3, 7]), encoded(&[2, 1, 5]));
+/// let (b_ids, b_freqs) = (encoded(&[3, 4]), encoded(&[10, 1]));
+///
+/// let a = ZipCursor::new([DecodeCursor::new(&a_ids, 3), DecodeCursor::new(&a_freqs, 3)]);
+/// let b = ZipCursor::new([DecodeCursor::new(&b_ids, 2), DecodeCursor::new(&b_freqs, 2)]);
+///
+/// let mut merged = Vec::new();
+/// let len = union_encoded_payloads::<Scalar, _>(a, b, |x, y| x + y, &mut merged);
+///
+/// assert_eq!(4, len);
+/// assert_eq!(vec![(1, 2), (3, 11), (4, 1), (7, 5)], merged);
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if the two streams of either list aren't the same length.
+pub fn union_encoded_payloads<'a, 'b, D: Decoder, F: FnMut(u32, u32) -> u32>(
+    a: ZipCursor<'a, 2>,
+    b: ZipCursor<'b, 2>,
+    mut combine: F,
+    output: &mut Vec<(u32, u32)>,
+) -> usize {
+    let start_len = output.len();
+    let mut a = Rows::new(a);
+    let mut b = Rows::new(b);
+
+    {
+        let mut add = |[id, payload]: [u32; 2]| {
+            if output.len() > start_len && output[output.len() - 1].0 == id {
+                let last = output.len() - 1;
+                output[last].1 = combine(output[last].1, payload);
+            } else {
+                output.push((id, payload));
+            }
+        };
+
+        loop {
+            let (a_used, b_used) = {
+                let a_rows = a.buffered::<D>();
+                let b_rows = b.buffered::<D>();
+                if a_rows.is_empty() && b_rows.is_empty() {
+                    break;
+                }
+
+                let mut i = 0;
+                let mut j = 0;
+                while i < a_rows.len() && j < b_rows.len() {
+                    if a_rows[i][0] <= b_rows[j][0] {
+                        add(a_rows[i]);
+                        i += 1;
+                    } else {
+                        add(b_rows[j]);
+                        j += 1;
+                    }
+                }
+
+                // as in `union_encoded_sink()`
+                if b_rows.is_empty() {
+                    for &row in &a_rows[i..] {
+                        add(row);
+                    }
+                    i = a_rows.len();
+                } else if a_rows.is_empty() {
+                    for &row in &b_rows[j..] {
+                        add(row);
+                    }
+                    j = b_rows.len();
+                }
+
+                (i, j)
+            };
+            a.consume(a_used);
+            b.consume(b_used);
+        }
+    }
+
+    output.len() - start_len
+}
+
+/// A block at a time of the rows of a `ZipCursor`.
+struct Rows<'a> {
+    cursor: ZipCursor<'a, 2>,
+    buf: [[u32; 2]; BLOCK_LEN],
+    buf_pos: usize,
+    buf_len: usize,
+}
+
+impl<'a> Rows<'a> {
+    fn new(cursor: ZipCursor<'a, 2>) -> Rows<'a> {
+        Rows {
+            cursor,
+            buf: [[0; 2]; BLOCK_LEN],
+            buf_pos: 0,
+            buf_len: 0,
+        }
+    }
+
+    /// The decoded rows from the current one to the end of the block, decoding the next block if
+    /// there are none left in this one. Empty once every row has been read.
+    fn buffered<D: Decoder>(&mut self) -> &[[u32; 2]] {
+        if self.buf_pos == self.buf_len {
+            self.buf_len = self.cursor.decode_rows::<D>(&mut self.buf);
+            self.buf_pos = 0;
+        }
+
+        &self.buf[self.buf_pos..self.buf_len]
+    }
+
+    fn consume(&mut self, n: usize) {
+        debug_assert!(self.buf_pos + n <= self.buf_len);
+        self.buf_pos += n;
+    }
+}
+
+/// The number being counted, and how many distinct numbers have been handed to the sink.
+struct Run {
+    num: u32,
+    multiplicity: usize,
+    distinct: usize,
+}
+
+impl Run {
+    #[inline]
+    fn add<S: UnionSink>(&mut self, num: u32, sink: &mut S) {
+        if self.multiplicity > 0 && num == self.num {
+            self.multiplicity += 1;
+            return;
+        }
+
+        if self.multiplicity > 0 {
+            sink.on_number(self.num, self.multiplicity);
+            self.distinct += 1;
+        }
+        self.num = num;
+        self.multiplicity = 1;
+    }
+
+    fn finish<S: UnionSink>(self, sink: &mut S) -> usize {
+        if self.multiplicity > 0 {
+            sink.on_number(self.num, self.multiplicity);
+            self.distinct + 1
+        } else {
+            self.distinct
+        }
+    }
+}
+
+/// Appends each number once, ignoring how many times it occurs.
+struct Distinct<'a> {
+    output: &'a mut Vec<u32>,
+}
+
+impl<'a> UnionSink for Distinct<'a> {
+    #[inline]
+    fn on_number(&mut self, num: u32, _multiplicity: usize) {
+        self.output.push(num);
+    }
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    extern crate rand;
+
+    use std::collections::BTreeMap;
+
+    use self::rand::Rng;
+
+    use super::*;
+    use {encode, DecodeCursor, Scalar};
+
+    #[test]
+    fn union_random_lists_with_duplicates() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..300 {
+            let universe = rng.gen_range(1, 5_000);
+            let a = random_list(&mut rng, universe);
+            let b = random_list(&mut rng, universe);
+            let mut expected = BTreeMap::new();
+            for &num in a.iter().chain(b.iter()) {
+                *expected.entry(num).or_insert(0) += 1;
+            }
+            let expected: Vec<(u32, usize)> = expected.into_iter().collect();
+
+            let mut encoded_a = vec![0; a.len() * 5];
+            encode::<Scalar>(&a, &mut encoded_a);
+            let mut encoded_b = vec![0; b.len() * 5];
+            encode::<Scalar>(&b, &mut encoded_b);
+
+            let mut counts = Vec::new();
+            let len = union_encoded_sink::<Scalar, _>(
+                &encoded_a,
+                a.len(),
+                &encoded_b,
+                b.len(),
+                &mut counts,
+            );
+            assert_eq!(expected.len(), len);
+            assert_eq!(expected, counts);
+
+            // existing contents are kept
+            let mut output = vec![7];
+            let len =
+                union_encoded::<Scalar>(&encoded_a, a.len(), &encoded_b, b.len(), &mut output);
+            assert_eq!(expected.len(), len);
+            assert_eq!(7, output[0]);
+            assert_eq!(
+                expected.iter().map(|&(num, _)| num).collect::<Vec<u32>>(),
+                &output[1..]
+            );
+        }
+    }
+
+    #[test]
+    fn union_with_empty_list() {
+        let nums = [0, 0, 5, 9, 9, 9];
+        let mut encoded = vec![0; nums.len() * 5];
+        encode::<Scalar>(&nums, &mut encoded);
+
+        let mut counts = Vec::new();
+        assert_eq!(
+            3,
+            union_encoded_sink::<Scalar, _>(&encoded, nums.len(), &[], 0, &mut counts)
+        );
+        assert_eq!(vec![(0, 2), (5, 1), (9, 3)], counts);
+
+        let mut output = Vec::new();
+        assert_eq!(
+            3,
+            union_encoded::<Scalar>(&[], 0, &encoded, nums.len(), &mut output)
+        );
+        assert_eq!(vec![0, 5, 9], output);
+
+        assert_eq!(0, union_encoded::<Scalar>(&[], 0, &[], 0, &mut output));
+        assert_eq!(3, output.len());
+    }
+
+    #[test]
+    fn union_payloads_random_lists_with_duplicates() {
+        let mut rng = rand::weak_rng();
+
+        for _ in 0..300 {
+            let universe = rng.gen_range(1, 5_000);
+            let a_ids = random_list(&mut rng, universe);
+            let b_ids = random_list(&mut rng, universe);
+            let a_payloads: Vec<u32> = a_ids.iter().map(|_| rng.gen_range(0, 1000)).collect();
+            let b_payloads: Vec<u32> = b_ids.iter().map(|_| rng.gen_range(0, 1000)).collect();
+
+            // an order-dependent combination, to check that payloads are folded in order
+            let combine = |x: u32, y: u32| x.wrapping_mul(31).wrapping_add(y);
+            let mut expected: BTreeMap<u32, u32> = BTreeMap::new();
+            for (&id, &payload) in a_ids
+                .iter()
+                .zip(a_payloads.iter())
+                .chain(b_ids.iter().zip(b_payloads.iter()))
+            {
+                let folded = match expected.get(&id) {
+                    Some(&so_far) => combine(so_far, payload),
+                    None => payload,
+                };
+                expected.insert(id, folded);
+            }
+            let expected: Vec<(u32, u32)> = expected.into_iter().collect();
+
+            let encoded: Vec<Vec<u8>> = [&a_ids, &a_payloads, &b_ids, &b_payloads]
+                .iter()
+                .map(|nums| {
+                    let mut encoded = vec![0; nums.len() * 5];
+                    encode::<Scalar>(nums, &mut encoded);
+                    encoded
+                })
+                .collect();
+            let a = ZipCursor::new([
+                DecodeCursor::new(&encoded[0], a_ids.len()),
+                DecodeCursor::new(&encoded[1], a_ids.len()),
+            ]);
+            let b = ZipCursor::new([
+                DecodeCursor::new(&encoded[2], b_ids.len()),
+                DecodeCursor::new(&encoded[3], b_ids.len()),
+            ]);
+
+            // existing contents are kept, and not combined with
+            let mut output = vec![(expected.first().map_or(0, |p| p.0), 7)];
+            let len = union_encoded_payloads::<Scalar, _>(a, b, combine, &mut output);
+            assert_eq!(expected.len(), len);
+            assert_eq!(7, output[0].1);
+            assert_eq!(expected, &output[1..]);
+        }
+    }
+
+    fn random_list<R: Rng>(rng: &mut R, universe: u32) -> Vec<u32> {
+        // anywhere from nearly every number to a handful, with some repeated
+        let density = rng.gen_range(1, 200);
+        let mut nums: Vec<u32> = (0..rng.gen_range(0, universe / density + 2))
+            .map(|_| rng.gen_range(0, universe))
+            .collect();
+        nums.sort();
+        nums
+    }
+}
@@ -1,7 +1,15 @@
 //! x86-specific accelerated code.
 
+// The SIMD code assumes little-endian lanes, which is only guaranteed on x86.
+#[cfg(all(
+    any(feature = "x86_ssse3", feature = "x86_sse41"),
+    not(any(target_arch = "x86", target_arch = "x86_64"))
+))]
+compile_error!("The x86_ssse3 and x86_sse41 features require an x86 or x86_64 target");
+
 #[cfg(feature = "x86_ssse3")]
-pub use decode::ssse3::Ssse3;
+pub use decode::ssse3::{Prefetch1024, Prefetch256, Prefetch512, PrefetchDistance, Ssse3,
+                        Ssse3Prefetch};
 
 #[cfg(feature = "x86_sse41")]
 pub use encode::sse41::Sse41;
@@ -0,0 +1,87 @@
+extern crate rand;
+extern crate stream_vbyte;
+
+use self::rand::Rng;
+
+use stream_vbyte::*;
+
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::*;
+
+#[test]
+fn encode_chunks_random_matches_encode_scalar() {
+    do_encode_chunks_random_matches_encode::<Scalar>();
+}
+
+#[cfg(feature = "x86_sse41")]
+#[test]
+fn encode_chunks_random_matches_encode_sse41() {
+    do_encode_chunks_random_matches_encode::<x86::Sse41>();
+}
+
+#[test]
+fn encode_chunks_with_empty_chunks() {
+    let nums: Vec<u32> = (0..9).map(|i| i * 1000).collect();
+
+    let chunks: Vec<&[u32]> = vec![&[], &nums[0..1], &[], &nums[1..9], &[]];
+    let mut encoded = vec![0; nums.len() * 5];
+    let encoded_len = encode_chunks::<Scalar>(&chunks, &mut encoded);
+
+    let mut expected = vec![0; nums.len() * 5];
+    let expected_len = encode::<Scalar>(&nums, &mut expected);
+
+    assert_eq!(&expected[0..expected_len], &encoded[0..encoded_len]);
+}
+
+#[test]
+fn encode_chunks_nothing() {
+    assert_eq!(0, encode_chunks::<Scalar>(&[], &mut []));
+}
+
+#[test]
+#[should_panic(expected = "Pushed more numbers than the declared count")]
+fn chunked_encoder_too_many_panics() {
+    let mut encoded = vec![0; 20];
+    let mut encoder = ChunkedEncoder::new(&mut encoded, 3);
+    encoder.push::<Scalar>(&[1, 2, 3, 4]);
+}
+
+#[test]
+#[should_panic(expected = "Pushed fewer numbers than the declared count")]
+fn chunked_encoder_too_few_panics() {
+    let mut encoded = vec![0; 20];
+    let mut encoder = ChunkedEncoder::new(&mut encoded, 3);
+    encoder.push::<Scalar>(&[1, 2]);
+    encoder.finish();
+}
+
+fn do_encode_chunks_random_matches_encode<E: Encoder>() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..1_000 {
+        let count = rng.gen_range(0, 1000);
+        let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+            .take(count)
+            .collect();
+
+        // split at random points, including empty chunks
+        let mut chunks: Vec<&[u32]> = Vec::new();
+        let mut start = 0;
+        while start < count {
+            let end = rng.gen_range(start, count + 1);
+            chunks.push(&nums[start..end]);
+            start = end;
+        }
+
+        let mut expected = vec![0; count * 5];
+        let expected_len = encode::<E>(&nums, &mut expected);
+
+        let mut encoded = vec![0; count * 5];
+        let encoded_len = encode_chunks::<E>(&chunks, &mut encoded);
+
+        assert_eq!(expected_len, encoded_len);
+        assert_eq!(&expected[0..expected_len], &encoded[0..encoded_len]);
+    }
+}
@@ -1,11 +1,13 @@
 extern crate rand;
 extern crate stream_vbyte;
 
-#[cfg(feature = "x86_ssse3")]
-extern crate stdsimd;
-
 use std::cmp;
 
+#[cfg(all(feature = "x86_ssse3", target_arch = "x86"))]
+use std::arch::x86::__m128i;
+#[cfg(all(feature = "x86_ssse3", target_arch = "x86_64"))]
+use std::arch::x86_64::__m128i;
+
 use self::rand::Rng;
 
 use stream_vbyte::*;
@@ -148,10 +150,52 @@ fn decode_cursor_sink_decode_after_finishing_input_decodes_0_numbers_ssse3() {
     do_decode_cursor_sink_decode_after_finishing_input_decodes_0_numbers::<x86::Ssse3>()
 }
 
-fn do_decode_cursor_slice_every_decode_len<D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+#[test]
+fn decode_cursor_byte_offset_of_resumes_with_from_parts() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..100 {
+        let count = rng.gen_range(0, 200);
+        let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+            .take(count)
+            .collect();
+        let mut encoded = vec![0; count * 5];
+        let encoded_len = encode::<Scalar>(&nums, &mut encoded);
+        let (control_bytes, encoded_nums) = encoded[0..encoded_len].split_at((count + 3) / 4);
+
+        let cursor = DecodeCursor::new(&encoded[0..encoded_len], count);
+
+        for index in (0..(count + 1)).filter(|i| i % QUAD_LEN == 0) {
+            let (control_byte_index, encoded_nums_offset) = cursor.byte_offset_of(index);
+
+            let mut skipped = DecodeCursor::new(&encoded[0..encoded_len], count);
+            skipped.skip(cmp::min(index, count / QUAD_LEN * QUAD_LEN));
+            assert_eq!(index / QUAD_LEN, control_byte_index);
+            assert_eq!(
+                skipped.input_consumed(),
+                control_bytes.len() + encoded_nums_offset
+            );
+
+            let mut resumed = DecodeCursor::from_parts(
+                &control_bytes[control_byte_index..],
+                &encoded_nums[encoded_nums_offset..],
+                count - index,
+            );
+            let mut decoded = vec![0; count - index + QUAD_LEN];
+            assert_eq!(count - index, resumed.decode_slice::<Scalar>(&mut decoded));
+            assert_eq!(&nums[index..], &decoded[0..(count - index)]);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Must be a multiple of 4")]
+fn decode_cursor_byte_offset_of_unaligned_panics() {
+    let encoded = [0; 10];
+    DecodeCursor::new(&encoded, 8).byte_offset_of(2);
+}
+
+fn do_decode_cursor_slice_every_decode_len<D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -219,10 +263,7 @@ where
     }
 }
 
-fn do_decode_cursor_slice_random_decode_len<D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_decode_cursor_slice_random_decode_len<D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -277,10 +318,7 @@ where
     }
 }
 
-fn do_decode_cursor_skip_every_allowable_len_from_start<D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_decode_cursor_skip_every_allowable_len_from_start<D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -329,10 +367,7 @@ where
     }
 }
 
-fn do_decode_cursor_slice_input_only_partial_quad_decodes_all<D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_decode_cursor_slice_input_only_partial_quad_decodes_all<D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -371,10 +406,7 @@ where
 }
 
 
-fn do_decode_cursor_skip_every_allowable_len_between_decodes<D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_decode_cursor_skip_every_allowable_len_between_decodes<D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -743,13 +775,14 @@ impl DecodeQuadSink<()> for TupleSink {
 }
 
 #[cfg(feature = "x86_ssse3")]
-impl DecodeQuadSink<stdsimd::simd::u8x16> for TupleSink {
-    fn on_quad(&mut self, quad: stdsimd::simd::u8x16, nums_decoded: usize) {
-        let u32s = stdsimd::simd::u32x4::from(quad);
-        self.tuples.push((nums_decoded, u32s.extract(0)));
-        self.tuples.push((nums_decoded + 1, u32s.extract(1)));
-        self.tuples.push((nums_decoded + 2, u32s.extract(2)));
-        self.tuples.push((nums_decoded + 3, u32s.extract(3)));
+impl DecodeQuadSink<__m128i> for TupleSink {
+    fn on_quad(&mut self, quad: __m128i, nums_decoded: usize) {
+        let mut u32s = [0; 4];
+        quad.write_to_slice(&mut u32s);
+        self.tuples.push((nums_decoded, u32s[0]));
+        self.tuples.push((nums_decoded + 1, u32s[1]));
+        self.tuples.push((nums_decoded + 2, u32s[2]));
+        self.tuples.push((nums_decoded + 3, u32s[3]));
     }
 }
 
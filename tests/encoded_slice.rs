@@ -0,0 +1,173 @@
+extern crate rand;
+extern crate stream_vbyte;
+
+use self::rand::Rng;
+
+use stream_vbyte::*;
+
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::*;
+
+#[test]
+fn random_nested_slices_scalar() {
+    do_random_nested_slices::<Scalar>();
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[test]
+fn random_nested_slices_ssse3() {
+    do_random_nested_slices::<x86::Ssse3>();
+}
+
+#[test]
+fn get_each_index() {
+    let nums: Vec<u32> = (0..23).map(|i| 1 << i).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded, nums.len());
+
+    for (i, &n) in nums.iter().enumerate() {
+        assert_eq!(Some(n), slice.get(i));
+    }
+    assert_eq!(None, slice.get(nums.len()));
+}
+
+#[test]
+fn rev_take_from_end() {
+    let nums: Vec<u32> = (0..1000).map(|i| i * 7).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded, nums.len());
+
+    let last: Vec<u32> = slice.iter::<Scalar>().rev().take(3).collect();
+    assert_eq!(vec![6993, 6986, 6979], last);
+}
+
+#[test]
+fn size_hint_is_exact_at_every_step() {
+    let nums: Vec<u32> = (0..300).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded, nums.len()).slice(5..290);
+
+    let mut iter = slice.iter::<Scalar>();
+    let mut rev_iter = slice.iter_rev::<Scalar>();
+    for remaining in (0..286).rev() {
+        assert_eq!((remaining, Some(remaining)), iter.size_hint());
+        assert_eq!((remaining, Some(remaining)), rev_iter.size_hint());
+        iter.next();
+        rev_iter.next();
+    }
+
+    // stays exhausted
+    assert_eq!(None, iter.next());
+    assert_eq!(None, rev_iter.next());
+    assert_eq!((0, Some(0)), iter.size_hint());
+}
+
+#[test]
+fn empty_slice() {
+    let nums: Vec<u32> = (0..10).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded, nums.len()).slice(3..3);
+
+    assert!(slice.is_empty());
+    assert_eq!(None, slice.get(0));
+    assert_eq!(0, slice.iter::<Scalar>().count());
+    assert_eq!(0, slice.iter_rev::<Scalar>().count());
+}
+
+#[test]
+fn debug_summarizes_structure() {
+    let nums: Vec<u32> = (0..10).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+    let slice = EncodedSlice::new(&encoded, nums.len()).slice(2..7);
+
+    assert_eq!(
+        "EncodedSlice { len: 5, quads: 2, head: 2 }",
+        format!("{:?}", slice)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Range out of bounds")]
+fn slice_past_end_panics() {
+    let nums: Vec<u32> = (0..10).collect();
+    let mut encoded = Vec::new();
+    encode_append::<Scalar>(&nums, &mut encoded);
+
+    EncodedSlice::new(&encoded, nums.len()).slice(5..11);
+}
+
+fn do_random_nested_slices<D: Decoder>() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..1_000 {
+        let count = rng.gen_range(0, 500);
+        let nums: Vec<u32> = RandomVarintEncodedLengthIter::new(rand::weak_rng())
+            .take(count)
+            .collect();
+        let mut encoded = Vec::new();
+        encode_append::<Scalar>(&nums, &mut encoded);
+
+        let mut slice = EncodedSlice::new(&encoded, count);
+        let mut expected = &nums[..];
+
+        for _ in 0..3 {
+            assert_eq!(expected.len(), slice.len());
+            assert_eq!(expected.len(), slice.iter::<D>().len());
+            assert_eq!(expected, &slice.iter::<D>().collect::<Vec<u32>>()[..]);
+
+            assert_eq!(expected.len(), slice.iter_rev::<D>().len());
+            let mut reversed = slice.iter_rev::<D>().collect::<Vec<u32>>();
+            reversed.reverse();
+            assert_eq!(expected, &reversed[..]);
+
+            let mut iter = slice.iter::<D>();
+            let mut pos = 0;
+            loop {
+                let n = rng.gen_range(0, 100);
+                let num = iter.nth(n);
+                assert_eq!(expected.get(pos + n).cloned(), num);
+                if num.is_none() {
+                    break;
+                }
+                pos += n + 1;
+                assert_eq!(expected.len() - pos, iter.len());
+            }
+
+            // alternate between the ends at random
+            let mut iter = slice.iter::<D>();
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            loop {
+                let next = if rng.gen() {
+                    iter.next().map(|n| front.push(n))
+                } else {
+                    iter.next_back().map(|n| back.push(n))
+                };
+                if next.is_none() {
+                    break;
+                }
+                assert_eq!(expected.len() - front.len() - back.len(), iter.len());
+            }
+            back.reverse();
+            front.extend_from_slice(&back);
+            assert_eq!(expected, &front[..]);
+
+            if !expected.is_empty() {
+                let i = rng.gen_range(0, expected.len());
+                assert_eq!(Some(expected[i]), slice.get(i));
+            }
+
+            let start = rng.gen_range(0, expected.len() + 1);
+            let end = rng.gen_range(start, expected.len() + 1);
+            slice = slice.slice(start..end);
+            expected = &expected[start..end];
+        }
+    }
+}
@@ -0,0 +1,91 @@
+extern crate stream_vbyte;
+
+use stream_vbyte::*;
+
+// Encoded numbers are always little-endian, whatever the target's byte order. These vectors are
+// written out byte by byte rather than computed, so they check the same bytes on every target.
+
+// numbers whose bytes are all different, so swapped or misplaced bytes are obvious
+const NUMS: &[u32] = &[
+    0x04,
+    0x0304,
+    0x02_0304,
+    0x0102_0304,
+    0xF1F2_F3F4,
+    0x00,
+    0xA1A2,
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const ENCODED: &[u8] = &[
+    // control bytes: lengths 1, 2, 3, 4, then 4, 1, 2 in the partial quad
+    0xE4, 0x13,
+    0x04,
+    0x04, 0x03,
+    0x04, 0x03, 0x02,
+    0x04, 0x03, 0x02, 0x01,
+    0xF4, 0xF3, 0xF2, 0xF1,
+    0x00,
+    0xA2, 0xA1,
+];
+
+#[test]
+fn encode_little_endian_scalar() {
+    do_encode_little_endian::<Scalar>();
+}
+
+#[cfg(feature = "x86_sse41")]
+#[test]
+fn encode_little_endian_sse41() {
+    do_encode_little_endian::<x86::Sse41>();
+}
+
+#[test]
+fn decode_little_endian_scalar() {
+    do_decode_little_endian::<Scalar>();
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[test]
+fn decode_little_endian_ssse3() {
+    do_decode_little_endian::<x86::Ssse3>();
+}
+
+#[test]
+fn cursor_skip_then_decode_little_endian() {
+    let mut cursor = DecodeCursor::new(ENCODED, NUMS.len());
+    cursor.skip(4);
+
+    let mut decoded = [0; 4];
+    assert_eq!(3, cursor.decode_slice::<Scalar>(&mut decoded));
+    assert_eq!(&NUMS[4..], &decoded[0..3]);
+    assert_eq!(ENCODED.len(), cursor.input_consumed());
+}
+
+#[test]
+fn frame_header_little_endian() {
+    let mut encoded = vec![0; frame::FRAME_HEADER_LEN + NUMS.len() * 5];
+    let frame_len = frame::encode_frame::<Scalar>(NUMS, &mut encoded);
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    let header = [
+        0x07, 0x00, 0x00, 0x00,
+        0x13, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(&header[..], &encoded[0..frame::FRAME_HEADER_LEN]);
+    assert_eq!(ENCODED, &encoded[frame::FRAME_HEADER_LEN..frame_len]);
+}
+
+fn do_encode_little_endian<E: Encoder>() {
+    let mut encoded = vec![0; NUMS.len() * 5];
+    let encoded_len = encode::<E>(NUMS, &mut encoded);
+
+    assert_eq!(ENCODED, &encoded[0..encoded_len]);
+}
+
+fn do_decode_little_endian<D: Decoder>() {
+    let mut decoded = vec![0; NUMS.len()];
+    assert_eq!(ENCODED.len(), decode::<D>(ENCODED, NUMS.len(), &mut decoded));
+
+    assert_eq!(NUMS, &decoded[..]);
+}
@@ -0,0 +1,97 @@
+extern crate rand;
+extern crate stream_vbyte;
+
+use self::rand::Rng;
+
+use stream_vbyte::*;
+use stream_vbyte::interleaved::*;
+
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::*;
+
+#[test]
+fn interleaved_random_roundtrip_scalar_scalar() {
+    do_interleaved_random_roundtrip::<Scalar, Scalar>();
+}
+
+#[cfg(feature = "x86_sse41")]
+#[test]
+fn interleaved_random_roundtrip_sse41_scalar() {
+    do_interleaved_random_roundtrip::<x86::Sse41, Scalar>();
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[test]
+fn interleaved_random_roundtrip_scalar_ssse3() {
+    do_interleaved_random_roundtrip::<Scalar, x86::Ssse3>();
+}
+
+#[test]
+fn interleaved_is_quad_interleaved_plain_encoding() {
+    let a: Vec<u32> = (0..6).collect();
+    let b: Vec<u32> = (100..106).collect();
+
+    let mut encoded = vec![0; 5 * 2 * 8];
+    let encoded_len = encode_interleaved::<Scalar>(&[&a, &b], &mut encoded);
+
+    // the same thing, laid out by hand with padding in the trailing partial quads
+    let by_hand = vec![0, 1, 2, 3, 100, 101, 102, 103, 4, 5, 0, 0, 104, 105, 0, 0];
+    let mut expected = vec![0; by_hand.len() * 5];
+    let expected_len = encode::<Scalar>(&by_hand, &mut expected);
+
+    assert_eq!(expected_len, encoded_len);
+    assert_eq!(&expected[0..expected_len], &encoded[0..encoded_len]);
+}
+
+#[test]
+#[should_panic(expected = "All streams must have the same length")]
+fn interleaved_panics_on_mismatched_lengths() {
+    let mut encoded = vec![0; 100];
+    encode_interleaved::<Scalar>(&[&[1, 2, 3], &[1, 2]], &mut encoded);
+}
+
+fn do_interleaved_random_roundtrip<E: Encoder, D: Decoder>() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..500 {
+        let count = rng.gen_range(0, 500);
+
+        let streams: Vec<Vec<u32>> = (0..3)
+            .map(|_| {
+                RandomVarintEncodedLengthIter::new(rand::weak_rng())
+                    .take(count)
+                    .collect()
+            })
+            .collect();
+        let stream_slices: Vec<&[u32]> = streams.iter().map(|s| &s[..]).collect();
+
+        let mut encoded = vec![0; 5 * 3 * (count + 4)];
+        let encoded_len = encode_interleaved::<E>(&stream_slices, &mut encoded);
+
+        for (stream_index, stream) in streams.iter().enumerate() {
+            let mut decoded = vec![0; count];
+            assert_eq!(
+                encoded_len,
+                decode_interleaved_stream::<D>(
+                    &encoded[0..encoded_len],
+                    count,
+                    3,
+                    stream_index,
+                    &mut decoded
+                )
+            );
+            assert_eq!(stream, &decoded);
+        }
+
+        let mut rows = vec![[0; 3]; count];
+        assert_eq!(
+            encoded_len,
+            decode_interleaved_rows::<D, _>(&encoded[0..encoded_len], count, &mut rows)
+        );
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!([streams[0][i], streams[1][i], streams[2][i]], *row);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+extern crate stream_vbyte;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+#[cfg(not(feature = "tracing"))]
+use stream_vbyte::*;
+
+/// Counts allocations, so that tests can check that code doesn't allocate.
+///
+/// The count is per thread, since the test harness and other tests allocate on their own threads
+/// while a test runs.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // `try_with`, since allocations can happen while the thread is being torn down
+    let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+}
+
+// only used by tests that don't run with `tracing`, since its subscribers may allocate
+#[cfg(not(feature = "tracing"))]
+fn allocations() -> usize {
+    ALLOCATIONS.with(|a| a.get())
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Panics if `f` allocates.
+#[cfg(not(feature = "tracing"))]
+fn assert_no_alloc<T, F: FnOnce() -> T>(what: &str, f: F) -> T {
+    let before = allocations();
+    let result = f();
+    let allocations = allocations() - before;
+
+    assert!(allocations == 0, "{} allocated {} times", what, allocations);
+    result
+}
+
+#[cfg(not(feature = "tracing"))]
+struct MaxSink {
+    max: u32,
+}
+
+#[cfg(not(feature = "tracing"))]
+impl<Q: WriteQuadToSlice> DecodeQuadSink<Q> for MaxSink {
+    fn on_quad(&mut self, quad: Q, _nums_decoded: usize) {
+        let mut nums = [0; 4];
+        quad.write_to_slice(&mut nums);
+        for &num in &nums {
+            self.max = std::cmp::max(self.max, num);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl DecodeSingleSink for MaxSink {
+    fn on_number(&mut self, num: u32, _nums_decoded: usize) {
+        self.max = std::cmp::max(self.max, num);
+    }
+}
+
+// tracing subscribers may allocate
+#[cfg(not(feature = "tracing"))]
+#[test]
+fn core_apis_do_not_allocate() {
+    // an odd count, so that every code path for partial quads runs too
+    let nums: Vec<u32> = (0..10_003).map(|i| (i * 7919) >> (i % 32)).collect();
+    let mut encoded = vec![0; nums.len() * 5];
+    let mut decoded = vec![0; nums.len()];
+
+    let encoded_len = assert_no_alloc("encode() with Scalar", || {
+        encode::<Scalar>(&nums, &mut encoded)
+    });
+    assert_no_alloc("encode() with DefaultEncoder", || {
+        encode::<DefaultEncoder>(&nums, &mut encoded)
+    });
+    let encoded = &encoded[0..encoded_len];
+
+    assert_no_alloc("decode() with Scalar", || {
+        decode::<Scalar>(encoded, nums.len(), &mut decoded)
+    });
+    assert_no_alloc("decode() with DefaultDecoder", || {
+        decode::<DefaultDecoder>(encoded, nums.len(), &mut decoded)
+    });
+    assert_no_alloc("decode_unchecked()", || unsafe {
+        decode_unchecked::<Scalar>(encoded, nums.len(), &mut decoded)
+    });
+    assert_eq!(nums, decoded);
+
+    assert_no_alloc("DecodeCursor.decode_slice()", || {
+        let mut cursor = DecodeCursor::new(encoded, nums.len());
+        cursor.skip(1000);
+        let mut buf = [0; 100];
+        while cursor.has_more() {
+            cursor.decode_slice::<DefaultDecoder>(&mut buf);
+        }
+    });
+
+    let max = assert_no_alloc("DecodeCursor.decode_sink()", || {
+        let mut cursor = DecodeCursor::new(encoded, nums.len());
+        let mut sink = MaxSink { max: 0 };
+        cursor.decode_sink::<Scalar, _>(&mut sink, nums.len());
+        sink.max
+    });
+    assert_eq!(*nums.iter().max().unwrap(), max);
+}
@@ -84,10 +84,7 @@ fn encode_sse41_compare_reference_impl() {
     do_compare_reference_data::<x86::Sse41>()
 }
 
-fn do_random_roundtrip<E: Encoder, D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_random_roundtrip<E: Encoder, D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded = Vec::new();
     let mut decoded = Vec::new();
@@ -137,10 +134,7 @@ where
     }
 }
 
-fn do_all_same_single_byte<E: Encoder, D: Decoder>()
-where
-    for<'a> SliceDecodeSink<'a>: DecodeQuadSink<<D as Decoder>::DecodedQuad>,
-{
+fn do_all_same_single_byte<E: Encoder, D: Decoder>() {
     let mut nums: Vec<u32> = Vec::new();
     let mut encoded: Vec<u8> = Vec::new();
     let mut decoded: Vec<u32> = Vec::new();
@@ -0,0 +1,118 @@
+extern crate rand;
+extern crate stream_vbyte;
+
+use self::rand::Rng;
+
+use stream_vbyte::*;
+
+#[path = "../src/random_varint.rs"]
+mod random_varint;
+
+use random_varint::*;
+
+#[test]
+fn zip_cursor_random_chunk_len_scalar() {
+    do_zip_cursor_random_chunk_len::<Scalar>();
+}
+
+#[cfg(feature = "x86_ssse3")]
+#[test]
+fn zip_cursor_random_chunk_len_ssse3() {
+    do_zip_cursor_random_chunk_len::<x86::Ssse3>();
+}
+
+#[test]
+fn zip_cursor_skip_then_decode_scalar() {
+    let a: Vec<u32> = (0..103).collect();
+    let b: Vec<u32> = (0..103).map(|i| i * 1000).collect();
+    let c: Vec<u32> = (0..103).map(|i| u32::MAX - i).collect();
+
+    let mut encoded_a = Vec::new();
+    encode_append::<Scalar>(&a, &mut encoded_a);
+    let mut encoded_b = Vec::new();
+    encode_append::<Scalar>(&b, &mut encoded_b);
+    let mut encoded_c = Vec::new();
+    encode_append::<Scalar>(&c, &mut encoded_c);
+
+    let mut zip = ZipCursor::new([
+        DecodeCursor::new(&encoded_a, a.len()),
+        DecodeCursor::new(&encoded_b, b.len()),
+        DecodeCursor::new(&encoded_c, c.len()),
+    ]);
+
+    zip.skip(40);
+
+    let mut rows = vec![[0; 3]; 100];
+    assert_eq!(63, zip.decode_rows::<Scalar>(&mut rows));
+    assert!(!zip.has_more());
+
+    for (i, row) in rows[0..63].iter().enumerate() {
+        assert_eq!([a[i + 40], b[i + 40], c[i + 40]], *row);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Streams must have the same remaining length")]
+fn zip_cursor_panics_on_mismatched_lengths() {
+    let a: Vec<u32> = (0..20).collect();
+    let b: Vec<u32> = (0..8).collect();
+
+    let mut encoded_a = Vec::new();
+    encode_append::<Scalar>(&a, &mut encoded_a);
+    let mut encoded_b = Vec::new();
+    encode_append::<Scalar>(&b, &mut encoded_b);
+
+    let mut zip = ZipCursor::new([
+        DecodeCursor::new(&encoded_a, a.len()),
+        DecodeCursor::new(&encoded_b, b.len()),
+    ]);
+
+    let mut rows = vec![[0; 2]; 20];
+    zip.decode_rows::<Scalar>(&mut rows);
+}
+
+fn do_zip_cursor_random_chunk_len<D: Decoder>() {
+    let mut rng = rand::weak_rng();
+
+    for _ in 0..1_000 {
+        let count = rng.gen_range(0, 1000);
+
+        let streams: Vec<Vec<u32>> = (0..4)
+            .map(|_| {
+                RandomVarintEncodedLengthIter::new(rand::weak_rng())
+                    .take(count)
+                    .collect()
+            })
+            .collect();
+        let encoded: Vec<Vec<u8>> = streams
+            .iter()
+            .map(|s| {
+                let mut encoded = Vec::new();
+                encode_append::<Scalar>(s, &mut encoded);
+                encoded
+            })
+            .collect();
+
+        let mut zip = ZipCursor::new([
+            DecodeCursor::new(&encoded[0], count),
+            DecodeCursor::new(&encoded[1], count),
+            DecodeCursor::new(&encoded[2], count),
+            DecodeCursor::new(&encoded[3], count),
+        ]);
+
+        let mut rows_accum = Vec::new();
+        let mut rows = vec![[0; 4]; count + 4];
+        while zip.has_more() {
+            let chunk_len = rng.gen_range(4, count + 5);
+            let rows_decoded = zip.decode_rows::<D>(&mut rows[0..chunk_len]);
+            rows_accum.extend_from_slice(&rows[0..rows_decoded]);
+        }
+
+        assert_eq!(count, rows_accum.len());
+        for (i, row) in rows_accum.iter().enumerate() {
+            for (s, stream) in streams.iter().enumerate() {
+                assert_eq!(stream[i], row[s], "row {} stream {}", i, s);
+            }
+        }
+    }
+}